@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+struct Entry<V> {
+    slot: u64,
+    value: V,
+    stored_at: Instant,
+}
+
+/// Values tagged with the slot they were read at, served until `ttl` elapses
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: RwLock<HashMap<K, Entry<V>>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Cached value and its slot, if still fresh at `now`
+    pub fn get(&self, key: &K, now: Instant) -> Option<(u64, V)> {
+        let entries = self.entries.read().unwrap();
+        let entry = entries.get(key)?;
+        if now.duration_since(entry.stored_at) > self.ttl {
+            return None;
+        }
+        Some((entry.slot, entry.value.clone()))
+    }
+
+    /// Store `value` read at `slot`; returns false if a newer slot is already cached
+    pub fn insert(&self, key: K, slot: u64, value: V, now: Instant) -> bool {
+        let mut entries = self.entries.write().unwrap();
+        if let Some(existing) = entries.get_mut(&key) {
+            if existing.slot > slot {
+                return false;
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                slot,
+                value,
+                stored_at: now,
+            },
+        );
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,11 @@
+//! Reference REST gateway for MoluscoYield.
+//!
+//! Serves vault, position and checkpoint data as JSON, read through RPC and
+//! held in a slot-aware TTL cache. A Geyser feed can write into the same cache
+//! with `TtlCache::insert`; entries only move forward in slot, so out-of-order
+//! updates never replace fresher data.
+
+pub mod cache;
+pub mod models;
+pub mod routes;
+pub mod source;
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use moluscoyield_api::routes::{router, AppState};
+use moluscoyield_api::source::RpcSource;
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let rpc_url = env_or("MOLUSCO_RPC_URL", "http://127.0.0.1:8899");
+    let bind = env_or("MOLUSCO_API_BIND", "0.0.0.0:8080");
+    let ttl_secs: u64 = env_or("MOLUSCO_API_CACHE_TTL_SECS", "5").parse()?;
+
+    let state = Arc::new(AppState::new(
+        RpcSource::new(rpc_url.clone()),
+        Duration::from_secs(ttl_secs),
+    ));
+    let listener = tokio::net::TcpListener::bind(&bind).await?;
+    eprintln!(
+        "moluscoyield-api listening on {} (rpc {}, cache ttl {}s)",
+        bind, rpc_url, ttl_secs
+    );
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
@@ -0,0 +1,177 @@
+//! JSON shapes served by the gateway. `u128` counters are strings so JavaScript
+//! clients do not lose precision.
+
+use anchor_lang::prelude::Pubkey;
+use moluscoyield::{Checkpoint, Position, Vault};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultSummary {
+    pub address: String,
+    pub slot: u64,
+    pub owner: String,
+    pub agent_name: String,
+    pub total_value_locked: String,
+    pub nav: u64,
+    pub smoothed_nav: u64,
+    pub active_positions: u16,
+    pub total_positions_created: u64,
+    pub created_at: i64,
+    pub last_rebalance: i64,
+    pub performance_fee_bps: u16,
+    pub management_fee_bps: u16,
+    pub deposits_paused: bool,
+    pub withdrawals_paused: bool,
+    pub is_paused: bool,
+    pub simulation: bool,
+    pub cumulative_inflows: String,
+    pub cumulative_outflows: String,
+    pub cumulative_yield: String,
+    pub cumulative_fees: String,
+    pub total_impaired: u64,
+    pub checkpoint_count: u64,
+    pub queued_withdrawals: u64,
+    pub fees_accrued: String,
+    pub fees_outstanding: String,
+    pub unrealized_yield: String,
+    pub min_update_interval_secs: i64,
+    pub max_staleness_secs: i64,
+    pub stale_positions: u32,
+}
+
+impl VaultSummary {
+    pub fn new(address: &Pubkey, slot: u64, vault: &Vault) -> Self {
+        Self {
+            address: address.to_string(),
+            slot,
+            owner: vault.owner.to_string(),
+            agent_name: vault.agent_name.clone(),
+            total_value_locked: vault.total_value_locked.to_string(),
+            nav: vault.nav,
+            smoothed_nav: vault.smoothed_nav,
+            active_positions: vault.active_positions,
+            total_positions_created: vault.total_positions_created,
+            created_at: vault.created_at,
+            last_rebalance: vault.last_rebalance,
+            performance_fee_bps: vault.performance_fee_bps,
+            management_fee_bps: vault.management_fee_bps,
+            deposits_paused: vault.deposits_paused,
+            withdrawals_paused: vault.withdrawals_paused,
+            is_paused: vault.is_paused,
+            simulation: vault.simulation,
+            cumulative_inflows: vault.cumulative_inflows.to_string(),
+            cumulative_outflows: vault.cumulative_outflows.to_string(),
+            cumulative_yield: vault.cumulative_yield.to_string(),
+            cumulative_fees: vault.cumulative_fees.to_string(),
+            total_impaired: vault.total_impaired,
+            checkpoint_count: vault.checkpoint_count,
+            queued_withdrawals: vault.queued_withdrawals,
+            fees_accrued: vault.fees_accrued.to_string(),
+            fees_outstanding: vault.fees_outstanding.to_string(),
+            unrealized_yield: vault.unrealized_yield.to_string(),
+            min_update_interval_secs: vault.min_update_interval_secs,
+            max_staleness_secs: vault.max_staleness_secs,
+            stale_positions: vault.stale_positions,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionSummary {
+    pub address: String,
+    pub vault: String,
+    /// Kind names as the program spells them, e.g. `Kamino` or `Other(7)`
+    pub protocol: String,
+    pub strategy: String,
+    pub asset: String,
+    pub asset_decimals: u8,
+    pub amount: u64,
+    pub target_apy: u16,
+    pub opened_at: i64,
+    pub last_update: i64,
+    pub last_update_slot: u64,
+    pub update_sequence: u64,
+    pub is_stale: bool,
+    pub is_active: bool,
+    pub accumulated_yield: String,
+    pub accumulated_loss: String,
+    pub unrealized_yield: u64,
+    pub realized_yield: String,
+    pub maturity_ts: i64,
+    pub last_value: u64,
+    /// Venue's own supply APR at the last mark that read it; absent until then
+    pub venue_supply_apr_bps: Option<u32>,
+    pub venue_utilization_bps: Option<u16>,
+    /// Predecessor this position was rolled out of
+    pub rolled_from: Option<String>,
+}
+
+impl PositionSummary {
+    pub fn new(address: &Pubkey, position: &Position) -> Self {
+        Self {
+            address: address.to_string(),
+            vault: position.vault.to_string(),
+            protocol: format!("{:?}", position.protocol),
+            strategy: format!("{:?}", position.strategy),
+            asset: format!("{:?}", position.asset),
+            asset_decimals: position.asset_decimals,
+            amount: position.amount,
+            target_apy: position.target_apy,
+            opened_at: position.opened_at,
+            last_update: position.last_update,
+            last_update_slot: position.last_update_slot,
+            update_sequence: position.update_sequence,
+            is_stale: position.is_stale,
+            is_active: position.is_active,
+            accumulated_yield: position.accumulated_yield.to_string(),
+            accumulated_loss: position.accumulated_loss.to_string(),
+            unrealized_yield: position.unrealized_yield,
+            realized_yield: position.realized_yield.to_string(),
+            maturity_ts: position.maturity_ts,
+            last_value: position.last_value,
+            venue_supply_apr_bps: (position.venue_rate_updated_at != 0)
+                .then_some(position.venue_supply_apr_bps),
+            venue_utilization_bps: (position.venue_rate_updated_at != 0)
+                .then_some(position.venue_utilization_bps),
+            rolled_from: (position.rolled_from != Pubkey::default())
+                .then(|| position.rolled_from.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckpointSummary {
+    pub address: String,
+    pub index: u64,
+    pub timestamp: i64,
+    pub raw_nav: u64,
+    pub smoothed_nav: u64,
+    pub period_return_bps: i64,
+    pub net_return_bps: i64,
+    pub benchmark_return_bps: i64,
+    pub excess_return_bps: i64,
+    pub volatility: u64,
+    pub operating_costs: u64,
+    pub share_price: u64,
+    pub sharpe_proxy: i64,
+}
+
+impl CheckpointSummary {
+    pub fn new(address: &Pubkey, checkpoint: &Checkpoint) -> Self {
+        Self {
+            address: address.to_string(),
+            index: checkpoint.index,
+            timestamp: checkpoint.timestamp,
+            raw_nav: checkpoint.raw_nav,
+            smoothed_nav: checkpoint.smoothed_nav,
+            period_return_bps: checkpoint.period_return_bps,
+            net_return_bps: checkpoint.net_return_bps,
+            benchmark_return_bps: checkpoint.benchmark_return_bps,
+            excess_return_bps: checkpoint.excess_return_bps,
+            volatility: checkpoint.volatility,
+            operating_costs: checkpoint.operating_costs,
+            share_price: checkpoint.share_price,
+            sharpe_proxy: checkpoint.sharpe_proxy,
+        }
+    }
+}
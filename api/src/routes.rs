@@ -0,0 +1,169 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use moluscoyield::{Checkpoint, Position, Vault};
+use moluscoyield_types::offsets;
+
+use crate::cache::TtlCache;
+use crate::models::{CheckpointSummary, PositionSummary, VaultSummary};
+use crate::source::{Accounts, RpcSource};
+
+/// `Checkpoint.vault` directly follows the discriminator
+const CHECKPOINT_VAULT_OFFSET: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("account not found: {0}")]
+    NotFound(Pubkey),
+    #[error("account {0} is not a {1}")]
+    WrongAccount(Pubkey, &'static str),
+    #[error("rpc error: {0}")]
+    Rpc(#[from] solana_client::client_error::ClientError),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ApiError::InvalidAddress(_) | ApiError::WrongAccount(..) => StatusCode::BAD_REQUEST,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Rpc(_) => StatusCode::BAD_GATEWAY,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Listing {
+    Positions,
+    Checkpoints,
+}
+
+pub struct AppState {
+    source: RpcSource,
+    accounts: TtlCache<Pubkey, Option<Vec<u8>>>,
+    listings: TtlCache<(Listing, Pubkey), Accounts>,
+}
+
+impl AppState {
+    pub fn new(source: RpcSource, ttl: Duration) -> Self {
+        Self {
+            source,
+            accounts: TtlCache::new(ttl),
+            listings: TtlCache::new(ttl),
+        }
+    }
+
+    async fn account(&self, address: &Pubkey) -> Result<(u64, Vec<u8>), ApiError> {
+        let now = Instant::now();
+        let (slot, data) = match self.accounts.get(address, now) {
+            Some(hit) => hit,
+            None => {
+                let (slot, data) = self.source.account(address).await?;
+                self.accounts.insert(*address, slot, data.clone(), now);
+                (slot, data)
+            }
+        };
+        data.map(|data| (slot, data))
+            .ok_or(ApiError::NotFound(*address))
+    }
+
+    async fn listing(&self, listing: Listing, vault: &Pubkey) -> Result<Accounts, ApiError> {
+        let now = Instant::now();
+        if let Some((_, accounts)) = self.listings.get(&(listing, *vault), now) {
+            return Ok(accounts);
+        }
+        let (discriminator, offset) = match listing {
+            Listing::Positions => (Position::DISCRIMINATOR, offsets::POSITION_VAULT),
+            Listing::Checkpoints => (Checkpoint::DISCRIMINATOR, CHECKPOINT_VAULT_OFFSET),
+        };
+        let (slot, accounts) = self
+            .source
+            .accounts_of_vault(&discriminator, offset, vault)
+            .await?;
+        self.listings
+            .insert((listing, *vault), slot, accounts.clone(), now);
+        Ok(accounts)
+    }
+}
+
+pub fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route("/vaults/:address", get(get_vault))
+        .route("/vaults/:address/positions", get(get_vault_positions))
+        .route("/vaults/:address/checkpoints", get(get_vault_checkpoints))
+        .route("/positions/:address", get(get_position))
+        .with_state(state)
+}
+
+fn parse_address(address: &str) -> Result<Pubkey, ApiError> {
+    Pubkey::from_str(address).map_err(|_| ApiError::InvalidAddress(address.to_string()))
+}
+
+fn decode<T: AccountDeserialize>(
+    address: &Pubkey,
+    data: &[u8],
+    kind: &'static str,
+) -> Result<T, ApiError> {
+    T::try_deserialize(&mut &data[..]).map_err(|_| ApiError::WrongAccount(*address, kind))
+}
+
+async fn get_vault(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<String>,
+) -> Result<Json<VaultSummary>, ApiError> {
+    let address = parse_address(&address)?;
+    let (slot, data) = state.account(&address).await?;
+    let vault: Vault = decode(&address, &data, "vault")?;
+    Ok(Json(VaultSummary::new(&address, slot, &vault)))
+}
+
+async fn get_position(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<String>,
+) -> Result<Json<PositionSummary>, ApiError> {
+    let address = parse_address(&address)?;
+    let (_, data) = state.account(&address).await?;
+    let position: Position = decode(&address, &data, "position")?;
+    Ok(Json(PositionSummary::new(&address, &position)))
+}
+
+async fn get_vault_positions(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<String>,
+) -> Result<Json<Vec<PositionSummary>>, ApiError> {
+    let vault = parse_address(&address)?;
+    let accounts = state.listing(Listing::Positions, &vault).await?;
+    let mut positions = Vec::with_capacity(accounts.len());
+    for (key, data) in &accounts {
+        let position: Position = decode(key, data, "position")?;
+        positions.push(PositionSummary::new(key, &position));
+    }
+    positions.sort_by_key(|p| p.opened_at);
+    Ok(Json(positions))
+}
+
+async fn get_vault_checkpoints(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<String>,
+) -> Result<Json<Vec<CheckpointSummary>>, ApiError> {
+    let vault = parse_address(&address)?;
+    let accounts = state.listing(Listing::Checkpoints, &vault).await?;
+    let mut checkpoints = Vec::with_capacity(accounts.len());
+    for (key, data) in &accounts {
+        let checkpoint: Checkpoint = decode(key, data, "checkpoint")?;
+        checkpoints.push(CheckpointSummary::new(key, &checkpoint));
+    }
+    checkpoints.sort_by_key(|c| c.index);
+    Ok(Json(checkpoints))
+}
@@ -0,0 +1,68 @@
+use anchor_lang::prelude::Pubkey;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+
+/// Raw account data keyed by address
+pub type Accounts = Vec<(Pubkey, Vec<u8>)>;
+
+/// Reads program accounts over RPC at confirmed commitment
+pub struct RpcSource {
+    rpc: RpcClient,
+}
+
+impl RpcSource {
+    pub fn new(rpc_url: String) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()),
+        }
+    }
+
+    /// Account data and the slot it was read at; `None` if the account does not exist
+    pub async fn account(&self, address: &Pubkey) -> Result<(u64, Option<Vec<u8>>), ClientError> {
+        let response = self
+            .rpc
+            .get_account_with_commitment(address, CommitmentConfig::confirmed())
+            .await?;
+        Ok((response.context.slot, response.value.map(|a| a.data)))
+    }
+
+    /// All program accounts with `discriminator` whose pubkey at `vault_offset` is `vault`
+    pub async fn accounts_of_vault(
+        &self,
+        discriminator: &[u8],
+        vault_offset: usize,
+        vault: &Pubkey,
+    ) -> Result<(u64, Accounts), ClientError> {
+        let slot = self.rpc.get_slot().await?;
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, discriminator.to_vec())),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    vault_offset,
+                    vault.to_bytes().to_vec(),
+                )),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let accounts = self
+            .rpc
+            .get_program_accounts_with_config(&moluscoyield::ID, config)
+            .await?;
+        Ok((
+            slot,
+            accounts
+                .into_iter()
+                .map(|(key, account)| (key, account.data))
+                .collect(),
+        ))
+    }
+}
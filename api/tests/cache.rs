@@ -0,0 +1,30 @@
+use std::time::{Duration, Instant};
+
+use moluscoyield_api::cache::TtlCache;
+
+#[test]
+fn entries_expire_after_ttl() {
+    let cache = TtlCache::new(Duration::from_secs(5));
+    let start = Instant::now();
+    cache.insert("vault", 100, 1u64, start);
+
+    assert_eq!(
+        cache.get(&"vault", start + Duration::from_secs(5)),
+        Some((100, 1))
+    );
+    assert_eq!(cache.get(&"vault", start + Duration::from_secs(6)), None);
+}
+
+#[test]
+fn older_slots_never_replace_newer_data() {
+    let cache = TtlCache::new(Duration::from_secs(5));
+    let now = Instant::now();
+    assert!(cache.insert("vault", 200, 2u64, now));
+
+    // A late Geyser update for an earlier slot is dropped
+    assert!(!cache.insert("vault", 150, 1, now));
+    assert_eq!(cache.get(&"vault", now), Some((200, 2)));
+
+    assert!(cache.insert("vault", 200, 3, now));
+    assert_eq!(cache.get(&"vault", now), Some((200, 3)));
+}
@@ -0,0 +1,393 @@
+//! `molusco bootstrap-devnet`: a working devnet environment in one command.
+//!
+//! Funds the payer, creates mock mints, initializes the program config, protocol
+//! registry and job queue when nobody has yet, registers the mock USDC mint as
+//! an asset, publishes demo rate feeds and a benchmark over
+//! them, and opens a demo vault with a deposit and two sample positions.
+//! Shared accounts that already exist are reused, so it is safe to rerun with a
+//! new agent name.
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token::spl_token;
+use moluscoyield::{
+    AssetKind, BenchmarkComponent, Config, ProgramConfig, ProtocolKind, ProtocolRegistry,
+    StrategyKind,
+};
+use moluscoyield_client::instructions::{self, PositionParams};
+use moluscoyield_client::vault_client::{SendError, VaultClient};
+use solana_client::client_error::ClientError;
+use solana_sdk::program_error::ProgramError;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+const USDC: u64 = 1_000_000;
+const BENCHMARK_ID: u64 = 1;
+
+/// A demo rate feed and the position that opens against it
+struct DemoFeed {
+    label: &'static str,
+    protocol: ProtocolKind,
+    strategy: StrategyKind,
+    /// Annualized rate in bps
+    rate_bps: u32,
+}
+
+const FEEDS: [DemoFeed; 2] = [
+    DemoFeed {
+        label: "demo-lend",
+        protocol: ProtocolKind::Kamino,
+        strategy: StrategyKind::Lending,
+        rate_bps: 650,
+    },
+    DemoFeed {
+        label: "demo-stake",
+        protocol: ProtocolKind::Jito,
+        strategy: StrategyKind::LiquidStaking,
+        rate_bps: 720,
+    },
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum BootstrapError {
+    #[error("send failed: {0}")]
+    Send(#[from] SendError),
+    #[error("rpc error: {0}")]
+    Rpc(#[from] ClientError),
+    #[error("instruction error: {0}")]
+    Instruction(#[from] ProgramError),
+    #[error("airdrop did not land; fund {0} from a faucet and rerun")]
+    Airdrop(Pubkey),
+    #[error("vault {0} already exists; pick another agent name")]
+    VaultExists(Pubkey),
+    #[error("account {0} could not be decoded")]
+    Decode(Pubkey),
+    #[error("protocol {0:?} is not whitelisted; ask the program admin to add it")]
+    ProtocolNotWhitelisted(ProtocolKind),
+    #[error("only the program admin can register the mock mints as assets")]
+    NotProgramAdmin,
+}
+
+#[derive(Debug, Clone)]
+pub struct BootstrapOptions {
+    pub agent_name: String,
+    /// Top the payer up to at least this balance before starting
+    pub min_balance: u64,
+    pub deposit: u64,
+}
+
+impl Default for BootstrapOptions {
+    fn default() -> Self {
+        Self {
+            agent_name: "demo".to_string(),
+            min_balance: LAMPORTS_PER_SOL,
+            deposit: 10_000 * USDC,
+        }
+    }
+}
+
+/// Everything the bootstrap created or reused
+#[derive(Debug, Clone)]
+pub struct Bootstrapped {
+    pub usdc_mint: Pubkey,
+    pub sol_mint: Pubkey,
+    pub usdc_account: Pubkey,
+    pub sol_account: Pubkey,
+    pub config: Pubkey,
+    /// False when another authority already owns the config and its registries
+    pub config_authority: bool,
+    pub feeds: Vec<Pubkey>,
+    pub benchmark: Pubkey,
+    pub vault: Pubkey,
+    pub share_account: Pubkey,
+    pub positions: Vec<Pubkey>,
+}
+
+struct Bootstrap<'a> {
+    client: &'a VaultClient,
+    payer: Pubkey,
+}
+
+impl Bootstrap<'_> {
+    fn exists(&self, address: &Pubkey) -> Result<bool, BootstrapError> {
+        let account = self
+            .client
+            .rpc()
+            .get_account_with_commitment(address, self.client.rpc().commitment())?;
+        Ok(account.value.is_some())
+    }
+
+    fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T, BootstrapError> {
+        let account = self.client.rpc().get_account(address)?;
+        T::try_deserialize(&mut account.data.as_slice())
+            .map_err(|_| BootstrapError::Decode(*address))
+    }
+
+    fn fund(&self, min_balance: u64) -> Result<(), BootstrapError> {
+        let rpc = self.client.rpc();
+        if rpc.get_balance(&self.payer)? >= min_balance {
+            return Ok(());
+        }
+        // The devnet faucet caps each request, so ask for a typical drop, not the gap
+        let signature = rpc.request_airdrop(&self.payer, 2 * LAMPORTS_PER_SOL)?;
+        let started = Instant::now();
+        while !rpc.confirm_transaction(&signature)? {
+            if started.elapsed() > Duration::from_secs(60) {
+                return Err(BootstrapError::Airdrop(self.payer));
+            }
+            sleep(Duration::from_secs(1));
+        }
+        Ok(())
+    }
+
+    /// A fresh mock mint the payer controls, and a payer token account holding `supply`
+    fn mock_mint(&self, decimals: u8, supply: u64) -> Result<(Pubkey, Pubkey), BootstrapError> {
+        let rpc = self.client.rpc();
+        let mint = Keypair::new();
+        let account = Keypair::new();
+        let mut setup = instructions::create_mint(
+            &self.payer,
+            &mint.pubkey(),
+            &self.payer,
+            decimals,
+            rpc.get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)?,
+        )?;
+        setup.extend(instructions::create_token_account(
+            &self.payer,
+            &account.pubkey(),
+            &mint.pubkey(),
+            &self.payer,
+            rpc.get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)?,
+        )?);
+        setup.push(spl_token::instruction::mint_to(
+            &spl_token::ID,
+            &mint.pubkey(),
+            &account.pubkey(),
+            &self.payer,
+            &[],
+            supply,
+        )?);
+        self.client.send(&setup, &[&mint, &account])?;
+        Ok((mint.pubkey(), account.pubkey()))
+    }
+
+    /// Initialize the program config, config and job queue if missing
+    ///
+    /// Creating the configs needs the payer to be the program's upgrade authority.
+    /// True when the payer is the config authority.
+    fn config(&self) -> Result<bool, BootstrapError> {
+        let pdas = self.client.pdas();
+        if !self.exists(&pdas.program_config())? {
+            self.client.send(
+                &[instructions::initialize_program_config(pdas, &self.payer)],
+                &[],
+            )?;
+        }
+        if !self.exists(&pdas.config())? {
+            self.client
+                .send(&[instructions::initialize_config(pdas, &self.payer)], &[])?;
+        }
+        let config: Config = self.fetch(&pdas.config())?;
+        if config.authority != self.payer {
+            return Ok(false);
+        }
+        if !self.exists(&pdas.job_queue())? {
+            self.client.send(
+                &[instructions::initialize_job_queue(pdas, &self.payer)],
+                &[],
+            )?;
+        }
+        Ok(true)
+    }
+
+    /// Whitelist the demo protocols, register `usdc_mint` as an asset and make
+    /// sure the demo strategies have capacity entries
+    ///
+    /// Needs the payer to be the program admin, except that under another admin
+    /// the demo protocols may already be listed.
+    fn registries(&self, usdc_mint: &Pubkey) -> Result<(), BootstrapError> {
+        let pdas = self.client.pdas();
+        let program_config: ProgramConfig = self.fetch(&pdas.program_config())?;
+        let is_admin = program_config.admin == self.payer;
+        if is_admin && !self.exists(&pdas.protocol_registry())? {
+            let initialize = instructions::initialize_protocol_registry(pdas, &self.payer);
+            self.client.send(&[initialize], &[])?;
+        }
+        let registry: ProtocolRegistry = self.fetch(&pdas.protocol_registry())?;
+        for feed in &FEEDS {
+            if registry.is_listed(feed.protocol) {
+                continue;
+            }
+            if !is_admin {
+                return Err(BootstrapError::ProtocolNotWhitelisted(feed.protocol));
+            }
+            let whitelist = instructions::whitelist_protocol(pdas, &self.payer, feed.protocol);
+            self.client.send(&[whitelist], &[])?;
+        }
+        if !is_admin {
+            return Err(BootstrapError::NotProgramAdmin);
+        }
+        let register =
+            instructions::register_asset(pdas, &self.payer, usdc_mint, AssetKind::Usdc, "USDC");
+        self.client.send(&[register], &[])?;
+        for feed in &FEEDS {
+            if !self.exists(&pdas.strategy_capacity(feed.strategy))? {
+                let uncapped =
+                    instructions::set_strategy_capacity(pdas, &self.payer, feed.strategy, 0);
+                self.client.send(&[uncapped], &[])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn feeds(&self) -> Result<(Vec<Pubkey>, Pubkey), BootstrapError> {
+        let pdas = self.client.pdas();
+        let mut feeds = Vec::with_capacity(FEEDS.len());
+        for demo in &FEEDS {
+            let (feed, create) =
+                instructions::create_rate_feed(pdas, &self.payer, demo.label, demo.rate_bps);
+            if !self.exists(&feed)? {
+                self.client.send(&[create], &[])?;
+            }
+            feeds.push(feed);
+        }
+
+        let weight_bps = (10_000 / feeds.len()) as u16;
+        let components = feeds
+            .iter()
+            .map(|feed| BenchmarkComponent {
+                feed: *feed,
+                weight_bps,
+            })
+            .collect();
+        let (benchmark, create) =
+            instructions::create_benchmark(pdas, &self.payer, BENCHMARK_ID, components);
+        if !self.exists(&benchmark)? {
+            self.client.send(&[create], &[])?;
+        }
+        Ok((feeds, benchmark))
+    }
+}
+
+/// Build the demo environment on the cluster `client` points at
+pub fn bootstrap(
+    client: &VaultClient,
+    options: &BootstrapOptions,
+) -> Result<Bootstrapped, BootstrapError> {
+    let run = Bootstrap {
+        client,
+        payer: client.payer(),
+    };
+    let pdas = client.pdas();
+    let payer = run.payer;
+
+    let (vault, initialize_vault) =
+        instructions::initialize_vault(pdas, &payer, &options.agent_name, false);
+    if run.exists(&vault)? {
+        return Err(BootstrapError::VaultExists(vault));
+    }
+
+    run.fund(options.min_balance)?;
+    let (usdc_mint, usdc_account) = run.mock_mint(6, 1_000_000 * USDC)?;
+    let (sol_mint, sol_account) = run.mock_mint(9, 1_000 * LAMPORTS_PER_SOL)?;
+    let config_authority = run.config()?;
+    run.registries(&usdc_mint)?;
+    let (feeds, benchmark) = run.feeds()?;
+
+    client.send(
+        &[
+            initialize_vault,
+            instructions::initialize_escrow(pdas, &payer, &vault, &usdc_mint),
+            instructions::initialize_share_mint(pdas, &payer, &vault, 6),
+            instructions::initialize_fee_ledger(pdas, &payer, &vault),
+            instructions::set_vault_benchmark(pdas, &payer, &vault, &benchmark),
+        ],
+        &[],
+    )?;
+
+    let share_account = Keypair::new();
+    let create_share_account = instructions::create_token_account(
+        &payer,
+        &share_account.pubkey(),
+        &pdas.share_mint(&vault),
+        &payer,
+        client
+            .rpc()
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)?,
+    )?;
+    client.send(&create_share_account, &[&share_account])?;
+    client.deposit(
+        &vault,
+        &usdc_account,
+        &share_account.pubkey(),
+        options.deposit,
+    )?;
+
+    // Two sample positions splitting most of the deposit, the first already showing yield
+    let mut positions = Vec::with_capacity(FEEDS.len());
+    for (index, feed) in FEEDS.iter().enumerate() {
+        let amount = options.deposit * 4 / 10;
+        let (position, open) = instructions::open_position(
+            pdas,
+            &payer,
+            &vault,
+            &usdc_mint,
+            index as u64,
+            PositionParams {
+                protocol: feed.protocol,
+                strategy: feed.strategy,
+                asset: AssetKind::Usdc,
+                amount,
+                target_apy: feed.rate_bps as u16,
+                idempotency_key: None,
+                adapter_program: None,
+            },
+        );
+        client.send(&[open], &[])?;
+        positions.push(position);
+    }
+    let first = positions[0];
+    let principal = options.deposit * 4 / 10;
+    let earned = principal / 100;
+    client.send(
+        &[
+            spl_token::instruction::mint_to(
+                &spl_token::ID,
+                &usdc_mint,
+                &pdas.position_escrow(&first),
+                &payer,
+                &[],
+                earned,
+            )?,
+            // The first mark on a position just opened
+            instructions::update_position(
+                pdas,
+                &payer,
+                &vault,
+                &first,
+                &pdas.asset_entry(&usdc_mint),
+                principal + earned,
+                0,
+            ),
+        ],
+        &[],
+    )?;
+
+    Ok(Bootstrapped {
+        usdc_mint,
+        sol_mint,
+        usdc_account,
+        sol_account,
+        config: pdas.config(),
+        config_authority,
+        feeds,
+        benchmark,
+        vault,
+        share_account: share_account.pubkey(),
+        positions,
+    })
+}
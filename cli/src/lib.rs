@@ -0,0 +1,3 @@
+//! Command-line tooling for MoluscoYield integrators.
+
+pub mod bootstrap;
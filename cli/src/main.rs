@@ -0,0 +1,100 @@
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use moluscoyield_cli::bootstrap::{bootstrap, BootstrapOptions};
+use moluscoyield_client::cluster::{Cluster, ClusterConfig};
+use moluscoyield_client::vault_client::{SendConfig, VaultClient};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::read_keypair_file;
+
+const USAGE: &str = "\
+MoluscoYield CLI
+
+Usage:
+  molusco bootstrap-devnet [--keypair PATH] [--agent-name NAME]
+
+Environment:
+  MOLUSCO_RPC_URL      override the devnet RPC endpoint
+  MOLUSCO_PROGRAM_ID   override the devnet program ID";
+
+fn default_keypair_path() -> String {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    format!("{}/.config/solana/id.json", home)
+}
+
+fn bootstrap_devnet(args: &[String]) -> Result<(), String> {
+    let mut keypair_path = default_keypair_path();
+    let mut options = BootstrapOptions::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        match flag.as_str() {
+            "--keypair" => keypair_path = value.clone(),
+            "--agent-name" => options.agent_name = value.clone(),
+            other => return Err(format!("unknown option {}\n\n{}", other, USAGE)),
+        }
+    }
+
+    // Always devnet, whatever MOLUSCO_CLUSTER says; the endpoint overrides still apply
+    let mut cluster = ClusterConfig::new(Cluster::Devnet);
+    if let Ok(rpc_url) = std::env::var("MOLUSCO_RPC_URL") {
+        cluster.rpc_url = rpc_url;
+    }
+    if let Ok(program_id) = std::env::var("MOLUSCO_PROGRAM_ID") {
+        cluster.program_id = Pubkey::from_str(&program_id)
+            .map_err(|_| format!("invalid MOLUSCO_PROGRAM_ID: {}", program_id))?;
+    }
+    let payer = read_keypair_file(&keypair_path)
+        .map_err(|e| format!("cannot read keypair {}: {}", keypair_path, e))?;
+    let client = VaultClient::for_cluster(&cluster, payer, SendConfig::default());
+
+    println!(
+        "Bootstrapping devnet at {} (program {})",
+        cluster.rpc_url, cluster.program_id
+    );
+    let env = bootstrap(&client, &options).map_err(|e| e.to_string())?;
+
+    println!(
+        "  mock USDC mint   {}  (your account {})",
+        env.usdc_mint, env.usdc_account
+    );
+    println!(
+        "  mock SOL mint    {}  (your account {})",
+        env.sol_mint, env.sol_account
+    );
+    if env.config_authority {
+        println!("  config           {}  (job queue ready)", env.config);
+    } else {
+        println!(
+            "  config           {}  (owned by another authority; registries skipped)",
+            env.config
+        );
+    }
+    for feed in &env.feeds {
+        println!("  rate feed        {}", feed);
+    }
+    println!("  benchmark        {}", env.benchmark);
+    println!("  vault            {}  ({})", env.vault, options.agent_name);
+    println!("  vault shares     {}", env.share_account);
+    for position in &env.positions {
+        println!("  position         {}", position);
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("bootstrap-devnet") => bootstrap_devnet(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
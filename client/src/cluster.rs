@@ -0,0 +1,256 @@
+//! Cluster selection and program-ID-aware PDA derivation.
+//!
+//! Every deployment is identified by a `ClusterConfig`. The cluster comes from
+//! `MOLUSCO_CLUSTER`, falling back to the one picked by the `devnet`, `testnet`
+//! or `mainnet` crate feature (localnet without any). `MOLUSCO_RPC_URL` and
+//! `MOLUSCO_PROGRAM_ID` override its RPC endpoint and program ID, so tests
+//! against a locally deployed copy run the same code as production.
+
+use std::str::FromStr;
+
+use moluscoyield::{AssetKind, ProtocolKind, StrategyKind};
+use solana_sdk::bpf_loader_upgradeable;
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClusterError {
+    #[error("unknown cluster: {0} (expected localnet, devnet, testnet or mainnet)")]
+    UnknownCluster(String),
+    #[error("invalid program id: {0}")]
+    InvalidProgramId(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cluster {
+    Localnet,
+    Devnet,
+    Testnet,
+    Mainnet,
+}
+
+impl FromStr for Cluster {
+    type Err = ClusterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "localnet" | "localhost" => Ok(Cluster::Localnet),
+            "devnet" => Ok(Cluster::Devnet),
+            "testnet" => Ok(Cluster::Testnet),
+            "mainnet" | "mainnet-beta" => Ok(Cluster::Mainnet),
+            other => Err(ClusterError::UnknownCluster(other.to_string())),
+        }
+    }
+}
+
+impl Cluster {
+    /// Cluster selected at compile time by crate feature
+    pub fn compiled_default() -> Self {
+        if cfg!(feature = "mainnet") {
+            Cluster::Mainnet
+        } else if cfg!(feature = "testnet") {
+            Cluster::Testnet
+        } else if cfg!(feature = "devnet") {
+            Cluster::Devnet
+        } else {
+            Cluster::Localnet
+        }
+    }
+
+    pub fn default_rpc_url(&self) -> &'static str {
+        match self {
+            Cluster::Localnet => "http://127.0.0.1:8899",
+            Cluster::Devnet => "https://api.devnet.solana.com",
+            Cluster::Testnet => "https://api.testnet.solana.com",
+            Cluster::Mainnet => "https://api.mainnet-beta.solana.com",
+        }
+    }
+
+    /// Program ID the cluster's deployment uses unless overridden
+    pub fn default_program_id(&self) -> Pubkey {
+        match self {
+            // Every published deployment currently reuses the declared ID
+            Cluster::Localnet | Cluster::Devnet | Cluster::Testnet | Cluster::Mainnet => {
+                moluscoyield::ID
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClusterConfig {
+    pub cluster: Cluster,
+    pub rpc_url: String,
+    pub program_id: Pubkey,
+}
+
+impl ClusterConfig {
+    pub fn new(cluster: Cluster) -> Self {
+        Self {
+            cluster,
+            rpc_url: cluster.default_rpc_url().to_string(),
+            program_id: cluster.default_program_id(),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, ClusterError> {
+        let cluster = match std::env::var("MOLUSCO_CLUSTER") {
+            Ok(name) => name.parse()?,
+            Err(_) => Cluster::compiled_default(),
+        };
+        let mut config = Self::new(cluster);
+        if let Ok(rpc_url) = std::env::var("MOLUSCO_RPC_URL") {
+            config.rpc_url = rpc_url;
+        }
+        if let Ok(program_id) = std::env::var("MOLUSCO_PROGRAM_ID") {
+            config.program_id = Pubkey::from_str(&program_id)
+                .map_err(|_| ClusterError::InvalidProgramId(program_id))?;
+        }
+        Ok(config)
+    }
+
+    pub fn pdas(&self) -> Pdas {
+        Pdas::new(self.program_id)
+    }
+}
+
+/// PDA derivation for one deployment of the program
+#[derive(Debug, Clone, Copy)]
+pub struct Pdas {
+    pub program_id: Pubkey,
+}
+
+impl Pdas {
+    pub fn new(program_id: Pubkey) -> Self {
+        Self { program_id }
+    }
+
+    fn find(&self, seeds: &[&[u8]]) -> Pubkey {
+        Pubkey::find_program_address(seeds, &self.program_id).0
+    }
+
+    /// The upgradeable loader's data account for the program, naming its upgrade authority
+    pub fn program_data(&self) -> Pubkey {
+        Pubkey::find_program_address(&[self.program_id.as_ref()], &bpf_loader_upgradeable::ID).0
+    }
+
+    pub fn program_config(&self) -> Pubkey {
+        self.find(&[b"program_config"])
+    }
+
+    pub fn protocol_registry(&self) -> Pubkey {
+        self.find(&[b"protocol_registry"])
+    }
+
+    pub fn asset_entry(&self, mint: &Pubkey) -> Pubkey {
+        self.find(&[b"asset", mint.as_ref()])
+    }
+
+    pub fn oracle_config(&self, asset_entry: &Pubkey) -> Pubkey {
+        self.find(&[b"oracle_config", asset_entry.as_ref()])
+    }
+
+    pub fn adapter_config(&self, protocol: ProtocolKind) -> Pubkey {
+        self.find(&[b"adapter", &protocol.seed()])
+    }
+
+    pub fn strategy_capacity(&self, strategy: StrategyKind) -> Pubkey {
+        self.find(&[b"strategy_capacity", &strategy.seed()])
+    }
+
+    pub fn config(&self) -> Pubkey {
+        self.find(&[b"config"])
+    }
+
+    pub fn job_queue(&self) -> Pubkey {
+        self.find(&[b"jobs"])
+    }
+
+    pub fn rate_feed(&self, authority: &Pubkey, label: &str) -> Pubkey {
+        self.find(&[b"rate_feed", authority.as_ref(), label.as_bytes()])
+    }
+
+    pub fn benchmark(&self, creator: &Pubkey, benchmark_id: u64) -> Pubkey {
+        self.find(&[b"benchmark", creator.as_ref(), &benchmark_id.to_le_bytes()])
+    }
+
+    pub fn vault(&self, owner: &Pubkey, agent_name: &str) -> Pubkey {
+        self.find(&[b"vault", owner.as_ref(), agent_name.as_bytes()])
+    }
+
+    pub fn escrow(&self, vault: &Pubkey) -> Pubkey {
+        self.find(&[b"escrow", vault.as_ref()])
+    }
+
+    pub fn sol_escrow(&self, vault: &Pubkey) -> Pubkey {
+        self.find(&[b"sol_escrow", vault.as_ref()])
+    }
+
+    pub fn share_mint(&self, vault: &Pubkey) -> Pubkey {
+        self.find(&[b"share_mint", vault.as_ref()])
+    }
+
+    pub fn rewards_distributor(&self, vault: &Pubkey) -> Pubkey {
+        self.find(&[b"rewards", vault.as_ref()])
+    }
+
+    pub fn vault_meta(&self, vault: &Pubkey) -> Pubkey {
+        self.find(&[b"vault_meta", vault.as_ref()])
+    }
+
+    pub fn agent_profile(&self, operator: &Pubkey) -> Pubkey {
+        self.find(&[b"agent_profile", operator.as_ref()])
+    }
+
+    /// Position opened when the vault's `total_positions_created` was `index`
+    pub fn position(
+        &self,
+        vault: &Pubkey,
+        protocol: ProtocolKind,
+        asset: AssetKind,
+        index: u64,
+    ) -> Pubkey {
+        self.find(&[
+            b"position",
+            vault.as_ref(),
+            &protocol.seed(),
+            &asset.seed(),
+            &index.to_le_bytes(),
+        ])
+    }
+
+    pub fn position_escrow(&self, position: &Pubkey) -> Pubkey {
+        self.find(&[b"position_escrow", position.as_ref()])
+    }
+
+    pub fn impairment(&self, position: &Pubkey, index: u16) -> Pubkey {
+        self.find(&[b"impairment", position.as_ref(), &index.to_le_bytes()])
+    }
+
+    pub fn delegate(&self, vault: &Pubkey, delegate: &Pubkey) -> Pubkey {
+        self.find(&[b"delegate", vault.as_ref(), delegate.as_ref()])
+    }
+
+    pub fn session(&self, vault: &Pubkey, session_key: &Pubkey) -> Pubkey {
+        self.find(&[b"session", vault.as_ref(), session_key.as_ref()])
+    }
+
+    pub fn depositor_record(&self, vault: &Pubkey, depositor: &Pubkey) -> Pubkey {
+        self.find(&[b"depositor", vault.as_ref(), depositor.as_ref()])
+    }
+
+    pub fn withdrawal_request(&self, vault: &Pubkey, index: u64) -> Pubkey {
+        self.find(&[b"withdrawal", vault.as_ref(), &index.to_le_bytes()])
+    }
+
+    pub fn queued_shares(&self, vault: &Pubkey) -> Pubkey {
+        self.find(&[b"queued_shares", vault.as_ref()])
+    }
+
+    pub fn fee_config(&self, vault: &Pubkey) -> Pubkey {
+        self.find(&[b"fee_config", vault.as_ref()])
+    }
+
+    pub fn fee_ledger(&self, vault: &Pubkey) -> Pubkey {
+        self.find(&[b"fee_ledger", vault.as_ref()])
+    }
+}
@@ -0,0 +1,543 @@
+//! Instruction builders for program setup, vault setup and the position lifecycle.
+//!
+//! Every builder derives its PDAs from the `Pdas` it is given, so the same code
+//! targets whichever deployment the caller's `ClusterConfig` points at.
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use moluscoyield::{AssetKind, BenchmarkComponent, Position, ProtocolKind, StrategyKind};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::program_error::ProgramError;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{system_instruction, system_program, sysvar};
+
+use crate::cluster::Pdas;
+
+fn build(pdas: &Pdas, accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: pdas.program_id,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// The program config every state-mutating instruction checks for a global pause
+pub fn guard(pdas: &Pdas) -> moluscoyield::accounts::ProgramGuard {
+    moluscoyield::accounts::ProgramGuard {
+        program_config: pdas.program_config(),
+    }
+}
+
+/// Create and initialize an SPL mint at `mint`; `rent` is its rent-exempt minimum
+pub fn create_mint(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    decimals: u8,
+    rent: u64,
+) -> Result<Vec<Instruction>, ProgramError> {
+    Ok(vec![
+        system_instruction::create_account(
+            payer,
+            mint,
+            rent,
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::initialize_mint2(&spl_token::ID, mint, authority, None, decimals)?,
+    ])
+}
+
+/// Create and initialize an SPL token account at `account`; `rent` is its rent-exempt minimum
+pub fn create_token_account(
+    payer: &Pubkey,
+    account: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    rent: u64,
+) -> Result<Vec<Instruction>, ProgramError> {
+    Ok(vec![
+        system_instruction::create_account(
+            payer,
+            account,
+            rent,
+            spl_token::state::Account::LEN as u64,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::initialize_account3(&spl_token::ID, account, mint, owner)?,
+    ])
+}
+
+pub fn initialize_program_config(pdas: &Pdas, admin: &Pubkey) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::InitializeProgramConfig {
+            admin: *admin,
+            program_config: pdas.program_config(),
+            program: pdas.program_id,
+            program_data: pdas.program_data(),
+            system_program: system_program::ID,
+        },
+        moluscoyield::instruction::InitializeProgramConfig {},
+    )
+}
+
+pub fn initialize_protocol_registry(pdas: &Pdas, admin: &Pubkey) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::InitializeProtocolRegistry {
+            admin: *admin,
+            program_config: pdas.program_config(),
+            protocol_registry: pdas.protocol_registry(),
+            system_program: system_program::ID,
+        },
+        moluscoyield::instruction::InitializeProtocolRegistry {},
+    )
+}
+
+/// Approve `protocol` for new positions; the program admin signs
+pub fn whitelist_protocol(pdas: &Pdas, admin: &Pubkey, protocol: ProtocolKind) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::UpdateProtocolRegistry {
+            admin: *admin,
+            program_config: pdas.program_config(),
+            protocol_registry: pdas.protocol_registry(),
+        },
+        moluscoyield::instruction::WhitelistProtocol { protocol },
+    )
+}
+
+/// Register `mint` in the asset registry as `kind`; the program admin signs
+pub fn register_asset(
+    pdas: &Pdas,
+    admin: &Pubkey,
+    mint: &Pubkey,
+    kind: AssetKind,
+    symbol: &str,
+) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::RegisterAsset {
+            admin: *admin,
+            program_config: pdas.program_config(),
+            mint: *mint,
+            asset_entry: pdas.asset_entry(mint),
+            system_program: system_program::ID,
+        },
+        moluscoyield::instruction::RegisterAsset {
+            kind,
+            symbol: symbol.to_string(),
+        },
+    )
+}
+
+/// Set (or create) `strategy`'s capacity; the program admin signs
+pub fn set_strategy_capacity(
+    pdas: &Pdas,
+    admin: &Pubkey,
+    strategy: StrategyKind,
+    max_tvl: u128,
+) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::SetStrategyCapacity {
+            admin: *admin,
+            program_config: pdas.program_config(),
+            strategy_capacity: pdas.strategy_capacity(strategy),
+            system_program: system_program::ID,
+        },
+        moluscoyield::instruction::SetStrategyCapacity { strategy, max_tvl },
+    )
+}
+
+pub fn initialize_config(pdas: &Pdas, authority: &Pubkey) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::InitializeConfig {
+            authority: *authority,
+            config: pdas.config(),
+            program: pdas.program_id,
+            program_data: pdas.program_data(),
+            system_program: system_program::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::InitializeConfig {},
+    )
+}
+
+pub fn initialize_job_queue(pdas: &Pdas, authority: &Pubkey) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::InitializeJobQueue {
+            authority: *authority,
+            config: pdas.config(),
+            job_queue: pdas.job_queue(),
+            system_program: system_program::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::InitializeJobQueue {},
+    )
+}
+
+pub fn create_rate_feed(
+    pdas: &Pdas,
+    authority: &Pubkey,
+    label: &str,
+    rate_bps: u32,
+) -> (Pubkey, Instruction) {
+    let feed = pdas.rate_feed(authority, label);
+    let instruction = build(
+        pdas,
+        moluscoyield::accounts::CreateRateFeed {
+            authority: *authority,
+            feed,
+            system_program: system_program::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::CreateRateFeed {
+            label: label.to_string(),
+            rate_bps,
+        },
+    );
+    (feed, instruction)
+}
+
+pub fn create_benchmark(
+    pdas: &Pdas,
+    creator: &Pubkey,
+    benchmark_id: u64,
+    components: Vec<BenchmarkComponent>,
+) -> (Pubkey, Instruction) {
+    let benchmark = pdas.benchmark(creator, benchmark_id);
+    let instruction = build(
+        pdas,
+        moluscoyield::accounts::CreateBenchmark {
+            creator: *creator,
+            benchmark,
+            system_program: system_program::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::CreateBenchmark {
+            benchmark_id,
+            components,
+        },
+    );
+    (benchmark, instruction)
+}
+
+/// `initialize_vault`, plus the vault's address
+pub fn initialize_vault(
+    pdas: &Pdas,
+    owner: &Pubkey,
+    agent_name: &str,
+    simulation: bool,
+) -> (Pubkey, Instruction) {
+    let vault = pdas.vault(owner, agent_name);
+    let instruction = build(
+        pdas,
+        moluscoyield::accounts::InitializeVault {
+            owner: *owner,
+            vault,
+            system_program: system_program::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::InitializeVault {
+            agent_name: agent_name.to_string(),
+            simulation,
+        },
+    );
+    (vault, instruction)
+}
+
+pub fn initialize_escrow(
+    pdas: &Pdas,
+    owner: &Pubkey,
+    vault: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::InitializeEscrow {
+            owner: *owner,
+            vault: *vault,
+            mint: *mint,
+            escrow: pdas.escrow(vault),
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::InitializeEscrow {},
+    )
+}
+
+pub fn initialize_share_mint(
+    pdas: &Pdas,
+    owner: &Pubkey,
+    vault: &Pubkey,
+    decimals: u8,
+) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::InitializeShareMint {
+            owner: *owner,
+            vault: *vault,
+            share_mint: pdas.share_mint(vault),
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::InitializeShareMint { decimals },
+    )
+}
+
+pub fn initialize_fee_ledger(pdas: &Pdas, owner: &Pubkey, vault: &Pubkey) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::InitializeFeeLedger {
+            owner: *owner,
+            vault: *vault,
+            fee_ledger: pdas.fee_ledger(vault),
+            system_program: system_program::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::InitializeFeeLedger {},
+    )
+}
+
+pub fn set_vault_benchmark(
+    pdas: &Pdas,
+    owner: &Pubkey,
+    vault: &Pubkey,
+    benchmark: &Pubkey,
+) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::SetVaultBenchmark {
+            owner: *owner,
+            vault: *vault,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::SetVaultBenchmark {
+            benchmark: *benchmark,
+        },
+    )
+}
+
+/// Deposit into a vault of `mint` whose escrow and share mint sit at their canonical PDAs
+pub fn deposit(
+    pdas: &Pdas,
+    depositor: &Pubkey,
+    vault: &Pubkey,
+    mint: &Pubkey,
+    depositor_token_account: &Pubkey,
+    depositor_share_account: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::Deposit {
+            depositor: *depositor,
+            vault: *vault,
+            escrow: pdas.escrow(vault),
+            depositor_token_account: *depositor_token_account,
+            share_mint: pdas.share_mint(vault),
+            mint: *mint,
+            depositor_share_account: *depositor_share_account,
+            depositor_record: pdas.depositor_record(vault, depositor),
+            fee_ledger: pdas.fee_ledger(vault),
+            rewards_distributor: pdas.rewards_distributor(vault),
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::Deposit { amount },
+    )
+}
+
+/// What a new position holds
+#[derive(Debug, Clone)]
+pub struct PositionParams {
+    pub protocol: ProtocolKind,
+    pub strategy: StrategyKind,
+    /// Kind the vault mint is registered under; it seeds the position address
+    pub asset: AssetKind,
+    pub amount: u64,
+    pub target_apy: u16,
+    /// Client-generated key the vault rejects if it has seen it recently
+    pub idempotency_key: Option<[u8; 16]>,
+    /// Program registered as the protocol's adapter, required once one is
+    pub adapter_program: Option<Pubkey>,
+}
+
+/// `open_position` on a funded vault, plus the position's address
+///
+/// `index` is the vault's `total_positions_created` before the open; it seeds the address.
+pub fn open_position(
+    pdas: &Pdas,
+    authority: &Pubkey,
+    vault: &Pubkey,
+    mint: &Pubkey,
+    index: u64,
+    params: PositionParams,
+) -> (Pubkey, Instruction) {
+    let position = pdas.position(vault, params.protocol, params.asset, index);
+    let instruction = build(
+        pdas,
+        moluscoyield::accounts::OpenPosition {
+            authority: *authority,
+            vault: *vault,
+            delegation: None,
+            position,
+            adapter_config: pdas.adapter_config(params.protocol),
+            adapter_program: params.adapter_program,
+            escrow: pdas.escrow(vault),
+            position_escrow: pdas.position_escrow(&position),
+            mint: *mint,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            protocol_registry: pdas.protocol_registry(),
+            asset_entry: pdas.asset_entry(mint),
+            strategy_capacity: pdas.strategy_capacity(params.strategy),
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::OpenPosition {
+            protocol: params.protocol,
+            strategy: params.strategy,
+            amount: params.amount,
+            target_apy: params.target_apy,
+            idempotency_key: params.idempotency_key,
+        },
+    );
+    (position, instruction)
+}
+
+/// `update_position`; `sequence` is the position's `update_sequence` as last read
+/// `update_position`; `asset_entry` is the position's, whose oracle config must not exist
+pub fn update_position(
+    pdas: &Pdas,
+    authority: &Pubkey,
+    vault: &Pubkey,
+    position: &Pubkey,
+    asset_entry: &Pubkey,
+    current_value: u64,
+    sequence: u64,
+) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::UpdatePosition {
+            authority: *authority,
+            vault: *vault,
+            delegation: None,
+            session: None,
+            position: *position,
+            adapter_config: None,
+            adapter_program: None,
+            oracle_config: pdas.oracle_config(asset_entry),
+            fee_ledger: pdas.fee_ledger(vault),
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::UpdatePosition {
+            current_value,
+            sequence,
+        },
+    )
+}
+
+/// `update_position_with_oracle` for a position in the vault's own `mint`; `oracles`
+/// are the price accounts of the mint's configured feeds, in the config's order
+pub fn update_position_with_oracle(
+    pdas: &Pdas,
+    authority: &Pubkey,
+    vault: &Pubkey,
+    position: &Pubkey,
+    mint: &Pubkey,
+    oracles: &[Pubkey],
+    sequence: u64,
+) -> Instruction {
+    let mut instruction = build(
+        pdas,
+        moluscoyield::accounts::UpdatePositionWithOracle {
+            authority: *authority,
+            vault: *vault,
+            delegation: None,
+            session: None,
+            position: *position,
+            position_escrow: pdas.position_escrow(position),
+            oracle_config: pdas.oracle_config(&pdas.asset_entry(mint)),
+            fee_ledger: pdas.fee_ledger(vault),
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::UpdatePositionWithOracle { sequence },
+    );
+    instruction.accounts.extend(
+        oracles
+            .iter()
+            .map(|oracle| AccountMeta::new_readonly(*oracle, false)),
+    );
+    instruction
+}
+
+/// `close_position`, freeing the capacity the position used in `strategy`
+pub fn close_position(
+    pdas: &Pdas,
+    authority: &Pubkey,
+    vault: &Pubkey,
+    position: &Pubkey,
+    asset_entry: &Pubkey,
+    strategy: StrategyKind,
+) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::ClosePosition {
+            authority: *authority,
+            vault: *vault,
+            delegation: None,
+            position: *position,
+            escrow: pdas.escrow(vault),
+            position_escrow: pdas.position_escrow(position),
+            fee_ledger: pdas.fee_ledger(vault),
+            strategy_capacity: pdas.strategy_capacity(strategy),
+            oracle_config: pdas.oracle_config(asset_entry),
+            token_program: spl_token::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::ClosePosition {},
+    )
+}
+
+/// `settle_and_close`: a final mark of `position`, currently `state`, at `current_value`,
+/// then `close_position`
+pub fn settle_and_close(
+    pdas: &Pdas,
+    authority: &Pubkey,
+    vault: &Pubkey,
+    position: &Pubkey,
+    state: &Position,
+    current_value: u64,
+) -> Instruction {
+    build(
+        pdas,
+        moluscoyield::accounts::ClosePosition {
+            authority: *authority,
+            vault: *vault,
+            delegation: None,
+            position: *position,
+            escrow: pdas.escrow(vault),
+            position_escrow: pdas.position_escrow(position),
+            fee_ledger: pdas.fee_ledger(vault),
+            strategy_capacity: pdas.strategy_capacity(state.strategy),
+            oracle_config: pdas.oracle_config(&state.asset_entry),
+            token_program: spl_token::ID,
+            guard: guard(pdas),
+        },
+        moluscoyield::instruction::SettleAndClose {
+            current_value,
+            sequence: state.update_sequence,
+        },
+    )
+}
@@ -0,0 +1,6 @@
+//! Client-side tooling for MoluscoYield vaults.
+
+pub mod cluster;
+pub mod instructions;
+pub mod replay;
+pub mod vault_client;
@@ -0,0 +1,236 @@
+//! Deterministic replay of a vault's event stream.
+//!
+//! Events are read back from transaction logs (`Program data: <base64>` lines
+//! written by `emit!`), folded in slot order into a `ReplayState`, and the
+//! result is diffed against the vault account to surface accounting drift.
+
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use moluscoyield::{
+    FeeChargedEvent, FlowKind, ImpairmentRecordedEvent, PositionClosedEvent,
+    PositionRolledOverEvent, Vault, VaultFlowEvent,
+};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::UiTransactionEncoding;
+use std::str::FromStr;
+
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("rpc error: {0}")]
+    Rpc(#[from] solana_client::client_error::ClientError),
+    #[error("invalid signature: {0}")]
+    Signature(String),
+    #[error("account is not a vault: {0}")]
+    NotAVault(String),
+}
+
+/// Events that change vault accounting
+#[derive(Debug, Clone)]
+pub enum VaultEvent {
+    Flow(VaultFlowEvent),
+    PositionClosed(PositionClosedEvent),
+    PositionRolledOver(PositionRolledOverEvent),
+    Impairment(ImpairmentRecordedEvent),
+    FeeCharged(FeeChargedEvent),
+}
+
+impl VaultEvent {
+    pub fn vault(&self) -> Pubkey {
+        match self {
+            VaultEvent::Flow(e) => e.vault,
+            VaultEvent::PositionClosed(e) => e.vault,
+            VaultEvent::PositionRolledOver(e) => e.vault,
+            VaultEvent::Impairment(e) => e.vault,
+            VaultEvent::FeeCharged(e) => e.vault,
+        }
+    }
+}
+
+fn decode<T: AnchorDeserialize + Discriminator>(data: &[u8]) -> Option<T> {
+    if data.len() < 8 || data[..8] != T::DISCRIMINATOR {
+        return None;
+    }
+    T::deserialize(&mut &data[8..]).ok()
+}
+
+/// Decode one log line into an accounting event, if it is one
+pub fn decode_event(line: &str) -> Option<VaultEvent> {
+    let data = STANDARD.decode(line.strip_prefix(PROGRAM_DATA_PREFIX)?).ok()?;
+    decode::<VaultFlowEvent>(&data)
+        .map(VaultEvent::Flow)
+        .or_else(|| decode::<PositionClosedEvent>(&data).map(VaultEvent::PositionClosed))
+        .or_else(|| decode::<PositionRolledOverEvent>(&data).map(VaultEvent::PositionRolledOver))
+        .or_else(|| decode::<ImpairmentRecordedEvent>(&data).map(VaultEvent::Impairment))
+        .or_else(|| decode::<FeeChargedEvent>(&data).map(VaultEvent::FeeCharged))
+}
+
+/// TVL observed on a flow that does not follow from the replayed TVL before it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TvlGap {
+    pub slot: u64,
+    pub expected_before: u128,
+    pub observed_before: u128,
+}
+
+/// Vault accounting reconstructed from events alone
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayState {
+    pub slot: u64,
+    pub total_value_locked: u128,
+    pub cumulative_inflows: u128,
+    pub cumulative_outflows: u128,
+    pub cumulative_fees: u128,
+    pub total_impaired: u64,
+    pub positions_closed: u64,
+    pub rollovers: u64,
+    pub tvl_gaps: Vec<TvlGap>,
+}
+
+impl ReplayState {
+    pub fn apply(&mut self, slot: u64, event: &VaultEvent) {
+        self.slot = slot;
+        match event {
+            VaultEvent::Flow(flow) => {
+                let observed_before = match flow.kind {
+                    FlowKind::Deposit | FlowKind::TransferIn => {
+                        self.cumulative_inflows += flow.amount as u128;
+                        flow.total_value_locked.wrapping_sub(flow.amount as u128)
+                    }
+                    FlowKind::Withdraw => {
+                        self.cumulative_outflows += (flow.amount - flow.fee) as u128;
+                        self.cumulative_fees += flow.fee as u128;
+                        flow.total_value_locked.wrapping_add(flow.amount as u128)
+                    }
+                    FlowKind::TransferOut => {
+                        self.cumulative_outflows += flow.amount as u128;
+                        flow.total_value_locked.wrapping_add(flow.amount as u128)
+                    }
+                };
+                // Anything between two flows (yield, impairments, shadow opens) shows up here
+                if observed_before != self.total_value_locked {
+                    self.tvl_gaps.push(TvlGap {
+                        slot,
+                        expected_before: self.total_value_locked,
+                        observed_before,
+                    });
+                }
+                self.total_value_locked = flow.total_value_locked;
+            }
+            VaultEvent::PositionClosed(_) => self.positions_closed += 1,
+            VaultEvent::PositionRolledOver(_) => self.rollovers += 1,
+            VaultEvent::Impairment(impairment) => {
+                self.total_impaired += impairment.write_down;
+            }
+            VaultEvent::FeeCharged(fee) => self.cumulative_fees += fee.fee_amount as u128,
+        }
+    }
+}
+
+/// Fold `events` (in slot order) for `vault` up to and including `until_slot`
+pub fn replay(events: &[(u64, VaultEvent)], vault: &Pubkey, until_slot: u64) -> ReplayState {
+    let mut state = ReplayState::default();
+    for (slot, event) in events {
+        if *slot > until_slot {
+            break;
+        }
+        if event.vault() == *vault {
+            state.apply(*slot, event);
+        }
+    }
+    state
+}
+
+/// A counter whose replayed value disagrees with the account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub field: &'static str,
+    pub replayed: u128,
+    pub on_chain: u128,
+}
+
+/// Compare a full replay against the vault account at the same slot
+pub fn diff(replayed: &ReplayState, vault: &Vault) -> Vec<Divergence> {
+    let fields = [
+        ("cumulative_inflows", replayed.cumulative_inflows, vault.cumulative_inflows),
+        ("cumulative_outflows", replayed.cumulative_outflows, vault.cumulative_outflows),
+        ("cumulative_fees", replayed.cumulative_fees, vault.cumulative_fees),
+        ("total_impaired", replayed.total_impaired as u128, vault.total_impaired as u128),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, replayed, on_chain)| replayed != on_chain)
+        .map(|(field, replayed, on_chain)| Divergence {
+            field,
+            replayed,
+            on_chain,
+        })
+        .collect()
+}
+
+/// Fetch every accounting event touching `vault`, oldest first
+pub fn fetch_vault_events(
+    rpc: &RpcClient,
+    vault: &Pubkey,
+) -> Result<Vec<(u64, VaultEvent)>, ReplayError> {
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let page = rpc.get_signatures_for_address_with_config(
+            vault,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: None,
+                limit: None,
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )?;
+        let Some(last) = page.last() else { break };
+        before = Some(
+            Signature::from_str(&last.signature)
+                .map_err(|_| ReplayError::Signature(last.signature.clone()))?,
+        );
+        signatures.extend(page.into_iter().filter(|s| s.err.is_none()));
+    }
+    signatures.reverse();
+
+    let mut events = Vec::new();
+    for status in signatures {
+        let signature = Signature::from_str(&status.signature)
+            .map_err(|_| ReplayError::Signature(status.signature.clone()))?;
+        let tx = rpc.get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )?;
+        let logs = match tx.transaction.meta.map(|meta| meta.log_messages) {
+            Some(OptionSerializer::Some(logs)) => logs,
+            _ => continue,
+        };
+        events.extend(logs.iter().filter_map(|line| decode_event(line)).map(|e| (tx.slot, e)));
+    }
+    Ok(events)
+}
+
+/// Replay the vault's full history and diff it against its current account state
+pub fn check_vault(rpc: &RpcClient, vault: &Pubkey) -> Result<(ReplayState, Vec<Divergence>), ReplayError> {
+    let slot = rpc.get_slot()?;
+    let account = rpc.get_account(vault)?;
+    let on_chain = Vault::try_deserialize(&mut account.data.as_slice())
+        .map_err(|e| ReplayError::NotAVault(e.to_string()))?;
+
+    let events = fetch_vault_events(rpc, vault)?;
+    let replayed = replay(&events, vault, slot);
+    let divergences = diff(&replayed, &on_chain);
+    Ok((replayed, divergences))
+}
@@ -0,0 +1,275 @@
+//! Transaction sending for vault automation.
+//!
+//! `VaultClient::send` wraps instructions with compute-budget instructions,
+//! signs against a fresh blockhash on every attempt, and resubmits with
+//! exponential backoff and an escalating compute-unit price until the
+//! transaction lands or the attempts run out. Program errors are not retried.
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anchor_lang::AccountDeserialize;
+use moluscoyield::Vault;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+use crate::cluster::{ClusterConfig, Pdas};
+use crate::instructions;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SendError {
+    #[error("rpc error: {0}")]
+    Rpc(#[from] ClientError),
+    #[error("transaction failed: {0}")]
+    Failed(TransactionError),
+    #[error("preflight simulation failed: {0}")]
+    Preflight(String),
+    #[error("not confirmed after {0} attempts")]
+    Exhausted(u32),
+    #[error("account is not a vault: {0}")]
+    NotAVault(String),
+}
+
+/// Retry, preflight and priority-fee policy for `VaultClient::send`
+#[derive(Debug, Clone)]
+pub struct SendConfig {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub skip_preflight: bool,
+    pub preflight_commitment: CommitmentLevel,
+    pub commitment: CommitmentConfig,
+    /// `None` leaves the runtime's default compute-unit limit
+    pub compute_unit_limit: Option<u32>,
+    /// Compute-unit price of the first attempt, in micro-lamports; 0 sends no price
+    pub compute_unit_price: u64,
+    /// Price multiplier per retry, in bps (15_000 = +50% per attempt)
+    pub compute_unit_price_escalation_bps: u32,
+    pub max_compute_unit_price: u64,
+    /// How often to poll signature status while waiting for confirmation
+    pub poll_interval: Duration,
+}
+
+impl Default for SendConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+            skip_preflight: false,
+            preflight_commitment: CommitmentLevel::Confirmed,
+            commitment: CommitmentConfig::confirmed(),
+            compute_unit_limit: None,
+            compute_unit_price: 0,
+            compute_unit_price_escalation_bps: 15_000,
+            max_compute_unit_price: 1_000_000,
+            poll_interval: Duration::from_millis(400),
+        }
+    }
+}
+
+impl SendConfig {
+    /// Delay before retry number `attempt` (0 = first retry), doubling up to `max_backoff`
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// Compute-unit price for attempt number `attempt` (0 = first send)
+    pub fn compute_unit_price_for(&self, attempt: u32) -> u64 {
+        let mut price = self.compute_unit_price as u128;
+        for _ in 0..attempt {
+            price = price * self.compute_unit_price_escalation_bps as u128 / 10_000;
+            if price >= self.max_compute_unit_price as u128 {
+                break;
+            }
+        }
+        price.min(self.max_compute_unit_price as u128) as u64
+    }
+
+    fn budget_instructions(&self, attempt: u32) -> Vec<Instruction> {
+        let mut instructions = Vec::with_capacity(2);
+        if let Some(units) = self.compute_unit_limit {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(units));
+        }
+        let price = self.compute_unit_price_for(attempt);
+        if price > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+        }
+        instructions
+    }
+}
+
+/// Errors the program or runtime would return again on resubmission
+fn is_permanent(error: &ClientError) -> Option<SendError> {
+    match error.kind() {
+        ClientErrorKind::TransactionError(e) => Some(SendError::Failed(e.clone())),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            message,
+            ..
+        }) => match &result.err {
+            // A stale blockhash fails preflight too; a fresh one fixes it
+            Some(TransactionError::BlockhashNotFound) => None,
+            _ => Some(SendError::Preflight(message.clone())),
+        },
+        _ => None,
+    }
+}
+
+pub struct VaultClient {
+    rpc: RpcClient,
+    payer: Keypair,
+    pdas: Pdas,
+    config: SendConfig,
+}
+
+impl VaultClient {
+    pub fn new(rpc: RpcClient, payer: Keypair, program_id: Pubkey, config: SendConfig) -> Self {
+        Self {
+            rpc,
+            payer,
+            pdas: Pdas::new(program_id),
+            config,
+        }
+    }
+
+    /// Client for the deployment described by `cluster`
+    pub fn for_cluster(cluster: &ClusterConfig, payer: Keypair, config: SendConfig) -> Self {
+        let rpc = RpcClient::new_with_commitment(cluster.rpc_url.clone(), config.commitment);
+        Self::new(rpc, payer, cluster.program_id, config)
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.pdas.program_id
+    }
+
+    pub fn pdas(&self) -> &Pdas {
+        &self.pdas
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    pub fn fetch_vault(&self, vault: &Pubkey) -> Result<Vault, SendError> {
+        let account = self.rpc.get_account(vault)?;
+        Vault::try_deserialize(&mut account.data.as_slice())
+            .map_err(|e| SendError::NotAVault(e.to_string()))
+    }
+
+    /// Send `instructions` signed by the payer and `signers`, retrying per the config
+    pub fn send(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Signature, SendError> {
+        let config = &self.config;
+        for attempt in 0..config.max_attempts {
+            if attempt > 0 {
+                sleep(config.backoff(attempt - 1));
+            }
+
+            let mut all = config.budget_instructions(attempt);
+            all.extend_from_slice(instructions);
+            let (blockhash, last_valid_block_height) = self
+                .rpc
+                .get_latest_blockhash_with_commitment(config.commitment)?;
+            let mut keys: Vec<&Keypair> = vec![&self.payer];
+            keys.extend_from_slice(signers);
+            let tx = Transaction::new_signed_with_payer(
+                &all,
+                Some(&self.payer.pubkey()),
+                &keys,
+                blockhash,
+            );
+
+            let sent = self.rpc.send_transaction_with_config(
+                &tx,
+                RpcSendTransactionConfig {
+                    skip_preflight: config.skip_preflight,
+                    preflight_commitment: Some(config.preflight_commitment),
+                    // Resubmission is ours; the RPC node should not rebroadcast stale copies
+                    max_retries: Some(0),
+                    ..Default::default()
+                },
+            );
+            let signature = match sent {
+                Ok(signature) => signature,
+                Err(e) => match is_permanent(&e) {
+                    Some(permanent) => return Err(permanent),
+                    None => continue,
+                },
+            };
+
+            if self.confirm(&signature, last_valid_block_height)? {
+                return Ok(signature);
+            }
+        }
+        Err(SendError::Exhausted(config.max_attempts))
+    }
+
+    /// Wait for `signature` until its blockhash expires; false means resend
+    fn confirm(
+        &self,
+        signature: &Signature,
+        last_valid_block_height: u64,
+    ) -> Result<bool, SendError> {
+        let started = Instant::now();
+        loop {
+            let statuses = self.rpc.get_signature_statuses(&[*signature])?;
+            if let Some(Some(status)) = statuses.value.first() {
+                if let Some(err) = &status.err {
+                    return Err(SendError::Failed(err.clone()));
+                }
+                if status.satisfies_commitment(self.config.commitment) {
+                    return Ok(true);
+                }
+            }
+            if self.rpc.get_block_height()? > last_valid_block_height {
+                return Ok(false);
+            }
+            // Guard against a node whose block height stalls
+            if started.elapsed() > Duration::from_secs(90) {
+                return Ok(false);
+            }
+            sleep(self.config.poll_interval);
+        }
+    }
+
+    /// Deposit `amount` of the vault's mint for shares, opening the depositor record if needed
+    pub fn deposit(
+        &self,
+        vault: &Pubkey,
+        depositor_token_account: &Pubkey,
+        depositor_share_account: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature, SendError> {
+        let mint = self.fetch_vault(vault)?.mint;
+        let instruction = instructions::deposit(
+            &self.pdas,
+            &self.payer.pubkey(),
+            vault,
+            &mint,
+            depositor_token_account,
+            depositor_share_account,
+            amount,
+        );
+        self.send(&[instruction], &[])
+    }
+}
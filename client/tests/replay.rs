@@ -0,0 +1,55 @@
+use anchor_lang::prelude::Pubkey;
+use moluscoyield::{FlowKind, VaultFlowEvent};
+use moluscoyield_client::replay::{replay, TvlGap, VaultEvent};
+
+fn flow(vault: Pubkey, kind: FlowKind, amount: u64, fee: u64, tvl_after: u128) -> VaultEvent {
+    VaultEvent::Flow(VaultFlowEvent {
+        vault,
+        kind,
+        amount,
+        fee,
+        shares: 0,
+        total_value_locked: tvl_after,
+        timestamp: 0,
+    })
+}
+
+#[test]
+fn test_replay_reconstructs_flows() {
+    let vault = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let events = vec![
+        (10, flow(vault, FlowKind::Deposit, 1_000, 0, 1_000)),
+        (11, flow(other, FlowKind::Deposit, 5_000, 0, 5_000)),
+        (12, flow(vault, FlowKind::Withdraw, 400, 4, 600)),
+        (20, flow(vault, FlowKind::Deposit, 100, 0, 700)),
+    ];
+
+    let state = replay(&events, &vault, 15);
+    assert_eq!(state.slot, 12);
+    assert_eq!(state.total_value_locked, 600);
+    assert_eq!(state.cumulative_inflows, 1_000);
+    assert_eq!(state.cumulative_outflows, 396);
+    assert_eq!(state.cumulative_fees, 4);
+    assert!(state.tvl_gaps.is_empty());
+}
+
+#[test]
+fn test_replay_flags_unexplained_tvl_changes() {
+    let vault = Pubkey::new_unique();
+    // TVL grew by 50 between the two deposits without any event explaining it
+    let events = vec![
+        (1, flow(vault, FlowKind::Deposit, 1_000, 0, 1_000)),
+        (2, flow(vault, FlowKind::Deposit, 100, 0, 1_150)),
+    ];
+
+    let state = replay(&events, &vault, u64::MAX);
+    assert_eq!(
+        state.tvl_gaps,
+        vec![TvlGap {
+            slot: 2,
+            expected_before: 1_000,
+            observed_before: 1_050,
+        }]
+    );
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use moluscoyield_client::vault_client::SendConfig;
+
+#[test]
+fn test_backoff_doubles_up_to_cap() {
+    let config = SendConfig {
+        initial_backoff: Duration::from_millis(500),
+        max_backoff: Duration::from_secs(3),
+        ..Default::default()
+    };
+    assert_eq!(config.backoff(0), Duration::from_millis(500));
+    assert_eq!(config.backoff(1), Duration::from_secs(1));
+    assert_eq!(config.backoff(2), Duration::from_secs(2));
+    assert_eq!(config.backoff(3), Duration::from_secs(3));
+    assert_eq!(config.backoff(40), Duration::from_secs(3));
+}
+
+#[test]
+fn test_compute_unit_price_escalates_to_cap() {
+    let config = SendConfig {
+        compute_unit_price: 10_000,
+        compute_unit_price_escalation_bps: 20_000,
+        max_compute_unit_price: 50_000,
+        ..Default::default()
+    };
+    assert_eq!(config.compute_unit_price_for(0), 10_000);
+    assert_eq!(config.compute_unit_price_for(1), 20_000);
+    assert_eq!(config.compute_unit_price_for(2), 40_000);
+    assert_eq!(config.compute_unit_price_for(3), 50_000);
+
+    // No base price means no priority fee on any attempt
+    let free = SendConfig::default();
+    assert_eq!(free.compute_unit_price_for(4), 0);
+}
+
+#[test]
+fn test_pdas_follow_program_id() {
+    use moluscoyield_client::cluster::{Cluster, ClusterConfig, Pdas};
+    use solana_sdk::pubkey::Pubkey;
+
+    let owner = Pubkey::new_unique();
+    let mainnet = ClusterConfig::new(Cluster::Mainnet);
+    assert_eq!(mainnet.program_id, moluscoyield::ID);
+    assert_eq!("mainnet-beta".parse::<Cluster>().unwrap(), Cluster::Mainnet);
+    assert!("moonnet".parse::<Cluster>().is_err());
+
+    // The same owner and name map to different vaults under another deployment
+    let local = Pdas::new(Pubkey::new_unique());
+    assert_ne!(
+        mainnet.pdas().vault(&owner, "molusco"),
+        local.vault(&owner, "molusco")
+    );
+    assert_eq!(
+        mainnet.pdas().vault(&owner, "molusco"),
+        Pubkey::find_program_address(&[b"vault", owner.as_ref(), b"molusco"], &moluscoyield::ID).0
+    );
+}
+
+#[test]
+fn test_position_pdas_stay_distinct_past_u8() {
+    use moluscoyield::{AssetKind, ProtocolKind};
+    use moluscoyield_client::cluster::Pdas;
+    use solana_sdk::pubkey::Pubkey;
+
+    let pdas = Pdas::new(moluscoyield::ID);
+    let vault = Pubkey::new_unique();
+    let at = |index| pdas.position(&vault, ProtocolKind::Kamino, AssetKind::Usdc, index);
+
+    // A one-byte seed wrapped here and collided with the first position
+    assert_ne!(at(0), at(256));
+    assert_ne!(at(255), at(256));
+    assert_ne!(at(1), at(1 << 32));
+}
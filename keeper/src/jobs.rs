@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use moluscoyield::{Job, JobQueue, TaskType};
+use solana_sdk::instruction::Instruction;
+
+pub fn task_label(task: TaskType) -> &'static str {
+    match task {
+        TaskType::Compound => "compound",
+        TaskType::Rollover => "rollover",
+        TaskType::MarkVault => "mark_vault",
+        TaskType::Checkpoint => "checkpoint",
+    }
+}
+
+pub fn job_queue_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"jobs"], &moluscoyield::ID).0
+}
+
+pub fn fee_ledger_address(vault: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"fee_ledger", vault.as_ref()], &moluscoyield::ID).0
+}
+
+pub fn position_escrow_address(position: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"position_escrow", position.as_ref()], &moluscoyield::ID).0
+}
+
+pub fn program_config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"program_config"], &moluscoyield::ID).0
+}
+
+fn guard() -> moluscoyield::accounts::ProgramGuard {
+    moluscoyield::accounts::ProgramGuard {
+        program_config: program_config_address(),
+    }
+}
+
+/// Jobs due at `slot`, highest queue index first
+///
+/// `complete_job` swap-removes, which moves the last job into the completed
+/// slot; working from the back keeps every remaining index valid.
+pub fn due_jobs(queue: &JobQueue, slot: u64) -> Vec<(u16, Job)> {
+    let mut due: Vec<(u16, Job)> = queue
+        .jobs
+        .iter()
+        .enumerate()
+        .filter(|(_, job)| job.due_slot <= slot)
+        .map(|(index, job)| (index as u16, *job))
+        .collect();
+    due.reverse();
+    due
+}
+
+pub fn tracked_vaults(queue: &JobQueue) -> usize {
+    queue
+        .jobs
+        .iter()
+        .map(|job| job.vault)
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Compound the job's position and claim its tip in one transaction
+pub fn compound_instructions(
+    keeper: &Pubkey,
+    job: &Job,
+    index: u16,
+) -> Vec<Instruction> {
+    let compound = Instruction {
+        program_id: moluscoyield::ID,
+        accounts: moluscoyield::accounts::CompoundPosition {
+            keeper: *keeper,
+            vault: job.vault,
+            position: job.target,
+            position_escrow: position_escrow_address(&job.target),
+            fee_ledger: fee_ledger_address(&job.vault),
+            guard: guard(),
+        }
+        .to_account_metas(None),
+        data: moluscoyield::instruction::CompoundPosition {}.data(),
+    };
+    let complete = Instruction {
+        program_id: moluscoyield::ID,
+        accounts: moluscoyield::accounts::CompleteJob {
+            keeper: *keeper,
+            job_queue: job_queue_address(),
+            target: job.target,
+            guard: guard(),
+        }
+        .to_account_metas(None),
+        data: moluscoyield::instruction::CompleteJob { index }.data(),
+    };
+    vec![compound, complete]
+}
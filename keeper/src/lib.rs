@@ -0,0 +1,8 @@
+//! Keeper for the MoluscoYield job queue.
+//!
+//! Owners schedule cranks with a lamport tip; the keeper performs due jobs,
+//! claims the tips with `complete_job`, and exposes Prometheus metrics so
+//! operators running fleets of vaults can watch it.
+
+pub mod jobs;
+pub mod metrics;
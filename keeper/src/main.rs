@@ -0,0 +1,149 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anchor_lang::AccountDeserialize;
+use axum::routing::get;
+use axum::Router;
+use moluscoyield::{Job, JobQueue, TaskType};
+use moluscoyield_keeper::jobs::{
+    compound_instructions, due_jobs, job_queue_address, task_label, tracked_vaults,
+};
+use moluscoyield_keeper::metrics::Metrics;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::UiTransactionEncoding;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+use tracing_subscriber::EnvFilter;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+async fn compute_units(rpc: &RpcClient, signature: &Signature) -> Option<u64> {
+    let tx = rpc
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await
+        .ok()?;
+    match tx.transaction.meta?.compute_units_consumed {
+        OptionSerializer::Some(units) => Some(units),
+        _ => None,
+    }
+}
+
+async fn run_job(
+    rpc: &RpcClient,
+    keeper: &Keypair,
+    metrics: &Metrics,
+    index: u16,
+    job: &Job,
+) -> Result<(), BoxError> {
+    let task = task_label(job.task);
+    if job.task != TaskType::Compound {
+        debug!(
+            task,
+            "no crank implemented for task, leaving it for another keeper"
+        );
+        return Ok(());
+    }
+
+    let instructions = compound_instructions(&keeper.pubkey(), job, index);
+    let blockhash = rpc.get_latest_blockhash().await?;
+    let tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&keeper.pubkey()),
+        &[keeper],
+        blockhash,
+    );
+
+    match rpc.send_and_confirm_transaction(&tx).await {
+        Ok(signature) => {
+            metrics.cranks_executed.with_label_values(&[task]).inc();
+            metrics.tips_earned_lamports.inc_by(job.tip_lamports);
+            if let Some(units) = compute_units(rpc, &signature).await {
+                metrics
+                    .compute_units_used
+                    .with_label_values(&[task])
+                    .inc_by(units);
+            }
+            info!(%signature, tip_lamports = job.tip_lamports, "job completed");
+            Ok(())
+        }
+        Err(e) => {
+            metrics.crank_failures.with_label_values(&[task]).inc();
+            Err(e.into())
+        }
+    }
+}
+
+async fn poll(rpc: &RpcClient, keeper: &Keypair, metrics: &Metrics) -> Result<(), BoxError> {
+    let account = rpc.get_account(&job_queue_address()).await?;
+    let queue = JobQueue::try_deserialize(&mut account.data.as_slice())?;
+    metrics.vaults_tracked.set(tracked_vaults(&queue) as i64);
+    metrics.jobs_pending.set(queue.jobs.len() as i64);
+
+    let slot = rpc.get_slot().await?;
+    for (index, job) in due_jobs(&queue, slot) {
+        let span = info_span!(
+            "job",
+            index,
+            task = task_label(job.task),
+            vault = %job.vault,
+            target = %job.target
+        );
+        if let Err(e) = run_job(rpc, keeper, metrics, index, &job)
+            .instrument(span)
+            .await
+        {
+            warn!(index, error = %e, "job failed");
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), BoxError> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .init();
+
+    let rpc_url = env_or("MOLUSCO_RPC_URL", "http://127.0.0.1:8899");
+    let keypair_path = env_or("MOLUSCO_KEEPER_KEYPAIR", "keeper.json");
+    let metrics_bind = env_or("MOLUSCO_KEEPER_METRICS_BIND", "0.0.0.0:9100");
+    let poll_secs: u64 = env_or("MOLUSCO_KEEPER_POLL_SECS", "10").parse()?;
+
+    let keeper = read_keypair_file(&keypair_path)?;
+    let rpc = RpcClient::new_with_commitment(rpc_url.clone(), CommitmentConfig::confirmed());
+    let metrics = Arc::new(Metrics::new()?);
+
+    let exporter = metrics.clone();
+    let app = Router::new().route("/metrics", get(move || async move { exporter.render() }));
+    let listener = tokio::net::TcpListener::bind(&metrics_bind).await?;
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            error!(error = %e, "metrics server stopped");
+        }
+    });
+    info!(keeper = %keeper.pubkey(), rpc = %rpc_url, metrics = %metrics_bind, "keeper started");
+
+    let mut interval = tokio::time::interval(Duration::from_secs(poll_secs));
+    loop {
+        interval.tick().await;
+        if let Err(e) = poll(&rpc, &keeper, &metrics).await {
+            warn!(error = %e, "poll failed");
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use prometheus::{Encoder, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+
+/// Keeper metrics, labelled by task (`compound`, `rollover`, `mark_vault`, `checkpoint`)
+pub struct Metrics {
+    registry: Registry,
+    pub vaults_tracked: IntGauge,
+    pub jobs_pending: IntGauge,
+    pub cranks_executed: IntCounterVec,
+    pub crank_failures: IntCounterVec,
+    pub compute_units_used: IntCounterVec,
+    pub tips_earned_lamports: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("moluscoyield_keeper".into()), None)?;
+        let vaults_tracked = IntGauge::new("vaults_tracked", "Distinct vaults with queued jobs")?;
+        let jobs_pending = IntGauge::new("jobs_pending", "Jobs in the queue, due or not")?;
+        let cranks_executed = IntCounterVec::new(
+            Opts::new("cranks_executed_total", "Jobs performed and completed"),
+            &["task"],
+        )?;
+        let crank_failures = IntCounterVec::new(
+            Opts::new("crank_failures_total", "Jobs whose transaction failed"),
+            &["task"],
+        )?;
+        let compute_units_used = IntCounterVec::new(
+            Opts::new(
+                "compute_units_used_total",
+                "Compute units consumed by crank transactions",
+            ),
+            &["task"],
+        )?;
+        let tips_earned_lamports = IntCounter::new(
+            "tips_earned_lamports_total",
+            "Job tips claimed, in lamports",
+        )?;
+
+        registry.register(Box::new(vaults_tracked.clone()))?;
+        registry.register(Box::new(jobs_pending.clone()))?;
+        registry.register(Box::new(cranks_executed.clone()))?;
+        registry.register(Box::new(crank_failures.clone()))?;
+        registry.register(Box::new(compute_units_used.clone()))?;
+        registry.register(Box::new(tips_earned_lamports.clone()))?;
+
+        Ok(Self {
+            registry,
+            vaults_tracked,
+            jobs_pending,
+            cranks_executed,
+            crank_failures,
+            compute_units_used,
+            tips_earned_lamports,
+        })
+    }
+
+    /// Prometheus text exposition of every metric
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding cannot fail");
+        String::from_utf8(buffer).expect("text encoding is UTF-8")
+    }
+}
@@ -0,0 +1,44 @@
+use anchor_lang::prelude::Pubkey;
+use moluscoyield::{Job, JobQueue, TaskType};
+use moluscoyield_keeper::jobs::{due_jobs, tracked_vaults};
+use moluscoyield_keeper::metrics::Metrics;
+
+fn job(vault: Pubkey, due_slot: u64) -> Job {
+    Job {
+        vault,
+        task: TaskType::Compound,
+        target: Pubkey::new_unique(),
+        due_slot,
+        tip_lamports: 5_000,
+        scheduled_at: 0,
+    }
+}
+
+#[test]
+fn due_jobs_run_back_to_front() {
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let queue = JobQueue {
+        jobs: vec![job(a, 10), job(b, 50), job(a, 20)],
+        total_scheduled: 3,
+        total_completed: 0,
+        bump: 255,
+    };
+
+    let due: Vec<u16> = due_jobs(&queue, 20).into_iter().map(|(i, _)| i).collect();
+    assert_eq!(due, vec![2, 0]);
+    assert_eq!(tracked_vaults(&queue), 2);
+}
+
+#[test]
+fn metrics_render_in_text_format() {
+    let metrics = Metrics::new().unwrap();
+    metrics
+        .cranks_executed
+        .with_label_values(&["compound"])
+        .inc();
+    metrics.tips_earned_lamports.inc_by(5_000);
+
+    let text = metrics.render();
+    assert!(text.contains("moluscoyield_keeper_cranks_executed_total{task=\"compound\"} 1"));
+    assert!(text.contains("moluscoyield_keeper_tips_earned_lamports_total 5000"));
+}
@@ -0,0 +1,277 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::{get_return_data, invoke, invoke_signed};
+
+use crate::MoluscoError;
+
+/// Anchor-style 8-byte discriminator for an adapter instruction
+pub fn discriminator(name: &str) -> [u8; 8] {
+    let mut out = [0u8; 8];
+    out.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    out
+}
+
+/// CPI into an adapter program with a program PDA as signer.
+///
+/// The accounts come straight from `remaining_accounts`; `authority` is
+/// marked as a signer so the PDA can authorize the token movements.
+pub fn invoke_as_pda<'info>(
+    adapter_program: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+    authority: &Pubkey,
+    data: Vec<u8>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let metas = accounts
+        .iter()
+        .map(|account| AccountMeta {
+            pubkey: account.key(),
+            is_signer: account.key() == *authority || account.is_signer,
+            is_writable: account.is_writable,
+        })
+        .collect();
+
+    let ix = Instruction {
+        program_id: adapter_program.key(),
+        accounts: metas,
+        data,
+    };
+
+    let mut infos = accounts.to_vec();
+    infos.push(adapter_program.clone());
+
+    invoke_signed(&ix, &infos, signer_seeds)?;
+    Ok(())
+}
+
+/// Lock held on a vault while an adapter CPI is in flight.
+///
+/// The runtime already forbids indirect reentrancy, but the lock is persisted to
+/// the vault before each CPI so the guarantee holds independently of it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReentrancyGuard {
+    pub locked: bool,
+}
+
+impl ReentrancyGuard {
+    pub const SIZE: usize = 1;
+
+    pub fn acquire(&mut self) -> Result<()> {
+        require!(!self.locked, MoluscoError::ReentrancyDetected);
+        self.locked = true;
+        Ok(())
+    }
+
+    pub fn release(&mut self) {
+        self.locked = false;
+    }
+}
+
+/// Fixed-point scale of `AdapterResult::rate`
+pub const RATE_SCALE: u64 = 1_000_000_000;
+
+/// Outcome every value-moving adapter call must set as borsh return data.
+///
+/// `rate` is `amount_out / amount_in` scaled by `RATE_SCALE` (the venue's
+/// effective price, or its exchange rate for deposits and withdrawals).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AdapterResult {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_paid: u64,
+    pub rate: u64,
+}
+
+impl AdapterResult {
+    /// Result a simulation vault books in place of a real CPI
+    pub fn simulated(amount_in: u64, amount_out: u64) -> Self {
+        Self {
+            amount_in,
+            amount_out,
+            fee_paid: 0,
+            rate: implied_rate(amount_in, amount_out),
+        }
+    }
+
+    /// Reject results that spend more than authorized, fill below the floor, or
+    /// report a rate inconsistent with their own amounts
+    pub fn validate(&self, max_amount_in: u64, min_amount_out: u64) -> Result<()> {
+        require!(self.amount_in <= max_amount_in, MoluscoError::InvalidAdapterReturn);
+        require!(
+            self.rate.abs_diff(implied_rate(self.amount_in, self.amount_out)) <= 1,
+            MoluscoError::InvalidAdapterReturn
+        );
+        require!(self.amount_out >= min_amount_out, MoluscoError::SlippageExceeded);
+        Ok(())
+    }
+}
+
+/// `amount_out / amount_in` scaled by `RATE_SCALE`; zero when nothing went in
+pub fn implied_rate(amount_in: u64, amount_out: u64) -> u64 {
+    if amount_in == 0 {
+        return 0;
+    }
+    let rate = amount_out as u128 * RATE_SCALE as u128 / amount_in as u128;
+    rate.min(u64::MAX as u128) as u64
+}
+
+/// Rounding dust (in token base units) a venue may leave between booked and observed deltas
+pub const BALANCE_TOLERANCE: u64 = 1;
+
+/// Assert an observed token balance change matches what was booked for a CPI.
+///
+/// `expected_delta` is signed: negative for tokens leaving the account.
+pub fn check_balance_delta(before: u64, after: u64, expected_delta: i128) -> Result<()> {
+    let observed = after as i128 - before as i128;
+    require!(
+        observed.abs_diff(expected_delta) <= BALANCE_TOLERANCE as u128,
+        MoluscoError::BalanceMismatch
+    );
+    Ok(())
+}
+
+/// Decode the `AdapterResult` left as return data by `adapter_program`
+pub fn read_result(adapter_program: &AccountInfo) -> Result<AdapterResult> {
+    let (program_id, data) = get_return_data().ok_or(MoluscoError::InvalidAdapterReturn)?;
+    require_keys_eq!(program_id, adapter_program.key(), MoluscoError::InvalidAdapterReturn);
+    AdapterResult::try_from_slice(&data).map_err(|_| MoluscoError::InvalidAdapterReturn.into())
+}
+
+/// `invoke_as_pda` for adapter calls that move value, returning the adapter's result
+pub fn invoke_with_result<'info>(
+    adapter_program: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+    authority: &Pubkey,
+    data: Vec<u8>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<AdapterResult> {
+    invoke_as_pda(adapter_program, accounts, authority, data, signer_seeds)?;
+    read_result(adapter_program)
+}
+
+/// CPI into a swap adapter (e.g. a Jupiter route) using caller-supplied route data
+pub fn invoke_swap<'info>(
+    swap_program: &AccountInfo<'info>,
+    route_accounts: &[AccountInfo<'info>],
+    authority: &Pubkey,
+    route_data: Vec<u8>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<AdapterResult> {
+    invoke_with_result(swap_program, route_accounts, authority, route_data, signer_seeds)
+}
+
+/// Ask a yield adapter to unwind `amount` of what `authority` holds in the venue
+///
+/// The adapter pays out into whichever token account `venue_accounts` name as the
+/// destination; callers check that account's balance delta.
+pub fn withdraw<'info>(
+    adapter_program: &AccountInfo<'info>,
+    venue_accounts: &[AccountInfo<'info>],
+    authority: &Pubkey,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<AdapterResult> {
+    let mut data = discriminator("withdraw").to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+    invoke_with_result(adapter_program, venue_accounts, authority, data, signer_seeds)
+}
+
+/// Ask a yield adapter for the value of rewards accrued but not yet harvested.
+///
+/// The adapter receives `reward_accounts` as-is and must set a little-endian
+/// `u64` as return data.
+pub fn quote_pending_rewards<'info>(
+    adapter_program: &AccountInfo<'info>,
+    reward_accounts: &[AccountInfo<'info>],
+) -> Result<u64> {
+    let ix = Instruction {
+        program_id: adapter_program.key(),
+        accounts: reward_accounts
+            .iter()
+            .map(|account| AccountMeta::new_readonly(account.key(), false))
+            .collect(),
+        data: discriminator("quote_pending_rewards").to_vec(),
+    };
+
+    let mut infos = reward_accounts.to_vec();
+    infos.push(adapter_program.clone());
+    invoke(&ix, &infos)?;
+
+    let (program_id, data) = get_return_data().ok_or(MoluscoError::InvalidAdapterReturn)?;
+    require_keys_eq!(program_id, adapter_program.key(), MoluscoError::InvalidAdapterReturn);
+    let bytes: [u8; 8] = data
+        .get(..8)
+        .and_then(|b| b.try_into().ok())
+        .ok_or(MoluscoError::InvalidAdapterReturn)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Venue status reported by an adapter's `health_check` instruction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct AdapterHealth {
+    pub is_frozen: bool,
+    pub utilization_bps: u16,
+    pub oracle_age_secs: i64,
+}
+
+/// Run an adapter's lightweight health probe and decode the returned `AdapterHealth`
+pub fn probe_health<'info>(
+    adapter_program: &AccountInfo<'info>,
+    venue_accounts: &[AccountInfo<'info>],
+) -> Result<AdapterHealth> {
+    let ix = Instruction {
+        program_id: adapter_program.key(),
+        accounts: venue_accounts
+            .iter()
+            .map(|account| AccountMeta::new_readonly(account.key(), false))
+            .collect(),
+        data: discriminator("health_check").to_vec(),
+    };
+
+    let mut infos = venue_accounts.to_vec();
+    infos.push(adapter_program.clone());
+    invoke(&ix, &infos)?;
+
+    let (program_id, data) = get_return_data().ok_or(MoluscoError::InvalidAdapterReturn)?;
+    require_keys_eq!(program_id, adapter_program.key(), MoluscoError::InvalidAdapterReturn);
+    AdapterHealth::try_from_slice(&data).map_err(|_| MoluscoError::InvalidAdapterReturn.into())
+}
+
+/// A lending venue's own rate, reported by an adapter's `reserve_rate` instruction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReserveRate {
+    pub supply_apr_bps: u32,
+    pub utilization_bps: u16,
+}
+
+/// Read the venue's current supply APR and utilization from its reserve accounts
+pub fn quote_reserve_rate<'info>(
+    adapter_program: &AccountInfo<'info>,
+    reserve_accounts: &[AccountInfo<'info>],
+) -> Result<ReserveRate> {
+    let ix = Instruction {
+        program_id: adapter_program.key(),
+        accounts: reserve_accounts
+            .iter()
+            .map(|account| AccountMeta::new_readonly(account.key(), false))
+            .collect(),
+        data: discriminator("reserve_rate").to_vec(),
+    };
+
+    let mut infos = reserve_accounts.to_vec();
+    infos.push(adapter_program.clone());
+    invoke(&ix, &infos)?;
+
+    let (program_id, data) = get_return_data().ok_or(MoluscoError::InvalidAdapterReturn)?;
+    require_keys_eq!(program_id, adapter_program.key(), MoluscoError::InvalidAdapterReturn);
+    ReserveRate::try_from_slice(&data).map_err(|_| MoluscoError::InvalidAdapterReturn.into())
+}
+
+/// Instruction data for an orderbook adapter call carrying the order terms
+pub fn order_data(name: &str, amount_in: u64, min_amount_out: u64) -> Vec<u8> {
+    let mut data = discriminator(name).to_vec();
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&min_amount_out.to_le_bytes());
+    data
+}
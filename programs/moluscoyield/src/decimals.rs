@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::shares::Rounding;
+use crate::MoluscoError;
+
+/// Decimals of the internal fixed-point representation used for cross-asset math
+pub const INTERNAL_DECIMALS: u8 = 18;
+
+/// Decimals of lamports, and of wrapped SOL
+pub const SOL_DECIMALS: u8 = 9;
+
+/// Scale factor lifting a `decimals`-token amount to `INTERNAL_DECIMALS`
+fn scale(decimals: u8) -> Result<u128> {
+    require!(decimals <= INTERNAL_DECIMALS, MoluscoError::UnsupportedDecimals);
+    Ok(10u128.pow((INTERNAL_DECIMALS - decimals) as u32))
+}
+
+/// Raw token amount with `decimals` places -> internal 18-decimal value (always exact)
+pub fn normalize(amount: u64, decimals: u8) -> Result<u128> {
+    // u64::MAX * 10^18 fits in u128, so this cannot overflow
+    Ok(amount as u128 * scale(decimals)?)
+}
+
+/// Internal 18-decimal value -> raw token amount with `decimals` places
+///
+/// Sub-unit dust is rounded as requested; `normalize` followed by `denormalize`
+/// returns the original amount in either direction.
+pub fn denormalize(value: u128, decimals: u8, rounding: Rounding) -> Result<u64> {
+    let factor = scale(decimals)?;
+    let mut amount = value / factor;
+    if rounding == Rounding::Up && value % factor != 0 {
+        amount += 1;
+    }
+    u64::try_from(amount).map_err(|_| MoluscoError::MathOverflow.into())
+}
+
+/// Convert a raw amount between two mints' decimals via the internal representation
+pub fn convert_decimals(amount: u64, from_decimals: u8, to_decimals: u8, rounding: Rounding) -> Result<u64> {
+    denormalize(normalize(amount, from_decimals)?, to_decimals, rounding)
+}
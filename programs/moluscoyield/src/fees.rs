@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::MoluscoError;
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Upper bound on the exit fee charged to early withdrawals
+pub const MAX_EXIT_FEE_BPS: u16 = 200;
+
+/// Exit fee that decays with holding time.
+///
+/// The full fee applies while a lot is younger than `full_fee_secs`, decays
+/// linearly to zero by `zero_fee_secs`, and is zero afterwards.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct ExitFeeSchedule {
+    pub full_fee_bps: u16,
+    pub full_fee_secs: i64,
+    pub zero_fee_secs: i64,
+}
+
+impl ExitFeeSchedule {
+    pub const SIZE: usize = 2 + 8 + 8;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(self.full_fee_bps <= MAX_EXIT_FEE_BPS, MoluscoError::InvalidFeeBps);
+        require!(
+            self.full_fee_secs >= 0 && self.full_fee_secs <= self.zero_fee_secs,
+            MoluscoError::InvalidFeeSchedule
+        );
+        Ok(())
+    }
+
+    /// Fee rate for a lot held for `held_secs`
+    pub fn rate_bps(&self, held_secs: i64) -> u16 {
+        if held_secs < self.full_fee_secs {
+            return self.full_fee_bps;
+        }
+        if held_secs >= self.zero_fee_secs {
+            return 0;
+        }
+        let window = (self.zero_fee_secs - self.full_fee_secs) as u128;
+        let remaining = (self.zero_fee_secs - held_secs) as u128;
+        (self.full_fee_bps as u128 * remaining / window) as u16
+    }
+
+    /// Total fee on withdrawing `(amount, deposited_at)` lots at `now`
+    pub fn fee_for_lots(&self, lots: &[(u64, i64)], now: i64) -> Result<u64> {
+        let mut fee: u128 = 0;
+        for (amount, deposited_at) in lots {
+            let held = now.saturating_sub(*deposited_at);
+            fee = fee
+                .checked_add(*amount as u128 * self.rate_bps(held) as u128 / BPS_DENOMINATOR)
+                .ok_or(MoluscoError::MathOverflow)?;
+        }
+        u64::try_from(fee).map_err(|_| MoluscoError::MathOverflow.into())
+    }
+}
+
+/// Part of `realized` yield that lifts net profit above the high-water mark
+///
+/// `net_profit` already includes `realized`. Yield that only wins back earlier
+/// losses stays under the mark and is not chargeable.
+pub fn above_high_water_mark(net_profit: u128, high_water_mark: u128, realized: u64) -> u64 {
+    net_profit
+        .saturating_sub(high_water_mark)
+        .min(realized as u128) as u64
+}
+
+/// Largest share of a crystallized performance fee a vault may rebate
+pub const MAX_REBATE_SHARE_BPS: u16 = 5_000;
+
+/// Split a crystallized performance fee into the operator's cut and the rebate
+///
+/// The rebate rounds down, so dust stays with the operator.
+pub fn split_performance_fee(fee: u64, rebate_share_bps: u16) -> (u64, u64) {
+    let rebate = (fee as u128 * rebate_share_bps as u128 / BPS_DENOMINATOR) as u64;
+    (fee - rebate, rebate)
+}
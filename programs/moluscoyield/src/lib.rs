@@ -1,4 +1,24 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::pubkey;
+use anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked;
+use anchor_spl::token::{self, Burn, CloseAccount, Mint, MintTo, Token, TokenAccount, Transfer};
+
+use adapters::ReentrancyGuard;
+use fees::ExitFeeSchedule;
+use oracle::OracleFeed;
+use shares::Rounding;
+use stats::RunningStats;
+
+pub mod adapters;
+pub mod decimals;
+pub mod fees;
+pub mod migration;
+pub mod oracle;
+pub mod shares;
+pub mod stats;
+pub mod strategy;
+pub mod withdrawals;
 
 declare_id!("MolY1dQfT7mK9JmM8J3nM8bG5sL6cK7dF4eS5tU7vW8");
 
@@ -7,242 +27,12575 @@ pub mod moluscoyield {
     use super::*;
 
     /// Initialize a new agent vault for tracking positions
-    pub fn initialize_vault(ctx: Context<InitializeVault>, agent_name: String) -> Result<()> {
+    pub fn initialize_vault(
+        ctx: Context<InitializeVault>,
+        agent_name: String,
+        simulation: bool, // Shadow mode: adapter CPIs become no-ops and no funds move in or out
+    ) -> Result<()> {
         let vault = &mut ctx.accounts.vault;
         vault.owner = ctx.accounts.owner.key();
+        vault.seed_owner = ctx.accounts.owner.key();
         vault.agent_name = agent_name;
+        vault.simulation = simulation;
         vault.total_value_locked = 0;
-        vault.position_count = 0;
+        vault.active_positions = 0;
+        vault.total_positions_created = 0;
         vault.created_at = Clock::get()?.unix_timestamp;
         vault.last_rebalance = 0;
+        vault.layout_version = migration::LAYOUT_VERSION;
         vault.bump = ctx.bumps.vault;
         
-        msg!("Vault initialized for agent: {}", vault.agent_name);
+        msg!("Vault initialized for agent: {} (simulation: {})", vault.agent_name, simulation);
         Ok(())
     }
 
     /// Record a new yield position
-    pub fn open_position(
-        ctx: Context<OpenPosition>,
-        protocol: String,
-        strategy: String,
-        asset: String,
+    pub fn open_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, OpenPosition<'info>>,
+        protocol: ProtocolKind,
+        strategy: StrategyKind,
         amount: u64,
         target_apy: u16, // Basis points (e.g., 850 = 8.50%)
+        idempotency_key: Option<[u8; 16]>,
     ) -> Result<()> {
+        // Probe the venue before committing capital when the protocol has an adapter
+        if ctx.accounts.vault.simulation {
+            msg!("Simulation vault: skipping adapter health probe");
+        } else if !ctx.accounts.adapter_config.data_is_empty() {
+            let adapter_config =
+                Account::<AdapterConfig>::try_from(&ctx.accounts.adapter_config.to_account_info())?;
+            let adapter_program = ctx
+                .accounts
+                .adapter_program
+                .as_ref()
+                .ok_or(MoluscoError::AdapterProgramRequired)?;
+            require_keys_eq!(
+                adapter_program.key(),
+                adapter_config.program_id,
+                MoluscoError::AdapterProgramRequired
+            );
+            let health = adapters::probe_health(
+                &adapter_program.to_account_info(),
+                ctx.remaining_accounts,
+            )?;
+            adapter_config.check_health(&health)?;
+        }
+
         let position = &mut ctx.accounts.position;
         let vault = &mut ctx.accounts.vault;
         
-        position.owner = ctx.accounts.owner.key();
+        require_authority(
+            vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            DelegateAction::Open,
+        )?;
+        vault.recent_keys.consume(idempotency_key)?;
+
+        // Positions belong to the vault owner even when a delegate opened them
+        position.owner = vault.owner;
         position.vault = vault.key();
         position.protocol = protocol;
         position.strategy = strategy;
-        position.asset = asset;
+        position.asset = ctx.accounts.asset_entry.kind;
+        position.asset_entry = ctx.accounts.asset_entry.key();
+        position.asset_decimals = ctx.accounts.asset_entry.decimals;
+        position.index = vault.total_positions_created;
+        position.legacy_seed = false;
+        position.rolled_from = Pubkey::default();
         position.amount = amount;
         position.target_apy = target_apy;
         position.opened_at = Clock::get()?.unix_timestamp;
         position.last_update = Clock::get()?.unix_timestamp;
+        position.last_update_slot = Clock::get()?.slot;
         position.is_active = true;
         position.accumulated_yield = 0;
+        position.accumulated_loss = 0;
+        position.last_compound = position.opened_at;
+        position.last_value = amount;
+        position.checkpoint_value = amount;
+        position.layout_version = migration::LAYOUT_VERSION;
         position.bump = ctx.bumps.position;
         
-        vault.position_count += 1;
-        vault.total_value_locked += amount;
+        require_gte!(amount, vault.min_position_amount, MoluscoError::PositionTooSmall);
+
+        // Zero means uncapped
+        if vault.max_positions > 0 {
+            require_gt!(vault.max_positions, vault.active_positions, MoluscoError::PositionLimitReached);
+        }
+
+        if vault.simulation {
+            // Shadow vaults take no deposits, so the notional capital is booked directly and
+            // no share is ever priced against it
+            if vault.deposit_cap > 0 {
+                require_gte!(
+                    vault.deposit_cap as u128,
+                    vault.total_value_locked.saturating_add(amount as u128),
+                    MoluscoError::DepositCapExceeded
+                );
+            }
+            vault.total_value_locked += amount as u128;
+        } else {
+            // Shadow capital never reaches the venue, so only funded vaults use capacity
+            let reserved = position.normalized_amount()?;
+            position.reserve_capacity(&mut ctx.accounts.strategy_capacity, reserved)?;
+            // Capital is already in TVL; it moves from idle escrow into the position's custody
+            require_gte!(ctx.accounts.escrow.amount, amount, MoluscoError::InsufficientBalance);
+            let seeds = vault.signer_seeds();
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.escrow.to_account_info(),
+                        to: ctx.accounts.position_escrow.to_account_info(),
+                        authority: vault.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                amount,
+            )?;
+        }
+
+        vault.active_positions += 1;
+        vault.total_positions_created += 1;
         
-        msg!("Position opened: {} in {}", asset, protocol);
+        msg!("Position opened: {:?} in {:?}", position.asset, protocol);
         Ok(())
     }
 
     /// Update position value and record yield
-    pub fn update_position(
-        ctx: Context<UpdatePosition>,
+    ///
+    /// With a lending adapter supplied, the venue's supply APR and utilization are
+    /// read from the reserve accounts in `remaining_accounts` and stored with the mark.
+    /// `sequence` must be the position's current `update_sequence`, so of two racing
+    /// marks taken from the same state only the first lands.
+    pub fn update_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdatePosition<'info>>,
         current_value: u64,
+        sequence: u64,
     ) -> Result<()> {
+        require_manual_mark(&ctx.accounts.oracle_config)?;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        require_authority_or_session(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            ctx.accounts.session.as_ref(),
+            DelegateAction::Update,
+            now,
+        )?;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        let venue_rate = match ctx.accounts.adapter_program.as_ref() {
+            Some(adapter_program) if !ctx.accounts.vault.simulation => Some(
+                adapters::quote_reserve_rate(&adapter_program.to_account_info(), ctx.remaining_accounts)?,
+            ),
+            _ => None,
+        };
         let position = &mut ctx.accounts.position;
-        let now = Clock::get()?.unix_timestamp;
+        position.check_update_interval(ctx.accounts.vault.min_update_interval_secs, now)?;
+        position.advance_sequence(sequence)?;
+        if let Some(rate) = venue_rate {
+            position.venue_supply_apr_bps = rate.supply_apr_bps;
+            position.venue_utilization_bps = rate.utilization_bps;
+            position.venue_rate_updated_at = now;
+        }
         
-        // Calculate yield since last update
-        let yield_earned = current_value.saturating_sub(position.amount);
-        position.accumulated_yield += yield_earned;
-        position.last_update = now;
+        let unrealized_before = position.unrealized_yield;
+        let (yield_earned, loss) = position.apply_mark(current_value, now, clock.slot);
+        let vault = &mut ctx.accounts.vault;
+        vault.cumulative_yield += yield_earned as u128;
+        vault.restate_unrealized(unrealized_before, position.unrealized_yield);
+        vault.clear_stale(position);
         
-        msg!("Position updated. Yield earned: {} lamports", yield_earned);
+        msg!("Position updated. Yield earned: {} lamports, loss: {} lamports", yield_earned, loss);
+        Ok(())
+    }
+
+    /// Mark many positions of a vault in one transaction
+    ///
+    /// Positions are passed as writable `remaining_accounts`, with `current_values[i]`
+    /// and `sequences[i]` applying to the i-th account.
+    pub fn update_positions_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdatePositionsBatch<'info>>,
+        current_values: Vec<u64>,
+        sequences: Vec<u64>,
+    ) -> Result<()> {
+        require_eq!(
+            current_values.len(),
+            ctx.remaining_accounts.len(),
+            MoluscoError::BatchLengthMismatch
+        );
+        require_eq!(sequences.len(), current_values.len(), MoluscoError::BatchLengthMismatch);
+        require_gte!(MAX_BATCH_SIZE, current_values.len(), MoluscoError::BatchTooLarge);
+        require_unique_accounts(ctx.remaining_accounts)?;
+        require_authority(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            DelegateAction::Update,
+        )?;
+        require_manual_mark(&ctx.accounts.oracle_config)?;
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        let vault_key = ctx.accounts.vault.key();
+        let asset_entry = ctx.accounts.asset_entry.key();
+        let min_interval_secs = ctx.accounts.vault.min_update_interval_secs;
+        let mut total_yield: u64 = 0;
+        let mut total_loss: u64 = 0;
+
+        let marks = current_values.into_iter().zip(sequences);
+        for (info, (current_value, sequence)) in ctx.remaining_accounts.iter().zip(marks) {
+            require!(info.is_writable, MoluscoError::BatchAccountNotWritable);
+            let mut position = Account::<Position>::try_from(info)?;
+            require_keys_eq!(position.vault, vault_key, MoluscoError::BatchAccountWrongVault);
+            require_keys_eq!(position.asset_entry, asset_entry, MoluscoError::BatchAccountWrongVault);
+            require!(position.is_active, MoluscoError::PositionClosed);
+            position.check_update_interval(min_interval_secs, now)?;
+            position.advance_sequence(sequence)?;
+
+            let unrealized_before = position.unrealized_yield;
+            let (yield_earned, loss) = position.apply_mark(current_value, now, clock.slot);
+            let vault = &mut ctx.accounts.vault;
+            vault.restate_unrealized(unrealized_before, position.unrealized_yield);
+            vault.clear_stale(&mut position);
+            total_yield = total_yield.saturating_add(yield_earned);
+            total_loss = total_loss.saturating_add(loss);
+            position.exit(&crate::ID)?;
+        }
+        ctx.accounts.vault.cumulative_yield += total_yield as u128;
+
+        msg!(
+            "Batch updated {} positions. Yield earned: {} lamports, loss: {} lamports",
+            ctx.remaining_accounts.len(),
+            total_yield,
+            total_loss
+        );
         Ok(())
     }
 
     /// Close a position and record final yield
+    ///
+    /// Yield realized into TVL here pays the vault's performance fee.
     pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        require_authority(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            DelegateAction::Close,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
         let position = &mut ctx.accounts.position;
         let vault = &mut ctx.accounts.vault;
-        
+        let mut realized = 0;
+
+        if vault.simulation {
+            // The notional principal leaves TVL; the last mark stands in for custody, so its
+            // surplus is realized and charged like a funded close
+            realized = position
+                .accumulated_yield
+                .saturating_sub(position.compounded_yield as u128)
+                .min(position.last_value.saturating_sub(position.amount) as u128) as u64;
+            vault.cumulative_losses += position.amount.saturating_sub(position.last_value) as u128;
+            vault.total_value_locked = vault
+                .total_value_locked
+                .checked_sub(position.amount as u128)
+                .ok_or(MoluscoError::TvlUnderflow)?
+                .checked_add(realized as u128)
+                .ok_or(MoluscoError::MathOverflow)?;
+        } else {
+            let reserved = position.reserved_capacity;
+            position.release_capacity(&mut ctx.accounts.strategy_capacity, reserved);
+            // Return the position's custody to idle escrow. Anything above principal is
+            // realized yield: it raises TVL, and with it the price of every share.
+            let returned = ctx.accounts.position_escrow.amount;
+            // Compounded yield already paid its fee when it was harvested
+            realized = position
+                .accumulated_yield
+                .saturating_sub(position.compounded_yield as u128)
+                .min(returned.saturating_sub(position.amount) as u128) as u64;
+            // A shortfall is a realized loss; fees wait until it is earned back
+            vault.cumulative_losses += position.amount.saturating_sub(returned) as u128;
+            vault.total_value_locked = vault
+                .total_value_locked
+                .saturating_sub(position.amount as u128)
+                .checked_add(returned as u128)
+                .ok_or(MoluscoError::MathOverflow)?;
+            let seeds = vault.signer_seeds();
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.position_escrow.to_account_info(),
+                        to: ctx.accounts.escrow.to_account_info(),
+                        authority: vault.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                ctx.accounts.position_escrow.amount,
+            )?;
+            token::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                CloseAccount {
+                    account: ctx.accounts.position_escrow.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ))?;
+        }
+
+        // Whatever was not realized is gone with the position
+        let unrealized_before = position.realize(realized);
+        vault.restate_unrealized(unrealized_before, 0);
+        position.unrealized_yield = 0;
+        vault.clear_stale(position);
+
+        let (fee, chargeable) = vault.take_performance_fee(realized)?;
+        let rate_bps = vault.performance_fee_bps;
+        book_fee(
+            vault,
+            &ctx.accounts.fee_ledger,
+            FeeKind::Performance,
+            rate_bps,
+            chargeable,
+            fee,
+            now,
+        )?;
+
         position.is_active = false;
-        vault.position_count -= 1;
-        vault.total_value_locked -= position.amount;
+        vault.active_positions -= 1;
+        
+        emit!(PositionClosedEvent {
+            vault: vault.key(),
+            position: position.key(),
+            amount: position.amount,
+            accumulated_yield: position.accumulated_yield,
+            accumulated_loss: position.accumulated_loss,
+            timestamp: now,
+        });
         
-        msg!("Position closed. Total yield: {} lamports", position.accumulated_yield);
+        msg!(
+            "Position closed. Total yield: {} lamports, loss: {} lamports",
+            position.accumulated_yield,
+            position.accumulated_loss
+        );
+        Ok(())
+    }
+
+    /// Record a final mark and close the position on it
+    ///
+    /// The terminal mark is booked like `update_position` and announced before
+    /// `close_position` runs and returns the rent. The position must have been
+    /// marked within `MAX_FINAL_MARK_AGE_SLOTS`, so a final value is only taken
+    /// from an agent that has kept the position current.
+    pub fn settle_and_close(
+        ctx: Context<ClosePosition>,
+        current_value: u64,
+        sequence: u64,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            DelegateAction::Close,
+        )?;
+        require_manual_mark(&ctx.accounts.oracle_config)?;
+        let clock = Clock::get()?;
+        let position = &mut ctx.accounts.position;
+        require!(position.is_active, MoluscoError::PositionClosed);
+        position.advance_sequence(sequence)?;
+        require_gte!(
+            position.last_update_slot.saturating_add(MAX_FINAL_MARK_AGE_SLOTS),
+            clock.slot,
+            MoluscoError::PositionMarkStale
+        );
+
+        let unrealized_before = position.unrealized_yield;
+        let (terminal_yield, loss) =
+            position.apply_mark(current_value, clock.unix_timestamp, clock.slot);
+        let vault = &mut ctx.accounts.vault;
+        vault.cumulative_yield += terminal_yield as u128;
+        vault.restate_unrealized(unrealized_before, position.unrealized_yield);
+        vault.clear_stale(position);
+
+        emit!(PositionFinalMarkEvent {
+            vault: vault.key(),
+            position: position.key(),
+            final_value: current_value,
+            terminal_yield,
+            loss,
+            timestamp: clock.unix_timestamp,
+        });
+
+        close_position(ctx)
+    }
+
+    /// Close inactive or dust positions in bulk, returning their rent to the owner
+    pub fn close_positions_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClosePositionsBatch<'info>>,
+        dust_threshold: u64,
+    ) -> Result<()> {
+        require_gte!(
+            MAX_BATCH_SIZE,
+            ctx.remaining_accounts.len(),
+            MoluscoError::BatchTooLarge
+        );
+        require_unique_accounts(ctx.remaining_accounts)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &mut ctx.accounts.vault;
+        let owner_info = ctx.accounts.owner.to_account_info();
+
+        for info in ctx.remaining_accounts.iter() {
+            require!(info.is_writable, MoluscoError::BatchAccountNotWritable);
+            let position = Account::<Position>::try_from(info)?;
+            require_keys_eq!(position.vault, vault.key(), MoluscoError::BatchAccountWrongVault);
+            require!(
+                !position.is_active || position.amount < dust_threshold,
+                MoluscoError::PositionNotClosable
+            );
+            // Active positions of funded vaults hold escrowed tokens; close those one by one
+            require!(
+                !position.is_active || vault.simulation,
+                MoluscoError::PositionNotClosable
+            );
+
+            // Inactive positions were already removed from the vault counters
+            if position.is_active {
+                vault.active_positions -= 1;
+                vault.total_value_locked -= position.amount as u128;
+                vault.restate_unrealized(position.unrealized_yield, 0);
+                if position.is_stale {
+                    vault.stale_positions -= 1;
+                }
+            }
+
+            emit!(PositionClosedEvent {
+                vault: vault.key(),
+                position: position.key(),
+                amount: position.amount,
+                accumulated_yield: position.accumulated_yield,
+                accumulated_loss: position.accumulated_loss,
+                timestamp: now,
+            });
+
+            position.close(owner_info.clone())?;
+        }
+
+        msg!("Batch closed {} positions", ctx.remaining_accounts.len());
         Ok(())
     }
 
     /// Record a rebalance event
-    pub fn record_rebalance(ctx: Context<RecordRebalance>) -> Result<()> {
+    pub fn record_rebalance(
+        ctx: Context<RecordRebalance>,
+        idempotency_key: Option<[u8; 16]>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require_authority_or_session(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            ctx.accounts.session.as_ref(),
+            DelegateAction::Rebalance,
+            now,
+        )?;
         let vault = &mut ctx.accounts.vault;
-        vault.last_rebalance = Clock::get()?.unix_timestamp;
+        vault.recent_keys.consume(idempotency_key)?;
+        vault.last_rebalance = now;
         
         msg!("Rebalance recorded at timestamp: {}", vault.last_rebalance);
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(agent_name: String)]
-pub struct InitializeVault<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    #[account(
-        init,
-        payer = owner,
-        space = 8 + Vault::SIZE,
-        seeds = [b"vault", owner.key().as_ref(), agent_name.as_bytes()],
-        bump
-    )]
-    pub vault: Account<'info, Vault>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Move idle tokens between two vaults of the same owner without leaving program custody
+    ///
+    /// The owner must hold every share of the source vault, since the tokens leave
+    /// without burning any. Above the source vault's large-withdrawal limit, co-signers
+    /// must sign and be passed as `remaining_accounts`.
+    pub fn transfer_between_vaults<'info>(
+        ctx: Context<'_, '_, '_, 'info, TransferBetweenVaults<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, MoluscoError::InvalidAmount);
+        if ctx.accounts.source_vault.share_mint != Pubkey::default() {
+            let share_mint = ctx
+                .accounts
+                .source_share_mint
+                .as_ref()
+                .ok_or(MoluscoError::VaultHasOtherShareholders)?;
+            let held = ctx.accounts.owner_share_account.as_ref().map_or(0, |account| account.amount);
+            require_eq!(held, share_mint.supply, MoluscoError::VaultHasOtherShareholders);
+        }
+        ctx.accounts
+            .source_vault
+            .check_cosigners(amount, ctx.remaining_accounts)?;
+        ctx.accounts.source_vault.check_destination(
+            ctx.accounts.destination_allowlist.as_ref(),
+            &ctx.accounts.destination_vault.key(),
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        let guardian_signed = ctx.accounts.source_vault.guardian_signed(ctx.remaining_accounts);
+        ctx.accounts
+            .source_vault
+            .charge_outflow(amount, now, guardian_signed)?;
+        require_gte!(
+            ctx.accounts.source_token_account.amount,
+            amount,
+            MoluscoError::InsufficientBalance
+        );
 
-#[derive(Accounts)]
-#[instruction(protocol: String, strategy: String, asset: String)]
-pub struct OpenPosition<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = vault.owner == owner.key()
-    )]
-    pub vault: Account<'info, Vault>,
-    
-    #[account(
-        init,
-        payer = owner,
-        space = 8 + Position::SIZE,
-        seeds = [
-            b"position",
-            vault.key().as_ref(),
-            protocol.as_bytes(),
-            asset.as_bytes(),
-            &[vault.position_count as u8]
-        ],
-        bump
-    )]
-    pub position: Account<'info, Position>,
-    
-    pub system_program: Program<'info, System>,
-}
+        let seeds = ctx.accounts.source_vault.signer_seeds();
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.source_token_account.to_account_info(),
+                    to: ctx.accounts.destination_token_account.to_account_info(),
+                    authority: ctx.accounts.source_vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+        )?;
 
-#[derive(Accounts)]
-pub struct UpdatePosition<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = position.owner == owner.key()
-    )]
-    pub position: Account<'info, Position>,
-}
+        let source = &mut ctx.accounts.source_vault;
+        source.total_value_locked = source
+            .total_value_locked
+            .checked_sub(amount as u128)
+            .ok_or(MoluscoError::TvlUnderflow)?;
+        source.cumulative_outflows += amount as u128;
+        let destination = &mut ctx.accounts.destination_vault;
+        destination.total_value_locked = destination
+            .total_value_locked
+            .checked_add(amount as u128)
+            .ok_or(MoluscoError::MathOverflow)?;
+        destination.cumulative_inflows += amount as u128;
 
-#[derive(Accounts)]
-pub struct ClosePosition<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = vault.owner == owner.key()
-    )]
-    pub vault: Account<'info, Vault>,
-    
-    #[account(
-        mut,
-        constraint = position.owner == owner.key(),
-        constraint = position.vault == vault.key(),
-        close = owner
-    )]
-    pub position: Account<'info, Position>,
-}
+        emit!(VaultFlowEvent {
+            vault: source.key(),
+            kind: FlowKind::TransferOut,
+            amount,
+            fee: 0,
+            shares: 0,
+            total_value_locked: source.total_value_locked,
+            timestamp: now,
+        });
+        emit!(VaultFlowEvent {
+            vault: destination.key(),
+            kind: FlowKind::TransferIn,
+            amount,
+            fee: 0,
+            shares: 0,
+            total_value_locked: destination.total_value_locked,
+            timestamp: now,
+        });
 
-#[derive(Accounts)]
-pub struct RecordRebalance<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = vault.owner == owner.key()
-    )]
-    pub vault: Account<'info, Vault>,
-}
+        msg!("Transferred {} from {} to {}", amount, source.key(), destination.key());
+        Ok(())
+    }
 
-#[account]
-pub struct Vault {
-    pub owner: Pubkey,
-    pub agent_name: String,
-    pub total_value_locked: u64,
-    pub position_count: u16,
-    pub created_at: i64,
-    pub last_rebalance: i64,
-    pub bump: u8,
-}
+    /// Set how often the compound crank may fold a position's yield into principal
+    pub fn set_compounding_frequency(
+        ctx: Context<SetCompoundingFrequency>,
+        compound_interval_secs: i64, // 0 disables compounding
+    ) -> Result<()> {
+        require!(compound_interval_secs >= 0, MoluscoError::InvalidCompoundInterval);
 
-impl Vault {
-    pub const SIZE: usize = 32 +      // owner
-        4 + 32 +                        // agent_name (String with max 32 chars)
-        8 +                             // total_value_locked
-        2 +                             // position_count
-        8 +                             // created_at
-        8 +                             // last_rebalance
-        1;                              // bump
-}
+        let position = &mut ctx.accounts.position;
+        position.compound_interval_secs = compound_interval_secs;
 
-#[account]
-pub struct Position {
-    pub owner: Pubkey,
-    pub vault: Pubkey,
-    pub protocol: String,
-    pub strategy: String,
-    pub asset: String,
-    pub amount: u64,
-    pub target_apy: u16,
-    pub opened_at: i64,
-    pub last_update: i64,
-    pub is_active: bool,
-    pub accumulated_yield: u64,
-    pub bump: u8,
-}
+        msg!("Compounding interval set to {} seconds", compound_interval_secs);
+        Ok(())
+    }
 
-impl Position {
-    pub const SIZE: usize = 32 +      // owner
-        32 +                            // vault
-        4 + 16 +                        // protocol (max 16 chars)
-        4 + 20 +                        // strategy (max 20 chars)
-        4 + 10 +                        // asset (max 10 chars)
-        8 +                             // amount
-        2 +                             // target_apy
-        8 +                             // opened_at
-        8 +                             // last_update
-        1 +                             // is_active
-        8 +                             // accumulated_yield
-        1;                              // bump
-}
+    /// Permissionless crank: compound uncompounded yield once the position's cadence has elapsed
+    pub fn compound_position(ctx: Context<CompoundPosition>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        let position = &mut ctx.accounts.position;
+        let vault = &mut ctx.accounts.vault;
 
-#[error_code]
-pub enum MoluscoError {
-    #[msg("Invalid APY value")]
-    InvalidApy,
-    #[msg("Position already closed")]
-    PositionClosed,
-    #[msg("Insufficient vault balance")]
-    InsufficientBalance,
+        require!(position.is_active, MoluscoError::PositionClosed);
+        require!(position.compound_interval_secs > 0, MoluscoError::CompoundingDisabled);
+        require_gte!(
+            now,
+            position.last_compound + position.compound_interval_secs,
+            MoluscoError::CompoundNotDue
+        );
+
+        // Each mark restates the gain above principal, so only the latest one counts, and
+        // only as far as custody actually holds it
+        let pending = if vault.simulation {
+            position.unrealized_yield
+        } else {
+            let held = ctx.accounts.position_escrow.amount.saturating_sub(position.amount);
+            position.unrealized_yield.min(held)
+        };
+        let unrealized_before = position.fold_into_principal(pending)?;
+        position.last_compound = now;
+        vault.restate_unrealized(unrealized_before, position.unrealized_yield);
+        vault.total_value_locked = vault
+            .total_value_locked
+            .checked_add(pending as u128)
+            .ok_or(MoluscoError::MathOverflow)?;
+        let (fee, chargeable) = vault.take_performance_fee(pending)?;
+        let rate_bps = vault.performance_fee_bps;
+        book_fee(
+            vault,
+            &ctx.accounts.fee_ledger,
+            FeeKind::Performance,
+            rate_bps,
+            chargeable,
+            fee,
+            now,
+        )?;
+
+        msg!("Position compounded: {} lamports (performance fee {})", pending, fee);
+        Ok(())
+    }
+
+    /// Configure the adapter quoting unharvested rewards and the haircut applied to them in NAV
+    pub fn set_reward_quote_config(
+        ctx: Context<SetRewardQuoteConfig>,
+        reward_adapter: Pubkey,
+        reward_haircut_bps: u16,
+    ) -> Result<()> {
+        require_gte!(10_000, reward_haircut_bps, MoluscoError::InvalidFeeBps);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.reward_adapter = reward_adapter;
+        vault.reward_haircut_bps = reward_haircut_bps;
+
+        msg!("Reward quotes via {} with {} bps haircut", reward_adapter, reward_haircut_bps);
+        Ok(())
+    }
+
+    /// Mark vault NAV, including haircut pending rewards quoted by the reward adapter
+    pub fn mark_vault<'info>(ctx: Context<'_, '_, '_, 'info, MarkVault<'info>>) -> Result<()> {
+        let pending = if ctx.accounts.vault.simulation {
+            0
+        } else {
+            adapters::quote_pending_rewards(
+                &ctx.accounts.reward_adapter.to_account_info(),
+                ctx.remaining_accounts,
+            )?
+        };
+
+        let vault = &mut ctx.accounts.vault;
+        let haircut = (pending as u128) * (vault.reward_haircut_bps as u128) / 10_000;
+        vault.pending_rewards_value = pending - haircut as u64;
+        vault.nav = vault
+            .total_assets()?
+            .checked_add(vault.pending_rewards_value)
+            .ok_or(MoluscoError::MathOverflow)?;
+        vault.last_mark = Clock::get()?.unix_timestamp;
+
+        msg!("Vault marked. NAV: {} (pending rewards {})", vault.nav, vault.pending_rewards_value);
+        Ok(())
+    }
+
+    /// Set the smallest principal a new position may be opened with
+    pub fn set_min_position_amount(
+        ctx: Context<SetMinPositionAmount>,
+        min_position_amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.vault.min_position_amount = min_position_amount;
+
+        msg!("Minimum position amount set to {}", min_position_amount);
+        Ok(())
+    }
+
+    /// Throttle marks: each position waits `min_update_interval_secs` between updates
+    ///
+    /// Applies to `update_position` and `update_positions_batch`; the final mark of
+    /// `settle_and_close` is never held back.
+    pub fn set_min_update_interval(
+        ctx: Context<SetMinPositionAmount>,
+        min_update_interval_secs: i64,
+    ) -> Result<()> {
+        require_gte!(min_update_interval_secs, 0, MoluscoError::InvalidUpdateInterval);
+        ctx.accounts.vault.min_update_interval_secs = min_update_interval_secs;
+
+        msg!("Minimum update interval set to {} seconds", min_update_interval_secs);
+        Ok(())
+    }
+
+    /// Let anyone flag positions whose last mark is older than `max_staleness_secs` (0 disables)
+    pub fn set_max_staleness(
+        ctx: Context<SetMinPositionAmount>,
+        max_staleness_secs: i64,
+    ) -> Result<()> {
+        require_gte!(max_staleness_secs, 0, MoluscoError::InvalidUpdateInterval);
+        ctx.accounts.vault.max_staleness_secs = max_staleness_secs;
+
+        msg!("Maximum staleness set to {} seconds", max_staleness_secs);
+        Ok(())
+    }
+
+    /// Create a rewards distributor emitting an incentive token to vault depositors
+    pub fn initialize_rewards_distributor(
+        ctx: Context<InitializeRewardsDistributor>,
+        emission_rate: u64, // Reward tokens (base units) per second
+        end_ts: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require_gt!(end_ts, now, MoluscoError::InvalidEmissionSchedule);
+
+        let distributor = &mut ctx.accounts.distributor;
+        distributor.vault = ctx.accounts.vault.key();
+        distributor.reward_mint = ctx.accounts.reward_mint.key();
+        distributor.reward_vault = ctx.accounts.reward_vault.key();
+        distributor.emission_rate = emission_rate;
+        distributor.end_ts = end_ts;
+        distributor.last_update_ts = now;
+        distributor.acc_reward_per_share = 0;
+        distributor.total_stake = 0;
+        distributor.total_funded = 0;
+        distributor.total_emitted = 0;
+        distributor.total_claimed = 0;
+        distributor.bump = ctx.bumps.distributor;
+
+        msg!("Rewards distributor initialized at {} per second", emission_rate);
+        Ok(())
+    }
+
+    /// Deposit incentive tokens into the distributor's reward vault
+    pub fn fund_rewards(ctx: Context<FundRewards>, amount: u64) -> Result<()> {
+        require!(amount > 0, MoluscoError::InvalidAmount);
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.funder_token_account.to_account_info(),
+                    to: ctx.accounts.reward_vault.to_account_info(),
+                    authority: ctx.accounts.funder.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let distributor = &mut ctx.accounts.distributor;
+        distributor.accrue(Clock::get()?.unix_timestamp)?;
+        distributor.total_funded = distributor
+            .total_funded
+            .checked_add(amount)
+            .ok_or(MoluscoError::MathOverflow)?;
+
+        msg!("Rewards funded: {} (total {})", amount, distributor.total_funded);
+        Ok(())
+    }
+
+    /// Change the emission rate and schedule end of a rewards distributor
+    pub fn update_emission_rate(
+        ctx: Context<UpdateEmissionRate>,
+        emission_rate: u64,
+        end_ts: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require_gt!(end_ts, now, MoluscoError::InvalidEmissionSchedule);
+
+        // Settle emissions at the old rate before switching
+        let distributor = &mut ctx.accounts.distributor;
+        distributor.accrue(now)?;
+        distributor.emission_rate = emission_rate;
+        distributor.end_ts = end_ts;
+
+        msg!("Emission rate updated to {} per second until {}", emission_rate, end_ts);
+        Ok(())
+    }
+
+    /// Claim accrued incentive tokens and resync the depositor's stake
+    ///
+    /// A depositor's stake is their vault shares. It is settled on every deposit and
+    /// withdrawal, and here against the share account's balance, so shares received
+    /// by transfer start earning from the depositor's next settlement.
+    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let record = &mut ctx.accounts.depositor_record;
+        record.sync(
+            ctx.accounts.vault.key(),
+            ctx.accounts.depositor.key(),
+            ctx.accounts.depositor_share_account.amount,
+            now,
+            ctx.bumps.depositor_record,
+        )?;
+        let distributor = &mut ctx.accounts.distributor;
+        record.settle_rewards(distributor, now)?;
+        let pending = record.rewards_owed;
+
+        if pending > 0 {
+            let vault_key = distributor.vault;
+            let seeds: &[&[u8]] = &[b"rewards", vault_key.as_ref(), &[distributor.bump]];
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.reward_vault.to_account_info(),
+                        to: ctx.accounts.depositor_token_account.to_account_info(),
+                        authority: distributor.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                pending,
+            )?;
+            distributor.total_claimed = distributor
+                .total_claimed
+                .checked_add(pending)
+                .ok_or(MoluscoError::MathOverflow)?;
+            record.rewards_owed = 0;
+        }
+
+        msg!("Rewards claimed: {}", pending);
+        Ok(())
+    }
+
+    /// Create the singleton config; the program admin, who must also still be the
+    /// program's upgrade authority, signs and becomes the config authority, so one key
+    /// holds both roles
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.authority = ctx.accounts.authority.key();
+        config.buyback_bps = 0;
+        config.buyback_mint = Pubkey::default();
+        config.buyback_treasury = Pubkey::default();
+        config.swap_program = Pubkey::default();
+        config.treasury = ctx.accounts.authority.key();
+        config.bump = ctx.bumps.config;
+
+        msg!("Config initialized with authority: {}", config.authority);
+        Ok(())
+    }
+
+    /// Create and fund a retroactive airdrop campaign claimable by Merkle proof
+    pub fn create_airdrop(
+        ctx: Context<CreateAirdrop>,
+        campaign_id: u64,
+        merkle_root: [u8; 32],
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, MoluscoError::InvalidAmount);
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.authority_token_account.to_account_info(),
+                    to: ctx.accounts.airdrop_vault.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let airdrop = &mut ctx.accounts.airdrop;
+        airdrop.campaign_id = campaign_id;
+        airdrop.mint = ctx.accounts.mint.key();
+        airdrop.token_vault = ctx.accounts.airdrop_vault.key();
+        airdrop.merkle_root = merkle_root;
+        airdrop.total_funded = amount;
+        airdrop.total_claimed = 0;
+        airdrop.claim_count = 0;
+        airdrop.bump = ctx.bumps.airdrop;
+
+        msg!("Airdrop campaign {} created with {} tokens", campaign_id, amount);
+        Ok(())
+    }
+
+    /// Replace the Merkle root of an airdrop campaign
+    pub fn set_merkle_root(ctx: Context<SetMerkleRoot>, merkle_root: [u8; 32]) -> Result<()> {
+        ctx.accounts.airdrop.merkle_root = merkle_root;
+
+        msg!("Merkle root updated for campaign {}", ctx.accounts.airdrop.campaign_id);
+        Ok(())
+    }
+
+    /// Claim an airdrop allocation; leaf = keccak(depositor || amount_le)
+    pub fn claim_airdrop(
+        ctx: Context<ClaimAirdrop>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let claimant = ctx.accounts.claimant.key();
+        let leaf = keccak::hashv(&[claimant.as_ref(), &amount.to_le_bytes()]).0;
+        require!(
+            verify_merkle_proof(&proof, ctx.accounts.airdrop.merkle_root, leaf),
+            MoluscoError::InvalidMerkleProof
+        );
+
+        let airdrop = &ctx.accounts.airdrop;
+        let campaign_id = airdrop.campaign_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"airdrop", airdrop.mint.as_ref(), &campaign_id, &[airdrop.bump]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.airdrop_vault.to_account_info(),
+                    to: ctx.accounts.claimant_token_account.to_account_info(),
+                    authority: ctx.accounts.airdrop.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+
+        let claim = &mut ctx.accounts.claim;
+        claim.airdrop = ctx.accounts.airdrop.key();
+        claim.claimant = claimant;
+        claim.amount = amount;
+        claim.claimed_at = Clock::get()?.unix_timestamp;
+        claim.bump = ctx.bumps.claim;
+
+        let airdrop = &mut ctx.accounts.airdrop;
+        airdrop.total_claimed = airdrop
+            .total_claimed
+            .checked_add(amount)
+            .ok_or(MoluscoError::MathOverflow)?;
+        airdrop.claim_count += 1;
+
+        msg!("Airdrop claimed: {} by {}", amount, claimant);
+        Ok(())
+    }
+
+    /// Configure the share of crystallized fees routed to protocol token buybacks
+    pub fn update_buyback_config(
+        ctx: Context<UpdateBuybackConfig>,
+        buyback_bps: u16,
+        buyback_mint: Pubkey,
+        buyback_treasury: Pubkey,
+        swap_program: Pubkey,
+    ) -> Result<()> {
+        require_gte!(MAX_BUYBACK_BPS, buyback_bps, MoluscoError::InvalidFeeBps);
+
+        let config = &mut ctx.accounts.config;
+        config.buyback_bps = buyback_bps;
+        config.buyback_mint = buyback_mint;
+        config.buyback_treasury = buyback_treasury;
+        config.swap_program = swap_program;
+
+        msg!("Buyback set to {} bps into {}", buyback_bps, buyback_mint);
+        Ok(())
+    }
+
+    /// Create the config-owned escrow collecting the buyback share of fees paid in `mint`
+    pub fn create_buyback_escrow(ctx: Context<CreateBuybackEscrow>) -> Result<()> {
+        msg!(
+            "Buyback escrow {} created for {}",
+            ctx.accounts.buyback_escrow.key(),
+            ctx.accounts.mint.key()
+        );
+        Ok(())
+    }
+
+    /// Set the maximum slippage buybacks accept when swapping between two mints
+    pub fn set_buyback_slippage(
+        ctx: Context<SetBuybackSlippage>,
+        mint_a: Pubkey,
+        mint_b: Pubkey,
+        max_slippage_bps: u16,
+    ) -> Result<()> {
+        require!(mint_a < mint_b, MoluscoError::UnorderedPair);
+        require_gte!(10_000, max_slippage_bps, MoluscoError::InvalidFeeBps);
+
+        let limit = &mut ctx.accounts.pair_limit;
+        limit.vault = ctx.accounts.config.key();
+        limit.mint_a = mint_a;
+        limit.mint_b = mint_b;
+        limit.max_slippage_bps = max_slippage_bps;
+        limit.bump = ctx.bumps.pair_limit;
+
+        msg!("Max buyback slippage for {}/{} set to {} bps", mint_a, mint_b, max_slippage_bps);
+        Ok(())
+    }
+
+    /// Swap fees held in a buyback escrow into the buyback token and send them to the treasury
+    ///
+    /// Only the config authority executes. The proceeds must reach the oracle value of
+    /// `amount_in` less the authority's slippage limit for the pair, so a route cannot be
+    /// filled off a moved pool, and `min_amount_out` can only raise that floor; the escrow may give
+    /// up no more than `amount_in`. The price accounts of the two oracle configs lead
+    /// `remaining_accounts`, as `OracleConfig::convert` takes them; the route's follow.
+    pub fn execute_buyback<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteBuyback<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        route_data: Vec<u8>,
+    ) -> Result<()> {
+        require!(amount_in > 0, MoluscoError::InvalidAmount);
+        require_gte!(
+            ctx.accounts.buyback_escrow.amount,
+            amount_in,
+            MoluscoError::InsufficientBalance
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let input_oracle = &ctx.accounts.input_oracle_config;
+        let price_accounts = input_oracle.price_accounts(&ctx.accounts.output_oracle_config);
+        require_gte!(ctx.remaining_accounts.len(), price_accounts, MoluscoError::OracleAccountsMismatch);
+        let (price_accounts, route_accounts) = ctx.remaining_accounts.split_at(price_accounts);
+        let (fair_amount_out, _) = input_oracle.convert(
+            amount_in,
+            ctx.accounts.input_asset_entry.decimals,
+            &ctx.accounts.output_oracle_config,
+            ctx.accounts.output_asset_entry.decimals,
+            price_accounts,
+            now,
+        )?;
+        let oracle_floor = ctx.accounts.pair_limit.min_amount_out(fair_amount_out);
+        let min_amount_out = min_amount_out.max(oracle_floor);
+
+        let config = &ctx.accounts.config;
+        let seeds: &[&[u8]] = &[b"config", &[config.bump]];
+        let escrow_before = ctx.accounts.buyback_escrow.amount;
+        let balance_before = ctx.accounts.buyback_output.amount;
+
+        adapters::invoke_swap(
+            &ctx.accounts.swap_program.to_account_info(),
+            route_accounts,
+            &config.key(),
+            route_data,
+            &[seeds],
+        )?;
+
+        ctx.accounts.buyback_escrow.reload()?;
+        let spent = escrow_before.saturating_sub(ctx.accounts.buyback_escrow.amount);
+        require_gte!(amount_in, spent, MoluscoError::BalanceMismatch);
+        ctx.accounts.buyback_output.reload()?;
+        let amount_out = ctx
+            .accounts
+            .buyback_output
+            .amount
+            .checked_sub(balance_before)
+            .ok_or(MoluscoError::MathOverflow)?;
+        require_gte!(amount_out, min_amount_out, MoluscoError::SlippageExceeded);
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.buyback_output.to_account_info(),
+                    to: ctx.accounts.buyback_treasury.to_account_info(),
+                    authority: ctx.accounts.config.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount_out,
+        )?;
+
+        msg!("Buyback executed: {} in, {} out (oracle floor {})", spent, amount_out, oracle_floor);
+        Ok(())
+    }
+
+    /// Publish a reusable vault template (fees, caps, mandate)
+    pub fn publish_vault_template(
+        ctx: Context<PublishVaultTemplate>,
+        template_id: u64,
+        performance_fee_bps: u16,
+        management_fee_bps: u16,
+        deposit_cap: u64,
+        max_positions: u16,
+        min_position_amount: u64,
+        creation_fee_lamports: u64,
+    ) -> Result<()> {
+        require_gte!(MAX_PERFORMANCE_FEE_BPS, performance_fee_bps, MoluscoError::InvalidFeeBps);
+        require_gte!(MAX_MANAGEMENT_FEE_BPS, management_fee_bps, MoluscoError::InvalidFeeBps);
+
+        let template = &mut ctx.accounts.template;
+        template.creator = ctx.accounts.creator.key();
+        template.template_id = template_id;
+        template.performance_fee_bps = performance_fee_bps;
+        template.management_fee_bps = management_fee_bps;
+        template.deposit_cap = deposit_cap;
+        template.max_positions = max_positions;
+        template.min_position_amount = min_position_amount;
+        template.creation_fee_lamports = creation_fee_lamports;
+        template.vaults_created = 0;
+        template.bump = ctx.bumps.template;
+
+        msg!("Vault template {} published", template_id);
+        Ok(())
+    }
+
+    /// Initialize a vault whose settings are cloned from a published template
+    pub fn create_vault_from_template(
+        ctx: Context<CreateVaultFromTemplate>,
+        agent_name: String,
+    ) -> Result<()> {
+        let template = &mut ctx.accounts.template;
+
+        if template.creation_fee_lamports > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: ctx.accounts.treasury.to_account_info(),
+                    },
+                ),
+                template.creation_fee_lamports,
+            )?;
+        }
+
+        let vault = &mut ctx.accounts.vault;
+        vault.owner = ctx.accounts.owner.key();
+        vault.seed_owner = ctx.accounts.owner.key();
+        vault.agent_name = agent_name;
+        vault.total_value_locked = 0;
+        vault.active_positions = 0;
+        vault.total_positions_created = 0;
+        vault.created_at = Clock::get()?.unix_timestamp;
+        vault.last_rebalance = 0;
+        vault.template = template.key();
+        vault.performance_fee_bps = template.performance_fee_bps;
+        vault.management_fee_bps = template.management_fee_bps;
+        vault.deposit_cap = template.deposit_cap;
+        vault.max_positions = template.max_positions;
+        vault.min_position_amount = template.min_position_amount;
+        vault.layout_version = migration::LAYOUT_VERSION;
+        vault.bump = ctx.bumps.vault;
+
+        template.vaults_created += 1;
+
+        msg!("Vault {} created from template {}", vault.agent_name, template.template_id);
+        Ok(())
+    }
+
+    /// Put a position on a fixed term (lending term, staking epoch) maturing after `term_secs`
+    pub fn set_position_term(ctx: Context<SetPositionTerm>, term_secs: i64) -> Result<()> {
+        require!(term_secs > 0, MoluscoError::InvalidTerm);
+
+        let position = &mut ctx.accounts.position;
+        require!(position.is_active, MoluscoError::PositionClosed);
+        position.term_secs = term_secs;
+        position.maturity_ts = Clock::get()?
+            .unix_timestamp
+            .checked_add(term_secs)
+            .ok_or(MoluscoError::MathOverflow)?;
+
+        msg!("Position term set; matures at {}", position.maturity_ts);
+        Ok(())
+    }
+
+    /// Roll a matured fixed-term position into its next term, carrying term yield into principal
+    pub fn rollover_position(ctx: Context<RolloverPosition>, next_target_apy: u16) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        let position = &mut ctx.accounts.position;
+        let vault = &mut ctx.accounts.vault;
+
+        require!(position.is_active, MoluscoError::PositionClosed);
+        require!(position.term_secs > 0, MoluscoError::InvalidTerm);
+        require_gte!(now, position.maturity_ts, MoluscoError::PositionNotMatured);
+
+        // Matured term proceeds, the gain at the latest mark that custody holds, become the
+        // next term's principal
+        let term_yield = if vault.simulation {
+            position.unrealized_yield
+        } else {
+            let held = ctx.accounts.position_escrow.amount.saturating_sub(position.amount);
+            position.unrealized_yield.min(held)
+        };
+        let unrealized_before = position.fold_into_principal(term_yield)?;
+        vault.restate_unrealized(unrealized_before, position.unrealized_yield);
+        vault.total_value_locked = vault
+            .total_value_locked
+            .checked_add(term_yield as u128)
+            .ok_or(MoluscoError::MathOverflow)?;
+        let (fee, chargeable) = vault.take_performance_fee(term_yield)?;
+        let rate_bps = vault.performance_fee_bps;
+        book_fee(
+            vault,
+            &ctx.accounts.fee_ledger,
+            FeeKind::Performance,
+            rate_bps,
+            chargeable,
+            fee,
+            now,
+        )?;
+
+        let matured_at = position.maturity_ts;
+        position.retarget(next_target_apy, now);
+        position.maturity_ts = now
+            .checked_add(position.term_secs)
+            .ok_or(MoluscoError::MathOverflow)?;
+        position.rollover_count += 1;
+        position.last_update = now;
+
+        emit!(PositionRolledOverEvent {
+            vault: vault.key(),
+            position: position.key(),
+            matured_at,
+            term_yield,
+            new_principal: position.amount,
+            next_maturity: position.maturity_ts,
+            rollover_count: position.rollover_count,
+        });
+
+        msg!(
+            "Position rolled over (#{}), next maturity {} (performance fee {})",
+            position.rollover_count,
+            position.maturity_ts,
+            fee
+        );
+        Ok(())
+    }
+
+    /// Configure the smoothing reserve: gains above `threshold_bps` per checkpoint are held back
+    /// and released linearly over `release_secs` (0 disables smoothing)
+    pub fn set_smoothing_config(
+        ctx: Context<SetSmoothingConfig>,
+        threshold_bps: u16,
+        release_secs: i64,
+    ) -> Result<()> {
+        require!(release_secs >= 0, MoluscoError::InvalidSmoothingConfig);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.smoothing_threshold_bps = threshold_bps;
+        vault.smoothing_release_secs = release_secs;
+
+        msg!("Smoothing: {} bps threshold, {}s release", threshold_bps, release_secs);
+        Ok(())
+    }
+
+    /// Write a vault checkpoint, routing exceptional gains through the smoothing reserve
+    ///
+    /// Checkpoints are at least `MIN_CHECKPOINT_INTERVAL_SECS` apart.
+    /// When the vault tracks a benchmark, pass the benchmark account and its rate
+    /// feeds (in component order) as `remaining_accounts` to record excess return.
+    /// Any writable position accounts after those get per-position return attribution.
+    pub fn checkpoint_vault<'info>(
+        ctx: Context<'_, '_, '_, 'info, CheckpointVault<'info>>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &mut ctx.accounts.vault;
+        // Strictly increasing timestamps keep checkpoints binary-searchable by time, and
+        // spacing them stops a gain being split under the smoothing threshold
+        require_gt!(now, vault.last_checkpoint_ts, MoluscoError::CheckpointTooSoon);
+        if vault.last_checkpoint_ts != 0 {
+            require_gte!(
+                now,
+                vault.last_checkpoint_ts.saturating_add(MIN_CHECKPOINT_INTERVAL_SECS),
+                MoluscoError::CheckpointTooSoon
+            );
+        }
+        let raw_nav = vault
+            .total_assets()?
+            .checked_add(vault.pending_rewards_value)
+            .ok_or(MoluscoError::MathOverflow)?;
+
+        let (held, released) = vault.apply_smoothing(raw_nav, now);
+        let smoothed_nav = raw_nav.saturating_sub(vault.smoothing_reserve);
+        let minted_supply = if vault.share_mint == Pubkey::default() {
+            0
+        } else {
+            let share_mint = ctx
+                .accounts
+                .share_mint
+                .as_ref()
+                .ok_or(MoluscoError::ShareMintRequired)?;
+            share_mint.supply
+        };
+        let total_shares = vault.total_shares(minted_supply);
+        // Shares are priced off the smoothed NAV, as deposits and withdrawals are
+        let share_price = shares::share_price(smoothed_nav, total_shares)?;
+        let last_share_price = if vault.checkpoint_count == 0 {
+            0
+        } else {
+            ctx.accounts
+                .previous_checkpoint
+                .as_ref()
+                .ok_or(MoluscoError::PreviousCheckpointRequired)?
+                .share_price
+        };
+
+        // Only what the vault itself spent counts; the payer covers the checkpoint's rent
+        let period_costs = vault.operating_costs - vault.last_checkpoint_costs;
+
+        // Returns are measured per share so deposits and withdrawals do not count as return
+        let period_return_bps = return_bps(last_share_price, share_price);
+        let net_share_price =
+            shares::share_price(smoothed_nav.saturating_sub(period_costs), total_shares)?;
+        let net_return_bps = return_bps(last_share_price, net_share_price);
+        if last_share_price > 0 {
+            vault.return_stats.push(period_return_bps);
+        }
+
+        let (benchmark_return_bps, position_infos) = if vault.benchmark == Pubkey::default() {
+            (0, ctx.remaining_accounts)
+        } else {
+            let (benchmark_info, rest) = ctx
+                .remaining_accounts
+                .split_first()
+                .ok_or(MoluscoError::BenchmarkMismatch)?;
+            require_keys_eq!(benchmark_info.key(), vault.benchmark, MoluscoError::BenchmarkMismatch);
+            let benchmark = Account::<Benchmark>::try_from(benchmark_info)?;
+            let feed_count = benchmark.components.len().min(rest.len());
+            let (feed_infos, position_infos) = rest.split_at(feed_count);
+            let blended_apr_bps = benchmark.blended_rate_bps(feed_infos)?;
+            let elapsed = if vault.last_checkpoint_ts == 0 { 0 } else { now - vault.last_checkpoint_ts };
+            (
+                (blended_apr_bps as i128 * elapsed as i128 / SECONDS_PER_YEAR as i128) as i64,
+                position_infos,
+            )
+        };
+
+        // Contribution of each position = weight x position return = value delta / total base
+        require_gte!(MAX_BATCH_SIZE, position_infos.len(), MoluscoError::BatchTooLarge);
+        require_unique_accounts(position_infos)?;
+        let mut positions = Vec::with_capacity(position_infos.len());
+        for info in position_infos {
+            require!(info.is_writable, MoluscoError::BatchAccountNotWritable);
+            let position = Account::<Position>::try_from(info)?;
+            require_keys_eq!(position.vault, vault.key(), MoluscoError::BatchAccountWrongVault);
+            positions.push(position);
+        }
+        let period_base: i128 = positions.iter().map(|p| p.checkpoint_value as i128).sum();
+        let mut contributions = Vec::with_capacity(positions.len());
+        for position in positions.iter_mut() {
+            let delta = position.last_value as i128 - position.checkpoint_value as i128;
+            let contribution_bps = if period_base == 0 { 0 } else { delta * 10_000 / period_base };
+            contributions.push(PositionContribution {
+                position: position.key(),
+                contribution_bps: contribution_bps as i32,
+            });
+            position.checkpoint_value = position.last_value;
+            position.exit(&crate::ID)?;
+        }
+
+        let checkpoint = &mut ctx.accounts.checkpoint;
+        checkpoint.vault = vault.key();
+        checkpoint.index = vault.checkpoint_count;
+        checkpoint.timestamp = now;
+        checkpoint.raw_nav = raw_nav;
+        checkpoint.smoothed_nav = smoothed_nav;
+        checkpoint.smoothing_held = held;
+        checkpoint.smoothing_released = released;
+        checkpoint.smoothing_reserve = vault.smoothing_reserve;
+        checkpoint.period_return_bps = period_return_bps;
+        checkpoint.volatility = vault.return_stats.std_dev();
+        checkpoint.benchmark_return_bps = benchmark_return_bps;
+        checkpoint.excess_return_bps = period_return_bps - benchmark_return_bps;
+        checkpoint.contributions = contributions;
+        checkpoint.operating_costs = period_costs;
+        checkpoint.net_return_bps = net_return_bps;
+        checkpoint.share_price = share_price;
+        checkpoint.sharpe_proxy = vault.return_stats.sharpe_proxy();
+        checkpoint.bump = ctx.bumps.checkpoint;
+
+        vault.smoothed_nav = smoothed_nav;
+        vault.last_checkpoint_nav = raw_nav;
+        vault.last_checkpoint_ts = now;
+        vault.last_checkpoint_costs = vault.operating_costs;
+        vault.checkpoint_count += 1;
+
+        msg!("Checkpoint {}: raw NAV {}, smoothed NAV {}", checkpoint.index, raw_nav, smoothed_nav);
+        Ok(())
+    }
+
+    /// Set the holding-time-decaying exit fee charged on withdrawals
+    pub fn set_exit_fee_schedule(
+        ctx: Context<SetExitFeeSchedule>,
+        schedule: ExitFeeSchedule,
+    ) -> Result<()> {
+        schedule.validate()?;
+        ctx.accounts.vault.exit_fee_schedule = schedule;
+
+        msg!(
+            "Exit fee: {} bps until {}s, zero after {}s",
+            schedule.full_fee_bps,
+            schedule.full_fee_secs,
+            schedule.zero_fee_secs
+        );
+        Ok(())
+    }
+
+    /// Register a protocol adapter and its deployment health thresholds
+    pub fn register_adapter(
+        ctx: Context<RegisterAdapter>,
+        protocol: ProtocolKind,
+        program_id: Pubkey,
+        max_utilization_bps: u16,
+        max_oracle_age_secs: i64,
+    ) -> Result<()> {
+        require_gte!(10_000, max_utilization_bps, MoluscoError::InvalidFeeBps);
+
+        let adapter = &mut ctx.accounts.adapter_config;
+        adapter.protocol = protocol;
+        adapter.program_id = program_id;
+        adapter.max_utilization_bps = max_utilization_bps;
+        adapter.max_oracle_age_secs = max_oracle_age_secs;
+        adapter.bump = ctx.bumps.adapter_config;
+
+        msg!("Adapter registered for {:?}: {}", adapter.protocol, program_id);
+        Ok(())
+    }
+
+    /// Toggle emergency exit for an adapter after a venue incident
+    pub fn set_emergency_exit(
+        ctx: Context<SetEmergencyExit>,
+        emergency_exit: bool,
+        max_slippage_bps: u16,
+    ) -> Result<()> {
+        require_gte!(10_000, max_slippage_bps, MoluscoError::InvalidFeeBps);
+
+        let adapter = &mut ctx.accounts.adapter_config;
+        adapter.emergency_exit = emergency_exit;
+        adapter.emergency_max_slippage_bps = max_slippage_bps;
+
+        msg!("Emergency exit for {:?}: {}", adapter.protocol, emergency_exit);
+        Ok(())
+    }
+
+    /// Permissionless: pull a position's funds out of an adapter in emergency exit mode
+    ///
+    /// Only the position's own share of the venue is unwound: the adapter is asked for
+    /// its latest mark and pays into the position's escrow, which `remaining_accounts`
+    /// must name as the destination. Everything the escrow then holds returns to idle
+    /// escrow as the position's proceeds; sibling positions in the venue are untouched.
+    /// Proceeds are booked as `close_position` books them, and the emptied escrow's rent
+    /// goes to the vault owner.
+    ///
+    /// The vault's other open positions lead `remaining_accounts`, ahead of the adapter's
+    /// accounts, and none on the same venue may have a lower unwind priority.
+    pub fn emergency_withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, EmergencyWithdraw<'info>>,
+    ) -> Result<()> {
+        let adapter = &ctx.accounts.adapter_config;
+        require!(adapter.emergency_exit, MoluscoError::EmergencyExitNotActive);
+        require!(ctx.accounts.position.is_active, MoluscoError::PositionClosed);
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+
+        // The vault's other open positions lead the remaining accounts; the adapter's follow
+        let others = ctx.accounts.vault.active_positions.saturating_sub(1) as usize;
+        require_gte!(
+            ctx.remaining_accounts.len(),
+            others,
+            MoluscoError::UnwindPositionsIncomplete
+        );
+        let (position_infos, adapter_accounts) = ctx.remaining_accounts.split_at(others);
+        require_unwind_turn(&ctx.accounts.position, position_infos)?;
+
+        let received = if ctx.accounts.vault.simulation {
+            // Shadow mode books the exit at the recorded mark
+            ctx.accounts.position.amount
+        } else {
+            let balance_before = ctx.accounts.position_escrow.amount;
+            lock_for_cpi(&mut ctx.accounts.vault)?;
+            let seeds = ctx.accounts.vault.signer_seeds();
+            let result = adapters::withdraw(
+                &ctx.accounts.adapter_program.to_account_info(),
+                adapter_accounts,
+                &ctx.accounts.vault.key(),
+                ctx.accounts.position.last_value,
+                &[&seeds[..]],
+            )?;
+            ctx.accounts.vault.cpi_guard.release();
+            // Withdrawals burn venue receipts, so amount_in is in venue units and unbounded here
+            result.validate(u64::MAX, 0)?;
+
+            ctx.accounts.position_escrow.reload()?;
+            adapters::check_balance_delta(
+                balance_before,
+                ctx.accounts.position_escrow.amount,
+                result.amount_out as i128,
+            )?;
+            let received = ctx.accounts.position_escrow.amount;
+            let seeds = ctx.accounts.vault.signer_seeds();
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.position_escrow.to_account_info(),
+                        to: ctx.accounts.escrow.to_account_info(),
+                        authority: ctx.accounts.vault.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                received,
+            )?;
+            token::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                CloseAccount {
+                    account: ctx.accounts.position_escrow.to_account_info(),
+                    destination: ctx.accounts.owner.to_account_info(),
+                    authority: ctx.accounts.vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ))?;
+            received
+        };
+
+        let principal = ctx.accounts.position.amount;
+        let min_received = (principal as u128)
+            * (10_000 - adapter.emergency_max_slippage_bps as u128)
+            / 10_000;
+        require_gte!(received as u128, min_received, MoluscoError::SlippageExceeded);
+
+        // Funds are now idle in the vault; book any shortfall against TVL
+        if !ctx.accounts.vault.simulation {
+            let reserved = ctx.accounts.position.reserved_capacity;
+            ctx.accounts
+                .position
+                .release_capacity(&mut ctx.accounts.strategy_capacity, reserved);
+        }
+
+        let position = &mut ctx.accounts.position;
+        let vault = &mut ctx.accounts.vault;
+        // As at a close: the surplus over principal is realized yield, a shortfall a loss
+        let realized = position
+            .accumulated_yield
+            .saturating_sub(position.compounded_yield as u128)
+            .min(received.saturating_sub(principal) as u128) as u64;
+        vault.cumulative_losses += principal.saturating_sub(received) as u128;
+        position.is_active = false;
+        vault.active_positions -= 1;
+        let unrealized_before = position.realize(realized);
+        vault.restate_unrealized(unrealized_before, 0);
+        position.unrealized_yield = 0;
+        vault.clear_stale(position);
+        vault.total_value_locked = vault
+            .total_value_locked
+            .saturating_sub(principal as u128)
+            .checked_add(received as u128)
+            .ok_or(MoluscoError::MathOverflow)?;
+
+        let (fee, chargeable) = vault.take_performance_fee(realized)?;
+        let rate_bps = vault.performance_fee_bps;
+        book_fee(
+            vault,
+            &ctx.accounts.fee_ledger,
+            FeeKind::Performance,
+            rate_bps,
+            chargeable,
+            fee,
+            now,
+        )?;
+
+        msg!(
+            "Emergency withdrawal from {:?}: {} of {} (performance fee {})",
+            adapter.protocol,
+            received,
+            principal,
+            fee
+        );
+        Ok(())
+    }
+
+    /// Write down a position after a venue haircut, leaving an auditable impairment record
+    pub fn record_impairment(
+        ctx: Context<RecordImpairment>,
+        write_down: u64,
+        incident_hash: [u8; 32], // Hash of the off-chain incident report
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let position = &mut ctx.accounts.position;
+        let vault = &mut ctx.accounts.vault;
+
+        require!(position.is_active, MoluscoError::PositionClosed);
+        require!(
+            write_down > 0 && write_down <= position.amount,
+            MoluscoError::InvalidImpairment
+        );
+
+        let impairment = &mut ctx.accounts.impairment;
+        impairment.vault = vault.key();
+        impairment.position = position.key();
+        impairment.protocol = position.protocol;
+        impairment.index = position.impairment_count;
+        impairment.amount_before = position.amount;
+        impairment.write_down = write_down;
+        impairment.incident_hash = incident_hash;
+        impairment.recorded_by = ctx.accounts.owner.key();
+        impairment.recorded_at = now;
+        impairment.bump = ctx.bumps.impairment;
+
+        position.amount -= write_down;
+        position.total_impaired += write_down;
+        position.impairment_count += 1;
+        position.last_update = now;
+        // Nothing above the written-down principal is still held; marks start again from it
+        position.last_value = position.last_value.min(position.amount);
+        position.checkpoint_value = position.checkpoint_value.min(position.amount);
+        let unrealized_before = position.unrealized_yield;
+        position.unrealized_yield = 0;
+        vault.restate_unrealized(unrealized_before, 0);
+        vault.total_value_locked = vault.total_value_locked.saturating_sub(write_down as u128);
+        vault.total_impaired += write_down;
+        vault.cumulative_losses += write_down as u128;
+
+        emit!(ImpairmentRecordedEvent {
+            vault: vault.key(),
+            position: position.key(),
+            protocol: position.protocol,
+            write_down,
+            remaining_principal: position.amount,
+            incident_hash,
+            timestamp: now,
+        });
+
+        // Depositor records in `remaining_accounts` are told their share of the loss
+        if !ctx.remaining_accounts.is_empty() {
+            require_gte!(
+                MAX_BATCH_SIZE,
+                ctx.remaining_accounts.len(),
+                MoluscoError::BatchTooLarge
+            );
+            require_unique_accounts(ctx.remaining_accounts)?;
+            let share_mint = ctx
+                .accounts
+                .share_mint
+                .as_ref()
+                .ok_or(MoluscoError::ShareMintRequired)?;
+            let total_shares = vault.total_shares(share_mint.supply);
+            for info in ctx.remaining_accounts.iter() {
+                let record = Account::<DepositorRecord>::try_from(info)?;
+                require_keys_eq!(record.vault, vault.key(), MoluscoError::BatchAccountWrongVault);
+                let loss = shares::mul_div(
+                    write_down,
+                    record.shares as u128,
+                    total_shares as u128,
+                    Rounding::Down,
+                )?;
+                notify_depositor(vault.key(), record.depositor, DepositorNotice::LossSocialized, loss, now);
+            }
+        }
+
+        msg!("Impairment recorded on {:?}: {} written down", position.protocol, write_down);
+        Ok(())
+    }
+
+    /// Register the off-chain optimizer key allowed to post allocation hints
+    pub fn set_strategist(ctx: Context<SetStrategist>, strategist: Pubkey) -> Result<()> {
+        ctx.accounts.vault.strategist = strategist;
+
+        msg!("Strategist set to {}", strategist);
+        Ok(())
+    }
+
+    /// Strategist posts recommended target weights for the vault's positions
+    pub fn post_allocation_hint(
+        ctx: Context<PostAllocationHint>,
+        weights: Vec<AllocationWeight>,
+        expires_at: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require_gt!(expires_at, now, MoluscoError::HintExpired);
+        require_gte!(MAX_HINT_WEIGHTS, weights.len(), MoluscoError::BatchTooLarge);
+        let total_bps: u32 = weights.iter().map(|w| w.weight_bps as u32).sum();
+        require_eq!(total_bps, 10_000, MoluscoError::InvalidHintWeights);
+
+        let hint = &mut ctx.accounts.hint;
+        hint.vault = ctx.accounts.vault.key();
+        hint.strategist = ctx.accounts.strategist.key();
+        hint.weights = weights;
+        hint.posted_at = now;
+        hint.expires_at = expires_at;
+        hint.consumed = false;
+        hint.nonce += 1;
+        hint.bump = ctx.bumps.hint;
+
+        msg!("Allocation hint #{} posted with {} weights", hint.nonce, hint.weights.len());
+        Ok(())
+    }
+
+    /// Re-allocate position principal according to a fresh strategist hint
+    ///
+    /// `remaining_accounts` are writable (position, position escrow, strategy capacity)
+    /// triples in the same order as the hint's weights. On funded vaults principal
+    /// moves between the escrows through the idle escrow, shrinking positions first, so
+    /// each escrow keeps holding its position's new amount, and each leg releases or
+    /// reserves its strategy's capacity for what it gave up or took on. Marks move with
+    /// the principal, so no leg books a gain or loss.
+    pub fn execute_rebalance<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteRebalance<'info>>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let hint = &mut ctx.accounts.hint;
+        require!(!hint.consumed, MoluscoError::HintConsumed);
+        require_gt!(hint.expires_at, now, MoluscoError::HintExpired);
+        require!(
+            hint.strategist == ctx.accounts.vault.strategist,
+            MoluscoError::InvalidStrategist
+        );
+        let triples = ctx.remaining_accounts;
+        require_eq!(hint.weights.len() * 3, triples.len(), MoluscoError::BatchLengthMismatch);
+        // Positions on one strategy share its capacity account
+        let custody: Vec<AccountInfo> = triples
+            .chunks(3)
+            .flat_map(|triple| triple[..2].iter().cloned())
+            .collect();
+        require_unique_accounts(&custody)?;
+
+        let vault_key = ctx.accounts.vault.key();
+        let mut positions = Vec::with_capacity(hint.weights.len());
+        for (triple, weight) in triples.chunks(3).zip(hint.weights.iter()) {
+            require!(
+                triple.iter().all(|info| info.is_writable),
+                MoluscoError::BatchAccountNotWritable
+            );
+            require_keys_eq!(triple[0].key(), weight.position, MoluscoError::BatchAccountMismatch);
+            let position = Account::<Position>::try_from(&triple[0])?;
+            require_keys_eq!(position.vault, vault_key, MoluscoError::BatchAccountWrongVault);
+            require!(position.is_active, MoluscoError::PositionClosed);
+            let (escrow, _) =
+                Pubkey::find_program_address(&[b"position_escrow", triple[0].key.as_ref()], &crate::ID);
+            require_keys_eq!(triple[1].key(), escrow, MoluscoError::BatchAccountMismatch);
+            let (capacity, _) = Pubkey::find_program_address(
+                &[b"strategy_capacity", &position.strategy.seed()],
+                &crate::ID,
+            );
+            require_keys_eq!(triple[2].key(), capacity, MoluscoError::BatchAccountMismatch);
+            positions.push(position);
+        }
+
+        let total: u128 = positions.iter().map(|p| p.amount as u128).sum();
+        let mut allocated: u128 = 0;
+        let last = positions.len().saturating_sub(1);
+        let mut targets = Vec::with_capacity(positions.len());
+        for (i, weight) in hint.weights.iter().enumerate() {
+            // Rounding dust goes to the last leg so total principal is preserved
+            let target = if i == last {
+                total - allocated
+            } else {
+                total * weight.weight_bps as u128 / 10_000
+            };
+            allocated += target;
+            targets.push(target as u64);
+        }
+
+        if !ctx.accounts.vault.simulation {
+            let vault = &ctx.accounts.vault;
+            let seeds = vault.signer_seeds();
+            let idle = ctx.accounts.escrow.to_account_info();
+            // Shrinking legs pay into the idle escrow, and free capacity, before growing
+            // legs draw on either
+            for shrinking in [true, false] {
+                let legs = positions.iter_mut().zip(&targets).zip(triples.chunks(3));
+                for ((position, &target), triple) in legs {
+                    let (from, to, amount) = if shrinking && position.amount > target {
+                        (triple[1].clone(), idle.clone(), position.amount - target)
+                    } else if !shrinking && target > position.amount {
+                        (idle.clone(), triple[1].clone(), target - position.amount)
+                    } else {
+                        continue;
+                    };
+                    let mut capacity = Account::<StrategyCapacity>::try_from(&triple[2])?;
+                    let normalized = decimals::normalize(amount, position.asset_decimals)?;
+                    if shrinking {
+                        position.release_capacity(&mut capacity, normalized);
+                    } else {
+                        position.reserve_capacity(&mut capacity, normalized)?;
+                    }
+                    capacity.exit(&crate::ID)?;
+                    token::transfer(
+                        CpiContext::new_with_signer(
+                            ctx.accounts.token_program.to_account_info(),
+                            Transfer { from, to, authority: vault.to_account_info() },
+                            &[&seeds[..]],
+                        ),
+                        amount,
+                    )?;
+                }
+            }
+        }
+
+        let vault = &mut ctx.accounts.vault;
+        for (position, target) in positions.iter_mut().zip(targets) {
+            // The last mark and checkpoint shift with the principal, keeping the gain above it
+            if target >= position.amount {
+                let grown = target - position.amount;
+                position.last_value = position.last_value.saturating_add(grown);
+                position.checkpoint_value = position.checkpoint_value.saturating_add(grown);
+            } else {
+                let shrunk = position.amount - target;
+                position.last_value = position.last_value.saturating_sub(shrunk);
+                position.checkpoint_value = position.checkpoint_value.saturating_sub(shrunk);
+            }
+            position.amount = target;
+            let unrealized_before = position.unrealized_yield;
+            position.unrealized_yield = unrealized_before
+                .min(position.last_value.saturating_sub(position.amount));
+            vault.restate_unrealized(unrealized_before, position.unrealized_yield);
+            position.last_update = now;
+            position.exit(&crate::ID)?;
+        }
+
+        hint.consumed = true;
+        vault.last_rebalance = now;
+
+        msg!("Rebalance executed from hint #{} across {} positions", hint.nonce, positions.len());
+        Ok(())
+    }
+
+    /// Set the maximum slippage cost the vault may burn on rebalances per epoch (0 = unlimited)
+    pub fn set_slippage_budget(ctx: Context<SetSlippageBudget>, budget_per_epoch: u64) -> Result<()> {
+        ctx.accounts.vault.slippage_budget_per_epoch = budget_per_epoch;
+
+        msg!("Slippage budget set to {} per epoch", budget_per_epoch);
+        Ok(())
+    }
+
+    /// Swap between two vault token accounts through the swap adapter as a rebalance leg
+    ///
+    /// Slippage cost is measured against the oracle value of `amount_in` in the
+    /// destination token and charged to the vault's per-epoch slippage budget. The
+    /// oracle price accounts for both mints lead `remaining_accounts`; the route follows.
+    pub fn rebalance_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, RebalanceSwap<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        route_data: Vec<u8>,
+        idempotency_key: Option<[u8; 16]>,
+    ) -> Result<()> {
+        require!(amount_in > 0, MoluscoError::InvalidAmount);
+        ctx.accounts.vault.recent_keys.consume(idempotency_key)?;
+        let clock = Clock::get()?;
+        let (prices, route_accounts) = ctx.accounts.oracles.prices(
+            &ctx.accounts.source_token_account.mint,
+            &ctx.accounts.destination_token_account.mint,
+            ctx.remaining_accounts,
+            clock.unix_timestamp,
+        )?;
+        let fair_amount_out = prices.destination_value(amount_in)?;
+        // The agent's floor may not be looser than the owner's limit for this pair
+        require_gte!(
+            min_amount_out,
+            ctx.accounts.pair_limit.min_amount_out(fair_amount_out),
+            MoluscoError::SlippageLimitTooLoose
+        );
+        if ctx.accounts.vault.bundle_only {
+            require!(
+                transaction_pays_bundle_tip(
+                    &ctx.accounts.instructions.to_account_info(),
+                    &ctx.accounts.vault.key()
+                )?,
+                MoluscoError::BundleTipMissing
+            );
+        }
+
+        let result = if ctx.accounts.vault.simulation {
+            // Shadow mode fills at the oracle price
+            adapters::AdapterResult::simulated(amount_in, fair_amount_out)
+        } else {
+            require_gte!(
+                ctx.accounts.source_token_account.amount,
+                amount_in,
+                MoluscoError::InsufficientBalance
+            );
+
+            let source_before = ctx.accounts.source_token_account.amount;
+            let destination_before = ctx.accounts.destination_token_account.amount;
+            lock_for_cpi(&mut ctx.accounts.vault)?;
+            let seeds = ctx.accounts.vault.signer_seeds();
+            let result = adapters::invoke_swap(
+                &ctx.accounts.swap_program.to_account_info(),
+                route_accounts,
+                &ctx.accounts.vault.key(),
+                route_data,
+                &[&seeds[..]],
+            )?;
+            ctx.accounts.vault.cpi_guard.release();
+
+            ctx.accounts.source_token_account.reload()?;
+            ctx.accounts.destination_token_account.reload()?;
+            adapters::check_balance_delta(
+                source_before,
+                ctx.accounts.source_token_account.amount,
+                -(result.amount_in as i128),
+            )?;
+            adapters::check_balance_delta(
+                destination_before,
+                ctx.accounts.destination_token_account.amount,
+                result.amount_out as i128,
+            )?;
+            result
+        };
+        result.validate(amount_in, min_amount_out)?;
+        let amount_out = result.amount_out;
+
+        let slippage_cost = fair_amount_out.saturating_sub(amount_out);
+        let vault = &mut ctx.accounts.vault;
+        vault.charge_slippage(slippage_cost, clock.epoch)?;
+        vault.last_rebalance = clock.unix_timestamp;
+
+        msg!(
+            "Rebalance swap: {} in, {} out, fee {}, slippage {} ({} spent this epoch)",
+            result.amount_in,
+            amount_out,
+            result.fee_paid,
+            slippage_cost,
+            vault.slippage_spent
+        );
+        Ok(())
+    }
+
+    /// Park a rebalance leg as a resting limit order on an orderbook adapter
+    pub fn place_resting_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceRestingOrder<'info>>,
+        amount_in: u64,
+        min_amount_out: u64, // Limit price expressed as minimum proceeds
+    ) -> Result<()> {
+        require!(amount_in > 0 && min_amount_out > 0, MoluscoError::InvalidAmount);
+
+        if !ctx.accounts.vault.simulation {
+            require_gte!(
+                ctx.accounts.source_token_account.amount,
+                amount_in,
+                MoluscoError::InsufficientBalance
+            );
+
+            let balance_before = ctx.accounts.source_token_account.amount;
+            lock_for_cpi(&mut ctx.accounts.vault)?;
+            let seeds = ctx.accounts.vault.signer_seeds();
+            adapters::invoke_as_pda(
+                &ctx.accounts.adapter_program.to_account_info(),
+                ctx.remaining_accounts,
+                &ctx.accounts.vault.key(),
+                adapters::order_data("place_order", amount_in, min_amount_out),
+                &[&seeds[..]],
+            )?;
+            ctx.accounts.vault.cpi_guard.release();
+
+            // The book escrows exactly the order size
+            ctx.accounts.source_token_account.reload()?;
+            adapters::check_balance_delta(
+                balance_before,
+                ctx.accounts.source_token_account.amount,
+                -(amount_in as i128),
+            )?;
+        }
+
+        let order = &mut ctx.accounts.order;
+        order.vault = ctx.accounts.vault.key();
+        order.order_id = ctx.accounts.vault.next_order_id;
+        order.adapter = ctx.accounts.adapter_config.key();
+        order.source_mint = ctx.accounts.source_token_account.mint;
+        order.destination_mint = ctx.accounts.destination_token_account.mint;
+        order.amount_in = amount_in;
+        order.min_amount_out = min_amount_out;
+        order.amount_out = 0;
+        order.placed_at = Clock::get()?.unix_timestamp;
+        order.status = OrderStatus::Open;
+        order.bump = ctx.bumps.order;
+
+        ctx.accounts.vault.next_order_id += 1;
+
+        msg!("Resting order {} placed: {} for at least {}", order.order_id, amount_in, min_amount_out);
+        Ok(())
+    }
+
+    /// Cancel an open resting order on its orderbook adapter
+    pub fn cancel_resting_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, ManageRestingOrder<'info>>,
+    ) -> Result<()> {
+        require!(ctx.accounts.order.status == OrderStatus::Open, MoluscoError::OrderNotOpen);
+
+        if !ctx.accounts.vault.simulation {
+            let order = &ctx.accounts.order;
+            lock_for_cpi(&mut ctx.accounts.vault)?;
+            let seeds = ctx.accounts.vault.signer_seeds();
+            adapters::invoke_as_pda(
+                &ctx.accounts.adapter_program.to_account_info(),
+                ctx.remaining_accounts,
+                &ctx.accounts.vault.key(),
+                adapters::order_data("cancel_order", order.amount_in, order.min_amount_out),
+                &[&seeds[..]],
+            )?;
+            ctx.accounts.vault.cpi_guard.release();
+        }
+
+        ctx.accounts.order.status = OrderStatus::Cancelled;
+
+        msg!("Resting order {} cancelled", ctx.accounts.order.order_id);
+        Ok(())
+    }
+
+    /// Settle a resting order's fills back into the vault's destination token account
+    ///
+    /// In a simulation vault the order fills in full at the oracle value of its size
+    /// once that value reaches its limit, and not at all before; the oracle price
+    /// accounts for both mints are then passed as `remaining_accounts`.
+    pub fn settle_resting_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleRestingOrder<'info>>,
+    ) -> Result<()> {
+        require!(ctx.accounts.order.status == OrderStatus::Open, MoluscoError::OrderNotOpen);
+
+        let settled = if ctx.accounts.vault.simulation {
+            let order = &ctx.accounts.order;
+            let (prices, _) = ctx.accounts.oracles.prices(
+                &order.source_mint,
+                &order.destination_mint,
+                ctx.remaining_accounts,
+                Clock::get()?.unix_timestamp,
+            )?;
+            let fill = prices.destination_value(order.amount_in)?;
+            if fill >= order.min_amount_out {
+                fill.saturating_sub(order.amount_out)
+            } else {
+                0
+            }
+        } else {
+            let balance_before = ctx.accounts.destination_token_account.amount;
+            let order = &ctx.accounts.order;
+            lock_for_cpi(&mut ctx.accounts.vault)?;
+            let seeds = ctx.accounts.vault.signer_seeds();
+            let result = adapters::invoke_with_result(
+                &ctx.accounts.adapter_program.to_account_info(),
+                ctx.remaining_accounts,
+                &ctx.accounts.vault.key(),
+                adapters::order_data("settle_order", order.amount_in, order.min_amount_out),
+                &[&seeds[..]],
+            )?;
+            ctx.accounts.vault.cpi_guard.release();
+            // Partial fills may land below the limit; only the input bound is enforced here
+            result.validate(order.amount_in, 0)?;
+
+            ctx.accounts.destination_token_account.reload()?;
+            adapters::check_balance_delta(
+                balance_before,
+                ctx.accounts.destination_token_account.amount,
+                result.amount_out as i128,
+            )?;
+            result.amount_out
+        };
+
+        let order = &mut ctx.accounts.order;
+        order.amount_out = order
+            .amount_out
+            .checked_add(settled)
+            .ok_or(MoluscoError::MathOverflow)?;
+        if order.amount_out >= order.min_amount_out {
+            order.status = OrderStatus::Settled;
+            ctx.accounts.vault.last_rebalance = Clock::get()?.unix_timestamp;
+        }
+
+        msg!("Resting order {} settled {} ({} total)", order.order_id, settled, order.amount_out);
+        Ok(())
+    }
+
+    /// Match opposite rebalance legs of two vaults under one operator internally
+    ///
+    /// `vault_a` sells `amount_a` of its base token to `vault_b`, which pays in its quote
+    /// token at the oracle mid price. The oracle price accounts for the base and then
+    /// the quote mint are passed as `remaining_accounts`. Both vaults' owners must have
+    /// set a slippage limit for the pair, as for a swap; a fill at the mid is within it.
+    pub fn net_rebalance<'info>(
+        ctx: Context<'_, '_, '_, 'info, NetRebalance<'info>>,
+        amount_a: u64,
+    ) -> Result<()> {
+        require!(amount_a > 0, MoluscoError::InvalidAmount);
+
+        let now = Clock::get()?.unix_timestamp;
+        let (prices, rest) = ctx.accounts.oracles.prices(
+            &ctx.accounts.vault_a_base.mint,
+            &ctx.accounts.vault_b_quote.mint,
+            ctx.remaining_accounts,
+            now,
+        )?;
+        require!(rest.is_empty(), MoluscoError::OracleAccountsMismatch);
+        let amount_b = prices.destination_value(amount_a)?;
+        require!(amount_b > 0, MoluscoError::InvalidAmount);
+        require_gte!(ctx.accounts.vault_a_base.amount, amount_a, MoluscoError::InsufficientBalance);
+        require_gte!(ctx.accounts.vault_b_quote.amount, amount_b, MoluscoError::InsufficientBalance);
+
+        let seeds_a = ctx.accounts.vault_a.signer_seeds();
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.vault_a_base.to_account_info(),
+                    to: ctx.accounts.vault_b_base.to_account_info(),
+                    authority: ctx.accounts.vault_a.to_account_info(),
+                },
+                &[&seeds_a[..]],
+            ),
+            amount_a,
+        )?;
+
+        let seeds_b = ctx.accounts.vault_b.signer_seeds();
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.vault_b_quote.to_account_info(),
+                    to: ctx.accounts.vault_a_quote.to_account_info(),
+                    authority: ctx.accounts.vault_b.to_account_info(),
+                },
+                &[&seeds_b[..]],
+            ),
+            amount_b,
+        )?;
+
+        ctx.accounts.vault_a.last_rebalance = now;
+        ctx.accounts.vault_b.last_rebalance = now;
+
+        msg!("Netted {} base against {} quote at the oracle mid", amount_a, amount_b);
+        Ok(())
+    }
+
+    /// Create the global keeper job board
+    pub fn initialize_job_queue(ctx: Context<InitializeJobQueue>) -> Result<()> {
+        let queue = &mut ctx.accounts.job_queue;
+        queue.jobs = Vec::new();
+        queue.total_scheduled = 0;
+        queue.total_completed = 0;
+        queue.bump = ctx.bumps.job_queue;
+
+        msg!("Job queue initialized");
+        Ok(())
+    }
+
+    /// Post a crank task for keepers, escrowing the tip in the job queue
+    pub fn schedule_job(
+        ctx: Context<ScheduleJob>,
+        task: TaskType,
+        due_slot: u64,
+        tip_lamports: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.job_queue.jobs.len() < MAX_JOBS,
+            MoluscoError::JobQueueFull
+        );
+        require_gte!(tip_lamports, MIN_JOB_TIP_LAMPORTS, MoluscoError::JobTipTooSmall);
+
+        let vault_key = ctx.accounts.vault.key();
+        let target = ctx.accounts.target.key();
+        match task {
+            TaskType::Compound | TaskType::Rollover => {
+                let position = Account::<Position>::try_from(&ctx.accounts.target.to_account_info())?;
+                require_keys_eq!(position.vault, vault_key, MoluscoError::JobTargetMismatch);
+            }
+            TaskType::MarkVault | TaskType::Checkpoint => {
+                require_keys_eq!(target, vault_key, MoluscoError::JobTargetMismatch);
+            }
+        }
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: ctx.accounts.job_queue.to_account_info(),
+                },
+            ),
+            tip_lamports,
+        )?;
+
+        ctx.accounts.vault.operating_costs += tip_lamports;
+
+        let queue = &mut ctx.accounts.job_queue;
+        queue.jobs.push(Job {
+            vault: vault_key,
+            task,
+            target,
+            due_slot,
+            tip_lamports,
+            scheduled_at: Clock::get()?.unix_timestamp,
+        });
+        queue.total_scheduled += 1;
+
+        msg!("Job scheduled for slot {} with {} lamport tip", due_slot, tip_lamports);
+        Ok(())
+    }
+
+    /// Keeper claims the tip for a due job after performing it
+    ///
+    /// `target` must be the job's target account; its state is checked to confirm
+    /// the crank actually ran after the job was scheduled.
+    pub fn complete_job(ctx: Context<CompleteJob>, index: u16) -> Result<()> {
+        let clock = Clock::get()?;
+        let job = *ctx
+            .accounts
+            .job_queue
+            .jobs
+            .get(index as usize)
+            .ok_or(MoluscoError::JobNotFound)?;
+        require_gte!(clock.slot, job.due_slot, MoluscoError::JobNotDue);
+        require_keys_eq!(ctx.accounts.target.key(), job.target, MoluscoError::JobNotFound);
+
+        let performed_at = match job.task {
+            TaskType::Compound => {
+                Account::<Position>::try_from(&ctx.accounts.target.to_account_info())?.last_compound
+            }
+            TaskType::Rollover => {
+                Account::<Position>::try_from(&ctx.accounts.target.to_account_info())?.last_update
+            }
+            TaskType::MarkVault => {
+                Account::<Vault>::try_from(&ctx.accounts.target.to_account_info())?.last_mark
+            }
+            TaskType::Checkpoint => {
+                Account::<Vault>::try_from(&ctx.accounts.target.to_account_info())?
+                    .last_checkpoint_ts
+            }
+        };
+        require_gte!(performed_at, job.scheduled_at, MoluscoError::JobNotPerformed);
+
+        let queue = &mut ctx.accounts.job_queue;
+        queue.jobs.swap_remove(index as usize);
+        queue.total_completed += 1;
+
+        if job.tip_lamports > 0 {
+            **queue.to_account_info().try_borrow_mut_lamports()? -= job.tip_lamports;
+            **ctx.accounts.keeper.to_account_info().try_borrow_mut_lamports()? += job.tip_lamports;
+        }
+
+        msg!("Job completed by {}; tip {} lamports", ctx.accounts.keeper.key(), job.tip_lamports);
+        Ok(())
+    }
+
+    /// Drop a job from the queue and refund its tip to the vault owner
+    ///
+    /// The owner may cancel any of the vault's jobs; anyone may cancel one left
+    /// uncompleted for `JOB_EXPIRY_SLOTS` past its due slot.
+    pub fn cancel_job(ctx: Context<CancelJob>, index: u16) -> Result<()> {
+        let clock = Clock::get()?;
+        let job = *ctx
+            .accounts
+            .job_queue
+            .jobs
+            .get(index as usize)
+            .ok_or(MoluscoError::JobNotFound)?;
+        require_keys_eq!(job.vault, ctx.accounts.vault.key(), MoluscoError::JobNotFound);
+        require!(
+            ctx.accounts.caller.key() == ctx.accounts.vault.owner
+                || clock.slot > job.due_slot.saturating_add(JOB_EXPIRY_SLOTS),
+            MoluscoError::JobNotExpired
+        );
+
+        let queue = &mut ctx.accounts.job_queue;
+        queue.jobs.swap_remove(index as usize);
+        **queue.to_account_info().try_borrow_mut_lamports()? -= job.tip_lamports;
+        **ctx.accounts.owner.to_account_info().try_borrow_mut_lamports()? += job.tip_lamports;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.operating_costs = vault.operating_costs.saturating_sub(job.tip_lamports);
+
+        msg!("Job for {} cancelled; {} lamport tip refunded", job.target, job.tip_lamports);
+        Ok(())
+    }
+
+    /// Rank a position for unwinding during liquidity crunches (lower unwinds first)
+    pub fn set_unwind_priority(ctx: Context<SetUnwindPriority>, unwind_priority: u8) -> Result<()> {
+        ctx.accounts.position.unwind_priority = unwind_priority;
+
+        msg!("Unwind priority set to {}", unwind_priority);
+        Ok(())
+    }
+
+    /// Assign separate keys allowed to toggle each pause flag (default: owner)
+    pub fn set_pause_authorities(
+        ctx: Context<SetPauseAuthorities>,
+        deposit_pause_authority: Pubkey,
+        withdrawal_pause_authority: Pubkey,
+        trading_pause_authority: Pubkey,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.deposit_pause_authority = deposit_pause_authority;
+        vault.withdrawal_pause_authority = withdrawal_pause_authority;
+        vault.trading_pause_authority = trading_pause_authority;
+
+        msg!("Pause authorities updated");
+        Ok(())
+    }
+
+    /// Toggle one of the vault's independent pause flags
+    pub fn set_paused(ctx: Context<SetPaused>, kind: PauseKind, paused: bool) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            vault.pause_authority(kind),
+            MoluscoError::InvalidPauseAuthority
+        );
+
+        match kind {
+            PauseKind::Deposits => vault.deposits_paused = paused,
+            PauseKind::Withdrawals => vault.withdrawals_paused = paused,
+            PauseKind::Trading => vault.trading_paused = paused,
+        }
+
+        msg!("{:?} paused: {}", kind, paused);
+        Ok(())
+    }
+
+    /// Check vault accounting invariants against all of its positions and emit a report
+    ///
+    /// Every position of the vault must be passed in `remaining_accounts`. With
+    /// `strict` set, a failed check aborts the transaction (useful in CI).
+    pub fn verify_invariants<'info>(
+        ctx: Context<'_, '_, '_, 'info, VerifyInvariants<'info>>,
+        strict: bool,
+    ) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        let mut active_positions: u16 = 0;
+        let mut active_principal: u128 = 0;
+        let mut seen: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+
+        for info in ctx.remaining_accounts.iter() {
+            require!(!seen.contains(info.key), MoluscoError::DuplicateAccount);
+            seen.push(info.key());
+
+            let position = Account::<Position>::try_from(info)?;
+            require_keys_eq!(position.vault, vault.key(), MoluscoError::BatchAccountWrongVault);
+            if position.is_active {
+                active_positions += 1;
+                active_principal += position.amount as u128;
+            }
+        }
+
+        // TVL also covers idle funds held by the vault, so principal may not exceed it
+        let tvl_ok = active_principal <= vault.total_value_locked;
+        let count_ok = active_positions == vault.active_positions;
+        let nav_ok = vault.pending_rewards_value <= vault.nav
+            && vault.smoothed_nav <= vault.last_checkpoint_nav;
+        let passed = tvl_ok && count_ok && nav_ok;
+
+        emit!(InvariantReportEvent {
+            vault: vault.key(),
+            positions_checked: ctx.remaining_accounts.len() as u16,
+            total_value_locked: vault.total_value_locked,
+            active_principal: active_principal as u64,
+            active_positions: vault.active_positions,
+            active_found: active_positions,
+            tvl_ok,
+            count_ok,
+            nav_ok,
+            passed,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        if strict {
+            require!(passed, MoluscoError::InvariantViolated);
+        }
+
+        msg!("Invariant check {}", if passed { "passed" } else { "FAILED" });
+        Ok(())
+    }
+
+    /// Create a sibling vault with the source's mandate and fees but a different delegate,
+    /// linked to the source by a comparison account for champion/challenger runs
+    ///
+    /// `delegate` is granted every delegate action on the new vault and must not
+    /// already be a delegate of the source.
+    pub fn clone_vault_config(
+        ctx: Context<CloneVaultConfig>,
+        agent_name: String,
+        delegate: Pubkey,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let source = &ctx.accounts.source_vault;
+        require_keys_neq!(delegate, source.delegate, MoluscoError::SameDelegate);
+        require!(ctx.accounts.source_delegation.data_is_empty(), MoluscoError::SameDelegate);
+        require_keys_neq!(delegate, ctx.accounts.owner.key(), MoluscoError::InvalidDelegate);
+
+        let vault = &mut ctx.accounts.vault;
+        vault.owner = ctx.accounts.owner.key();
+        vault.seed_owner = ctx.accounts.owner.key();
+        vault.agent_name = agent_name;
+        vault.created_at = now;
+        vault.template = source.template;
+        vault.performance_fee_bps = source.performance_fee_bps;
+        vault.management_fee_bps = source.management_fee_bps;
+        vault.deposit_cap = source.deposit_cap;
+        vault.max_positions = source.max_positions;
+        vault.min_position_amount = source.min_position_amount;
+        vault.reward_haircut_bps = source.reward_haircut_bps;
+        vault.smoothing_threshold_bps = source.smoothing_threshold_bps;
+        vault.smoothing_release_secs = source.smoothing_release_secs;
+        vault.exit_fee_schedule = source.exit_fee_schedule;
+        vault.slippage_budget_per_epoch = source.slippage_budget_per_epoch;
+        vault.min_update_interval_secs = source.min_update_interval_secs;
+        vault.max_staleness_secs = source.max_staleness_secs;
+        vault.simulation = source.simulation;
+        vault.delegate = delegate;
+        vault.cloned_from = source.key();
+        vault.layout_version = migration::LAYOUT_VERSION;
+        vault.bump = ctx.bumps.vault;
+
+        let comparison = &mut ctx.accounts.comparison;
+        comparison.champion = source.key();
+        comparison.challenger = vault.key();
+        comparison.started_at = now;
+        comparison.champion_start_nav = source.total_assets()?;
+        comparison.challenger_start_nav = 0;
+        comparison.bump = ctx.bumps.comparison;
+
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.vault = vault.key();
+        delegation.delegate = delegate;
+        delegation.can_update = true;
+        delegation.can_rebalance = true;
+        delegation.can_open = true;
+        delegation.can_close = true;
+        delegation.granted_at = now;
+        delegation.bump = ctx.bumps.delegation;
+
+        msg!("Vault {} cloned from {} for delegate {}", vault.agent_name, source.agent_name, delegate);
+        Ok(())
+    }
+
+    /// Snapshot champion vs challenger NAV into the comparison account
+    pub fn update_comparison(ctx: Context<UpdateComparison>) -> Result<()> {
+        let comparison = &mut ctx.accounts.comparison;
+        comparison.champion_nav = ctx.accounts.champion.total_assets()?;
+        comparison.challenger_nav = ctx.accounts.challenger.total_assets()?;
+        // A fresh clone starts empty; its baseline is the first funded snapshot
+        if comparison.challenger_start_nav == 0 {
+            comparison.challenger_start_nav = comparison.challenger_nav;
+        }
+        if comparison.champion_start_nav == 0 {
+            comparison.champion_start_nav = comparison.champion_nav;
+        }
+        comparison.champion_return_bps =
+            return_bps(comparison.champion_start_nav, comparison.champion_nav);
+        comparison.challenger_return_bps =
+            return_bps(comparison.challenger_start_nav, comparison.challenger_nav);
+        comparison.last_update = Clock::get()?.unix_timestamp;
+
+        msg!(
+            "Comparison: champion {} bps, challenger {} bps",
+            comparison.champion_return_bps,
+            comparison.challenger_return_bps
+        );
+        Ok(())
+    }
+
+    /// Write a depositor's performance statement for the period since their last one
+    ///
+    /// Flows and fees come from the depositor's record. The closing balance is what
+    /// the share account's balance redeems for now, so yield credited, or the loss, is
+    /// whatever the balance moved by beyond those flows.
+    pub fn generate_statement(ctx: Context<GenerateStatement>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &ctx.accounts.vault;
+        let record = &ctx.accounts.depositor_record;
+        let cursor = &mut ctx.accounts.cursor;
+
+        if cursor.vault == Pubkey::default() {
+            cursor.vault = vault.key();
+            cursor.depositor = record.depositor;
+            cursor.period_start = record.first_deposit_at;
+            cursor.bump = ctx.bumps.cursor;
+        }
+
+        let asset_decimals = vault.asset_decimals(ctx.accounts.mint.as_ref())?;
+        let closing_balance = vault
+            .share_scale(asset_decimals, &ctx.accounts.share_mint)
+            .assets_for_redeem(
+                ctx.accounts.depositor_share_account.amount,
+                vault.share_assets()?,
+                vault.total_shares(ctx.accounts.share_mint.supply),
+            )?;
+        let inflows = record.deposited - cursor.inflows_snapshot;
+        let outflows = record.withdrawn - cursor.outflows_snapshot;
+        let fees_charged = record.fees_paid - cursor.fees_snapshot;
+        let net = closing_balance as i128 + outflows as i128 + fees_charged as i128
+            - cursor.closing_balance as i128
+            - inflows as i128;
+
+        let statement = &mut ctx.accounts.statement;
+        statement.vault = vault.key();
+        statement.depositor = cursor.depositor;
+        statement.index = cursor.statement_count;
+        statement.period_start = cursor.period_start;
+        statement.period_end = now;
+        statement.opening_balance = cursor.closing_balance;
+        statement.inflows = inflows;
+        statement.outflows = outflows;
+        statement.yield_credited = net.max(0) as u64;
+        statement.losses = (-net).max(0) as u64;
+        statement.fees_charged = fees_charged;
+        statement.closing_balance = closing_balance;
+        statement.bump = ctx.bumps.statement;
+
+        cursor.inflows_snapshot = record.deposited;
+        cursor.outflows_snapshot = record.withdrawn;
+        cursor.fees_snapshot = record.fees_paid;
+        cursor.closing_balance = statement.closing_balance;
+        cursor.period_start = now;
+        cursor.statement_count += 1;
+
+        msg!(
+            "Statement #{}: {} -> {} (yield {}, fees {})",
+            statement.index,
+            statement.opening_balance,
+            statement.closing_balance,
+            statement.yield_credited,
+            statement.fees_charged
+        );
+        Ok(())
+    }
+
+    /// View: annualized APY in basis points between two checkpoints of a vault
+    ///
+    /// Clients binary-search `checkpoint_{n}` PDAs by timestamp to pick the window.
+    pub fn get_apy_between(ctx: Context<GetApyBetween>) -> Result<i64> {
+        let start = &ctx.accounts.start;
+        let end = &ctx.accounts.end;
+        require_gt!(end.index, start.index, MoluscoError::InvalidCheckpointRange);
+
+        // Share price, unlike NAV, does not move with deposits and withdrawals
+        let apy_bps = annualized_return_bps(
+            start.share_price,
+            end.share_price,
+            end.timestamp - start.timestamp,
+        );
+
+        msg!("APY between checkpoints {} and {}: {} bps", start.index, end.index, apy_bps);
+        Ok(apy_bps)
+    }
+
+
+    /// Create a rate feed (e.g. SOL staking rate) maintained by the caller
+    pub fn create_rate_feed(ctx: Context<CreateRateFeed>, label: String, rate_bps: u32) -> Result<()> {
+        require!(label.len() <= 16, MoluscoError::NameTooLong);
+
+        let feed = &mut ctx.accounts.feed;
+        feed.authority = ctx.accounts.authority.key();
+        feed.label = label;
+        feed.rate_bps = rate_bps;
+        feed.updated_at = Clock::get()?.unix_timestamp;
+        feed.bump = ctx.bumps.feed;
+
+        msg!("Rate feed {} created at {} bps", feed.label, rate_bps);
+        Ok(())
+    }
+
+    /// Publish the latest annualized rate on a feed
+    pub fn update_rate_feed(ctx: Context<UpdateRateFeed>, rate_bps: u32) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        feed.rate_bps = rate_bps;
+        feed.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("Rate feed {} updated to {} bps", feed.label, rate_bps);
+        Ok(())
+    }
+
+    /// Define a benchmark as a weighted basket of rate feeds
+    pub fn create_benchmark(
+        ctx: Context<CreateBenchmark>,
+        benchmark_id: u64,
+        components: Vec<BenchmarkComponent>,
+    ) -> Result<()> {
+        require!(
+            !components.is_empty() && components.len() <= MAX_BENCHMARK_COMPONENTS,
+            MoluscoError::InvalidBenchmark
+        );
+        let total_bps: u32 = components.iter().map(|c| c.weight_bps as u32).sum();
+        require_eq!(total_bps, 10_000, MoluscoError::InvalidBenchmark);
+
+        let benchmark = &mut ctx.accounts.benchmark;
+        benchmark.creator = ctx.accounts.creator.key();
+        benchmark.benchmark_id = benchmark_id;
+        benchmark.components = components;
+        benchmark.bump = ctx.bumps.benchmark;
+
+        msg!("Benchmark {} created with {} components", benchmark_id, benchmark.components.len());
+        Ok(())
+    }
+
+    /// Select the benchmark a vault's checkpoints are compared against
+    pub fn set_vault_benchmark(ctx: Context<SetVaultBenchmark>, benchmark: Pubkey) -> Result<()> {
+        ctx.accounts.vault.benchmark = benchmark;
+
+        msg!("Vault benchmark set to {}", benchmark);
+        Ok(())
+    }
+
+    /// Create the vault's fee audit ring buffer
+    pub fn initialize_fee_ledger(ctx: Context<InitializeFeeLedger>) -> Result<()> {
+        let ledger = &mut ctx.accounts.fee_ledger;
+        ledger.vault = ctx.accounts.vault.key();
+        ledger.entries = Vec::new();
+        ledger.head = 0;
+        ledger.total_entries = 0;
+        ledger.bump = ctx.bumps.fee_ledger;
+
+        msg!("Fee ledger initialized for vault {}", ledger.vault);
+        Ok(())
+    }
+
+    /// Book lamports the agent spent running the vault (priority fees, rent) as operating costs
+    pub fn record_operating_cost(ctx: Context<RecordOperatingCost>, lamports: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.operating_costs = vault
+            .operating_costs
+            .checked_add(lamports)
+            .ok_or(MoluscoError::MathOverflow)?;
+
+        msg!("Operating cost recorded: {} lamports (total {})", lamports, vault.operating_costs);
+        Ok(())
+    }
+
+    /// Register N-of-M co-signers required for withdrawals above `large_withdrawal_limit`
+    ///
+    /// Loosening an existing config takes the current quorum, passed as signing
+    /// `remaining_accounts`, so a compromised owner key cannot switch it off alone.
+    pub fn set_cosigners<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetCosigners<'info>>,
+        cosigners: Vec<Pubkey>,
+        threshold: u8,
+        large_withdrawal_limit: u64,
+    ) -> Result<()> {
+        require_gte!(MAX_COSIGNERS, cosigners.len(), MoluscoError::InvalidCosignerConfig);
+        require!(
+            (threshold as usize) <= cosigners.len() && (threshold > 0 || cosigners.is_empty()),
+            MoluscoError::InvalidCosignerConfig
+        );
+        for (i, key) in cosigners.iter().enumerate() {
+            require!(!cosigners[..i].contains(key), MoluscoError::InvalidCosignerConfig);
+        }
+        let vault = &ctx.accounts.vault;
+        if vault.loosens_cosigners(&cosigners, threshold, large_withdrawal_limit) {
+            vault.require_cosigner_quorum(ctx.remaining_accounts)?;
+        }
+
+        let vault = &mut ctx.accounts.vault;
+        vault.cosigners = cosigners;
+        vault.cosign_threshold = threshold;
+        vault.large_withdrawal_limit = large_withdrawal_limit;
+
+        msg!(
+            "Withdrawals above {} need {}-of-{} co-signers",
+            large_withdrawal_limit,
+            threshold,
+            vault.cosigners.len()
+        );
+        Ok(())
+    }
+
+    /// Create the vault's destination allowlist; once set, outflows may only go to approved addresses
+    pub fn initialize_destination_allowlist(ctx: Context<InitializeDestinationAllowlist>) -> Result<()> {
+        let allowlist = &mut ctx.accounts.allowlist;
+        allowlist.vault = ctx.accounts.vault.key();
+        allowlist.destinations = Vec::new();
+        allowlist.pending = Vec::new();
+        allowlist.bump = ctx.bumps.allowlist;
+        ctx.accounts.vault.destination_allowlist = allowlist.key();
+
+        msg!("Destination allowlist enabled for vault {}", allowlist.vault);
+        Ok(())
+    }
+
+    /// Queue a destination; it becomes usable after `ALLOWLIST_TIMELOCK_SECS`
+    pub fn propose_destination(ctx: Context<ManageDestinationAllowlist>, destination: Pubkey) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let allowlist = &mut ctx.accounts.allowlist;
+        require!(
+            !allowlist.destinations.contains(&destination)
+                && !allowlist.pending.iter().any(|p| p.destination == destination),
+            MoluscoError::DestinationAlreadyListed
+        );
+        require!(
+            allowlist.pending.len() < MAX_ALLOWLIST_DESTINATIONS,
+            MoluscoError::AllowlistFull
+        );
+
+        let activates_at = now
+            .checked_add(ALLOWLIST_TIMELOCK_SECS)
+            .ok_or(MoluscoError::MathOverflow)?;
+        allowlist.pending.push(PendingDestination { destination, activates_at });
+
+        msg!("Destination {} proposed, active from {}", destination, activates_at);
+        Ok(())
+    }
+
+    /// Promote a pending destination once its timelock has elapsed
+    pub fn activate_destination(ctx: Context<ManageDestinationAllowlist>, destination: Pubkey) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let allowlist = &mut ctx.accounts.allowlist;
+        let index = allowlist
+            .pending
+            .iter()
+            .position(|p| p.destination == destination)
+            .ok_or(MoluscoError::DestinationNotPending)?;
+        require_gte!(
+            now,
+            allowlist.pending[index].activates_at,
+            MoluscoError::TimelockNotElapsed
+        );
+        require!(
+            allowlist.destinations.len() < MAX_ALLOWLIST_DESTINATIONS,
+            MoluscoError::AllowlistFull
+        );
+
+        allowlist.pending.remove(index);
+        allowlist.destinations.push(destination);
+
+        msg!("Destination {} activated", destination);
+        Ok(())
+    }
+
+    /// Drop a destination or cancel a pending proposal; removals take effect immediately
+    pub fn remove_destination(ctx: Context<ManageDestinationAllowlist>, destination: Pubkey) -> Result<()> {
+        let allowlist = &mut ctx.accounts.allowlist;
+        let before = allowlist.destinations.len() + allowlist.pending.len();
+        allowlist.destinations.retain(|d| *d != destination);
+        allowlist.pending.retain(|p| p.destination != destination);
+        require!(
+            allowlist.destinations.len() + allowlist.pending.len() < before,
+            MoluscoError::DestinationNotAllowed
+        );
+
+        msg!("Destination {} removed", destination);
+        Ok(())
+    }
+
+    /// Cap outflows at `limit_bps` of TVL per 24h window; the guardian may co-sign to exceed it
+    ///
+    /// A tighter limit under the same guardian applies at once and drops any queued
+    /// change. Raising or lifting an active limit, or changing its guardian, is queued
+    /// for `VELOCITY_TIMELOCK_SECS` and applied by `activate_velocity_limit`.
+    pub fn set_velocity_limit(
+        ctx: Context<SetVelocityLimit>,
+        limit_bps: u16, // 0 disables the limit
+        guardian: Pubkey,
+    ) -> Result<()> {
+        require_gte!(10_000, limit_bps, MoluscoError::InvalidVelocityLimit);
+        let vault = &mut ctx.accounts.vault;
+        require_keys_neq!(guardian, vault.owner, MoluscoError::GuardianIsOwner);
+
+        let pending = &mut ctx.accounts.pending_velocity;
+        pending.vault = vault.key();
+        pending.bump = ctx.bumps.pending_velocity;
+        let loosens = vault.velocity_limit_bps > 0
+            && (limit_bps == 0 || limit_bps > vault.velocity_limit_bps || guardian != vault.guardian);
+        if loosens {
+            let activates_at = Clock::get()?
+                .unix_timestamp
+                .checked_add(VELOCITY_TIMELOCK_SECS)
+                .ok_or(MoluscoError::MathOverflow)?;
+            pending.limit_bps = limit_bps;
+            pending.guardian = guardian;
+            pending.activates_at = activates_at;
+
+            msg!(
+                "Velocity limit of {} bps (guardian: {}) queued until {}",
+                limit_bps,
+                guardian,
+                activates_at
+            );
+            return Ok(());
+        }
+
+        pending.activates_at = 0;
+        vault.velocity_limit_bps = limit_bps;
+        vault.guardian = guardian;
+
+        msg!("Velocity limit set to {} bps per day (guardian: {})", limit_bps, guardian);
+        Ok(())
+    }
+
+    /// Apply a queued velocity limit change once its timelock has elapsed
+    pub fn activate_velocity_limit(ctx: Context<ActivateVelocityLimit>) -> Result<()> {
+        let pending = &mut ctx.accounts.pending_velocity;
+        require!(pending.activates_at > 0, MoluscoError::VelocityChangeNotPending);
+        require_gte!(
+            Clock::get()?.unix_timestamp,
+            pending.activates_at,
+            MoluscoError::TimelockNotElapsed
+        );
+
+        let vault = &mut ctx.accounts.vault;
+        vault.velocity_limit_bps = pending.limit_bps;
+        vault.guardian = pending.guardian;
+        pending.activates_at = 0;
+
+        msg!(
+            "Velocity limit set to {} bps per day (guardian: {})",
+            vault.velocity_limit_bps,
+            vault.guardian
+        );
+        Ok(())
+    }
+
+    /// Grant a read-only viewer that may pay for statements and reports but not move funds
+    pub fn set_viewer(ctx: Context<SetViewer>, viewer: Pubkey) -> Result<()> {
+        ctx.accounts.vault.viewer = viewer;
+
+        msg!("Viewer set to {}", viewer);
+        Ok(())
+    }
+
+    /// Register a verified third-party strategy program that vaults may invoke via CPI
+    pub fn register_strategy_program(
+        ctx: Context<RegisterStrategyProgram>,
+        program_id: Pubkey,
+        name: String,
+        max_allocation_bps: u16, // Share of vault TVL a single execution may move
+    ) -> Result<()> {
+        require!(name.len() <= 16, MoluscoError::NameTooLong);
+        require!(
+            max_allocation_bps > 0 && max_allocation_bps <= 10_000,
+            MoluscoError::InvalidFeeBps
+        );
+
+        let registry = &mut ctx.accounts.strategy_program;
+        registry.program_id = program_id;
+        registry.name = name;
+        registry.max_allocation_bps = max_allocation_bps;
+        registry.enabled = true;
+        registry.registered_at = Clock::get()?.unix_timestamp;
+        registry.bump = ctx.bumps.strategy_program;
+
+        msg!("Strategy program {} registered: {}", registry.name, program_id);
+        Ok(())
+    }
+
+    /// Enable or disable a registered strategy program
+    pub fn set_strategy_program_enabled(
+        ctx: Context<SetStrategyProgramEnabled>,
+        enabled: bool,
+    ) -> Result<()> {
+        let registry = &mut ctx.accounts.strategy_program;
+        registry.enabled = enabled;
+
+        msg!("Strategy program {} enabled: {}", registry.name, enabled);
+        Ok(())
+    }
+
+    /// Run a registered strategy program under the vault's allocation and slippage constraints
+    ///
+    /// The oracle price accounts for both mints lead `remaining_accounts`, as
+    /// `SwapOracles::prices` takes them; the rest go to the strategy, and none of them
+    /// may be a token account the vault owns, since the strategy runs with the vault
+    /// as signer. When one side is the vault mint, the fill is booked into TVL at the
+    /// oracle value of the other side, so a fill below it is a realized loss.
+    pub fn execute_strategy<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteStrategy<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        params: Vec<u8>,
+    ) -> Result<()> {
+        require!(amount_in > 0, MoluscoError::InvalidAmount);
+        let max_allocation = ctx.accounts.vault.total_value_locked
+            * ctx.accounts.strategy_registry.max_allocation_bps as u128
+            / 10_000;
+        require_gte!(
+            max_allocation,
+            amount_in as u128,
+            MoluscoError::StrategyAllocationExceeded
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let source_mint = ctx.accounts.source_token_account.mint;
+        let destination_mint = ctx.accounts.destination_token_account.mint;
+        let (prices, strategy_accounts) =
+            ctx.accounts
+                .oracles
+                .prices(&source_mint, &destination_mint, ctx.remaining_accounts, now)?;
+        // The floor may not be looser than the owner's limit for this pair
+        require_gte!(
+            min_amount_out,
+            ctx.accounts
+                .pair_limit
+                .min_amount_out(prices.destination_value(amount_in)?),
+            MoluscoError::SlippageLimitTooLoose
+        );
+
+        let result = if ctx.accounts.vault.simulation {
+            // Shadow mode fills at the oracle price
+            adapters::AdapterResult::simulated(amount_in, prices.destination_value(amount_in)?)
+        } else {
+            require_no_vault_token_accounts(strategy_accounts, &ctx.accounts.vault.key())?;
+            require_gte!(
+                ctx.accounts.source_token_account.amount,
+                amount_in,
+                MoluscoError::InsufficientBalance
+            );
+
+            let mut accounts = vec![
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.source_token_account.to_account_info(),
+                ctx.accounts.destination_token_account.to_account_info(),
+            ];
+            accounts.extend_from_slice(strategy_accounts);
+
+            let source_before = ctx.accounts.source_token_account.amount;
+            let destination_before = ctx.accounts.destination_token_account.amount;
+            lock_for_cpi(&mut ctx.accounts.vault)?;
+            let seeds = ctx.accounts.vault.signer_seeds();
+            let result = strategy::invoke_strategy(
+                &ctx.accounts.strategy_program.to_account_info(),
+                &accounts,
+                &ctx.accounts.vault.key(),
+                strategy::execute_data(amount_in, min_amount_out, &params),
+                &[&seeds[..]],
+            )?;
+            ctx.accounts.vault.cpi_guard.release();
+
+            ctx.accounts.source_token_account.reload()?;
+            ctx.accounts.destination_token_account.reload()?;
+            adapters::check_balance_delta(
+                source_before,
+                ctx.accounts.source_token_account.amount,
+                -(result.amount_in as i128),
+            )?;
+            adapters::check_balance_delta(
+                destination_before,
+                ctx.accounts.destination_token_account.amount,
+                result.amount_out as i128,
+            )?;
+            result
+        };
+        result.validate(amount_in, min_amount_out)?;
+
+        let vault = &mut ctx.accounts.vault;
+        let (value_in, value_out) = if source_mint == vault.mint {
+            (result.amount_in, prices.source_value(result.amount_out)?)
+        } else if destination_mint == vault.mint {
+            (prices.destination_value(result.amount_in)?, result.amount_out)
+        } else {
+            // Neither side is counted in TVL
+            (0, 0)
+        };
+        if value_out >= value_in {
+            let gain = (value_out - value_in) as u128;
+            vault.total_value_locked = vault
+                .total_value_locked
+                .checked_add(gain)
+                .ok_or(MoluscoError::MathOverflow)?;
+            vault.cumulative_yield += gain;
+        } else {
+            let loss = (value_in - value_out) as u128;
+            vault.total_value_locked = vault.total_value_locked.saturating_sub(loss);
+            vault.cumulative_losses += loss;
+        }
+        vault.last_rebalance = now;
+
+        msg!(
+            "Strategy {}: {} in, {} out, fee {}; booked {} against {} at the oracle",
+            ctx.accounts.strategy_registry.name,
+            result.amount_in,
+            result.amount_out,
+            result.fee_paid,
+            value_out,
+            value_in
+        );
+        Ok(())
+    }
+
+    /// Set the maximum slippage the vault accepts when swapping between two mints
+    pub fn set_pair_slippage(
+        ctx: Context<SetPairSlippage>,
+        mint_a: Pubkey,
+        mint_b: Pubkey,
+        max_slippage_bps: u16,
+    ) -> Result<()> {
+        require!(mint_a < mint_b, MoluscoError::UnorderedPair);
+        require_gte!(10_000, max_slippage_bps, MoluscoError::InvalidFeeBps);
+
+        let limit = &mut ctx.accounts.pair_limit;
+        limit.vault = ctx.accounts.vault.key();
+        limit.mint_a = mint_a;
+        limit.mint_b = mint_b;
+        limit.max_slippage_bps = max_slippage_bps;
+        limit.bump = ctx.bumps.pair_limit;
+
+        msg!("Max slippage for {}/{} set to {} bps", mint_a, mint_b, max_slippage_bps);
+        Ok(())
+    }
+
+    /// Require rebalance swaps to travel in a Jito bundle that tips from the vault
+    pub fn set_bundle_config(
+        ctx: Context<SetBundleConfig>,
+        bundle_only: bool,
+        max_tip_lamports: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.bundle_only = bundle_only;
+        vault.max_bundle_tip_lamports = max_tip_lamports;
+
+        msg!("Bundle-only: {} (max tip {} lamports)", bundle_only, max_tip_lamports);
+        Ok(())
+    }
+
+    /// Pay a Jito tip out of the vault's spare lamports; included in the same bundle as the swap
+    pub fn pay_bundle_tip(ctx: Context<PayBundleTip>, lamports: u64) -> Result<()> {
+        require!(lamports > 0, MoluscoError::InvalidAmount);
+        require_gte!(
+            ctx.accounts.vault.max_bundle_tip_lamports,
+            lamports,
+            MoluscoError::BundleTipTooLarge
+        );
+
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let rent_floor = Rent::get()?.minimum_balance(vault_info.data_len());
+        require_gte!(
+            vault_info.lamports().saturating_sub(rent_floor),
+            lamports,
+            MoluscoError::InsufficientLamports
+        );
+        **vault_info.try_borrow_mut_lamports()? -= lamports;
+        **ctx.accounts.tip_account.to_account_info().try_borrow_mut_lamports()? += lamports;
+
+        ctx.accounts.vault.operating_costs += lamports;
+
+        msg!("Bundle tip of {} lamports paid to {}", lamports, ctx.accounts.tip_account.key());
+        Ok(())
+    }
+
+    /// View: amount `depositor` could redeem right now, net of exit fees and outflow limits
+    ///
+    /// Only idle vault liquidity counts; capital deployed in positions must be unwound first.
+    pub fn get_withdrawable_amount(ctx: Context<GetWithdrawableAmount>, depositor: Pubkey) -> Result<u64> {
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &ctx.accounts.vault;
+        let record = &ctx.accounts.depositor_record;
+
+        if vault.withdrawals_paused {
+            return Ok(0);
+        }
+
+        let asset_decimals = vault.asset_decimals(ctx.accounts.mint.as_ref())?;
+        let scale = vault.share_scale(asset_decimals, &ctx.accounts.share_mint);
+        let share_assets = vault.share_assets()?;
+        let total_shares = vault.total_shares(ctx.accounts.share_mint.supply);
+        let claim = scale.assets_for_redeem(record.shares, share_assets, total_shares)?;
+        // A SOL vault's idle funds are its lamport escrow's, less the rent reserve
+        let idle = if vault.holds_native_sol() {
+            let sol_escrow = ctx
+                .accounts
+                .sol_escrow
+                .as_ref()
+                .ok_or(MoluscoError::EscrowAccountRequired)?;
+            sol_escrow.lamports().saturating_sub(Rent::get()?.minimum_balance(0))
+        } else {
+            ctx.accounts
+                .vault_token_account
+                .as_ref()
+                .ok_or(MoluscoError::EscrowAccountRequired)?
+                .amount
+        };
+        let liquid = claim
+            .min(idle.saturating_sub(vault.queued_withdrawals))
+            .min(vault.velocity_headroom(now));
+        // Withdrawing `liquid` would burn the depositor's oldest shares first
+        let burned = scale
+            .shares_for_withdraw(liquid, share_assets, total_shares)?
+            .min(record.shares);
+        let exit_fee = vault
+            .exit_fee_schedule
+            .fee_for_lots(&record.exit_lots(burned, liquid)?, now)?;
+        let withdrawable = liquid.saturating_sub(exit_fee);
+
+        msg!("Withdrawable for {}: {} (exit fee {})", depositor, withdrawable, exit_fee);
+        Ok(withdrawable)
+    }
+
+    /// Create the vault's token escrow; all deposits are held here until deployed
+    pub fn initialize_escrow(ctx: Context<InitializeEscrow>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.mint = ctx.accounts.mint.key();
+        vault.escrow = ctx.accounts.escrow.key();
+
+        msg!("Escrow {} created for mint {}", vault.escrow, vault.mint);
+        Ok(())
+    }
+
+    /// Deposit tokens into the vault escrow
+    ///
+    /// Open to any depositor; each gets shares priced against current TVL, so yield
+    /// accrued before the deposit stays with existing holders.
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        require!(amount > 0, MoluscoError::InvalidAmount);
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        let vault = &ctx.accounts.vault;
+        if vault.deposit_cap > 0 {
+            require_gte!(
+                vault.deposit_cap as u128,
+                vault.total_value_locked.saturating_add(amount as u128),
+                MoluscoError::DepositCapExceeded
+            );
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.depositor_token_account.to_account_info(),
+                    to: ctx.accounts.escrow.to_account_info(),
+                    authority: ctx.accounts.depositor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let shares = mint_shares(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.share_mint,
+            &ctx.accounts.depositor_share_account,
+            &ctx.accounts.token_program,
+            ctx.accounts.mint.decimals,
+            amount,
+        )?;
+        ctx.accounts.depositor_record.credit(
+            ctx.accounts.vault.key(),
+            ctx.accounts.depositor.key(),
+            amount,
+            shares,
+            now,
+            ctx.bumps.depositor_record,
+        )?;
+        settle_depositor_rewards(
+            &ctx.accounts.rewards_distributor,
+            &mut ctx.accounts.depositor_record,
+            now,
+        )?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.total_value_locked = vault
+            .total_value_locked
+            .checked_add(amount as u128)
+            .ok_or(MoluscoError::MathOverflow)?;
+        vault.cumulative_inflows += amount as u128;
+
+        emit!(VaultFlowEvent {
+            vault: vault.key(),
+            kind: FlowKind::Deposit,
+            amount,
+            fee: 0,
+            shares,
+            total_value_locked: vault.total_value_locked,
+            timestamp: now,
+        });
+
+        msg!("Deposited {} for {} shares (TVL {})", amount, shares, vault.total_value_locked);
+        Ok(())
+    }
+
+    /// Withdraw idle tokens from the vault escrow, net of the exit fee
+    ///
+    /// A depositor can take out at most what their shares are worth, yield included.
+    /// Co-signers and the guardian, when required, are passed as `remaining_accounts`.
+    pub fn withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, MoluscoError::InvalidAmount);
+        // Liquidity owed to the withdrawal queue is not available to jump it
+        require_gte!(
+            ctx.accounts.escrow.amount.saturating_sub(ctx.accounts.vault.queued_withdrawals),
+            amount,
+            MoluscoError::InsufficientLiquidity
+        );
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        ctx.accounts.depositor_record.sync(
+            ctx.accounts.vault.key(),
+            ctx.accounts.depositor.key(),
+            ctx.accounts.depositor_share_account.amount,
+            now,
+            ctx.bumps.depositor_record,
+        )?;
+
+        let vault = &ctx.accounts.vault;
+        if ctx.accounts.depositor.key() == vault.owner {
+            vault.check_cosigners(amount, ctx.remaining_accounts)?;
+            vault.check_destination(
+                ctx.accounts.destination_allowlist.as_ref(),
+                &ctx.accounts.destination_token_account.key(),
+            )?;
+        } else {
+            // Outside depositors can only pay out to themselves
+            require_keys_eq!(
+                ctx.accounts.destination_token_account.owner,
+                ctx.accounts.depositor.key(),
+                MoluscoError::DestinationNotAllowed
+            );
+        }
+        let guardian_signed = vault.guardian_signed(ctx.remaining_accounts);
+
+        ctx.accounts
+            .vault
+            .charge_outflow(amount, now, guardian_signed)?;
+        let shares = burn_shares(
+            &ctx.accounts.vault,
+            &ctx.accounts.share_mint,
+            &ctx.accounts.depositor_share_account,
+            &ctx.accounts.depositor,
+            &ctx.accounts.token_program,
+            ctx.accounts.mint.decimals,
+            amount,
+        )?;
+        // Each lot the burned shares come from is aged on its own; the exit fee stays in
+        // escrow until collected
+        let lots = ctx.accounts.depositor_record.exit_lots(shares, amount)?;
+        let exit_fee = ctx.accounts.vault.exit_fee_schedule.fee_for_lots(&lots, now)?;
+        let payout = amount - exit_fee;
+        audit_exit_fee(&ctx.accounts.vault, &lots, now);
+        ctx.accounts.depositor_record.debit(shares)?;
+        ctx.accounts.depositor_record.record_outflow(payout, exit_fee)?;
+        settle_depositor_rewards(
+            &ctx.accounts.rewards_distributor,
+            &mut ctx.accounts.depositor_record,
+            now,
+        )?;
+
+        let seeds = ctx.accounts.vault.signer_seeds();
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.escrow.to_account_info(),
+                    to: ctx.accounts.destination_token_account.to_account_info(),
+                    authority: ctx.accounts.vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            payout,
+        )?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.total_value_locked = vault
+            .total_value_locked
+            .checked_sub(amount as u128)
+            .ok_or(MoluscoError::TvlUnderflow)?;
+        vault.cumulative_outflows += payout as u128;
+        vault.cumulative_fees += exit_fee as u128;
+        vault.fees_accrued += exit_fee as u128;
+        if exit_fee > 0 {
+            notify_depositor(
+                vault.key(),
+                ctx.accounts.depositor.key(),
+                DepositorNotice::FeeCharged,
+                exit_fee,
+                now,
+            );
+        }
+
+        emit!(VaultFlowEvent {
+            vault: vault.key(),
+            kind: FlowKind::Withdraw,
+            amount,
+            fee: exit_fee,
+            shares,
+            total_value_locked: vault.total_value_locked,
+            timestamp: now,
+        });
+
+        msg!(
+            "Withdrew {} for {} shares (exit fee {}, TVL {})",
+            payout,
+            shares,
+            exit_fee,
+            vault.total_value_locked
+        );
+        Ok(())
+    }
+
+    /// Deposit native SOL into the vault's lamport escrow without wrapping
+    pub fn deposit_sol(ctx: Context<DepositSol>, lamports: u64) -> Result<()> {
+        require!(lamports > 0, MoluscoError::InvalidAmount);
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        let vault = &ctx.accounts.vault;
+        require!(vault.holds_native_sol(), MoluscoError::NotSolVault);
+        if vault.deposit_cap > 0 {
+            require_gte!(
+                vault.deposit_cap as u128,
+                vault.total_value_locked.saturating_add(lamports as u128),
+                MoluscoError::DepositCapExceeded
+            );
+        }
+
+        // The escrow must end up rent exempt, or the runtime would reap it
+        let rent_floor = Rent::get()?.minimum_balance(0);
+        require_gte!(
+            ctx.accounts.sol_escrow.lamports().saturating_add(lamports),
+            rent_floor,
+            MoluscoError::EscrowNotRentExempt
+        );
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.depositor.to_account_info(),
+                    to: ctx.accounts.sol_escrow.to_account_info(),
+                },
+            ),
+            lamports,
+        )?;
+
+        let shares = mint_shares(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.share_mint,
+            &ctx.accounts.depositor_share_account,
+            &ctx.accounts.token_program,
+            decimals::SOL_DECIMALS,
+            lamports,
+        )?;
+        ctx.accounts.depositor_record.credit(
+            ctx.accounts.vault.key(),
+            ctx.accounts.depositor.key(),
+            lamports,
+            shares,
+            now,
+            ctx.bumps.depositor_record,
+        )?;
+        settle_depositor_rewards(
+            &ctx.accounts.rewards_distributor,
+            &mut ctx.accounts.depositor_record,
+            now,
+        )?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.total_value_locked = vault
+            .total_value_locked
+            .checked_add(lamports as u128)
+            .ok_or(MoluscoError::MathOverflow)?;
+        vault.cumulative_inflows += lamports as u128;
+
+        emit!(VaultFlowEvent {
+            vault: vault.key(),
+            kind: FlowKind::Deposit,
+            amount: lamports,
+            fee: 0,
+            shares,
+            total_value_locked: vault.total_value_locked,
+            timestamp: now,
+        });
+
+        msg!("Deposited {} lamports for {} shares (TVL {})", lamports, shares, vault.total_value_locked);
+        Ok(())
+    }
+
+    /// Withdraw native SOL from the vault's lamport escrow, net of the exit fee
+    ///
+    /// The escrow keeps its rent-exempt reserve; that reserve is not part of TVL.
+    pub fn withdraw_sol<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawSol<'info>>,
+        lamports: u64,
+    ) -> Result<()> {
+        require!(lamports > 0, MoluscoError::InvalidAmount);
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        ctx.accounts.depositor_record.sync(
+            ctx.accounts.vault.key(),
+            ctx.accounts.depositor.key(),
+            ctx.accounts.depositor_share_account.amount,
+            now,
+            ctx.bumps.depositor_record,
+        )?;
+        let rent_floor = Rent::get()?.minimum_balance(0);
+        require_gte!(
+            ctx.accounts.sol_escrow.lamports().saturating_sub(rent_floor),
+            lamports,
+            MoluscoError::InsufficientLamports
+        );
+
+        let vault = &ctx.accounts.vault;
+        if ctx.accounts.depositor.key() == vault.owner {
+            vault.check_cosigners(lamports, ctx.remaining_accounts)?;
+            vault.check_destination(
+                ctx.accounts.destination_allowlist.as_ref(),
+                &ctx.accounts.destination.key(),
+            )?;
+        } else {
+            require_keys_eq!(
+                ctx.accounts.destination.key(),
+                ctx.accounts.depositor.key(),
+                MoluscoError::DestinationNotAllowed
+            );
+        }
+        let guardian_signed = vault.guardian_signed(ctx.remaining_accounts);
+
+        ctx.accounts
+            .vault
+            .charge_outflow(lamports, now, guardian_signed)?;
+        let shares = burn_shares(
+            &ctx.accounts.vault,
+            &ctx.accounts.share_mint,
+            &ctx.accounts.depositor_share_account,
+            &ctx.accounts.depositor,
+            &ctx.accounts.token_program,
+            decimals::SOL_DECIMALS,
+            lamports,
+        )?;
+        let lots = ctx.accounts.depositor_record.exit_lots(shares, lamports)?;
+        let exit_fee = ctx.accounts.vault.exit_fee_schedule.fee_for_lots(&lots, now)?;
+        let payout = lamports - exit_fee;
+        audit_exit_fee(&ctx.accounts.vault, &lots, now);
+        ctx.accounts.depositor_record.debit(shares)?;
+        ctx.accounts.depositor_record.record_outflow(payout, exit_fee)?;
+        settle_depositor_rewards(
+            &ctx.accounts.rewards_distributor,
+            &mut ctx.accounts.depositor_record,
+            now,
+        )?;
+
+        let vault_key = ctx.accounts.vault.key();
+        let escrow_seeds: &[&[u8]] = &[b"sol_escrow", vault_key.as_ref(), &[ctx.bumps.sol_escrow]];
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.sol_escrow.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                },
+                &[escrow_seeds],
+            ),
+            payout,
+        )?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.total_value_locked = vault
+            .total_value_locked
+            .checked_sub(lamports as u128)
+            .ok_or(MoluscoError::TvlUnderflow)?;
+        vault.cumulative_outflows += payout as u128;
+        vault.cumulative_fees += exit_fee as u128;
+        vault.fees_accrued += exit_fee as u128;
+        if exit_fee > 0 {
+            notify_depositor(
+                vault.key(),
+                ctx.accounts.depositor.key(),
+                DepositorNotice::FeeCharged,
+                exit_fee,
+                now,
+            );
+        }
+
+        emit!(VaultFlowEvent {
+            vault: vault.key(),
+            kind: FlowKind::Withdraw,
+            amount: lamports,
+            fee: exit_fee,
+            shares,
+            total_value_locked: vault.total_value_locked,
+            timestamp: now,
+        });
+
+        msg!(
+            "Withdrew {} lamports for {} shares (exit fee {}, TVL {})",
+            payout,
+            shares,
+            exit_fee,
+            vault.total_value_locked
+        );
+        Ok(())
+    }
+
+    /// Toggle audit mode: verbose events with every intermediate in fee and share math
+    pub fn set_audit_mode(ctx: Context<SetAuditMode>, enabled: bool) -> Result<()> {
+        ctx.accounts.vault.audit_mode = enabled;
+
+        msg!("Audit mode: {}", enabled);
+        Ok(())
+    }
+
+    /// Create the vault's share mint; deposits mint shares and withdrawals burn them
+    pub fn initialize_share_mint(ctx: Context<InitializeShareMint>, decimals: u8) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.share_mint = ctx.accounts.share_mint.key();
+
+        msg!("Share mint {} created with {} decimals", vault.share_mint, decimals);
+        Ok(())
+    }
+
+    /// Queue a withdrawal of `shares`, priced now and paid out by `process_withdrawals`
+    ///
+    /// The shares move into the vault's queue escrow until settled. Co-signers and
+    /// allowlist rules apply to the owner as they do for `withdraw`.
+    pub fn request_withdrawal<'info>(
+        ctx: Context<'_, '_, '_, 'info, RequestWithdrawal<'info>>,
+        shares: u64,
+    ) -> Result<()> {
+        require!(shares > 0, MoluscoError::InvalidAmount);
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        ctx.accounts.depositor_record.sync(
+            ctx.accounts.vault.key(),
+            ctx.accounts.depositor.key(),
+            ctx.accounts.depositor_share_account.amount,
+            now,
+            ctx.bumps.depositor_record,
+        )?;
+        let vault = &ctx.accounts.vault;
+        let assets = vault
+            .share_scale(ctx.accounts.mint.decimals, &ctx.accounts.share_mint)
+            .assets_for_redeem(
+                shares,
+                vault.share_assets()?,
+                vault.total_shares(ctx.accounts.share_mint.supply),
+            )?;
+        require!(assets > 0, MoluscoError::InvalidAmount);
+
+        if ctx.accounts.depositor.key() == vault.owner {
+            vault.check_cosigners(assets, ctx.remaining_accounts)?;
+            vault.check_destination(
+                ctx.accounts.destination_allowlist.as_ref(),
+                &ctx.accounts.destination_token_account.key(),
+            )?;
+        } else {
+            require_keys_eq!(
+                ctx.accounts.destination_token_account.owner,
+                ctx.accounts.depositor.key(),
+                MoluscoError::DestinationNotAllowed
+            );
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.depositor_share_account.to_account_info(),
+                    to: ctx.accounts.queued_shares.to_account_info(),
+                    authority: ctx.accounts.depositor.to_account_info(),
+                },
+            ),
+            shares,
+        )?;
+        // The exit fee is fixed now, from the lots the queued shares come from
+        let lots = ctx.accounts.depositor_record.exit_lots(shares, assets)?;
+        let exit_fee = ctx.accounts.vault.exit_fee_schedule.fee_for_lots(&lots, now)?;
+        audit_exit_fee(&ctx.accounts.vault, &lots, now);
+        ctx.accounts.depositor_record.debit(shares)?;
+        ctx.accounts.depositor_record.record_outflow(assets - exit_fee, exit_fee)?;
+        settle_depositor_rewards(
+            &ctx.accounts.rewards_distributor,
+            &mut ctx.accounts.depositor_record,
+            now,
+        )?;
+
+        let vault = &mut ctx.accounts.vault;
+        let request = &mut ctx.accounts.withdrawal_request;
+        request.vault = vault.key();
+        request.depositor = ctx.accounts.depositor.key();
+        request.destination = ctx.accounts.destination_token_account.key();
+        request.index = vault.withdrawal_queue_tail;
+        request.epoch = vault.withdrawal_epoch;
+        request.assets = assets;
+        request.remaining_assets = assets;
+        request.remaining_shares = shares;
+        request.exit_fee = exit_fee;
+        request.requested_at = now;
+        request.bump = ctx.bumps.withdrawal_request;
+
+        vault.withdrawal_queue_tail += 1;
+        vault.queued_withdrawals = vault
+            .queued_withdrawals
+            .checked_add(assets)
+            .ok_or(MoluscoError::MathOverflow)?;
+
+        emit!(WithdrawalRequestedEvent {
+            vault: vault.key(),
+            request: request.key(),
+            depositor: request.depositor,
+            index: request.index,
+            epoch: request.epoch,
+            shares,
+            assets,
+            timestamp: now,
+        });
+
+        msg!("Withdrawal #{} queued: {} shares for {}", request.index, shares, assets);
+        Ok(())
+    }
+
+    /// Crank: settle queued withdrawals in FIFO order from idle escrow liquidity
+    ///
+    /// `remaining_accounts` are (request, destination token account) pairs starting at
+    /// the queue head. The first request that cannot be covered is filled partially;
+    /// anything after it waits for the next epoch. Callable by anyone.
+    ///
+    /// When idle funds fall short of the batch, (position, position escrow, strategy
+    /// capacity) triples for every open position may follow the pairs; the shortfall is
+    /// then taken from their idle custody, lowest unwind priority first.
+    pub fn process_withdrawals<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProcessWithdrawals<'info>>,
+    ) -> Result<()> {
+        // Request pairs come first; any accounts after them are unwind triples
+        let request_accounts = ctx
+            .remaining_accounts
+            .chunks(2)
+            .take_while(|pair| pair.len() == 2 && is_account::<WithdrawalRequest>(&pair[0]))
+            .count()
+            * 2;
+        let (pairs, unwind_accounts) = ctx.remaining_accounts.split_at(request_accounts);
+        require_gte!(MAX_BATCH_SIZE, pairs.len() / 2, MoluscoError::BatchTooLarge);
+        require_unique_accounts(pairs)?;
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+
+        let vault_key = ctx.accounts.vault.key();
+        let head = ctx.accounts.vault.withdrawal_queue_head;
+        let mut requests = Vec::with_capacity(pairs.len() / 2);
+        for (i, pair) in pairs.chunks(2).enumerate() {
+            require!(pair[0].is_writable && pair[1].is_writable, MoluscoError::BatchAccountNotWritable);
+            let request = Account::<WithdrawalRequest>::try_from(&pair[0])?;
+            require_keys_eq!(request.vault, vault_key, MoluscoError::BatchAccountWrongVault);
+            require_eq!(request.index, head + i as u64, MoluscoError::WithdrawalOutOfOrder);
+            require_keys_eq!(pair[1].key(), request.destination, MoluscoError::BatchAccountMismatch);
+            requests.push(request);
+        }
+
+        let outstanding: Vec<u64> = requests.iter().map(|r| r.remaining_assets).collect();
+        let queued = outstanding.iter().sum::<u64>();
+        if queued > ctx.accounts.escrow.amount && !unwind_accounts.is_empty() {
+            let needed = queued - ctx.accounts.escrow.amount;
+            unwind_for_withdrawals(
+                &ctx.accounts.vault,
+                &ctx.accounts.escrow,
+                &ctx.accounts.token_program,
+                unwind_accounts,
+                needed,
+            )?;
+            ctx.accounts.escrow.reload()?;
+        }
+
+        let vault = &ctx.accounts.vault;
+        let available = ctx.accounts.escrow.amount.min(vault.velocity_headroom(now));
+        let fills = withdrawals::allocate_fills(&outstanding, available);
+
+        let seeds = vault.signer_seeds();
+        let epoch = vault.withdrawal_epoch;
+        let mut tvl = vault.total_value_locked;
+        let (mut filled, mut paid, mut fees, mut settled) = (0u64, 0u64, 0u64, 0u64);
+        for ((request, fill), pair) in requests.iter_mut().zip(fills).zip(pairs.chunks(2)) {
+            if fill == 0 {
+                break;
+            }
+            let (left, done) = withdrawals::apply_fill(request.remaining_assets, fill);
+            let burned = if done {
+                request.remaining_shares
+            } else {
+                shares::mul_div(
+                    request.remaining_shares,
+                    fill as u128,
+                    request.remaining_assets as u128,
+                    Rounding::Up,
+                )?
+            };
+            // Each fill pays its share of the fee fixed when the request was queued
+            let exit_fee = shares::mul_div(
+                request.exit_fee,
+                fill as u128,
+                request.assets as u128,
+                Rounding::Down,
+            )?;
+            let payout = fill - exit_fee;
+
+            token::burn(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Burn {
+                        mint: ctx.accounts.share_mint.to_account_info(),
+                        from: ctx.accounts.queued_shares.to_account_info(),
+                        authority: vault.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                burned,
+            )?;
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.escrow.to_account_info(),
+                        to: pair[1].clone(),
+                        authority: vault.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                payout,
+            )?;
+
+            request.remaining_assets = left;
+            request.remaining_shares -= burned;
+            request.exit(&crate::ID)?;
+
+            tvl = tvl.checked_sub(fill as u128).ok_or(MoluscoError::TvlUnderflow)?;
+            filled += fill;
+            paid += payout;
+            fees += exit_fee;
+            if done {
+                settled += 1;
+            }
+            emit!(VaultFlowEvent {
+                vault: vault_key,
+                kind: FlowKind::Withdraw,
+                amount: fill,
+                fee: exit_fee,
+                shares: burned,
+                total_value_locked: tvl,
+                timestamp: now,
+            });
+            notify_depositor(vault_key, request.depositor, DepositorNotice::WithdrawalFilled, payout, now);
+            if exit_fee > 0 {
+                notify_depositor(vault_key, request.depositor, DepositorNotice::FeeCharged, exit_fee, now);
+            }
+        }
+
+        let vault = &mut ctx.accounts.vault;
+        vault.charge_outflow(filled, now, false)?;
+        vault.total_value_locked = tvl;
+        vault.cumulative_outflows += paid as u128;
+        vault.cumulative_fees += fees as u128;
+        vault.fees_accrued += fees as u128;
+        vault.queued_withdrawals = vault.queued_withdrawals.saturating_sub(filled);
+        vault.withdrawal_queue_head += settled;
+        vault.withdrawal_epoch += 1;
+
+        emit!(WithdrawalsProcessedEvent {
+            vault: vault_key,
+            epoch,
+            filled,
+            settled,
+            queue_head: vault.withdrawal_queue_head,
+            queued_withdrawals: vault.queued_withdrawals,
+            timestamp: now,
+        });
+
+        msg!("Epoch {}: filled {} across the queue, {} requests settled", epoch, filled, settled);
+        Ok(())
+    }
+
+    /// Set the performance fee charged on yield as it is harvested or realized at close
+    pub fn set_performance_fee(ctx: Context<SetPerformanceFee>, performance_fee_bps: u16) -> Result<()> {
+        require_gte!(MAX_PERFORMANCE_FEE_BPS, performance_fee_bps, MoluscoError::InvalidFeeBps);
+        ctx.accounts.vault.performance_fee_bps = performance_fee_bps;
+        if let Some(config) = ctx.accounts.fee_config.as_mut() {
+            config.performance_fee_bps = performance_fee_bps;
+        }
+
+        msg!("Performance fee set to {} bps", performance_fee_bps);
+        Ok(())
+    }
+
+    /// Hand fee collection to a separate authority, e.g. the agent operator's treasury
+    pub fn set_fee_authority(ctx: Context<SetPerformanceFee>, fee_authority: Pubkey) -> Result<()> {
+        ctx.accounts.vault.fee_authority = fee_authority;
+
+        msg!("Fee authority set to {}", fee_authority);
+        Ok(())
+    }
+
+    /// Pay every accrued fee out of the vault's escrow to the fee authority
+    ///
+    /// Covers performance and exit fees plus the management fee streamed up to now.
+    /// Token vaults pass `escrow` and `fee_token_account`; native SOL vaults pass
+    /// `sol_escrow`, and the fee authority receives the lamports directly.
+    pub fn collect_fees(ctx: Context<CollectFees>) -> Result<()> {
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, Clock::get()?.unix_timestamp)?;
+        let amount = token_amount(ctx.accounts.vault.fees_due())?;
+        require!(amount > 0, MoluscoError::NoFeesAccrued);
+
+        let destination = if ctx.accounts.vault.holds_native_sol() {
+            ctx.accounts.fee_authority.to_account_info()
+        } else {
+            ctx.accounts
+                .fee_token_account
+                .as_ref()
+                .ok_or(MoluscoError::MissingFeeAccounts)?
+                .to_account_info()
+        };
+        let buyback = pay_buyback(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.config,
+            ctx.accounts.escrow.as_ref(),
+            ctx.accounts.buyback_escrow.as_ref(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            amount,
+        )?;
+        pay_fees(
+            &mut ctx.accounts.vault,
+            ctx.accounts.escrow.as_ref(),
+            ctx.accounts.sol_escrow.as_ref(),
+            destination,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            amount - buyback,
+        )?;
+
+        msg!(
+            "Collected {} in fees to {}, {} to buybacks",
+            amount - buyback,
+            ctx.accounts.fee_authority.key(),
+            buyback
+        );
+        Ok(())
+    }
+
+    /// Set the yearly management fee streamed against TVL; accrues at the old rate first
+    pub fn set_management_fee(ctx: Context<SetPerformanceFee>, management_fee_bps: u16) -> Result<()> {
+        require_gte!(MAX_MANAGEMENT_FEE_BPS, management_fee_bps, MoluscoError::InvalidFeeBps);
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, Clock::get()?.unix_timestamp)?;
+        ctx.accounts.vault.management_fee_bps = management_fee_bps;
+        if let Some(config) = ctx.accounts.fee_config.as_mut() {
+            config.management_fee_bps = management_fee_bps;
+        }
+
+        msg!("Management fee set to {} bps/year", management_fee_bps);
+        Ok(())
+    }
+
+    /// Publish the vault's fee policy: rates, where swept fees go, and the smallest sweep
+    ///
+    /// The rates are copied onto the vault, which every fee path reads. The recipient
+    /// is a token account of the vault mint, or a system account for native SOL vaults.
+    pub fn set_fee_config(
+        ctx: Context<SetFeeConfig>,
+        performance_fee_bps: u16,
+        management_fee_bps: u16,
+        recipient: Pubkey,
+        min_sweep_amount: u64,
+    ) -> Result<()> {
+        require_gte!(MAX_PERFORMANCE_FEE_BPS, performance_fee_bps, MoluscoError::InvalidFeeBps);
+        require_gte!(MAX_MANAGEMENT_FEE_BPS, management_fee_bps, MoluscoError::InvalidFeeBps);
+        require!(recipient != Pubkey::default(), MoluscoError::InvalidFeeRecipient);
+
+        // Stream the management fee at the old rate up to the change
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, Clock::get()?.unix_timestamp)?;
+        let vault = &mut ctx.accounts.vault;
+        vault.performance_fee_bps = performance_fee_bps;
+        vault.management_fee_bps = management_fee_bps;
+
+        let config = &mut ctx.accounts.fee_config;
+        config.vault = vault.key();
+        config.performance_fee_bps = performance_fee_bps;
+        config.management_fee_bps = management_fee_bps;
+        config.recipient = recipient;
+        config.min_sweep_amount = min_sweep_amount;
+        config.bump = ctx.bumps.fee_config;
+
+        msg!(
+            "Fee config: {} bps performance, {} bps/year management, sweeps of at least {} to {}",
+            performance_fee_bps,
+            management_fee_bps,
+            min_sweep_amount,
+            recipient
+        );
+        Ok(())
+    }
+
+    /// Permissionless: send accrued fees to the configured recipient once they reach the minimum
+    pub fn sweep_fees(ctx: Context<SweepFees>) -> Result<()> {
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, Clock::get()?.unix_timestamp)?;
+        let amount = token_amount(ctx.accounts.vault.fees_due())?;
+        require!(amount > 0, MoluscoError::NoFeesAccrued);
+        require_gte!(
+            amount,
+            ctx.accounts.fee_config.min_sweep_amount,
+            MoluscoError::SweepBelowMinimum
+        );
+
+        let buyback = pay_buyback(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.config,
+            ctx.accounts.escrow.as_ref(),
+            ctx.accounts.buyback_escrow.as_ref(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            amount,
+        )?;
+        pay_fees(
+            &mut ctx.accounts.vault,
+            ctx.accounts.escrow.as_ref(),
+            ctx.accounts.sol_escrow.as_ref(),
+            ctx.accounts.recipient.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            amount - buyback,
+        )?;
+
+        msg!(
+            "Swept {} in fees to {}, {} to buybacks",
+            amount - buyback,
+            ctx.accounts.fee_config.recipient,
+            buyback
+        );
+        Ok(())
+    }
+
+    /// Grant (or change) a delegate's permissions to act on the vault for the owner
+    ///
+    /// Lets an agent's hot key mark, open, close or rebalance positions without
+    /// the owner's wallet signing each transaction.
+    pub fn grant_delegate(
+        ctx: Context<GrantDelegate>,
+        delegate: Pubkey,
+        can_update: bool,
+        can_rebalance: bool,
+        can_open: bool,
+        can_close: bool,
+    ) -> Result<()> {
+        require_keys_neq!(delegate, ctx.accounts.vault.owner, MoluscoError::InvalidDelegate);
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.vault = ctx.accounts.vault.key();
+        delegation.delegate = delegate;
+        delegation.can_update = can_update;
+        delegation.can_rebalance = can_rebalance;
+        delegation.can_open = can_open;
+        delegation.can_close = can_close;
+        delegation.granted_at = Clock::get()?.unix_timestamp;
+        delegation.bump = ctx.bumps.delegation;
+
+        msg!(
+            "Delegate {} granted (update: {}, rebalance: {}, open: {}, close: {})",
+            delegate,
+            can_update,
+            can_rebalance,
+            can_open,
+            can_close
+        );
+        Ok(())
+    }
+
+    /// Revoke a delegate, returning the record's rent to the owner
+    pub fn revoke_delegate(ctx: Context<RevokeDelegate>) -> Result<()> {
+        msg!("Delegate {} revoked", ctx.accounts.delegation.delegate);
+        Ok(())
+    }
+
+    /// Open a short-lived session for an ephemeral key to mark or rebalance the vault
+    ///
+    /// Unlike a delegate, a session lapses on its own at `expiry`, so a leaked bot
+    /// key is only good until then.
+    pub fn create_session(
+        ctx: Context<CreateSession>,
+        session_key: Pubkey,
+        expiry: i64,
+        scope: SessionScope,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require_keys_neq!(session_key, ctx.accounts.vault.owner, MoluscoError::InvalidSessionKey);
+        require!(
+            expiry > now && expiry - now <= MAX_SESSION_DURATION_SECS,
+            MoluscoError::InvalidSessionExpiry
+        );
+        require!(scope.update || scope.rebalance, MoluscoError::EmptySessionScope);
+
+        let session = &mut ctx.accounts.session;
+        session.vault = ctx.accounts.vault.key();
+        session.session_key = session_key;
+        session.scope = scope;
+        session.created_at = now;
+        session.expires_at = expiry;
+        session.bump = ctx.bumps.session;
+
+        msg!(
+            "Session {} opened until {} (update: {}, rebalance: {})",
+            session_key,
+            expiry,
+            scope.update,
+            scope.rebalance
+        );
+        Ok(())
+    }
+
+    /// End a session early (or clean up an expired one), returning its rent to the owner
+    pub fn revoke_session(ctx: Context<RevokeSession>) -> Result<()> {
+        msg!("Session {} revoked", ctx.accounts.session.session_key);
+        Ok(())
+    }
+
+    /// Propose a new vault owner, who must accept before anything changes
+    ///
+    /// Proposing `Pubkey::default()` cancels a pending transfer.
+    pub fn propose_owner(ctx: Context<ProposeOwner>, new_owner: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require_keys_neq!(new_owner, vault.owner, MoluscoError::InvalidPendingOwner);
+        vault.pending_owner = new_owner;
+
+        msg!("Ownership of {} proposed to {}", vault.agent_name, new_owner);
+        Ok(())
+    }
+
+    /// Accept a proposed ownership transfer; the vault address does not change
+    ///
+    /// Delegates and sessions granted by the previous owner stay in force until
+    /// the new owner revokes them.
+    pub fn accept_owner(ctx: Context<AcceptOwner>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let previous_owner = vault.owner;
+        vault.owner = ctx.accounts.new_owner.key();
+        vault.pending_owner = Pubkey::default();
+
+        msg!("Ownership of {} transferred from {} to {}", vault.agent_name, previous_owner, vault.owner);
+        Ok(())
+    }
+
+    /// Stop opens, marks and rebalances on the vault during an incident
+    ///
+    /// The owner or, when one is set, the guardian may pause. Positions can still
+    /// be closed so capital can be pulled back to escrow.
+    pub fn pause_vault(ctx: Context<PauseVault>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == vault.owner
+                || (vault.guardian != Pubkey::default() && authority == vault.guardian),
+            MoluscoError::InvalidPauseAuthority
+        );
+        vault.is_paused = true;
+
+        msg!("Vault {} paused by {}", vault.agent_name, authority);
+        Ok(())
+    }
+
+    /// Lift an incident pause; only the owner may
+    pub fn unpause_vault(ctx: Context<UnpauseVault>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.is_paused = false;
+
+        msg!("Vault {} unpaused", vault.agent_name);
+        Ok(())
+    }
+
+    /// Reconcile the vault's books against the tokens actually sitting in its escrow
+    ///
+    /// Every active position of the vault must be passed in `remaining_accounts`;
+    /// idle funds are TVL less their principal, plus fees and rebates still held for
+    /// payout. A surplus (tokens sent outside program flows) is booked to TVL as
+    /// a donation to depositors, with no performance fee; a deficit is only
+    /// reported, for the owner to investigate.
+    pub fn reconcile_vault<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReconcileVault<'info>>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.vault.simulation, MoluscoError::SimulationVault);
+        require_unique_accounts(ctx.remaining_accounts)?;
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+
+        let vault_key = ctx.accounts.vault.key();
+        let mut active_positions: u16 = 0;
+        let mut active_principal: u64 = 0;
+        for info in ctx.remaining_accounts.iter() {
+            let position = Account::<Position>::try_from(info)?;
+            require_keys_eq!(position.vault, vault_key, MoluscoError::BatchAccountWrongVault);
+            if position.is_active {
+                active_positions += 1;
+                active_principal = active_principal
+                    .checked_add(position.amount)
+                    .ok_or(MoluscoError::MathOverflow)?;
+            }
+        }
+        let vault = &mut ctx.accounts.vault;
+        require_eq!(active_positions, vault.active_positions, MoluscoError::PositionSetIncomplete);
+
+        let recorded_idle = token_amount(
+            vault
+                .total_value_locked
+                .saturating_sub(active_principal as u128)
+                .checked_add(vault.fees_due())
+                .and_then(|idle| idle.checked_add(vault.rebates_accrued))
+                .ok_or(MoluscoError::MathOverflow)?,
+        )?;
+        let actual_idle = ctx.accounts.escrow.amount;
+        let surplus = actual_idle.saturating_sub(recorded_idle);
+        let deficit = recorded_idle.saturating_sub(actual_idle);
+        if surplus > 0 {
+            vault.total_value_locked = vault
+                .total_value_locked
+                .checked_add(surplus as u128)
+                .ok_or(MoluscoError::MathOverflow)?;
+            vault.cumulative_yield += surplus as u128;
+        }
+
+        emit!(VaultReconciledEvent {
+            vault: vault_key,
+            recorded_idle,
+            actual_idle,
+            surplus,
+            deficit,
+            total_value_locked: vault.total_value_locked,
+            timestamp: now,
+        });
+
+        msg!("Vault reconciled: surplus {}, deficit {}", surplus, deficit);
+        Ok(())
+    }
+
+    /// Create the singleton program config; the program's upgrade authority signs and
+    /// becomes the program admin
+    pub fn initialize_program_config(ctx: Context<InitializeProgramConfig>) -> Result<()> {
+        let program_config = &mut ctx.accounts.program_config;
+        program_config.admin = ctx.accounts.admin.key();
+        program_config.paused = false;
+        program_config.paused_at = 0;
+        program_config.bump = ctx.bumps.program_config;
+
+        msg!("Program config initialized with admin: {}", program_config.admin);
+        Ok(())
+    }
+
+    /// Halt (or resume) every state-mutating instruction across all vaults
+    pub fn set_program_paused(ctx: Context<SetProgramPaused>, paused: bool) -> Result<()> {
+        let program_config = &mut ctx.accounts.program_config;
+        program_config.paused = paused;
+        program_config.paused_at = if paused { Clock::get()?.unix_timestamp } else { 0 };
+
+        msg!("Program paused: {}", paused);
+        Ok(())
+    }
+
+    /// Create the registry of protocols positions may be opened in; admin only
+    pub fn initialize_protocol_registry(ctx: Context<InitializeProtocolRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.protocol_registry;
+        registry.protocols = Vec::new();
+        registry.bump = ctx.bumps.protocol_registry;
+
+        msg!("Protocol registry initialized");
+        Ok(())
+    }
+
+    /// Approve `protocol` for new positions and rollovers
+    pub fn whitelist_protocol(ctx: Context<UpdateProtocolRegistry>, protocol: ProtocolKind) -> Result<()> {
+        ctx.accounts.protocol_registry.add(protocol)?;
+
+        msg!("Protocol whitelisted: {:?}", protocol);
+        Ok(())
+    }
+
+    /// Withdraw approval for `protocol`; open positions in it can still be marked and closed
+    pub fn delist_protocol(ctx: Context<UpdateProtocolRegistry>, protocol: ProtocolKind) -> Result<()> {
+        ctx.accounts.protocol_registry.remove(protocol)?;
+
+        msg!("Protocol delisted: {:?}", protocol);
+        Ok(())
+    }
+
+    /// Register `mint` in the asset registry as `kind`, with a display symbol; admin only
+    pub fn register_asset(ctx: Context<RegisterAsset>, kind: AssetKind, symbol: String) -> Result<()> {
+        require!(!symbol.is_empty() && symbol.len() <= 10, MoluscoError::NameTooLong);
+
+        let asset_entry = &mut ctx.accounts.asset_entry;
+        asset_entry.mint = ctx.accounts.mint.key();
+        asset_entry.decimals = ctx.accounts.mint.decimals;
+        asset_entry.kind = kind;
+        asset_entry.symbol = symbol;
+        asset_entry.bump = ctx.bumps.asset_entry;
+
+        msg!("Asset registered: {} ({} decimals)", asset_entry.symbol, asset_entry.decimals);
+        Ok(())
+    }
+
+    /// Set the aggregate principal funded vaults may deploy into `strategy` (0 = uncapped),
+    /// at `decimals::INTERNAL_DECIMALS`
+    pub fn set_strategy_capacity(
+        ctx: Context<SetStrategyCapacity>,
+        strategy: StrategyKind,
+        max_tvl: u128,
+    ) -> Result<()> {
+        let strategy_capacity = &mut ctx.accounts.strategy_capacity;
+        strategy_capacity.strategy = strategy;
+        strategy_capacity.max_tvl = max_tvl;
+        strategy_capacity.bump = ctx.bumps.strategy_capacity;
+
+        msg!(
+            "Strategy {:?} capacity: {} ({} deployed)",
+            strategy_capacity.strategy,
+            max_tvl,
+            strategy_capacity.deployed
+        );
+        Ok(())
+    }
+
+    /// Permissionless: tell a depositor the exit fee on their latest deposit has run out
+    ///
+    /// Changes no state, so it can repeat; the event's timestamp is the expiry time,
+    /// `last_deposit_at + zero_fee_secs`, which services can dedupe on.
+    pub fn notify_lockup_expired(ctx: Context<NotifyLockupExpired>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &ctx.accounts.vault;
+        let record = &ctx.accounts.depositor_record;
+        let schedule = vault.exit_fee_schedule;
+        require!(schedule.full_fee_bps > 0, MoluscoError::LockupNotExpired);
+        let expires_at = record.last_deposit_at.saturating_add(schedule.zero_fee_secs);
+        require_gte!(now, expires_at, MoluscoError::LockupNotExpired);
+
+        let asset_decimals = vault.asset_decimals(ctx.accounts.mint.as_ref())?;
+        let value = vault
+            .share_scale(asset_decimals, &ctx.accounts.share_mint)
+            .assets_for_redeem(
+                record.shares,
+                vault.share_assets()?,
+                vault.total_shares(ctx.accounts.share_mint.supply),
+            )?;
+        notify_depositor(vault.key(), record.depositor, DepositorNotice::LockupExpired, value, expires_at);
+
+        msg!("Lockup expired for {} at {}", record.depositor, expires_at);
+        Ok(())
+    }
+
+    /// Give a position opened under the old one-byte count seed an index
+    ///
+    /// Its address is unchanged; `legacy_seed` tells clients which scheme derives
+    /// it. Indices come from the same counter as new opens, so they stay unique.
+    pub fn migrate_legacy_position(
+        ctx: Context<MigrateLegacyPosition>,
+        legacy_seed: u8,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let position = &mut ctx.accounts.position;
+        require!(!position.legacy_seed, MoluscoError::PositionAlreadyMigrated);
+
+        let derived = Pubkey::create_program_address(
+            &[
+                b"position",
+                vault.key().as_ref(),
+                &position.protocol.seed(),
+                &position.asset.seed(),
+                &[legacy_seed],
+                &[position.bump],
+            ],
+            ctx.program_id,
+        )
+        .map_err(|_| MoluscoError::LegacySeedMismatch)?;
+        require_keys_eq!(derived, position.key(), MoluscoError::LegacySeedMismatch);
+
+        position.index = vault.total_positions_created;
+        position.legacy_seed = true;
+        vault.total_positions_created += 1;
+
+        msg!("Legacy position {} assigned index {}", legacy_seed, position.index);
+        Ok(())
+    }
+
+    /// Undo an open made in error, before the position has been marked or deployed
+    ///
+    /// Only within `VOID_WINDOW_SECS` of the open and while the principal is still
+    /// in the position's custody. Nothing is realized and no fee is taken; a
+    /// completed strategy goes through `close_position` instead.
+    pub fn void_position(ctx: Context<VoidPosition>) -> Result<()> {
+        require_authority(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            DelegateAction::Open,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        let position = &mut ctx.accounts.position;
+        let vault = &mut ctx.accounts.vault;
+        require!(position.is_voidable(now), MoluscoError::VoidWindowElapsed);
+
+        if vault.simulation {
+            vault.total_value_locked -= position.amount as u128;
+        } else {
+            require_eq!(
+                ctx.accounts.position_escrow.amount,
+                position.amount,
+                MoluscoError::PositionAlreadyDeployed
+            );
+            let reserved = position.reserved_capacity;
+            position.release_capacity(&mut ctx.accounts.strategy_capacity, reserved);
+            // The principal never left the vault's books; it moves back to idle escrow
+            let seeds = vault.signer_seeds();
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.position_escrow.to_account_info(),
+                        to: ctx.accounts.escrow.to_account_info(),
+                        authority: vault.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                position.amount,
+            )?;
+            token::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                CloseAccount {
+                    account: ctx.accounts.position_escrow.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ))?;
+        }
+
+        position.is_active = false;
+        vault.active_positions -= 1;
+        vault.clear_stale(position);
+
+        emit!(PositionVoidedEvent {
+            vault: vault.key(),
+            position: position.key(),
+            amount: position.amount,
+            timestamp: now,
+        });
+
+        msg!("Position voided: {} returned", position.amount);
+        Ok(())
+    }
+
+    /// Publish the vault's metadata pointer and social links for explorers and aggregators
+    ///
+    /// Empty strings clear a field. `description_hash` is the SHA-256 of the document
+    /// at `metadata_uri`, so readers can check what they fetched.
+    pub fn set_vault_metadata(
+        ctx: Context<SetVaultMetadata>,
+        metadata_uri: String,
+        description_hash: [u8; 32],
+        website: String,
+        twitter: String,
+        discord: String,
+    ) -> Result<()> {
+        require!(metadata_uri.len() <= MAX_METADATA_URI_LEN, MoluscoError::MetadataTooLong);
+        for link in [&website, &twitter, &discord] {
+            require!(link.len() <= MAX_SOCIAL_LINK_LEN, MoluscoError::MetadataTooLong);
+        }
+
+        let meta = &mut ctx.accounts.vault_meta;
+        meta.vault = ctx.accounts.vault.key();
+        meta.metadata_uri = metadata_uri;
+        meta.description_hash = description_hash;
+        meta.website = website;
+        meta.twitter = twitter;
+        meta.discord = discord;
+        meta.updated_at = Clock::get()?.unix_timestamp;
+        meta.bump = ctx.bumps.vault_meta;
+
+        msg!("Vault metadata set: {}", meta.metadata_uri);
+        Ok(())
+    }
+
+    /// Create the operator's profile, which lists the vaults it runs
+    pub fn initialize_agent_profile(ctx: Context<InitializeAgentProfile>) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+        profile.operator = ctx.accounts.operator.key();
+        profile.vaults = Vec::new();
+        profile.bump = ctx.bumps.profile;
+
+        msg!("Agent profile created for {}", profile.operator);
+        Ok(())
+    }
+
+    /// List a vault the operator owns on its profile
+    pub fn add_profile_vault(ctx: Context<AddProfileVault>) -> Result<()> {
+        let vault = ctx.accounts.vault.key();
+        ctx.accounts.profile.add(vault)?;
+
+        msg!("Vault {} added to profile", vault);
+        Ok(())
+    }
+
+    /// Drop a vault from the profile, e.g. after transferring it
+    pub fn remove_profile_vault(ctx: Context<RemoveProfileVault>, vault: Pubkey) -> Result<()> {
+        ctx.accounts.profile.remove(vault)?;
+
+        msg!("Vault {} removed from profile", vault);
+        Ok(())
+    }
+
+    /// View: TVL, blended APY and position totals across an operator's vaults
+    ///
+    /// Pass every vault on the profile as `remaining_accounts`, in any order.
+    /// Vaults the operator no longer owns are left out of the totals.
+    pub fn get_operator_summary(ctx: Context<GetOperatorSummary>) -> Result<OperatorSummary> {
+        let profile = &ctx.accounts.profile;
+        require_eq!(
+            ctx.remaining_accounts.len(),
+            profile.vaults.len(),
+            MoluscoError::ProfileVaultMismatch
+        );
+        require_unique_accounts(ctx.remaining_accounts)?;
+
+        let mut summary = OperatorSummary::default();
+        let mut weighted_apy: i128 = 0;
+        for info in ctx.remaining_accounts {
+            require!(profile.vaults.contains(info.key), MoluscoError::ProfileVaultMismatch);
+            let vault = Account::<Vault>::try_from(info)?;
+            if vault.owner != profile.operator {
+                continue;
+            }
+            summary.vault_count += 1;
+            summary.total_value_locked += vault.total_value_locked;
+            summary.active_positions += vault.active_positions as u64;
+            summary.total_positions_created += vault.total_positions_created;
+            weighted_apy += vault.trailing_apy_bps() as i128 * vault.total_value_locked as i128;
+        }
+        if summary.total_value_locked > 0 {
+            summary.blended_apy_bps = (weighted_apy / summary.total_value_locked as i128) as i64;
+        }
+
+        msg!(
+            "Operator {}: {} vaults, TVL {}, blended APY {} bps",
+            profile.operator,
+            summary.vault_count,
+            summary.total_value_locked,
+            summary.blended_apy_bps
+        );
+        Ok(summary)
+    }
+
+    /// Route `rebate_share_bps` of each crystallized performance fee to rebates
+    ///
+    /// With no `rebate_recipient` the rebate stays in TVL for depositors; otherwise it
+    /// accrues for `pay_rebates`. Rebates already accrued return to TVL when the
+    /// recipient is cleared. Applies to fees crystallized from now on.
+    pub fn set_profit_share(
+        ctx: Context<SetPerformanceFee>,
+        rebate_share_bps: u16,
+        rebate_recipient: Pubkey,
+    ) -> Result<()> {
+        require_gte!(fees::MAX_REBATE_SHARE_BPS, rebate_share_bps, MoluscoError::InvalidFeeBps);
+        let vault = &mut ctx.accounts.vault;
+        if rebate_recipient == Pubkey::default() && vault.rebates_accrued > 0 {
+            vault.total_value_locked = vault
+                .total_value_locked
+                .checked_add(vault.rebates_accrued)
+                .ok_or(MoluscoError::MathOverflow)?;
+            vault.rebates_accrued = 0;
+        }
+        vault.rebate_share_bps = rebate_share_bps;
+        vault.rebate_recipient = rebate_recipient;
+
+        msg!("Profit share: {} bps of performance fees rebated to {}", rebate_share_bps, rebate_recipient);
+        Ok(())
+    }
+
+    /// Permissionless: pay accrued rebates out of escrow to the vault's rebate recipient
+    pub fn pay_rebates(ctx: Context<PayRebates>) -> Result<()> {
+        let amount = token_amount(ctx.accounts.vault.rebates_accrued)?;
+        require!(amount > 0, MoluscoError::NoRebatesAccrued);
+
+        pay_from_escrow(
+            &mut ctx.accounts.vault,
+            ctx.accounts.escrow.as_ref(),
+            ctx.accounts.sol_escrow.as_ref(),
+            ctx.accounts.recipient.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            amount,
+        )?;
+        ctx.accounts.vault.rebates_accrued = 0;
+
+        msg!("Paid {} in rebates to {}", amount, ctx.accounts.recipient.key());
+        Ok(())
+    }
+
+    /// Permissionless: rewrite a vault written under an earlier layout in the current one
+    ///
+    /// The account grows by the fields later layouts added; the payer covers the
+    /// extra rent. Values are carried over unchanged and new fields start at zero.
+    pub fn migrate_vault_layout(ctx: Context<MigrateVaultLayout>) -> Result<()> {
+        let info = ctx.accounts.vault.to_account_info();
+        let mut data = info.try_borrow_data()?.to_vec();
+        require!(data.starts_with(&Vault::DISCRIMINATOR), MoluscoError::NotLegacyLayout);
+
+        migration::upgrade_vault(&mut data)?;
+        require_eq!(data.len(), 8 + Vault::SIZE, MoluscoError::NotLegacyLayout);
+        Vault::try_deserialize(&mut &data[..])?;
+        migration::rewrite_account(&info, &ctx.accounts.payer, &ctx.accounts.system_program, &data)?;
+
+        msg!("Vault {} migrated to layout {}", info.key, migration::LAYOUT_VERSION);
+        Ok(())
+    }
+
+    /// Permissionless: rewrite a position written under an earlier layout in the current one
+    ///
+    /// Earlier layouts did not record the strategy capacity a position holds; a live
+    /// position of a funded vault is taken to hold its principal less the yield folded
+    /// into it. The vault must already be on the current layout.
+    pub fn migrate_position_layout(ctx: Context<MigratePositionLayout>) -> Result<()> {
+        let info = ctx.accounts.position.to_account_info();
+        let mut data = info.try_borrow_data()?.to_vec();
+        require!(data.starts_with(&Position::DISCRIMINATOR), MoluscoError::NotLegacyLayout);
+
+        migration::upgrade_position(&mut data)?;
+        require_eq!(data.len(), 8 + Position::SIZE, MoluscoError::NotLegacyLayout);
+        let mut position = Position::try_deserialize(&mut &data[..])?;
+        require_keys_eq!(position.vault, ctx.accounts.vault.key(), MoluscoError::PositionVaultMismatch);
+        if position.is_active && !ctx.accounts.vault.simulation {
+            position.reserved_capacity = decimals::normalize(
+                position.amount.saturating_sub(position.compounded_yield),
+                position.asset_decimals,
+            )?;
+            position.try_serialize(&mut &mut data[..])?;
+        }
+        migration::rewrite_account(&info, &ctx.accounts.payer, &ctx.accounts.system_program, &data)?;
+
+        msg!("Position {} migrated to layout {}", info.key, migration::LAYOUT_VERSION);
+        Ok(())
+    }
+
+    /// Settle a position's latest mark: the gain above principal becomes principal
+    ///
+    /// Moves `unrealized_yield` to `realized_yield`, adds it to TVL and takes the
+    /// performance fee on it, as a compound does, without waiting for the cadence.
+    /// In a funded vault only yield the position's custody holds above principal is
+    /// settled; the rest stays unrealized until the tokens arrive.
+    pub fn settle_position(ctx: Context<SettlePosition>) -> Result<()> {
+        require_authority(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            DelegateAction::Update,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        let position = &mut ctx.accounts.position;
+        let vault = &mut ctx.accounts.vault;
+
+        require!(position.is_active, MoluscoError::PositionClosed);
+        let settled = if vault.simulation {
+            position.unrealized_yield
+        } else {
+            let held = ctx.accounts.position_escrow.amount.saturating_sub(position.amount);
+            position.unrealized_yield.min(held)
+        };
+        require_gt!(settled, 0, MoluscoError::NothingToSettle);
+
+        let unrealized_before = position.realize(settled);
+        vault.restate_unrealized(unrealized_before, position.unrealized_yield);
+        position.amount = position
+            .amount
+            .checked_add(settled)
+            .ok_or(MoluscoError::MathOverflow)?;
+        position.compounded_yield += settled;
+        vault.total_value_locked = vault
+            .total_value_locked
+            .checked_add(settled as u128)
+            .ok_or(MoluscoError::MathOverflow)?;
+        let (fee, chargeable) = vault.take_performance_fee(settled)?;
+        let rate_bps = vault.performance_fee_bps;
+        book_fee(
+            vault,
+            &ctx.accounts.fee_ledger,
+            FeeKind::Performance,
+            rate_bps,
+            chargeable,
+            fee,
+            now,
+        )?;
+
+        emit!(PositionSettledEvent {
+            vault: vault.key(),
+            position: position.key(),
+            settled,
+            fee,
+            new_principal: position.amount,
+            realized_yield: position.realized_yield,
+            timestamp: now,
+        });
+
+        msg!("Position settled: {} lamports (performance fee {})", settled, fee);
+        Ok(())
+    }
+
+    /// Trim `amount` of principal off a position, keeping the account open
+    ///
+    /// The same share of the position's unrealized yield is realized with it: in a
+    /// funded vault both come back from the position's custody into idle escrow and
+    /// the yield pays the performance fee, as on a close. What remains must still
+    /// meet `min_position_amount`; a full exit goes through `close_position`.
+    pub fn reduce_position(ctx: Context<ReducePosition>, amount: u64) -> Result<()> {
+        require_authority(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            DelegateAction::Close,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        let position = &mut ctx.accounts.position;
+        let vault = &mut ctx.accounts.vault;
+
+        require!(position.is_active, MoluscoError::PositionClosed);
+        require!(amount > 0, MoluscoError::InvalidAmount);
+        let remaining = position
+            .amount
+            .checked_sub(amount)
+            .ok_or(MoluscoError::InvalidAmount)?;
+        require_gte!(remaining, vault.min_position_amount.max(1), MoluscoError::PositionTooSmall);
+
+        let mut realized = 0;
+        if vault.simulation {
+            vault.total_value_locked -= amount as u128;
+        } else {
+            realized = shares::mul_div(
+                position.unrealized_yield,
+                amount as u128,
+                position.amount as u128,
+                Rounding::Down,
+            )?;
+            let returned = amount + realized;
+            require_gte!(
+                ctx.accounts.position_escrow.amount,
+                returned,
+                MoluscoError::PositionCustodyShort
+            );
+            position.release_capacity(
+                &mut ctx.accounts.strategy_capacity,
+                decimals::normalize(amount, position.asset_decimals)?,
+            );
+            vault.total_value_locked = vault
+                .total_value_locked
+                .checked_add(realized as u128)
+                .ok_or(MoluscoError::MathOverflow)?;
+            let seeds = vault.signer_seeds();
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.position_escrow.to_account_info(),
+                        to: ctx.accounts.escrow.to_account_info(),
+                        authority: vault.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                returned,
+            )?;
+        }
+
+        let unrealized_before = position.realize(realized);
+        vault.restate_unrealized(unrealized_before, position.unrealized_yield);
+        position.compounded_yield += realized;
+        position.amount = remaining;
+        position.last_value = position.last_value.saturating_sub(amount + realized);
+        position.checkpoint_value = position.checkpoint_value.saturating_sub(amount + realized);
+
+        let (fee, chargeable) = vault.take_performance_fee(realized)?;
+        let rate_bps = vault.performance_fee_bps;
+        book_fee(
+            vault,
+            &ctx.accounts.fee_ledger,
+            FeeKind::Performance,
+            rate_bps,
+            chargeable,
+            fee,
+            now,
+        )?;
+
+        emit!(PositionReducedEvent {
+            vault: vault.key(),
+            position: position.key(),
+            principal_returned: amount,
+            realized_yield: realized,
+            fee,
+            remaining_principal: remaining,
+            timestamp: now,
+        });
+
+        msg!("Position reduced by {} (realized yield {}), {} remains", amount, realized, remaining);
+        Ok(())
+    }
+
+    /// Top up an open position's principal instead of opening a duplicate
+    ///
+    /// The added capital raises principal and the last mark alike, so the gain
+    /// above principal, and with it the yield math, is unchanged. Funded vaults
+    /// move the tokens from idle escrow into the position's custody and reserve
+    /// the strategy capacity for them, as an open does.
+    pub fn add_to_position(
+        ctx: Context<AddToPosition>,
+        amount: u64,
+        idempotency_key: Option<[u8; 16]>,
+    ) -> Result<()> {
+        let position = &mut ctx.accounts.position;
+        let vault = &mut ctx.accounts.vault;
+
+        require_authority(
+            vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            DelegateAction::Open,
+        )?;
+        vault.recent_keys.consume(idempotency_key)?;
+        require!(position.is_active, MoluscoError::PositionClosed);
+        require!(amount > 0, MoluscoError::InvalidAmount);
+
+        if vault.simulation {
+            if vault.deposit_cap > 0 {
+                require_gte!(
+                    vault.deposit_cap as u128,
+                    vault.total_value_locked.saturating_add(amount as u128),
+                    MoluscoError::DepositCapExceeded
+                );
+            }
+            vault.total_value_locked += amount as u128;
+        } else {
+            position.reserve_capacity(
+                &mut ctx.accounts.strategy_capacity,
+                decimals::normalize(amount, position.asset_decimals)?,
+            )?;
+            require_gte!(ctx.accounts.escrow.amount, amount, MoluscoError::InsufficientBalance);
+            let seeds = vault.signer_seeds();
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.escrow.to_account_info(),
+                        to: ctx.accounts.position_escrow.to_account_info(),
+                        authority: vault.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                amount,
+            )?;
+        }
+
+        position.amount = position
+            .amount
+            .checked_add(amount)
+            .ok_or(MoluscoError::MathOverflow)?;
+        position.last_value = position.last_value.saturating_add(amount);
+        position.checkpoint_value = position.checkpoint_value.saturating_add(amount);
+
+        msg!("Position topped up by {}, principal now {}", amount, position.amount);
+        Ok(())
+    }
+
+    /// Close a position and open its successor in another protocol in one step
+    ///
+    /// Settles the predecessor as `close_position` does: yield above principal is
+    /// realized into idle escrow and pays the performance fee, a shortfall is a
+    /// realized loss. The successor keeps the strategy and asset and takes over
+    /// the principal, straight from the predecessor's custody, with the realized
+    /// and loss history carried over and `rolled_from` pointing back. The mark
+    /// accumulators restart against the new venue.
+    pub fn roll_position(
+        ctx: Context<RollPosition>,
+        protocol: ProtocolKind,
+        target_apy: u16,
+    ) -> Result<()> {
+        require_authority(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            DelegateAction::Open,
+        )?;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        let position = &mut ctx.accounts.position;
+        let successor = &mut ctx.accounts.successor;
+        let vault = &mut ctx.accounts.vault;
+
+        require!(position.is_active, MoluscoError::PositionClosed);
+        require!(position.protocol != protocol, MoluscoError::RollToSameProtocol);
+
+        let mut realized = 0;
+        let mut principal = position.amount;
+        if vault.simulation {
+            // A shadow roll realizes the last mark's surplus and carries no more than it forward
+            realized = position
+                .accumulated_yield
+                .saturating_sub(position.compounded_yield as u128)
+                .min(position.last_value.saturating_sub(position.amount) as u128) as u64;
+            vault.cumulative_losses += position.amount.saturating_sub(position.last_value) as u128;
+            principal = position.last_value.min(position.amount);
+            vault.total_value_locked = vault
+                .total_value_locked
+                .checked_sub((position.amount - principal) as u128)
+                .ok_or(MoluscoError::TvlUnderflow)?
+                .checked_add(realized as u128)
+                .ok_or(MoluscoError::MathOverflow)?;
+        } else {
+            let returned = ctx.accounts.position_escrow.amount;
+            realized = position
+                .accumulated_yield
+                .saturating_sub(position.compounded_yield as u128)
+                .min(returned.saturating_sub(position.amount) as u128) as u64;
+            vault.cumulative_losses += position.amount.saturating_sub(returned) as u128;
+            vault.total_value_locked = vault
+                .total_value_locked
+                .saturating_sub(position.amount as u128)
+                .checked_add(returned as u128)
+                .ok_or(MoluscoError::MathOverflow)?;
+            // A shortfall shrinks the successor; it frees capacity like a close would
+            principal = returned.min(position.amount);
+            position.release_capacity(
+                &mut ctx.accounts.strategy_capacity,
+                decimals::normalize(position.amount - principal, position.asset_decimals)?,
+            );
+
+            let seeds = vault.signer_seeds();
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.position_escrow.to_account_info(),
+                        to: ctx.accounts.successor_escrow.to_account_info(),
+                        authority: vault.to_account_info(),
+                    },
+                    &[&seeds[..]],
+                ),
+                principal,
+            )?;
+            let surplus = returned - principal;
+            if surplus > 0 {
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        Transfer {
+                            from: ctx.accounts.position_escrow.to_account_info(),
+                            to: ctx.accounts.escrow.to_account_info(),
+                            authority: vault.to_account_info(),
+                        },
+                        &[&seeds[..]],
+                    ),
+                    surplus,
+                )?;
+            }
+            token::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                CloseAccount {
+                    account: ctx.accounts.position_escrow.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ))?;
+        }
+
+        let unrealized_before = position.realize(realized);
+        vault.restate_unrealized(unrealized_before, 0);
+        position.unrealized_yield = 0;
+        position.is_active = false;
+        vault.clear_stale(position);
+
+        let (fee, chargeable) = vault.take_performance_fee(realized)?;
+        let rate_bps = vault.performance_fee_bps;
+        book_fee(
+            vault,
+            &ctx.accounts.fee_ledger,
+            FeeKind::Performance,
+            rate_bps,
+            chargeable,
+            fee,
+            now,
+        )?;
+
+        successor.owner = vault.owner;
+        successor.vault = vault.key();
+        successor.protocol = protocol;
+        successor.strategy = position.strategy;
+        successor.asset = position.asset;
+        successor.asset_entry = position.asset_entry;
+        successor.asset_decimals = position.asset_decimals;
+        successor.index = vault.total_positions_created;
+        successor.legacy_seed = false;
+        successor.rolled_from = position.key();
+        successor.amount = principal;
+        successor.target_apy = target_apy;
+        successor.opened_at = now;
+        successor.last_update = now;
+        successor.last_update_slot = clock.slot;
+        successor.is_active = true;
+        // Yield history carries over; the close only realizes what custody holds above
+        // principal, so the predecessor's yield is not realized twice
+        successor.accumulated_yield = position.accumulated_yield;
+        successor.compounded_yield = position.compounded_yield;
+        successor.accumulated_loss = position.accumulated_loss;
+        successor.realized_yield = position.realized_yield;
+        successor.compound_interval_secs = position.compound_interval_secs;
+        successor.last_compound = now;
+        successor.term_secs = position.term_secs;
+        if position.term_secs > 0 {
+            successor.maturity_ts = now
+                .checked_add(position.term_secs)
+                .ok_or(MoluscoError::MathOverflow)?;
+        }
+        successor.unwind_priority = position.unwind_priority;
+        successor.last_value = principal;
+        successor.checkpoint_value = principal;
+        // The capacity the predecessor still holds moves with its principal
+        successor.reserved_capacity = position.reserved_capacity;
+        position.reserved_capacity = 0;
+        successor.layout_version = migration::LAYOUT_VERSION;
+        successor.bump = ctx.bumps.successor;
+        vault.total_positions_created += 1;
+
+        emit!(PositionClosedEvent {
+            vault: vault.key(),
+            position: position.key(),
+            amount: position.amount,
+            accumulated_yield: position.accumulated_yield,
+            accumulated_loss: position.accumulated_loss,
+            timestamp: now,
+        });
+        emit!(PositionRolledEvent {
+            vault: vault.key(),
+            from: position.key(),
+            to: successor.key(),
+            from_protocol: position.protocol,
+            to_protocol: protocol,
+            principal,
+            realized_yield: realized,
+            fee,
+            timestamp: now,
+        });
+
+        msg!("Position rolled from {:?} to {:?} with {} principal", position.protocol, protocol, principal);
+        Ok(())
+    }
+
+    /// Retarget a live position as market rates move
+    ///
+    /// The outgoing target and the period it applied over go into the
+    /// position's history, so performance can still be read against the target
+    /// that was in force at the time.
+    pub fn update_target_apy(ctx: Context<UpdateTargetApy>, target_apy: u16) -> Result<()> {
+        require_authority(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            DelegateAction::Update,
+        )?;
+        let position = &mut ctx.accounts.position;
+        require!(position.is_active, MoluscoError::PositionClosed);
+        require_gte!(MAX_TARGET_APY_BPS, target_apy, MoluscoError::InvalidTargetApy);
+        require_neq!(position.target_apy, target_apy, MoluscoError::InvalidTargetApy);
+
+        let previous = position.target_apy;
+        position.retarget(target_apy, Clock::get()?.unix_timestamp);
+
+        msg!("Target APY moved from {} to {} bps", previous, target_apy);
+        Ok(())
+    }
+
+    /// Permissionless: flag a position the agent has stopped marking
+    ///
+    /// A flagged position blocks deposits into the vault until it is marked again
+    /// or leaves the book, so new money never prices off a value nobody reports.
+    pub fn flag_stale(ctx: Context<FlagStale>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let position = &mut ctx.accounts.position;
+        let vault = &mut ctx.accounts.vault;
+
+        require!(vault.max_staleness_secs > 0, MoluscoError::StalenessDisabled);
+        require!(position.is_active, MoluscoError::PositionClosed);
+        require!(!position.is_stale, MoluscoError::PositionAlreadyStale);
+        require_gt!(
+            now,
+            position.last_update.saturating_add(vault.max_staleness_secs),
+            MoluscoError::PositionNotStale
+        );
+
+        position.is_stale = true;
+        vault.stale_positions += 1;
+
+        emit!(PositionFlaggedStaleEvent {
+            vault: vault.key(),
+            position: position.key(),
+            last_update: position.last_update,
+            flagged_by: ctx.accounts.keeper.key(),
+            timestamp: now,
+        });
+
+        msg!("Position flagged stale: last marked {}s ago", now - position.last_update);
+        Ok(())
+    }
+
+    /// Set the feeds `asset_entry`'s oracle marks are priced from; admin only
+    ///
+    /// Up to `MAX_ORACLE_FEEDS` distinct feeds, Pyth or Switchboard. A mark uses the
+    /// median value of the feeds that pass the age and confidence checks, needs at
+    /// least `min_sources` of them, and is rejected if any is more than
+    /// `max_divergence_bps` from the median. Oracle marks value custody in US
+    /// dollars, scaled to the asset's decimals, and compare that against principal,
+    /// so only a dollar-denominated mint should get a config.
+    pub fn set_oracle_config(
+        ctx: Context<SetOracleConfig>,
+        feeds: Vec<OracleFeed>,
+        min_sources: u8,
+        max_divergence_bps: u16,
+        max_age_secs: i64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        require!(
+            !feeds.is_empty() && feeds.len() <= MAX_ORACLE_FEEDS,
+            MoluscoError::InvalidOracleConfig
+        );
+        for (i, feed) in feeds.iter().enumerate() {
+            require!(
+                feed.feed != [0u8; 32] && !feeds[..i].iter().any(|other| other.feed == feed.feed),
+                MoluscoError::InvalidOracleConfig
+            );
+        }
+        require!(
+            min_sources > 0 && min_sources as usize <= feeds.len(),
+            MoluscoError::InvalidOracleConfig
+        );
+        require_gt!(max_age_secs, 0, MoluscoError::InvalidOracleConfig);
+        require!(
+            max_confidence_bps > 0 && max_confidence_bps as u64 <= oracle::BPS,
+            MoluscoError::InvalidOracleConfig
+        );
+        require_gte!(oracle::BPS, max_divergence_bps as u64, MoluscoError::InvalidOracleConfig);
+
+        let oracle_config = &mut ctx.accounts.oracle_config;
+        oracle_config.asset_entry = ctx.accounts.asset_entry.key();
+        oracle_config.feeds = feeds;
+        oracle_config.min_sources = min_sources;
+        oracle_config.max_divergence_bps = max_divergence_bps;
+        oracle_config.max_age_secs = max_age_secs;
+        oracle_config.max_confidence_bps = max_confidence_bps;
+        oracle_config.bump = ctx.bumps.oracle_config;
+
+        msg!(
+            "Oracle config set for {}: {} feeds, quorum {}, max divergence {} bps, max age {}s, max confidence {} bps",
+            ctx.accounts.asset_entry.symbol,
+            oracle_config.feeds.len(),
+            min_sources,
+            max_divergence_bps,
+            max_age_secs,
+            max_confidence_bps
+        );
+        Ok(())
+    }
+
+    /// Mark a position at its asset's oracle prices instead of a caller-supplied value
+    ///
+    /// The position's custody, or its notional principal in a simulation vault, is
+    /// valued in US dollars at the median of the asset's feeds, as
+    /// `OracleConfig::value_in_usd` describes; a dollar mint trading off its peg marks
+    /// away from principal. The feeds' price accounts are passed as
+    /// `remaining_accounts`, in the config's order. Sequencing and the update interval
+    /// apply as in `update_position`.
+    pub fn update_position_with_oracle<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdatePositionWithOracle<'info>>,
+        sequence: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        require_authority_or_session(
+            &ctx.accounts.vault,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.delegation.as_ref(),
+            ctx.accounts.session.as_ref(),
+            DelegateAction::Update,
+            now,
+        )?;
+
+        accrue_management_fee(&mut ctx.accounts.vault, &ctx.accounts.fee_ledger, now)?;
+        let units = if ctx.accounts.vault.simulation {
+            ctx.accounts.position.amount
+        } else {
+            ctx.accounts.position_escrow.amount
+        };
+        // Marks compare against principal, so the dollar value keeps the mint's decimals
+        let (current_value, sources) = ctx.accounts.oracle_config.value_in_usd(
+            units,
+            ctx.accounts.position.asset_decimals,
+            ctx.remaining_accounts,
+            now,
+        )?;
+
+        let position = &mut ctx.accounts.position;
+        position.check_update_interval(ctx.accounts.vault.min_update_interval_secs, now)?;
+        position.advance_sequence(sequence)?;
+
+        let unrealized_before = position.unrealized_yield;
+        let (yield_earned, loss) = position.apply_mark(current_value, now, clock.slot);
+        let vault = &mut ctx.accounts.vault;
+        vault.cumulative_yield += yield_earned as u128;
+        vault.restate_unrealized(unrealized_before, position.unrealized_yield);
+        vault.clear_stale(position);
+
+        emit!(PositionOracleMarkEvent {
+            vault: vault.key(),
+            position: position.key(),
+            sources,
+            current_value,
+            timestamp: now,
+        });
+
+        msg!(
+            "Position marked at the median of {} oracle prices: value {}, yield earned: {} lamports, loss: {} lamports",
+            sources,
+            current_value,
+            yield_earned,
+            loss
+        );
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(agent_name: String)]
+pub struct InitializeVault<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Vault::SIZE,
+        seeds = [b"vault", owner.key().as_ref(), agent_name.as_bytes()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+    
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(protocol: ProtocolKind, strategy: StrategyKind)]
+pub struct OpenPosition<'info> {
+    /// The vault owner, or a delegate allowed to open positions
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused,
+        constraint = !vault.trading_paused @ MoluscoError::TradingPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Position::SIZE,
+        seeds = [
+            b"position",
+            vault.key().as_ref(),
+            &protocol.seed(),
+            &asset_entry.kind.seed(),
+            &vault.total_positions_created.to_le_bytes()
+        ],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    /// CHECK: the protocol's adapter config PDA; the venue is probed once it is registered
+    #[account(
+        seeds = [b"adapter", &protocol.seed()],
+        bump
+    )]
+    pub adapter_config: UncheckedAccount<'info>,
+
+    /// CHECK: must match the program registered in adapter_config
+    #[account(executable)]
+    pub adapter_program: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = mint,
+        token::authority = vault,
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        address = vault.mint
+    )]
+    pub mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    #[account(
+        seeds = [b"protocol_registry"],
+        bump = protocol_registry.bump,
+        constraint = protocol_registry.is_listed(protocol) @ MoluscoError::ProtocolNotWhitelisted
+    )]
+    pub protocol_registry: Account<'info, ProtocolRegistry>,
+
+    /// Positions hold the vault's own mint, so its amounts carry these decimals
+    #[account(
+        seeds = [b"asset", mint.key().as_ref()],
+        bump = asset_entry.bump
+    )]
+    pub asset_entry: Account<'info, AssetEntry>,
+
+    #[account(
+        mut,
+        seeds = [b"strategy_capacity", &strategy.seed()],
+        bump = strategy_capacity.bump
+    )]
+    pub strategy_capacity: Account<'info, StrategyCapacity>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePosition<'info> {
+    /// The vault owner, a delegate or a session key allowed to mark positions
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    /// Present when `authority` is a session key
+    #[account(
+        seeds = [b"session", vault.key().as_ref(), authority.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Option<Account<'info, Session>>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    /// Lending adapter for the position's venue, to read its reserve rate
+    #[account(
+        constraint = adapter_config.protocol == position.protocol
+    )]
+    pub adapter_config: Option<Account<'info, AdapterConfig>>,
+
+    /// CHECK: must match the program registered in adapter_config
+    #[account(
+        executable,
+        constraint = adapter_config.as_ref().map(|a| a.program_id) == Some(adapter_program.key())
+    )]
+    pub adapter_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: must be empty; an asset with an oracle config is marked at its prices
+    #[account(
+        seeds = [b"oracle_config", position.asset_entry.as_ref()],
+        bump
+    )]
+    pub oracle_config: UncheckedAccount<'info>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCompoundingFrequency<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CompoundPosition<'info> {
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    /// Caps what is compounded at the yield actually held above principal
+    #[account(
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPositionTerm<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RolloverPosition<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key(),
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        seeds = [b"protocol_registry"],
+        bump = protocol_registry.bump,
+        constraint = protocol_registry.is_listed(position.protocol) @ MoluscoError::ProtocolNotWhitelisted
+    )]
+    pub protocol_registry: Account<'info, ProtocolRegistry>,
+
+    /// Caps the term yield carried into principal at what is held above it
+    #[account(
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePositionsBatch<'info> {
+    /// The vault owner, or a delegate allowed to mark positions
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    /// Registry entry of the vault mint, which every marked position holds
+    #[account(
+        seeds = [b"asset", vault.mint.as_ref()],
+        bump = asset_entry.bump
+    )]
+    pub asset_entry: Account<'info, AssetEntry>,
+
+    /// CHECK: must be empty; an asset with an oracle config is marked at its prices
+    #[account(
+        seeds = [b"oracle_config", asset_entry.key().as_ref()],
+        bump
+    )]
+    pub oracle_config: UncheckedAccount<'info>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    /// The vault owner, or a delegate allowed to close positions; receives the rent
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key(),
+        close = authority
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    /// Frees the strategy capacity the position used
+    #[account(
+        mut,
+        seeds = [b"strategy_capacity", &position.strategy.seed()],
+        bump = strategy_capacity.bump
+    )]
+    pub strategy_capacity: Account<'info, StrategyCapacity>,
+
+    /// CHECK: must be empty for `settle_and_close`, which takes a caller-supplied mark
+    #[account(
+        seeds = [b"oracle_config", position.asset_entry.as_ref()],
+        bump
+    )]
+    pub oracle_config: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePositionsBatch<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RecordRebalance<'info> {
+    /// The vault owner, a delegate or a session key allowed to rebalance
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused,
+        constraint = !vault.trading_paused @ MoluscoError::TradingPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    /// Present when `authority` is a session key
+    #[account(
+        seeds = [b"session", vault.key().as_ref(), authority.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Option<Account<'info, Session>>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRewardsDistributor<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub reward_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + RewardsDistributor::SIZE,
+        seeds = [b"rewards", vault.key().as_ref()],
+        bump
+    )]
+    pub distributor: Account<'info, RewardsDistributor>,
+
+    #[account(
+        init,
+        payer = owner,
+        token::mint = reward_mint,
+        token::authority = distributor,
+        seeds = [b"reward_vault", distributor.key().as_ref()],
+        bump
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FundRewards<'info> {
+    pub funder: Signer<'info>,
+
+    #[account(mut)]
+    pub distributor: Account<'info, RewardsDistributor>,
+
+    #[account(
+        mut,
+        address = distributor.reward_vault
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = funder_token_account.mint == distributor.reward_mint
+    )]
+    pub funder_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEmissionRate<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        has_one = vault
+    )]
+    pub distributor: Account<'info, RewardsDistributor>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        has_one = vault,
+        seeds = [b"rewards", vault.key().as_ref()],
+        bump = distributor.bump
+    )]
+    pub distributor: Account<'info, RewardsDistributor>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + DepositorRecord::SIZE,
+        seeds = [b"depositor", vault.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    #[account(
+        constraint = depositor_share_account.mint == vault.share_mint,
+        constraint = depositor_share_account.owner == depositor.key()
+    )]
+    pub depositor_share_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        address = distributor.reward_vault
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = depositor_token_account.mint == distributor.reward_mint
+    )]
+    pub depositor_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == guard.program_config.admin @ MoluscoError::NotProgramAdmin
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Config::SIZE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Moluscoyield>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+            @ MoluscoError::NotUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(campaign_id: u64)]
+pub struct CreateAirdrop<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Airdrop::SIZE,
+        seeds = [b"airdrop", mint.key().as_ref(), &campaign_id.to_le_bytes()],
+        bump
+    )]
+    pub airdrop: Account<'info, Airdrop>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = mint,
+        token::authority = airdrop,
+        seeds = [b"airdrop_vault", airdrop.key().as_ref()],
+        bump
+    )]
+    pub airdrop_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = authority_token_account.mint == mint.key()
+    )]
+    pub authority_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMerkleRoot<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub airdrop: Account<'info, Airdrop>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAirdrop<'info> {
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    #[account(mut)]
+    pub airdrop: Account<'info, Airdrop>,
+
+    // Existence of this PDA marks the allocation as claimed
+    #[account(
+        init,
+        payer = claimant,
+        space = 8 + AirdropClaim::SIZE,
+        seeds = [b"airdrop_claim", airdrop.key().as_ref(), claimant.key().as_ref()],
+        bump
+    )]
+    pub claim: Account<'info, AirdropClaim>,
+
+    #[account(
+        mut,
+        address = airdrop.token_vault
+    )]
+    pub airdrop_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = claimant_token_account.mint == airdrop.mint
+    )]
+    pub claimant_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateBuybackConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateBuybackEscrow<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = mint,
+        token::authority = config,
+        seeds = [b"buyback_escrow", mint.key().as_ref()],
+        bump
+    )]
+    pub buyback_escrow: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteBuyback<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Holds the buyback share of crystallized fees awaiting a swap
+    #[account(
+        mut,
+        seeds = [b"buyback_escrow", buyback_escrow.mint.as_ref()],
+        bump,
+        constraint = buyback_escrow.owner == config.key()
+    )]
+    pub buyback_escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"asset", buyback_escrow.mint.as_ref()],
+        bump = input_asset_entry.bump
+    )]
+    pub input_asset_entry: Account<'info, AssetEntry>,
+
+    #[account(
+        seeds = [b"oracle_config", input_asset_entry.key().as_ref()],
+        bump = input_oracle_config.bump
+    )]
+    pub input_oracle_config: Account<'info, OracleConfig>,
+
+    #[account(
+        seeds = [b"asset", config.buyback_mint.as_ref()],
+        bump = output_asset_entry.bump
+    )]
+    pub output_asset_entry: Account<'info, AssetEntry>,
+
+    #[account(
+        seeds = [b"oracle_config", output_asset_entry.key().as_ref()],
+        bump = output_oracle_config.bump
+    )]
+    pub output_oracle_config: Account<'info, OracleConfig>,
+
+    #[account(
+        constraint = pair_limit.vault == config.key(),
+        constraint = pair_limit.matches(
+            &buyback_escrow.mint,
+            &config.buyback_mint
+        ) @ MoluscoError::PairSlippageMismatch
+    )]
+    pub pair_limit: Account<'info, PairSlippageLimit>,
+
+    #[account(
+        mut,
+        constraint = buyback_output.owner == config.key(),
+        constraint = buyback_output.mint == config.buyback_mint
+    )]
+    pub buyback_output: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        address = config.buyback_treasury
+    )]
+    pub buyback_treasury: Account<'info, TokenAccount>,
+
+    /// CHECK: must match the swap adapter program registered in config
+    #[account(
+        executable,
+        address = config.swap_program
+    )]
+    pub swap_program: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint_a: Pubkey, mint_b: Pubkey)]
+pub struct SetBuybackSlippage<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + PairSlippageLimit::SIZE,
+        seeds = [b"pair_slippage", config.key().as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        bump
+    )]
+    pub pair_limit: Account<'info, PairSlippageLimit>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id: u64)]
+pub struct PublishVaultTemplate<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + VaultTemplate::SIZE,
+        seeds = [b"template", creator.key().as_ref(), &template_id.to_le_bytes()],
+        bump
+    )]
+    pub template: Account<'info, VaultTemplate>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_name: String)]
+pub struct CreateVaultFromTemplate<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut)]
+    pub template: Account<'info, VaultTemplate>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = treasury
+    )]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: lamport destination, validated against config.treasury
+    #[account(mut)]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Vault::SIZE,
+        seeds = [b"vault", owner.key().as_ref(), agent_name.as_bytes()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferBetweenVaults<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = source_vault.owner == owner.key(),
+        constraint = !source_vault.withdrawals_paused @ MoluscoError::WithdrawalsPaused,
+        constraint = !source_vault.cpi_guard.locked @ MoluscoError::ReentrancyDetected
+    )]
+    pub source_vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = destination_vault.owner == owner.key(),
+        constraint = destination_vault.key() != source_vault.key(),
+        constraint = !destination_vault.deposits_paused @ MoluscoError::DepositsPaused,
+        constraint = destination_vault.stale_positions == 0 @ MoluscoError::VaultHasStalePositions
+    )]
+    pub destination_vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = source_token_account.owner == source_vault.key()
+    )]
+    pub source_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = destination_token_account.owner == destination_vault.key(),
+        constraint = destination_token_account.mint == source_token_account.mint
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    /// Required once the source vault has enabled its allowlist
+    pub destination_allowlist: Option<Account<'info, DestinationAllowlist>>,
+
+    /// Required once the source vault has a share mint
+    #[account(address = source_vault.share_mint)]
+    pub source_share_mint: Option<Account<'info, Mint>>,
+
+    #[account(
+        constraint = owner_share_account.owner == owner.key(),
+        constraint = owner_share_account.mint == source_vault.share_mint
+    )]
+    pub owner_share_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRewardQuoteConfig<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinPositionAmount<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MarkVault<'info> {
+    pub keeper: Signer<'info>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: must match the reward adapter registered on the vault
+    #[account(
+        executable,
+        address = vault.reward_adapter
+    )]
+    pub reward_adapter: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSmoothingConfig<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CheckpointVault<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Checkpoint::SIZE,
+        seeds = [b"checkpoint", vault.key().as_ref(), &vault.checkpoint_count.to_le_bytes()],
+        bump
+    )]
+    pub checkpoint: Account<'info, Checkpoint>,
+
+    /// The vault's latest checkpoint, required once it has one
+    #[account(
+        seeds = [
+            b"checkpoint",
+            vault.key().as_ref(),
+            &vault.checkpoint_count.saturating_sub(1).to_le_bytes()
+        ],
+        bump = previous_checkpoint.bump
+    )]
+    pub previous_checkpoint: Option<Account<'info, Checkpoint>>,
+
+    /// Required once the vault has a share mint, to price its shares
+    #[account(address = vault.share_mint)]
+    pub share_mint: Option<Account<'info, Mint>>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetExitFeeSchedule<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(protocol: ProtocolKind)]
+pub struct RegisterAdapter<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AdapterConfig::SIZE,
+        seeds = [b"adapter", &protocol.seed()],
+        bump
+    )]
+    pub adapter_config: Account<'info, AdapterConfig>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetEmergencyExit<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub adapter_config: Account<'info, AdapterConfig>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    pub caller: Signer<'info>,
+
+    pub adapter_config: Account<'info, AdapterConfig>,
+
+    /// CHECK: must match the program registered in adapter_config
+    #[account(
+        executable,
+        address = adapter_config.program_id
+    )]
+    pub adapter_program: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key(),
+        constraint = position.protocol == adapter_config.protocol
+    )]
+    pub position: Account<'info, Position>,
+
+    /// Receives the position's unwound funds before they return to idle escrow
+    #[account(
+        mut,
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// Frees the strategy capacity the position used
+    #[account(
+        mut,
+        seeds = [b"strategy_capacity", &position.strategy.seed()],
+        bump = strategy_capacity.bump
+    )]
+    pub strategy_capacity: Account<'info, StrategyCapacity>,
+
+    /// CHECK: receives the rent of the emptied position escrow
+    #[account(mut, address = vault.owner)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RecordImpairment<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Impairment::SIZE,
+        seeds = [b"impairment", position.key().as_ref(), &position.impairment_count.to_le_bytes()],
+        bump
+    )]
+    pub impairment: Account<'info, Impairment>,
+
+    /// Needed to price each depositor's part of the loss when records are passed
+    #[account(address = vault.share_mint)]
+    pub share_mint: Option<Account<'info, Mint>>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetStrategist<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PostAllocationHint<'info> {
+    #[account(mut)]
+    pub strategist: Signer<'info>,
+
+    #[account(
+        constraint = vault.strategist == strategist.key() @ MoluscoError::InvalidStrategist
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init_if_needed,
+        payer = strategist,
+        space = 8 + AllocationHint::SIZE,
+        seeds = [b"hint", vault.key().as_ref()],
+        bump
+    )]
+    pub hint: Account<'info, AllocationHint>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRebalance<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key(),
+        constraint = !vault.trading_paused @ MoluscoError::TradingPaused,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"hint", vault.key().as_ref()],
+        bump = hint.bump
+    )]
+    pub hint: Account<'info, AllocationHint>,
+
+    /// Idle escrow, through which principal moves between position escrows
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSlippageBudget<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RebalanceSwap<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key(),
+        constraint = !vault.trading_paused @ MoluscoError::TradingPaused,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = source_token_account.owner == vault.key()
+    )]
+    pub source_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = destination_token_account.owner == vault.key(),
+        constraint = destination_token_account.mint != source_token_account.mint
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        has_one = vault,
+        constraint = pair_limit.matches(
+            &source_token_account.mint,
+            &destination_token_account.mint
+        ) @ MoluscoError::PairSlippageMismatch
+    )]
+    pub pair_limit: Account<'info, PairSlippageLimit>,
+
+    pub oracles: SwapOracles<'info>,
+
+    /// CHECK: instructions sysvar, inspected for a bundle tip on bundle-only vaults
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: must match the swap adapter program registered in config
+    #[account(
+        executable,
+        address = config.swap_program
+    )]
+    pub swap_program: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceRestingOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key(),
+        constraint = !vault.trading_paused @ MoluscoError::TradingPaused,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub adapter_config: Account<'info, AdapterConfig>,
+
+    /// CHECK: must match the program registered in adapter_config
+    #[account(
+        executable,
+        address = adapter_config.program_id
+    )]
+    pub adapter_program: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + RestingOrder::SIZE,
+        seeds = [b"order", vault.key().as_ref(), &vault.next_order_id.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, RestingOrder>,
+
+    #[account(
+        constraint = source_token_account.owner == vault.key()
+    )]
+    pub source_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        constraint = destination_token_account.owner == vault.key(),
+        constraint = destination_token_account.mint != source_token_account.mint
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageRestingOrder<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        address = order.adapter
+    )]
+    pub adapter_config: Account<'info, AdapterConfig>,
+
+    /// CHECK: must match the program registered in adapter_config
+    #[account(
+        executable,
+        address = adapter_config.program_id
+    )]
+    pub adapter_program: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = vault
+    )]
+    pub order: Account<'info, RestingOrder>,
+
+    #[account(
+        mut,
+        constraint = destination_token_account.owner == vault.key(),
+        constraint = destination_token_account.mint == order.destination_mint
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleRestingOrder<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        address = order.adapter
+    )]
+    pub adapter_config: Account<'info, AdapterConfig>,
+
+    /// CHECK: must match the program registered in adapter_config
+    #[account(
+        executable,
+        address = adapter_config.program_id
+    )]
+    pub adapter_program: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = vault
+    )]
+    pub order: Account<'info, RestingOrder>,
+
+    #[account(
+        mut,
+        constraint = destination_token_account.owner == vault.key(),
+        constraint = destination_token_account.mint == order.destination_mint
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    pub oracles: SwapOracles<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct NetRebalance<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault_a.owner == owner.key(),
+        constraint = !vault_a.trading_paused @ MoluscoError::TradingPaused,
+        constraint = !vault_a.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault_a: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = vault_b.owner == owner.key(),
+        constraint = vault_b.key() != vault_a.key(),
+        constraint = !vault_b.trading_paused @ MoluscoError::TradingPaused,
+        constraint = !vault_b.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault_b: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = vault_a_base.owner == vault_a.key()
+    )]
+    pub vault_a_base: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b_base.owner == vault_b.key(),
+        constraint = vault_b_base.mint == vault_a_base.mint
+    )]
+    pub vault_b_base: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b_quote.owner == vault_b.key(),
+        constraint = vault_b_quote.mint != vault_a_base.mint
+    )]
+    pub vault_b_quote: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_a_quote.owner == vault_a.key(),
+        constraint = vault_a_quote.mint == vault_b_quote.mint
+    )]
+    pub vault_a_quote: Account<'info, TokenAccount>,
+
+    #[account(
+        constraint = pair_limit_a.vault == vault_a.key(),
+        constraint = pair_limit_a.matches(
+            &vault_a_base.mint,
+            &vault_a_quote.mint
+        ) @ MoluscoError::PairSlippageMismatch
+    )]
+    pub pair_limit_a: Account<'info, PairSlippageLimit>,
+
+    #[account(
+        constraint = pair_limit_b.vault == vault_b.key(),
+        constraint = pair_limit_b.matches(
+            &vault_b_base.mint,
+            &vault_b_quote.mint
+        ) @ MoluscoError::PairSlippageMismatch
+    )]
+    pub pair_limit_b: Account<'info, PairSlippageLimit>,
+
+    pub oracles: SwapOracles<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeJobQueue<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + JobQueue::SIZE,
+        seeds = [b"jobs"],
+        bump
+    )]
+    pub job_queue: Account<'info, JobQueue>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ScheduleJob<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: the vault itself, or one of its positions for compound and rollover jobs
+    pub target: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"jobs"],
+        bump = job_queue.bump
+    )]
+    pub job_queue: Account<'info, JobQueue>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelJob<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: receives the refunded tip
+    #[account(mut, address = vault.owner)]
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"jobs"],
+        bump = job_queue.bump
+    )]
+    pub job_queue: Account<'info, JobQueue>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteJob<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"jobs"],
+        bump = job_queue.bump
+    )]
+    pub job_queue: Account<'info, JobQueue>,
+
+    /// CHECK: deserialized according to the job's task type
+    pub target: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetUnwindPriority<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPauseAuthorities<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReconcileVault<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(address = vault.escrow)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyInvariants<'info> {
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_name: String, delegate: Pubkey)]
+pub struct CloneVaultConfig<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = source_vault.owner == owner.key()
+    )]
+    pub source_vault: Account<'info, Vault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Vault::SIZE,
+        seeds = [b"vault", owner.key().as_ref(), agent_name.as_bytes()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + VaultComparison::SIZE,
+        seeds = [b"comparison", source_vault.key().as_ref(), vault.key().as_ref()],
+        bump
+    )]
+    pub comparison: Account<'info, VaultComparison>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Delegate::SIZE,
+        seeds = [b"delegate", vault.key().as_ref(), delegate.as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegate>,
+
+    /// CHECK: must be empty; the new delegate may not already act for the source vault
+    #[account(
+        seeds = [b"delegate", source_vault.key().as_ref(), delegate.as_ref()],
+        bump
+    )]
+    pub source_delegation: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateComparison<'info> {
+    #[account(
+        mut,
+        has_one = champion,
+        has_one = challenger
+    )]
+    pub comparison: Account<'info, VaultComparison>,
+
+    pub champion: Account<'info, Vault>,
+
+    pub challenger: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GenerateStatement<'info> {
+    /// The depositor, the vault owner or its viewer; pays rent for the statement
+    #[account(
+        mut,
+        constraint = payer.key() == depositor_record.depositor
+            || vault.can_view(&payer.key()) @ MoluscoError::NotOwnerOrViewer
+    )]
+    pub payer: Signer<'info>,
+
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        has_one = vault,
+        seeds = [b"depositor", vault.key().as_ref(), depositor_record.depositor.as_ref()],
+        bump = depositor_record.bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    #[account(address = vault.share_mint)]
+    pub share_mint: Account<'info, Mint>,
+
+    /// The vault mint; omitted for a native SOL vault
+    #[account(address = vault.mint)]
+    pub mint: Option<Account<'info, Mint>>,
+
+    #[account(
+        constraint = depositor_share_account.mint == share_mint.key(),
+        constraint = depositor_share_account.owner == depositor_record.depositor
+    )]
+    pub depositor_share_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + StatementCursor::SIZE,
+        seeds = [b"statement_cursor", vault.key().as_ref(), depositor_record.depositor.as_ref()],
+        bump
+    )]
+    pub cursor: Account<'info, StatementCursor>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + DepositorStatement::SIZE,
+        seeds = [
+            b"statement",
+            vault.key().as_ref(),
+            depositor_record.depositor.as_ref(),
+            &cursor.statement_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub statement: Account<'info, DepositorStatement>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetApyBetween<'info> {
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        has_one = vault,
+        seeds = [b"checkpoint", vault.key().as_ref(), &start.index.to_le_bytes()],
+        bump = start.bump
+    )]
+    pub start: Account<'info, Checkpoint>,
+
+    #[account(
+        has_one = vault,
+        seeds = [b"checkpoint", vault.key().as_ref(), &end.index.to_le_bytes()],
+        bump = end.bump
+    )]
+    pub end: Account<'info, Checkpoint>,
+}
+
+#[derive(Accounts)]
+#[instruction(label: String)]
+pub struct CreateRateFeed<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RateFeed::SIZE,
+        seeds = [b"rate_feed", authority.key().as_ref(), label.as_bytes()],
+        bump
+    )]
+    pub feed: Account<'info, RateFeed>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRateFeed<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub feed: Account<'info, RateFeed>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(benchmark_id: u64)]
+pub struct CreateBenchmark<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Benchmark::SIZE,
+        seeds = [b"benchmark", creator.key().as_ref(), &benchmark_id.to_le_bytes()],
+        bump
+    )]
+    pub benchmark: Account<'info, Benchmark>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultBenchmark<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFeeLedger<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + FeeLedger::SIZE,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: Account<'info, FeeLedger>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RecordOperatingCost<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCosigners<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeDestinationAllowlist<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + DestinationAllowlist::SIZE,
+        seeds = [b"allowlist", vault.key().as_ref()],
+        bump
+    )]
+    pub allowlist: Account<'info, DestinationAllowlist>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageDestinationAllowlist<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"allowlist", vault.key().as_ref()],
+        bump = allowlist.bump,
+        has_one = vault
+    )]
+    pub allowlist: Account<'info, DestinationAllowlist>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVelocityLimit<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + PendingVelocityLimit::SIZE,
+        seeds = [b"pending_velocity", vault.key().as_ref()],
+        bump
+    )]
+    pub pending_velocity: Account<'info, PendingVelocityLimit>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ActivateVelocityLimit<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_velocity", vault.key().as_ref()],
+        bump = pending_velocity.bump,
+        has_one = vault
+    )]
+    pub pending_velocity: Account<'info, PendingVelocityLimit>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetViewer<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(program_id: Pubkey)]
+pub struct RegisterStrategyProgram<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + StrategyProgram::SIZE,
+        seeds = [b"strategy_program", program_id.as_ref()],
+        bump
+    )]
+    pub strategy_program: Account<'info, StrategyProgram>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetStrategyProgramEnabled<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"strategy_program", strategy_program.program_id.as_ref()],
+        bump = strategy_program.bump
+    )]
+    pub strategy_program: Account<'info, StrategyProgram>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteStrategy<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key(),
+        constraint = !vault.trading_paused @ MoluscoError::TradingPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        seeds = [b"strategy_program", strategy_registry.program_id.as_ref()],
+        bump = strategy_registry.bump,
+        constraint = strategy_registry.enabled @ MoluscoError::StrategyProgramDisabled
+    )]
+    pub strategy_registry: Account<'info, StrategyProgram>,
+
+    /// CHECK: must match the registered strategy program
+    #[account(
+        executable,
+        address = strategy_registry.program_id
+    )]
+    pub strategy_program: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = source_token_account.owner == vault.key()
+    )]
+    pub source_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = destination_token_account.owner == vault.key(),
+        constraint = destination_token_account.key() != source_token_account.key()
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        has_one = vault,
+        constraint = pair_limit.matches(
+            &source_token_account.mint,
+            &destination_token_account.mint
+        ) @ MoluscoError::PairSlippageMismatch
+    )]
+    pub pair_limit: Account<'info, PairSlippageLimit>,
+
+    pub oracles: SwapOracles<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint_a: Pubkey, mint_b: Pubkey)]
+pub struct SetPairSlippage<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + PairSlippageLimit::SIZE,
+        seeds = [b"pair_slippage", vault.key().as_ref(), mint_a.as_ref(), mint_b.as_ref()],
+        bump
+    )]
+    pub pair_limit: Account<'info, PairSlippageLimit>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetBundleConfig<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PayBundleTip<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: must be one of the published Jito tip accounts
+    #[account(
+        mut,
+        constraint = JITO_TIP_ACCOUNTS.contains(&tip_account.key()) @ MoluscoError::InvalidTipAccount
+    )]
+    pub tip_account: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(depositor: Pubkey)]
+pub struct GetWithdrawableAmount<'info> {
+    pub vault: Account<'info, Vault>,
+
+    /// The vault's token escrow; not needed for SOL vaults
+    #[account(address = vault.escrow)]
+    pub vault_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: system-owned lamport escrow PDA of a SOL vault; holds no data
+    #[account(
+        seeds = [b"sol_escrow", vault.key().as_ref()],
+        bump
+    )]
+    pub sol_escrow: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"depositor", vault.key().as_ref(), depositor.as_ref()],
+        bump = depositor_record.bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    #[account(address = vault.share_mint)]
+    pub share_mint: Account<'info, Mint>,
+
+    /// The vault's mint, whose decimals share math converts from; not needed for SOL vaults
+    #[account(address = vault.mint)]
+    pub mint: Option<Account<'info, Mint>>,
+}
+
+#[derive(Accounts)]
+pub struct NotifyLockupExpired<'info> {
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        seeds = [b"depositor", vault.key().as_ref(), depositor_record.depositor.as_ref()],
+        bump = depositor_record.bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    #[account(address = vault.share_mint)]
+    pub share_mint: Account<'info, Mint>,
+
+    /// The vault's mint, whose decimals share math converts from; not needed for SOL vaults
+    #[account(address = vault.mint)]
+    pub mint: Option<Account<'info, Mint>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeEscrow<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key(),
+        constraint = vault.escrow == Pubkey::default() @ MoluscoError::EscrowAlreadyInitialized
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = owner,
+        token::mint = mint,
+        token::authority = vault,
+        seeds = [b"escrow", vault.key().as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.deposits_paused @ MoluscoError::DepositsPaused,
+        constraint = vault.stale_positions == 0 @ MoluscoError::VaultHasStalePositions,
+        constraint = !vault.simulation @ MoluscoError::SimulationVault
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = depositor_token_account.owner == depositor.key(),
+        constraint = depositor_token_account.mint == vault.mint
+    )]
+    pub depositor_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        address = vault.share_mint
+    )]
+    pub share_mint: Account<'info, Mint>,
+
+    /// The vault's mint, whose decimals share math converts from
+    #[account(address = vault.mint)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = depositor_share_account.owner == depositor.key(),
+        constraint = depositor_share_account.mint == vault.share_mint
+    )]
+    pub depositor_share_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + DepositorRecord::SIZE,
+        seeds = [b"depositor", vault.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    /// CHECK: the vault's rewards distributor PDA; the depositor's stake is settled in it
+    /// once it is initialized
+    #[account(
+        mut,
+        seeds = [b"rewards", vault.key().as_ref()],
+        bump
+    )]
+    pub rewards_distributor: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.withdrawals_paused @ MoluscoError::WithdrawalsPaused,
+        constraint = !vault.cpi_guard.locked @ MoluscoError::ReentrancyDetected,
+        constraint = !vault.simulation @ MoluscoError::SimulationVault
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = destination_token_account.mint == vault.mint
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    /// Required once the vault has enabled its allowlist
+    pub destination_allowlist: Option<Account<'info, DestinationAllowlist>>,
+
+    #[account(
+        mut,
+        address = vault.share_mint
+    )]
+    pub share_mint: Account<'info, Mint>,
+
+    /// The vault's mint, whose decimals share math converts from
+    #[account(address = vault.mint)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = depositor_share_account.owner == depositor.key(),
+        constraint = depositor_share_account.mint == vault.share_mint
+    )]
+    pub depositor_share_account: Account<'info, TokenAccount>,
+
+    /// Created for a holder who received their shares by transfer
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + DepositorRecord::SIZE,
+        seeds = [b"depositor", vault.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    /// CHECK: the vault's rewards distributor PDA; the depositor's stake is settled in it
+    /// once it is initialized
+    #[account(
+        mut,
+        seeds = [b"rewards", vault.key().as_ref()],
+        bump
+    )]
+    pub rewards_distributor: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DepositSol<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.deposits_paused @ MoluscoError::DepositsPaused,
+        constraint = vault.stale_positions == 0 @ MoluscoError::VaultHasStalePositions,
+        constraint = !vault.simulation @ MoluscoError::SimulationVault
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: system-owned lamport escrow PDA; holds no data
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", vault.key().as_ref()],
+        bump
+    )]
+    pub sol_escrow: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = vault.share_mint
+    )]
+    pub share_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = depositor_share_account.owner == depositor.key(),
+        constraint = depositor_share_account.mint == vault.share_mint
+    )]
+    pub depositor_share_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + DepositorRecord::SIZE,
+        seeds = [b"depositor", vault.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    /// CHECK: the vault's rewards distributor PDA; the depositor's stake is settled in it
+    /// once it is initialized
+    #[account(
+        mut,
+        seeds = [b"rewards", vault.key().as_ref()],
+        bump
+    )]
+    pub rewards_distributor: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSol<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.withdrawals_paused @ MoluscoError::WithdrawalsPaused,
+        constraint = !vault.cpi_guard.locked @ MoluscoError::ReentrancyDetected,
+        constraint = !vault.simulation @ MoluscoError::SimulationVault
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: system-owned lamport escrow PDA; holds no data
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", vault.key().as_ref()],
+        bump
+    )]
+    pub sol_escrow: UncheckedAccount<'info>,
+
+    /// CHECK: any system account; checked against the allowlist when enabled
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+
+    /// Required once the vault has enabled its allowlist
+    pub destination_allowlist: Option<Account<'info, DestinationAllowlist>>,
+
+    #[account(
+        mut,
+        address = vault.share_mint
+    )]
+    pub share_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = depositor_share_account.owner == depositor.key(),
+        constraint = depositor_share_account.mint == vault.share_mint
+    )]
+    pub depositor_share_account: Account<'info, TokenAccount>,
+
+    /// Created for a holder who received their shares by transfer
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + DepositorRecord::SIZE,
+        seeds = [b"depositor", vault.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    /// CHECK: the vault's rewards distributor PDA; the depositor's stake is settled in it
+    /// once it is initialized
+    #[account(
+        mut,
+        seeds = [b"rewards", vault.key().as_ref()],
+        bump
+    )]
+    pub rewards_distributor: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAuditMode<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(decimals: u8)]
+pub struct InitializeShareMint<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key(),
+        constraint = vault.share_mint == Pubkey::default() @ MoluscoError::ShareMintAlreadyInitialized
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init,
+        payer = owner,
+        mint::decimals = decimals,
+        mint::authority = vault,
+        seeds = [b"share_mint", vault.key().as_ref()],
+        bump
+    )]
+    pub share_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RequestWithdrawal<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.withdrawals_paused @ MoluscoError::WithdrawalsPaused,
+        constraint = !vault.simulation @ MoluscoError::SimulationVault
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// Created for a holder who received their shares by transfer
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + DepositorRecord::SIZE,
+        seeds = [b"depositor", vault.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Account<'info, DepositorRecord>,
+
+    #[account(address = vault.share_mint)]
+    pub share_mint: Account<'info, Mint>,
+
+    /// The vault's mint, whose decimals share math converts from
+    #[account(address = vault.mint)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = depositor_share_account.owner == depositor.key(),
+        constraint = depositor_share_account.mint == vault.share_mint
+    )]
+    pub depositor_share_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        token::mint = share_mint,
+        token::authority = vault,
+        seeds = [b"queued_shares", vault.key().as_ref()],
+        bump
+    )]
+    pub queued_shares: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = depositor,
+        space = 8 + WithdrawalRequest::SIZE,
+        seeds = [
+            b"withdrawal",
+            vault.key().as_ref(),
+            &vault.withdrawal_queue_tail.to_le_bytes()
+        ],
+        bump
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(constraint = destination_token_account.mint == vault.mint)]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    /// Required once the vault has enabled its allowlist
+    pub destination_allowlist: Option<Account<'info, DestinationAllowlist>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    /// CHECK: the vault's rewards distributor PDA; the depositor's stake is settled in it
+    /// once it is initialized
+    #[account(
+        mut,
+        seeds = [b"rewards", vault.key().as_ref()],
+        bump
+    )]
+    pub rewards_distributor: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessWithdrawals<'info> {
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.withdrawals_paused @ MoluscoError::WithdrawalsPaused,
+        constraint = !vault.cpi_guard.locked @ MoluscoError::ReentrancyDetected
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        address = vault.share_mint
+    )]
+    pub share_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"queued_shares", vault.key().as_ref()],
+        bump
+    )]
+    pub queued_shares: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPerformanceFee<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// Kept in step with the vault's rates once the vault has one
+    #[account(
+        mut,
+        seeds = [b"fee_config", vault.key().as_ref()],
+        bump = fee_config.bump
+    )]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    #[account(mut)]
+    pub fee_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.fee_authority() == fee_authority.key() @ MoluscoError::NotFeeAuthority,
+        constraint = !vault.cpi_guard.locked @ MoluscoError::ReentrancyDetected
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = fee_token_account.mint == vault.mint
+    )]
+    pub fee_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: system-owned lamport escrow PDA; holds no data
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", vault.key().as_ref()],
+        bump
+    )]
+    pub sol_escrow: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Receives `config.buyback_bps` of every token fee payout
+    #[account(
+        mut,
+        seeds = [b"buyback_escrow", vault.mint.as_ref()],
+        bump
+    )]
+    pub buyback_escrow: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeConfig<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + FeeConfig::SIZE,
+        seeds = [b"fee_config", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_config: Account<'info, FeeConfig>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SweepFees<'info> {
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.cpi_guard.locked @ MoluscoError::ReentrancyDetected
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        seeds = [b"fee_config", vault.key().as_ref()],
+        bump = fee_config.bump
+    )]
+    pub fee_config: Account<'info, FeeConfig>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: system-owned lamport escrow PDA; holds no data
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", vault.key().as_ref()],
+        bump
+    )]
+    pub sol_escrow: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Receives `config.buyback_bps` of every token fee payout
+    #[account(
+        mut,
+        seeds = [b"buyback_escrow", vault.mint.as_ref()],
+        bump
+    )]
+    pub buyback_escrow: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: must be the configured recipient; the token program checks its mint
+    #[account(
+        mut,
+        address = fee_config.recipient @ MoluscoError::InvalidFeeRecipient
+    )]
+    pub recipient: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(delegate: Pubkey)]
+pub struct GrantDelegate<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Delegate::SIZE,
+        seeds = [b"delegate", vault.key().as_ref(), delegate.as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegate>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeDelegate<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"delegate", vault.key().as_ref(), delegation.delegate.as_ref()],
+        bump = delegation.bump,
+        close = owner
+    )]
+    pub delegation: Account<'info, Delegate>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_key: Pubkey)]
+pub struct CreateSession<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Session::SIZE,
+        seeds = [b"session", vault.key().as_ref(), session_key.as_ref()],
+        bump
+    )]
+    pub session: Account<'info, Session>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSession<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"session", vault.key().as_ref(), session.session_key.as_ref()],
+        bump = session.bump,
+        close = owner
+    )]
+    pub session: Account<'info, Session>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeOwner<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOwner<'info> {
+    pub new_owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.pending_owner != Pubkey::default() @ MoluscoError::NoPendingOwner,
+        constraint = vault.pending_owner == new_owner.key() @ MoluscoError::NotPendingOwner
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+/// Idempotency keys a vault keeps to reject retried instructions
+pub const IDEMPOTENCY_RING_SIZE: usize = 8;
+
+/// Ring buffer of the last `IDEMPOTENCY_RING_SIZE` client idempotency keys
+///
+/// A retried transaction that already landed carries a key still in the ring,
+/// so it fails instead of deploying capital twice.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IdempotencyRing {
+    pub keys: [[u8; 16]; IDEMPOTENCY_RING_SIZE],
+    pub cursor: u8,
+}
+
+impl IdempotencyRing {
+    pub const SIZE: usize = 16 * IDEMPOTENCY_RING_SIZE + 1;
+
+    /// Reject a key seen recently, otherwise remember it; `None` skips the check
+    pub fn consume(&mut self, key: Option<[u8; 16]>) -> Result<()> {
+        let Some(key) = key else {
+            return Ok(());
+        };
+        // Empty slots are zeroed, so a zero key would always look like a duplicate
+        require!(key != [0; 16], MoluscoError::InvalidIdempotencyKey);
+        require!(!self.keys.contains(&key), MoluscoError::DuplicateIdempotencyKey);
+        self.keys[self.cursor as usize] = key;
+        self.cursor = ((self.cursor as usize + 1) % IDEMPOTENCY_RING_SIZE) as u8;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct PauseVault<'info> {
+    /// The vault owner or guardian
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UnpauseVault<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+/// The global program config, rejecting the instruction while the program is paused
+#[derive(Accounts)]
+pub struct ProgramGuard<'info> {
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = !program_config.paused @ MoluscoError::ProgramPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+/// Registry entries and oracle configs of the two mints of a swap, to price it
+/// independently of the caller
+#[derive(Accounts)]
+pub struct SwapOracles<'info> {
+    #[account(
+        seeds = [b"asset", source_asset_entry.mint.as_ref()],
+        bump = source_asset_entry.bump
+    )]
+    pub source_asset_entry: Account<'info, AssetEntry>,
+
+    #[account(
+        seeds = [b"oracle_config", source_asset_entry.key().as_ref()],
+        bump = source_oracle_config.bump
+    )]
+    pub source_oracle_config: Account<'info, OracleConfig>,
+
+    #[account(
+        seeds = [b"asset", destination_asset_entry.mint.as_ref()],
+        bump = destination_asset_entry.bump
+    )]
+    pub destination_asset_entry: Account<'info, AssetEntry>,
+
+    #[account(
+        seeds = [b"oracle_config", destination_asset_entry.key().as_ref()],
+        bump = destination_oracle_config.bump
+    )]
+    pub destination_oracle_config: Account<'info, OracleConfig>,
+}
+
+impl<'info> SwapOracles<'info> {
+    /// Median prices of `source_mint` and `destination_mint`, with the accounts after theirs
+    ///
+    /// The source config's price accounts lead `accounts`, then the destination's unless
+    /// both mints share an entry.
+    pub fn prices<'a>(
+        &self,
+        source_mint: &Pubkey,
+        destination_mint: &Pubkey,
+        accounts: &'a [AccountInfo<'info>],
+        now: i64,
+    ) -> Result<(oracle::PricePair, &'a [AccountInfo<'info>])> {
+        require_keys_eq!(self.source_asset_entry.mint, *source_mint, MoluscoError::OracleAccountsMismatch);
+        require_keys_eq!(
+            self.destination_asset_entry.mint,
+            *destination_mint,
+            MoluscoError::OracleAccountsMismatch
+        );
+        let source_feeds = self.source_oracle_config.feeds.len();
+        let feeds = self.source_oracle_config.price_accounts(&self.destination_oracle_config);
+        require_gte!(accounts.len(), feeds, MoluscoError::OracleAccountsMismatch);
+        let (source, _) = self.source_oracle_config.price(&accounts[..source_feeds], now)?;
+        let destination = if self.source_asset_entry.key() == self.destination_asset_entry.key() {
+            source
+        } else {
+            self.destination_oracle_config.price(&accounts[source_feeds..feeds], now)?.0
+        };
+        let prices = oracle::PricePair {
+            source,
+            source_decimals: self.source_asset_entry.decimals,
+            destination,
+            destination_decimals: self.destination_asset_entry.decimals,
+        };
+        Ok((prices, &accounts[feeds..]))
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeProgramConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProgramConfig::SIZE,
+        seeds = [b"program_config"],
+        bump
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Moluscoyield>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ MoluscoError::NotUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetProgramPaused<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = program_config.admin == admin.key() @ MoluscoError::NotProgramAdmin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeProtocolRegistry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = program_config.admin == admin.key() @ MoluscoError::NotProgramAdmin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProtocolRegistry::SIZE,
+        seeds = [b"protocol_registry"],
+        bump
+    )]
+    pub protocol_registry: Account<'info, ProtocolRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProtocolRegistry<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = program_config.admin == admin.key() @ MoluscoError::NotProgramAdmin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_registry"],
+        bump = protocol_registry.bump
+    )]
+    pub protocol_registry: Account<'info, ProtocolRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterAsset<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = program_config.admin == admin.key() @ MoluscoError::NotProgramAdmin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + AssetEntry::SIZE,
+        seeds = [b"asset", mint.key().as_ref()],
+        bump
+    )]
+    pub asset_entry: Account<'info, AssetEntry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(strategy: StrategyKind)]
+pub struct SetStrategyCapacity<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = program_config.admin == admin.key() @ MoluscoError::NotProgramAdmin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + StrategyCapacity::SIZE,
+        seeds = [b"strategy_capacity", &strategy.seed()],
+        bump
+    )]
+    pub strategy_capacity: Account<'info, StrategyCapacity>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateLegacyPosition<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VoidPosition<'info> {
+    /// The vault owner, or a delegate allowed to open positions; receives the rent
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key(),
+        close = authority
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    /// Every position still inside the window reserved capacity at open
+    #[account(
+        mut,
+        seeds = [b"strategy_capacity", &position.strategy.seed()],
+        bump = strategy_capacity.bump
+    )]
+    pub strategy_capacity: Account<'info, StrategyCapacity>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultMetadata<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        constraint = vault.owner == owner.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + VaultMeta::SIZE,
+        seeds = [b"vault_meta", vault.key().as_ref()],
+        bump
+    )]
+    pub vault_meta: Account<'info, VaultMeta>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeAgentProfile<'info> {
+    #[account(mut)]
+    pub operator: Signer<'info>,
+
+    #[account(
+        init,
+        payer = operator,
+        space = 8 + AgentProfile::SIZE,
+        seeds = [b"agent_profile", operator.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, AgentProfile>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddProfileVault<'info> {
+    pub operator: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = operator,
+        seeds = [b"agent_profile", operator.key().as_ref()],
+        bump = profile.bump
+    )]
+    pub profile: Account<'info, AgentProfile>,
+
+    #[account(
+        constraint = vault.owner == operator.key()
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveProfileVault<'info> {
+    pub operator: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = operator,
+        seeds = [b"agent_profile", operator.key().as_ref()],
+        bump = profile.bump
+    )]
+    pub profile: Account<'info, AgentProfile>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetOperatorSummary<'info> {
+    pub profile: Account<'info, AgentProfile>,
+}
+
+#[derive(Accounts)]
+pub struct PayRebates<'info> {
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.cpi_guard.locked @ MoluscoError::ReentrancyDetected
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: system-owned lamport escrow PDA; holds no data
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", vault.key().as_ref()],
+        bump
+    )]
+    pub sol_escrow: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: must be the vault's rebate recipient; the token program checks its mint
+    #[account(
+        mut,
+        address = vault.rebate_recipient @ MoluscoError::InvalidFeeRecipient
+    )]
+    pub recipient: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateVaultLayout<'info> {
+    /// Pays the rent for the bytes the migration adds
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: a layout 1 vault, parsed by hand; length and discriminator are checked
+    #[account(mut, owner = crate::ID)]
+    pub vault: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePositionLayout<'info> {
+    /// Pays the rent for the bytes the migration adds
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: a position on an earlier layout, parsed by hand; length and discriminator are checked
+    #[account(mut, owner = crate::ID)]
+    pub position: UncheckedAccount<'info>,
+
+    /// The position's vault, on the current layout
+    pub vault: Account<'info, Vault>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettlePosition<'info> {
+    /// The vault owner, or a delegate allowed to mark positions
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    /// Caps what is settled at the yield actually held above principal
+    #[account(
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReducePosition<'info> {
+    /// The vault owner, or a delegate allowed to close positions
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    /// Frees the strategy capacity the trimmed principal used
+    #[account(
+        mut,
+        seeds = [b"strategy_capacity", &position.strategy.seed()],
+        bump = strategy_capacity.bump
+    )]
+    pub strategy_capacity: Account<'info, StrategyCapacity>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddToPosition<'info> {
+    /// The vault owner, or a delegate allowed to open positions
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused,
+        constraint = !vault.trading_paused @ MoluscoError::TradingPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"strategy_capacity", &position.strategy.seed()],
+        bump = strategy_capacity.bump
+    )]
+    pub strategy_capacity: Account<'info, StrategyCapacity>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(protocol: ProtocolKind)]
+pub struct RollPosition<'info> {
+    /// The vault owner, or a delegate allowed to open positions; pays for the
+    /// successor and receives the predecessor's rent
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused,
+        constraint = !vault.trading_paused @ MoluscoError::TradingPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key(),
+        close = authority
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Position::SIZE,
+        seeds = [
+            b"position",
+            vault.key().as_ref(),
+            &protocol.seed(),
+            &position.asset.seed(),
+            &vault.total_positions_created.to_le_bytes()
+        ],
+        bump
+    )]
+    pub successor: Account<'info, Position>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = mint,
+        token::authority = vault,
+        seeds = [b"position_escrow", successor.key().as_ref()],
+        bump
+    )]
+    pub successor_escrow: Account<'info, TokenAccount>,
+
+    /// Idle escrow, credited with yield realized above principal
+    #[account(
+        mut,
+        address = vault.escrow
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        address = vault.mint
+    )]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        seeds = [b"protocol_registry"],
+        bump = protocol_registry.bump,
+        constraint = protocol_registry.is_listed(protocol) @ MoluscoError::ProtocolNotWhitelisted
+    )]
+    pub protocol_registry: Account<'info, ProtocolRegistry>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    /// Frees capacity when a shortfall shrinks the successor
+    #[account(
+        mut,
+        seeds = [b"strategy_capacity", &position.strategy.seed()],
+        bump = strategy_capacity.bump
+    )]
+    pub strategy_capacity: Account<'info, StrategyCapacity>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTargetApy<'info> {
+    /// The vault owner, or a delegate allowed to mark positions
+    pub authority: Signer<'info>,
+
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FlagStale<'info> {
+    pub keeper: Signer<'info>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetOracleConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"program_config"],
+        bump = program_config.bump,
+        constraint = program_config.admin == admin.key() @ MoluscoError::NotProgramAdmin
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    pub asset_entry: Account<'info, AssetEntry>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + OracleConfig::SIZE,
+        seeds = [b"oracle_config", asset_entry.key().as_ref()],
+        bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    pub system_program: Program<'info, System>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePositionWithOracle<'info> {
+    /// The vault owner, a delegate or a session key allowed to mark positions
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !vault.is_paused @ MoluscoError::VaultPaused
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// Present when `authority` is a delegate rather than the owner
+    #[account(
+        seeds = [b"delegate", vault.key().as_ref(), authority.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Option<Account<'info, Delegate>>,
+
+    /// Present when `authority` is a session key
+    #[account(
+        seeds = [b"session", vault.key().as_ref(), authority.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Option<Account<'info, Session>>,
+
+    #[account(
+        mut,
+        constraint = position.vault == vault.key()
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        seeds = [b"position_escrow", position.key().as_ref()],
+        bump
+    )]
+    pub position_escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"oracle_config", position.asset_entry.as_ref()],
+        bump = oracle_config.bump
+    )]
+    /// Its feeds' price accounts are the `remaining_accounts`
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    /// CHECK: the vault's fee ledger PDA; fees are recorded in it once it is initialized
+    #[account(
+        mut,
+        seeds = [b"fee_ledger", vault.key().as_ref()],
+        bump
+    )]
+    pub fee_ledger: UncheckedAccount<'info>,
+
+    pub guard: ProgramGuard<'info>,
+}
+
+#[account]
+pub struct Vault {
+    pub owner: Pubkey,
+    pub agent_name: String,
+    pub total_value_locked: u128,
+    /// Open positions; falls on close, so it cannot seed addresses
+    pub active_positions: u16,
+    /// Every position ever opened; seeds the next position address, so it is never reused
+    pub total_positions_created: u64,
+    pub created_at: i64,
+    pub last_rebalance: i64,
+    pub template: Pubkey,
+    pub performance_fee_bps: u16,
+    pub management_fee_bps: u16,
+    pub deposit_cap: u64,
+    pub max_positions: u16,
+    pub min_position_amount: u64,
+    pub reward_adapter: Pubkey,
+    pub reward_haircut_bps: u16,
+    pub pending_rewards_value: u64,
+    pub nav: u64,
+    pub last_mark: i64,
+    pub smoothing_threshold_bps: u16,
+    pub smoothing_release_secs: i64,
+    pub smoothing_reserve: u64,
+    pub smoothed_nav: u64,
+    pub last_checkpoint_nav: u64,
+    pub last_checkpoint_ts: i64,
+    pub checkpoint_count: u64,
+    pub exit_fee_schedule: ExitFeeSchedule,
+    pub total_impaired: u64,
+    pub strategist: Pubkey,
+    pub slippage_budget_per_epoch: u64,
+    pub slippage_epoch: u64,
+    pub slippage_spent: u64,
+    pub next_order_id: u64,
+    pub deposits_paused: bool,
+    pub withdrawals_paused: bool,
+    pub trading_paused: bool,
+    pub deposit_pause_authority: Pubkey,
+    pub withdrawal_pause_authority: Pubkey,
+    pub trading_pause_authority: Pubkey,
+    pub simulation: bool,
+    pub delegate: Pubkey,
+    pub cloned_from: Pubkey,
+    pub cumulative_inflows: u128,
+    pub cumulative_outflows: u128,
+    pub cumulative_yield: u128,
+    pub cumulative_losses: u128,
+    pub cumulative_fees: u128,
+    pub return_stats: RunningStats,
+    pub benchmark: Pubkey,
+    pub operating_costs: u64,
+    pub last_checkpoint_costs: u64,
+    pub cosigners: Vec<Pubkey>,
+    pub cosign_threshold: u8,
+    pub large_withdrawal_limit: u64,
+    pub destination_allowlist: Pubkey,
+    pub velocity_limit_bps: u16,
+    pub velocity_window_start: i64,
+    pub velocity_window_tvl: u64,
+    pub velocity_window_outflow: u64,
+    pub guardian: Pubkey,
+    pub viewer: Pubkey,
+    pub cpi_guard: ReentrancyGuard,
+    pub bundle_only: bool,
+    pub max_bundle_tip_lamports: u64,
+    pub mint: Pubkey,
+    pub escrow: Pubkey,
+    pub audit_mode: bool,
+    pub share_mint: Pubkey,
+    pub dead_shares: u64,
+    pub withdrawal_epoch: u64,
+    pub withdrawal_queue_head: u64,
+    pub withdrawal_queue_tail: u64,
+    pub queued_withdrawals: u64,
+    pub fee_authority: Pubkey,
+    pub fees_accrued: u128,
+    pub fee_last_accrued: i64,
+    pub fees_outstanding: u128,
+    pub realized_gains: u128,
+    pub fee_high_water_mark: u128,
+    /// Owner the vault address was derived from; unchanged by ownership transfers
+    pub seed_owner: Pubkey,
+    /// Proposed next owner, `Pubkey::default()` when none
+    pub pending_owner: Pubkey,
+    /// Idempotency keys of recent agent-submitted instructions
+    pub recent_keys: IdempotencyRing,
+    /// Incident stop: no opens, marks or rebalances; closing positions stays open
+    pub is_paused: bool,
+    /// Share of each crystallized performance fee rebated instead of paid to the fee authority
+    pub rebate_share_bps: u16,
+    /// Token account (system account for SOL vaults) paid rebates; default keeps them in TVL
+    pub rebate_recipient: Pubkey,
+    pub rebates_accrued: u128,
+    /// Sum of open positions' `unrealized_yield`; realized yield rolls up into `realized_gains`
+    pub unrealized_yield: u128,
+    /// Shortest gap between two marks of one position; zero leaves marks unthrottled
+    pub min_update_interval_secs: i64,
+    /// Age of a position's last mark past which anyone may flag it stale; zero disables flagging
+    pub max_staleness_secs: i64,
+    /// Open positions flagged stale; deposits wait until none are left
+    pub stale_positions: u32,
+    /// `migration::LAYOUT_VERSION` the account was written under
+    pub layout_version: u8,
+    pub bump: u8,
+}
+
+impl Vault {
+    pub const SIZE: usize = 32 +      // owner
+        4 + 32 +                        // agent_name (String with max 32 chars)
+        16 +                            // total_value_locked
+        2 +                             // active_positions
+        8 +                             // total_positions_created
+        8 +                             // created_at
+        8 +                             // last_rebalance
+        32 +                            // template
+        2 +                             // performance_fee_bps
+        2 +                             // management_fee_bps
+        8 +                             // deposit_cap
+        2 +                             // max_positions
+        8 +                             // min_position_amount
+        32 +                            // reward_adapter
+        2 +                             // reward_haircut_bps
+        8 +                             // pending_rewards_value
+        8 +                             // nav
+        8 +                             // last_mark
+        2 +                             // smoothing_threshold_bps
+        8 +                             // smoothing_release_secs
+        8 +                             // smoothing_reserve
+        8 +                             // smoothed_nav
+        8 +                             // last_checkpoint_nav
+        8 +                             // last_checkpoint_ts
+        8 +                             // checkpoint_count
+        ExitFeeSchedule::SIZE +         // exit_fee_schedule
+        8 +                             // total_impaired
+        32 +                            // strategist
+        8 +                             // slippage_budget_per_epoch
+        8 +                             // slippage_epoch
+        8 +                             // slippage_spent
+        8 +                             // next_order_id
+        1 +                             // deposits_paused
+        1 +                             // withdrawals_paused
+        1 +                             // trading_paused
+        32 +                            // deposit_pause_authority
+        32 +                            // withdrawal_pause_authority
+        32 +                            // trading_pause_authority
+        1 +                             // simulation
+        32 +                            // delegate
+        32 +                            // cloned_from
+        16 +                            // cumulative_inflows
+        16 +                            // cumulative_outflows
+        16 +                            // cumulative_yield
+        16 +                            // cumulative_losses
+        16 +                            // cumulative_fees
+        RunningStats::SIZE +            // return_stats
+        32 +                            // benchmark
+        8 +                             // operating_costs
+        8 +                             // last_checkpoint_costs
+        4 + MAX_COSIGNERS * 32 +        // cosigners
+        1 +                             // cosign_threshold
+        8 +                             // large_withdrawal_limit
+        32 +                            // destination_allowlist
+        2 +                             // velocity_limit_bps
+        8 +                             // velocity_window_start
+        8 +                             // velocity_window_tvl
+        8 +                             // velocity_window_outflow
+        32 +                            // guardian
+        32 +                            // viewer
+        ReentrancyGuard::SIZE +         // cpi_guard
+        1 +                             // bundle_only
+        8 +                             // max_bundle_tip_lamports
+        32 +                            // mint
+        32 +                            // escrow
+        1 +                             // audit_mode
+        32 +                            // share_mint
+        8 +                             // dead_shares
+        8 +                             // withdrawal_epoch
+        8 +                             // withdrawal_queue_head
+        8 +                             // withdrawal_queue_tail
+        8 +                             // queued_withdrawals
+        32 +                            // fee_authority
+        16 +                            // fees_accrued
+        8 +                             // fee_last_accrued
+        16 +                            // fees_outstanding
+        16 +                            // realized_gains
+        16 +                            // fee_high_water_mark
+        32 +                            // seed_owner
+        32 +                            // pending_owner
+        IdempotencyRing::SIZE +         // recent_keys
+        1 +                             // is_paused
+        2 +                             // rebate_share_bps
+        32 +                            // rebate_recipient
+        16 +                            // rebates_accrued
+        16 +                            // unrealized_yield
+        8 +                             // min_update_interval_secs
+        8 +                             // max_staleness_secs
+        4 +                             // stale_positions
+        1 +                             // layout_version
+        1;                              // bump
+
+    /// Drop a position's stale flag, on a fresh mark or when it leaves the book
+    pub fn clear_stale(&mut self, position: &mut Position) {
+        if position.is_stale {
+            position.is_stale = false;
+            self.stale_positions -= 1;
+        }
+    }
+
+    /// Replace one position's contribution to `unrealized_yield`
+    pub fn restate_unrealized(&mut self, before: u64, after: u64) {
+        self.unrealized_yield = self.unrealized_yield.saturating_sub(before as u128) + after as u128;
+    }
+
+    /// TVL as a token amount, for share pricing and the u64 figures derived from it
+    pub fn total_assets(&self) -> Result<u64> {
+        token_amount(self.total_value_locked)
+    }
+
+    /// Assets shares are priced against: TVL less the gains the smoothing reserve holds back
+    pub fn share_assets(&self) -> Result<u64> {
+        Ok(self.total_assets()?.saturating_sub(self.smoothing_reserve))
+    }
+
+    /// Mean checkpoint return annualized over the vault's average checkpoint spacing
+    pub fn trailing_apy_bps(&self) -> i64 {
+        let elapsed = self.last_checkpoint_ts - self.created_at;
+        if self.return_stats.count == 0 || elapsed <= 0 {
+            return 0;
+        }
+        let scaled = self.return_stats.mean as i128 * self.checkpoint_count as i128;
+        (scaled * SECONDS_PER_YEAR as i128 / (elapsed as i128 * stats::STAT_SCALE)) as i64
+    }
+
+    /// PDA signer seeds for CPIs where the vault is the token authority
+    pub fn signer_seeds(&self) -> [&[u8]; 4] {
+        [
+            b"vault",
+            self.seed_owner.as_ref(),
+            self.agent_name.as_bytes(),
+            std::slice::from_ref(&self.bump),
+        ]
+    }
+
+    /// Require `cosign_threshold` distinct registered co-signers for large withdrawals
+    pub fn check_cosigners(&self, amount: u64, signers: &[AccountInfo]) -> Result<()> {
+        if self.cosign_threshold == 0 || amount <= self.large_withdrawal_limit {
+            return Ok(());
+        }
+        self.require_cosigner_quorum(signers)
+    }
+
+    /// Whether a new co-signer config would let through an outflow the current one stops
+    ///
+    /// A lower threshold, a higher limit or any new co-signer loosens it; keeping a
+    /// subset of the co-signers at the same or a higher threshold does not.
+    pub fn loosens_cosigners(&self, cosigners: &[Pubkey], threshold: u8, large_withdrawal_limit: u64) -> bool {
+        self.cosign_threshold > 0
+            && (threshold < self.cosign_threshold
+                || large_withdrawal_limit > self.large_withdrawal_limit
+                || cosigners.iter().any(|key| !self.cosigners.contains(key)))
+    }
+
+    /// Require `cosign_threshold` distinct registered co-signers among `signers`
+    pub fn require_cosigner_quorum(&self, signers: &[AccountInfo]) -> Result<()> {
+        let mut approved: Vec<Pubkey> = Vec::with_capacity(self.cosigners.len());
+        for info in signers {
+            if info.is_signer && self.cosigners.contains(info.key) && !approved.contains(info.key) {
+                approved.push(info.key());
+            }
+        }
+        require_gte!(
+            approved.len(),
+            self.cosign_threshold as usize,
+            MoluscoError::InsufficientCosigners
+        );
+        Ok(())
+    }
+
+    /// Reject outflows to addresses missing from an enabled allowlist
+    pub fn check_destination(
+        &self,
+        allowlist: Option<&Account<DestinationAllowlist>>,
+        destination: &Pubkey,
+    ) -> Result<()> {
+        if self.destination_allowlist == Pubkey::default() {
+            return Ok(());
+        }
+
+        let allowlist = allowlist.ok_or(MoluscoError::DestinationNotAllowed)?;
+        require_keys_eq!(
+            allowlist.key(),
+            self.destination_allowlist,
+            MoluscoError::DestinationNotAllowed
+        );
+        require!(
+            allowlist.destinations.contains(destination),
+            MoluscoError::DestinationNotAllowed
+        );
+        Ok(())
+    }
+
+    /// Key allowed to toggle a pause flag; unset authorities fall back to the owner
+    pub fn pause_authority(&self, kind: PauseKind) -> Pubkey {
+        let authority = match kind {
+            PauseKind::Deposits => self.deposit_pause_authority,
+            PauseKind::Withdrawals => self.withdrawal_pause_authority,
+            PauseKind::Trading => self.trading_pause_authority,
+        };
+        if authority == Pubkey::default() {
+            self.owner
+        } else {
+            authority
+        }
+    }
+
+    /// Charge rebalance slippage against the current epoch's budget
+    pub fn charge_slippage(&mut self, cost: u64, epoch: u64) -> Result<()> {
+        if epoch != self.slippage_epoch {
+            self.slippage_epoch = epoch;
+            self.slippage_spent = 0;
+        }
+        self.slippage_spent = self
+            .slippage_spent
+            .checked_add(cost)
+            .ok_or(MoluscoError::MathOverflow)?;
+        if self.slippage_budget_per_epoch > 0 {
+            require_gte!(
+                self.slippage_budget_per_epoch,
+                self.slippage_spent,
+                MoluscoError::SlippageBudgetExhausted
+            );
+        }
+        Ok(())
+    }
+
+    /// Share supply including the permanently dead shares from the first deposit
+    pub fn total_shares(&self, minted_supply: u64) -> u64 {
+        minted_supply.saturating_add(self.dead_shares)
+    }
+
+    /// Decimals of the vault's asset: SOL's for a native vault, otherwise `mint`'s
+    pub fn asset_decimals(&self, mint: Option<&Account<Mint>>) -> Result<u8> {
+        if self.holds_native_sol() {
+            return Ok(decimals::SOL_DECIMALS);
+        }
+        Ok(mint.ok_or(MoluscoError::VaultMintRequired)?.decimals)
+    }
+
+    /// Decimals the vault's share math converts between
+    pub fn share_scale(&self, asset_decimals: u8, share_mint: &Mint) -> shares::ShareScale {
+        shares::ShareScale {
+            asset_decimals,
+            share_decimals: share_mint.decimals,
+        }
+    }
+
+    /// Who may collect accrued fees; the owner until a fee authority is set
+    pub fn fee_authority(&self) -> Pubkey {
+        if self.fee_authority == Pubkey::default() {
+            self.owner
+        } else {
+            self.fee_authority
+        }
+    }
+
+    /// Take the performance fee out of yield that has just been added to TVL
+    ///
+    /// Only net-new profit pays: realized gains less losses must clear the high-water
+    /// mark, so yield that merely wins back an earlier loss is fee free. The fee is
+    /// split between the fee authority and rebates by `rebate_share_bps`. Returns what
+    /// left TVL and the part of `realized_yield` it was charged on.
+    pub fn take_performance_fee(&mut self, realized_yield: u64) -> Result<(u64, u64)> {
+        self.realized_gains += realized_yield as u128;
+        let net_profit = self.realized_gains.saturating_sub(self.cumulative_losses);
+        let chargeable =
+            fees::above_high_water_mark(net_profit, self.fee_high_water_mark, realized_yield);
+        self.fee_high_water_mark += chargeable as u128;
+        let fee = (chargeable as u128 * self.performance_fee_bps as u128 / 10_000) as u64;
+        let (operator_fee, rebate) = fees::split_performance_fee(fee, self.rebate_share_bps);
+        // A depositor rebate never leaves TVL, so depositors are only charged the operator's cut
+        let charged = if self.rebate_recipient == Pubkey::default() {
+            operator_fee
+        } else {
+            self.rebates_accrued = self
+                .rebates_accrued
+                .checked_add(rebate as u128)
+                .ok_or(MoluscoError::MathOverflow)?;
+            fee
+        };
+        self.total_value_locked = self
+            .total_value_locked
+            .checked_sub(charged as u128)
+            .ok_or(MoluscoError::TvlUnderflow)?;
+        self.fees_accrued = self
+            .fees_accrued
+            .checked_add(operator_fee as u128)
+            .ok_or(MoluscoError::MathOverflow)?;
+        Ok((charged, chargeable))
+    }
+
+    /// Fees owed to the fee authority and still held in escrow
+    ///
+    /// Rebates owed to `rebate_recipient` are held apart in `rebates_accrued`.
+    pub fn fees_due(&self) -> u128 {
+        self.fees_accrued + self.fees_outstanding
+    }
+
+    /// Accrue the management fee on TVL for the time since the last accrual
+    ///
+    /// The clock only advances once at least one unit of fee is due, so frequent
+    /// touches on a small vault do not round the fee away.
+    pub fn accrue_management_fee(&mut self, now: i64) -> Result<u64> {
+        let elapsed = now.saturating_sub(self.fee_last_accrued);
+        if self.fee_last_accrued == 0 || self.management_fee_bps == 0 || self.total_value_locked == 0 {
+            self.fee_last_accrued = now;
+            return Ok(0);
+        }
+        let fee = (self.total_value_locked * self.management_fee_bps as u128 * elapsed as u128
+            / (10_000 * SECONDS_PER_YEAR as u128))
+            .min(self.total_value_locked);
+        if fee == 0 {
+            return Ok(0);
+        }
+        self.total_value_locked -= fee;
+        self.fees_outstanding = self
+            .fees_outstanding
+            .checked_add(fee)
+            .ok_or(MoluscoError::MathOverflow)?;
+        self.fee_last_accrued = now;
+        token_amount(fee)
+    }
+
+    /// Whether TVL is denominated in SOL: no escrow mint yet, or wrapped SOL
+    pub fn holds_native_sol(&self) -> bool {
+        self.mint == Pubkey::default() || self.mint == token::spl_token::native_mint::ID
+    }
+
+    /// Owner or registered viewer; gates report instructions that only cost rent
+    pub fn can_view(&self, key: &Pubkey) -> bool {
+        *key == self.owner || (self.viewer != Pubkey::default() && *key == self.viewer)
+    }
+
+    /// Whether the vault's guardian signed among the supplied accounts
+    pub fn guardian_signed(&self, signers: &[AccountInfo]) -> bool {
+        self.guardian != Pubkey::default()
+            && signers.iter().any(|info| info.is_signer && *info.key == self.guardian)
+    }
+
+    /// Outflow still allowed in the velocity window at `now`; unlimited when no limit is set
+    pub fn velocity_headroom(&self, now: i64) -> u64 {
+        if self.velocity_limit_bps == 0 {
+            return u64::MAX;
+        }
+        let window_expired = now >= self.velocity_window_start.saturating_add(VELOCITY_WINDOW_SECS);
+        let (base, used) = if window_expired {
+            (self.total_value_locked, 0)
+        } else {
+            (self.velocity_window_tvl as u128, self.velocity_window_outflow)
+        };
+        let allowed = base * self.velocity_limit_bps as u128 / 10_000;
+        u64::try_from(allowed).unwrap_or(u64::MAX).saturating_sub(used)
+    }
+
+    /// Count an outflow against the current 24h window, enforcing the velocity limit
+    /// unless the guardian overrides it
+    pub fn charge_outflow(&mut self, amount: u64, now: i64, guardian_override: bool) -> Result<()> {
+        if now >= self.velocity_window_start.saturating_add(VELOCITY_WINDOW_SECS) {
+            self.velocity_window_start = now;
+            self.velocity_window_tvl = u64::try_from(self.total_value_locked).unwrap_or(u64::MAX);
+            self.velocity_window_outflow = 0;
+        }
+        self.velocity_window_outflow = self
+            .velocity_window_outflow
+            .checked_add(amount)
+            .ok_or(MoluscoError::MathOverflow)?;
+
+        if self.velocity_limit_bps > 0 && !guardian_override {
+            let allowed = (self.velocity_window_tvl as u128)
+                * self.velocity_limit_bps as u128
+                / 10_000;
+            require_gte!(
+                allowed,
+                self.velocity_window_outflow as u128,
+                MoluscoError::VelocityLimitExceeded
+            );
+        }
+        Ok(())
+    }
+
+    /// Release part of the smoothing reserve and hold back gains above the threshold.
+    /// Returns `(held, released)` for the period ending at `now`.
+    pub fn apply_smoothing(&mut self, raw_nav: u64, now: i64) -> (u64, u64) {
+        if self.smoothing_release_secs == 0 || self.last_checkpoint_ts == 0 {
+            return (0, 0);
+        }
+
+        let elapsed = now.saturating_sub(self.last_checkpoint_ts).max(0) as u128;
+        let released = ((self.smoothing_reserve as u128) * elapsed
+            / self.smoothing_release_secs as u128)
+            .min(self.smoothing_reserve as u128) as u64;
+        self.smoothing_reserve -= released;
+
+        let gain = raw_nav.saturating_sub(self.last_checkpoint_nav);
+        let allowed = ((self.last_checkpoint_nav as u128) * (self.smoothing_threshold_bps as u128)
+            / 10_000) as u64;
+        let held = gain.saturating_sub(allowed);
+        self.smoothing_reserve = self.smoothing_reserve.saturating_add(held);
+
+        (held, released)
+    }
+}
+
+/// Length of the outflow velocity window
+pub const VELOCITY_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+/// Maximum number of withdrawal co-signers registered on a vault
+pub const MAX_COSIGNERS: usize = 5;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseKind {
+    Deposits,
+    Withdrawals,
+    Trading,
+}
+
+/// Encoded size of a kind enum at its largest (`Other`: tag plus u16)
+pub const KIND_SIZE: usize = 3;
+
+/// Borsh encoding of a kind enum, zero-padded to `KIND_SIZE`, for use as a PDA seed
+pub fn kind_seed<T: AnchorSerialize>(kind: &T) -> [u8; KIND_SIZE] {
+    let mut seed = [0; KIND_SIZE];
+    // Every kind encodes to at most KIND_SIZE bytes, so this cannot run out of room
+    let _ = kind.serialize(&mut seed.as_mut_slice());
+    seed
+}
+
+/// Venue a position is deployed in
+///
+/// `Other` is tag 0 in each kind enum, so named variants are appended without
+/// moving any existing encoding or seed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtocolKind {
+    /// A venue with no named variant yet, by an off-chain code
+    Other(u16),
+    Kamino,
+    Marginfi,
+    Drift,
+    Solend,
+    Jito,
+    Marinade,
+    Sanctum,
+}
+
+impl ProtocolKind {
+    pub fn seed(&self) -> [u8; KIND_SIZE] {
+        kind_seed(self)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StrategyKind {
+    Other(u16),
+    Lending,
+    LiquidStaking,
+    LiquidityProvision,
+    BasisTrade,
+    ManagedVault,
+}
+
+impl StrategyKind {
+    pub fn seed(&self) -> [u8; KIND_SIZE] {
+        kind_seed(self)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetKind {
+    Other(u16),
+    Usdc,
+    Usdt,
+    Sol,
+    JitoSol,
+    MSol,
+    BSol,
+}
+
+impl AssetKind {
+    pub fn seed(&self) -> [u8; KIND_SIZE] {
+        kind_seed(self)
+    }
+}
+
+/// Highest `target_apy` a live position can be retargeted to (100%)
+pub const MAX_TARGET_APY_BPS: u16 = 10_000;
+
+/// Earlier targets a position remembers
+pub const TARGET_APY_HISTORY_LEN: usize = 4;
+
+/// A target a position held, and over which period
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TargetApyChange {
+    pub target_apy: u16,
+    pub applied_from: i64,
+    pub applied_until: i64,
+}
+
+/// A position's most recent earlier targets, newest first
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TargetApyHistory {
+    pub entries: [TargetApyChange; TARGET_APY_HISTORY_LEN],
+}
+
+impl TargetApyHistory {
+    pub const SIZE: usize = (2 + 8 + 8) * TARGET_APY_HISTORY_LEN;
+
+    /// When the current target took over: the last change, else `opened_at`
+    pub fn current_since(&self, opened_at: i64) -> i64 {
+        match self.entries[0].applied_until {
+            0 => opened_at,
+            until => until,
+        }
+    }
+
+    /// Remember `target_apy` as replaced at `now`; the oldest entry drops off
+    pub fn push(&mut self, target_apy: u16, opened_at: i64, now: i64) {
+        let applied_from = self.current_since(opened_at);
+        self.entries.copy_within(0..TARGET_APY_HISTORY_LEN - 1, 1);
+        self.entries[0] = TargetApyChange {
+            target_apy,
+            applied_from,
+            applied_until: now,
+        };
+    }
+}
+
+/// Oldest mark, in slots, `settle_and_close` accepts a final value on top of (about a minute)
+pub const MAX_FINAL_MARK_AGE_SLOTS: u64 = 150;
+
+/// How long after opening a position can still be voided
+pub const VOID_WINDOW_SECS: i64 = 10 * 60;
+
+#[account]
+pub struct Position {
+    /// Vault owner when the position was opened; authority follows `vault.owner`
+    pub owner: Pubkey,
+    pub vault: Pubkey,
+    pub protocol: ProtocolKind,
+    pub strategy: StrategyKind,
+    pub asset: AssetKind,
+    pub amount: u64,
+    pub target_apy: u16,
+    pub opened_at: i64,
+    pub last_update: i64,
+    /// Slot of the open or the latest mark
+    pub last_update_slot: u64,
+    pub is_active: bool,
+    pub accumulated_yield: u128,
+    /// Falls in value, counted as each mark shows them; the counterpart of
+    /// `accumulated_yield`, which counts the rises
+    pub accumulated_loss: u128,
+    /// Gain above principal at the latest mark, not yet settled into principal
+    pub unrealized_yield: u64,
+    /// Yield crystallized by compounds, rollovers, settles and the close
+    pub realized_yield: u128,
+    pub compound_interval_secs: i64,
+    pub last_compound: i64,
+    pub compounded_yield: u64,
+    pub term_secs: i64,
+    pub maturity_ts: i64,
+    pub rollover_count: u32,
+    pub impairment_count: u16,
+    pub total_impaired: u64,
+    pub unwind_priority: u8,
+    pub last_value: u64,
+    pub checkpoint_value: u64,
+    /// Venue supply APR at the last mark that read it, from the lending reserve
+    pub venue_supply_apr_bps: u32,
+    pub venue_utilization_bps: u16,
+    /// Zero until a mark has read the venue rate
+    pub venue_rate_updated_at: i64,
+    /// Registry entry for `asset`; `asset_decimals` is cached from it
+    pub asset_entry: Pubkey,
+    pub asset_decimals: u8,
+    /// Vault's `total_positions_created` at open, little-endian in the address seeds
+    pub index: u64,
+    /// Address derives from the pre-index one-byte count seed; `index` came from a migration
+    pub legacy_seed: bool,
+    /// Position this one was rolled out of, `Pubkey::default()` when opened directly
+    pub rolled_from: Pubkey,
+    pub target_apy_history: TargetApyHistory,
+    /// Marks accepted so far; the next mark must carry this number
+    pub update_sequence: u64,
+    /// Flagged by `flag_stale`; cleared by the next mark
+    pub is_stale: bool,
+    /// Strategy capacity held, at `decimals::INTERNAL_DECIMALS`; never more is released
+    pub reserved_capacity: u128,
+    /// `migration::LAYOUT_VERSION` the account was written under
+    pub layout_version: u8,
+    pub bump: u8,
+}
+
+impl Position {
+    pub const SIZE: usize = 32 +      // owner
+        32 +                            // vault
+        KIND_SIZE +                     // protocol
+        KIND_SIZE +                     // strategy
+        KIND_SIZE +                     // asset
+        8 +                             // amount
+        2 +                             // target_apy
+        8 +                             // opened_at
+        8 +                             // last_update
+        8 +                             // last_update_slot
+        1 +                             // is_active
+        16 +                            // accumulated_yield
+        16 +                            // accumulated_loss
+        8 +                             // unrealized_yield
+        16 +                            // realized_yield
+        8 +                             // compound_interval_secs
+        8 +                             // last_compound
+        8 +                             // compounded_yield
+        8 +                             // term_secs
+        8 +                             // maturity_ts
+        4 +                             // rollover_count
+        2 +                             // impairment_count
+        8 +                             // total_impaired
+        1 +                             // unwind_priority
+        8 +                             // last_value
+        8 +                             // checkpoint_value
+        4 +                             // venue_supply_apr_bps
+        2 +                             // venue_utilization_bps
+        8 +                             // venue_rate_updated_at
+        32 +                            // asset_entry
+        1 +                             // asset_decimals
+        8 +                             // index
+        1 +                             // legacy_seed
+        32 +                            // rolled_from
+        TargetApyHistory::SIZE +        // target_apy_history
+        8 +                             // update_sequence
+        1 +                             // is_stale
+        16 +                            // reserved_capacity
+        1 +                             // layout_version
+        1;                              // bump
+
+    /// Inside the void window and never marked, compounded, rolled or impaired
+    pub fn is_voidable(&self, now: i64) -> bool {
+        self.is_active
+            && now <= self.opened_at.saturating_add(VOID_WINDOW_SECS)
+            && self.last_update == self.opened_at
+            && self.accumulated_yield == 0
+            && self.accumulated_loss == 0
+            && self.rollover_count == 0
+            && self.impairment_count == 0
+    }
+
+    /// Principal at `decimals::INTERNAL_DECIMALS`, comparable across assets
+    pub fn normalized_amount(&self) -> Result<u128> {
+        decimals::normalize(self.amount, self.asset_decimals)
+    }
+
+    /// Take `amount` of normalized capacity from `capacity` and hold it against this position
+    pub fn reserve_capacity(&mut self, capacity: &mut StrategyCapacity, amount: u128) -> Result<()> {
+        capacity.reserve(amount)?;
+        self.reserved_capacity = self
+            .reserved_capacity
+            .checked_add(amount)
+            .ok_or(MoluscoError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Give `amount` of this position's capacity back, no more than it holds
+    ///
+    /// Compounded and rolled-over yield grows `amount` without reserving capacity, so
+    /// principal leaving can be worth more than the position ever took.
+    pub fn release_capacity(&mut self, capacity: &mut StrategyCapacity, amount: u128) {
+        let amount = amount.min(self.reserved_capacity);
+        self.reserved_capacity -= amount;
+        capacity.release(amount);
+    }
+
+    /// Venue supply APR less the position's target APY, once a mark has read it
+    pub fn venue_rate_vs_target_bps(&self) -> Option<i64> {
+        (self.venue_rate_updated_at != 0)
+            .then(|| self.venue_supply_apr_bps as i64 - self.target_apy as i64)
+    }
+
+    /// Reject a mark sooner than `min_interval_secs` after the previous one (or the open)
+    pub fn check_update_interval(&self, min_interval_secs: i64, now: i64) -> Result<()> {
+        require_gte!(
+            now,
+            self.last_update.saturating_add(min_interval_secs),
+            MoluscoError::UpdateTooFrequent
+        );
+        Ok(())
+    }
+
+    /// Take the mark numbered `sequence` if it is the next one; stale or replayed marks fail
+    pub fn advance_sequence(&mut self, sequence: u64) -> Result<()> {
+        require_eq!(sequence, self.update_sequence, MoluscoError::StaleUpdateSequence);
+        self.update_sequence += 1;
+        Ok(())
+    }
+
+    /// Switch to a new target, recording the outgoing one in the history
+    pub fn retarget(&mut self, target_apy: u16, now: i64) {
+        self.target_apy_history.push(self.target_apy, self.opened_at, now);
+        self.target_apy = target_apy;
+    }
+
+    /// Move `amount` of yield from unrealized to realized; returns the unrealized figure it replaced
+    pub fn realize(&mut self, amount: u64) -> u64 {
+        let before = self.unrealized_yield;
+        self.unrealized_yield = before.saturating_sub(amount);
+        self.realized_yield += amount as u128;
+        before
+    }
+
+    /// Realize `amount` of yield into principal; returns the unrealized figure it replaced
+    pub fn fold_into_principal(&mut self, amount: u64) -> Result<u64> {
+        self.amount = self
+            .amount
+            .checked_add(amount)
+            .ok_or(MoluscoError::MathOverflow)?;
+        self.compounded_yield += amount;
+        Ok(self.realize(amount))
+    }
+
+    /// Record a new mark and return the yield and the loss it added to the running totals
+    ///
+    /// Only the move since the last mark is added: a rise to the yield, a fall to the
+    /// loss. A position held at one value is not counted again on every mark, a swing
+    /// is counted once each way, and callers can add the returned yield straight into
+    /// vault totals.
+    pub fn apply_mark(&mut self, current_value: u64, now: i64, slot: u64) -> (u64, u64) {
+        let yield_earned = current_value.saturating_sub(self.amount);
+        let gained = current_value.saturating_sub(self.last_value);
+        let lost = self.last_value.saturating_sub(current_value);
+        self.accumulated_yield += gained as u128;
+        self.accumulated_loss += lost as u128;
+        self.unrealized_yield = yield_earned;
+        self.last_value = current_value;
+        self.last_update = now;
+        self.last_update_slot = slot;
+        (gained, lost)
+    }
+}
+
+/// Maximum number of positions a batch instruction may touch
+pub const MAX_BATCH_SIZE: usize = 16;
+
+/// Whether any key appears more than once; batch instructions reject such inputs
+/// so one position cannot be counted (or paid out) twice
+pub fn has_duplicate_keys(keys: &[Pubkey]) -> bool {
+    keys.iter()
+        .enumerate()
+        .any(|(i, key)| keys[..i].contains(key))
+}
+
+/// Reject `remaining_accounts` that repeat an account
+pub fn require_unique_accounts(infos: &[AccountInfo]) -> Result<()> {
+    let keys: Vec<Pubkey> = infos.iter().map(|info| info.key()).collect();
+    require!(!has_duplicate_keys(&keys), MoluscoError::DuplicateAccount);
+    Ok(())
+}
+
+/// Reject token accounts the vault owns among `accounts`, which a CPI signed by the
+/// vault could move outside the balance checks
+pub fn require_no_vault_token_accounts(accounts: &[AccountInfo], vault: &Pubkey) -> Result<()> {
+    for account in accounts {
+        if *account.owner == token::ID {
+            let data = account.try_borrow_data()?;
+            // An SPL token account's owner follows its 32-byte mint
+            let owner = data.get(32..64).unwrap_or_default();
+            require!(owner != vault.as_ref(), MoluscoError::VaultAccountExposed);
+        }
+    }
+    Ok(())
+}
+
+/// Reject a caller-supplied mark for an asset that has an oracle config
+pub fn require_manual_mark(oracle_config: &AccountInfo) -> Result<()> {
+    require!(oracle_config.data_is_empty(), MoluscoError::OracleMarkRequired);
+    Ok(())
+}
+
+/// Jito block engine tip accounts (mainnet)
+pub const JITO_TIP_ACCOUNTS: [Pubkey; 8] = [
+    pubkey!("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5"),
+    pubkey!("HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe"),
+    pubkey!("Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY"),
+    pubkey!("ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49"),
+    pubkey!("DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh"),
+    pubkey!("ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt"),
+    pubkey!("DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL"),
+    pubkey!("3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT"),
+];
+
+/// Whether the current transaction also calls `pay_bundle_tip` for `vault`.
+///
+/// A tip-paying transaction is only worth landing through the block engine, so
+/// this is how bundle-only vaults keep large swaps out of the public mempool.
+pub fn transaction_pays_bundle_tip(instructions: &AccountInfo, vault: &Pubkey) -> Result<bool> {
+    let tip_discriminator = adapters::discriminator("pay_bundle_tip");
+    let mut index = 0;
+    while let Ok(ix) = load_instruction_at_checked(index, instructions) {
+        if ix.program_id == crate::ID
+            && ix.data.get(..8) == Some(&tip_discriminator[..])
+            && ix.accounts.iter().any(|meta| meta.pubkey == *vault)
+        {
+            return Ok(true);
+        }
+        index += 1;
+    }
+    Ok(false)
+}
+
+/// A widened aggregate as a token amount; errors rather than truncating
+pub fn token_amount(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| error!(MoluscoError::MathOverflow))
+}
+
+/// Settle `record`'s reward stake in the vault's rewards distributor once it exists
+///
+/// `distributor` is always the vault's distributor PDA, so no share change can
+/// skip an existing one.
+pub fn settle_depositor_rewards(
+    distributor: &AccountInfo,
+    record: &mut DepositorRecord,
+    now: i64,
+) -> Result<()> {
+    if distributor.data_is_empty() {
+        return Ok(());
+    }
+    let mut distributor = Account::<RewardsDistributor>::try_from(distributor)?;
+    record.settle_rewards(&mut distributor, now)?;
+    distributor.exit(&crate::ID)
+}
+
+/// Book a fee in the vault's fee ledger once it is initialized, otherwise emit it directly
+///
+/// `ledger` is always the vault's fee ledger PDA, so no caller can leave out an
+/// existing ledger.
+pub fn book_fee(
+    vault: &mut Account<Vault>,
+    ledger: &AccountInfo,
+    kind: FeeKind,
+    rate_bps: u16,
+    base_amount: u64,
+    fee_amount: u64,
+    timestamp: i64,
+) -> Result<()> {
+    if fee_amount == 0 {
+        return Ok(());
+    }
+    if ledger.data_is_empty() {
+        vault.cumulative_fees += fee_amount as u128;
+        emit!(FeeChargedEvent {
+            vault: vault.key(),
+            kind,
+            rate_bps,
+            base_amount,
+            fee_amount,
+            sequence: 0,
+            timestamp,
+        });
+        return Ok(());
+    }
+    let mut ledger = Account::<FeeLedger>::try_from(ledger)?;
+    ledger.record(vault, kind, rate_bps, base_amount, fee_amount, timestamp);
+    ledger.exit(&crate::ID)
+}
+
+/// Bring the streaming management fee up to `now` before the vault is priced or changed
+pub fn accrue_management_fee(vault: &mut Account<Vault>, ledger: &AccountInfo, now: i64) -> Result<()> {
+    let base_amount = vault.total_assets()?;
+    let fee = vault.accrue_management_fee(now)?;
+    let rate_bps = vault.management_fee_bps;
+    book_fee(vault, ledger, FeeKind::Management, rate_bps, base_amount, fee, now)
+}
+
+/// Move `amount` of accrued fees out of the vault's escrow and clear the fee buckets
+pub fn pay_fees<'info>(
+    vault: &mut Account<'info, Vault>,
+    escrow: Option<&Account<'info, TokenAccount>>,
+    sol_escrow: Option<&UncheckedAccount<'info>>,
+    destination: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    pay_from_escrow(vault, escrow, sol_escrow, destination, token_program, system_program, amount)?;
+    vault.fees_accrued = 0;
+    vault.fees_outstanding = 0;
+    Ok(())
+}
+
+/// Route the buyback share of `fee` into the config's buyback escrow; returns the amount routed
+///
+/// Native SOL vaults have no token escrow to route from, so their fees are paid out whole.
+pub fn pay_buyback<'info>(
+    vault: &mut Account<'info, Vault>,
+    config: &Account<'info, Config>,
+    escrow: Option<&Account<'info, TokenAccount>>,
+    buyback_escrow: Option<&Account<'info, TokenAccount>>,
+    token_program: &Program<'info, Token>,
+    system_program: &Program<'info, System>,
+    fee: u64,
+) -> Result<u64> {
+    let buyback = config.buyback_amount(fee);
+    if buyback == 0 || vault.holds_native_sol() {
+        return Ok(0);
+    }
+    let buyback_escrow = buyback_escrow.ok_or(MoluscoError::MissingFeeAccounts)?;
+    pay_from_escrow(
+        vault,
+        escrow,
+        None,
+        buyback_escrow.to_account_info(),
+        token_program,
+        system_program,
+        buyback,
+    )?;
+    Ok(buyback)
+}
+
+/// Pay `amount` out of the vault's escrow
+///
+/// Token vaults pay from `escrow` into a token account; native SOL vaults pay
+/// lamports from `sol_escrow` into a system account.
+pub fn pay_from_escrow<'info>(
+    vault: &mut Account<'info, Vault>,
+    escrow: Option<&Account<'info, TokenAccount>>,
+    sol_escrow: Option<&UncheckedAccount<'info>>,
+    destination: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    if vault.holds_native_sol() {
+        let sol_escrow = sol_escrow.ok_or(MoluscoError::MissingFeeAccounts)?;
+        let vault_key = vault.key();
+        let (_, bump) = Pubkey::find_program_address(&[b"sol_escrow", vault_key.as_ref()], &crate::ID);
+        let escrow_seeds: &[&[u8]] = &[b"sol_escrow", vault_key.as_ref(), &[bump]];
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: sol_escrow.to_account_info(),
+                    to: destination,
+                },
+                &[escrow_seeds],
+            ),
+            amount,
+        )?;
+    } else {
+        let escrow = escrow.ok_or(MoluscoError::MissingFeeAccounts)?;
+        let seeds = vault.signer_seeds();
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Transfer {
+                    from: escrow.to_account_info(),
+                    to: destination,
+                    authority: vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+        )?;
+    }
+    Ok(())
+}
+
+/// Mint shares for `assets` about to be added to TVL, priced before the deposit lands
+pub fn mint_shares<'info>(
+    vault: &mut Account<'info, Vault>,
+    share_mint: &Account<'info, Mint>,
+    recipient: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    asset_decimals: u8,
+    assets: u64,
+) -> Result<u64> {
+    let scale = vault.share_scale(asset_decimals, share_mint);
+    let total_shares = vault.total_shares(share_mint.supply);
+    let shares = if total_shares == 0 {
+        // Dead shares are never minted but count toward supply forever
+        let (shares, dead) = scale.initial_deposit_shares(assets)?;
+        vault.dead_shares = dead;
+        shares
+    } else {
+        scale.shares_for_deposit(assets, vault.share_assets()?, total_shares)?
+    };
+    require!(shares > 0, MoluscoError::ZeroShares);
+    audit_share_math(vault, ShareOperation::Deposit, assets, shares, total_shares, Rounding::Down)?;
+
+    let seeds = vault.signer_seeds();
+    token::mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo {
+                mint: share_mint.to_account_info(),
+                to: recipient.to_account_info(),
+                authority: vault.to_account_info(),
+            },
+            &[&seeds[..]],
+        ),
+        shares,
+    )?;
+    Ok(shares)
+}
+
+/// Burn the holder's shares worth `assets` at the current price (rounded up)
+pub fn burn_shares<'info>(
+    vault: &Account<'info, Vault>,
+    share_mint: &Account<'info, Mint>,
+    holder_account: &Account<'info, TokenAccount>,
+    holder: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+    asset_decimals: u8,
+    assets: u64,
+) -> Result<u64> {
+    let scale = vault.share_scale(asset_decimals, share_mint);
+    let total_shares = vault.total_shares(share_mint.supply);
+    let shares = scale.shares_for_withdraw(assets, vault.share_assets()?, total_shares)?;
+    require_gte!(holder_account.amount, shares, MoluscoError::InsufficientShares);
+    audit_share_math(vault, ShareOperation::Withdraw, assets, shares, total_shares, Rounding::Up)?;
+
+    token::burn(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Burn {
+                mint: share_mint.to_account_info(),
+                from: holder_account.to_account_info(),
+                authority: holder.to_account_info(),
+            },
+        ),
+        shares,
+    )?;
+    Ok(shares)
+}
+
+/// Emit the share-conversion intermediates when the vault is in audit mode
+pub fn audit_share_math(
+    vault: &Account<Vault>,
+    operation: ShareOperation,
+    input: u64,
+    output: u64,
+    total_shares: u64,
+    rounding: Rounding,
+) -> Result<()> {
+    if !vault.audit_mode {
+        return Ok(());
+    }
+    emit!(AuditShareMathEvent {
+        vault: vault.key(),
+        operation,
+        input,
+        output,
+        total_assets: vault.share_assets()?,
+        total_shares,
+        virtual_assets: shares::VIRTUAL_ASSETS as u64,
+        virtual_shares: shares::VIRTUAL_SHARES as u64,
+        rounding,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Emit the exit-fee intermediates when the vault is in audit mode
+pub fn audit_exit_fee(vault: &Account<Vault>, lots: &[(u64, i64)], now: i64) {
+    if !vault.audit_mode {
+        return;
+    }
+    for &(amount, deposited_at) in lots {
+        let held_secs = now.saturating_sub(deposited_at);
+        let rate_bps = vault.exit_fee_schedule.rate_bps(held_secs);
+        let fee_numerator = amount as u128 * rate_bps as u128;
+        let fee = (fee_numerator / fees::BPS_DENOMINATOR) as u64;
+        emit!(AuditFeeMathEvent {
+            vault: vault.key(),
+            kind: FeeKind::Exit,
+            base_amount: amount,
+            held_secs,
+            rate_bps,
+            fee_numerator,
+            fee_amount: fee,
+            net_amount: amount - fee,
+            timestamp: now,
+        });
+    }
+}
+
+/// Emit a notice addressed to one depositor
+pub fn notify_depositor(vault: Pubkey, depositor: Pubkey, kind: DepositorNotice, amount: u64, now: i64) {
+    emit!(DepositorNotificationEvent {
+        depositor,
+        vault,
+        kind,
+        amount,
+        timestamp: now,
+    });
+}
+
+/// Persist the vault's reentrancy lock before handing control to an external program,
+/// so a nested call back into this program finds it held
+pub fn lock_for_cpi(vault: &mut Account<Vault>) -> Result<()> {
+    vault.cpi_guard.acquire()?;
+    vault.exit(&crate::ID)
+}
+
+/// Order `(position, unwind_priority, amount)` legs and take just enough to cover `needed`.
+///
+/// Returns `(position, amount_to_unwind)` pairs, lowest priority first; ties keep
+/// the caller's order so the plan is deterministic.
+pub fn unwind_plan(legs: &[(Pubkey, u8, u64)], needed: u64) -> Vec<(Pubkey, u64)> {
+    let mut ordered = legs.to_vec();
+    ordered.sort_by_key(|(_, priority, _)| *priority);
+
+    let mut remaining = needed;
+    let mut plan = Vec::new();
+    for (position, _, amount) in ordered {
+        if remaining == 0 {
+            break;
+        }
+        let take = amount.min(remaining);
+        if take > 0 {
+            plan.push((position, take));
+            remaining -= take;
+        }
+    }
+    plan
+}
+
+/// Whether `info` holds an account of type `T` owned by this program
+pub fn is_account<T: Discriminator>(info: &AccountInfo) -> bool {
+    *info.owner == crate::ID
+        && info
+            .try_borrow_data()
+            .map_or(false, |data| data.starts_with(&T::DISCRIMINATOR))
+}
+
+/// Reject an emergency exit of `position` while one of its venue's positions ranks ahead
+///
+/// `others` must be every other open position of the vault. Ties go to `position`.
+pub fn require_unwind_turn(position: &Account<Position>, others: &[AccountInfo]) -> Result<()> {
+    require_unique_accounts(others)?;
+    let mut legs = vec![(position.key(), position.unwind_priority, position.amount)];
+    for info in others {
+        let other = Account::<Position>::try_from(info)?;
+        require_keys_eq!(other.vault, position.vault, MoluscoError::BatchAccountWrongVault);
+        require!(other.is_active, MoluscoError::UnwindPositionsIncomplete);
+        require_keys_neq!(other.key(), position.key(), MoluscoError::DuplicateAccount);
+        if other.protocol == position.protocol {
+            legs.push((other.key(), other.unwind_priority, other.amount));
+        }
+    }
+    let plan = unwind_plan(&legs, u64::MAX);
+    require!(
+        plan.first().map(|(key, _)| *key) == Some(position.key()),
+        MoluscoError::UnwindOutOfOrder
+    );
+    Ok(())
+}
+
+/// Move up to `needed` of principal from the vault's positions' idle custody back into
+/// its escrow, in `unwind_plan` order; returns the amount moved
+///
+/// `accounts` are `(position, position_escrow, strategy_capacity)` triples for every open
+/// position, so the order cannot be sidestepped by leaving one out. Each position keeps
+/// at least the vault's minimum position size.
+pub fn unwind_for_withdrawals<'info>(
+    vault: &Account<'info, Vault>,
+    escrow: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    accounts: &[AccountInfo<'info>],
+    needed: u64,
+) -> Result<u64> {
+    require!(!vault.simulation, MoluscoError::SimulationVault);
+    require!(accounts.len() % 3 == 0, MoluscoError::BatchLengthMismatch);
+    require_eq!(
+        accounts.len() / 3,
+        vault.active_positions as usize,
+        MoluscoError::UnwindPositionsIncomplete
+    );
+    let custody: Vec<AccountInfo> = accounts
+        .chunks(3)
+        .flat_map(|triple| triple[..2].iter().cloned())
+        .collect();
+    require_unique_accounts(&custody)?;
+
+    let keep = vault.min_position_amount.max(1);
+    let mut legs = Vec::with_capacity(accounts.len() / 3);
+    for triple in accounts.chunks(3) {
+        require!(
+            triple.iter().all(|info| info.is_writable),
+            MoluscoError::BatchAccountNotWritable
+        );
+        let position = Account::<Position>::try_from(&triple[0])?;
+        require_keys_eq!(position.vault, vault.key(), MoluscoError::BatchAccountWrongVault);
+        require!(position.is_active, MoluscoError::UnwindPositionsIncomplete);
+        let (position_escrow, _) =
+            Pubkey::find_program_address(&[b"position_escrow", position.key().as_ref()], &crate::ID);
+        require_keys_eq!(triple[1].key(), position_escrow, MoluscoError::BatchAccountMismatch);
+        let idle = Account::<TokenAccount>::try_from(&triple[1])?.amount;
+        let spare = position.amount.saturating_sub(keep).min(idle);
+        legs.push((position.key(), position.unwind_priority, spare));
+    }
+
+    let seeds = vault.signer_seeds();
+    let mut unwound = 0u64;
+    for (key, take) in unwind_plan(&legs, needed) {
+        let triple = accounts
+            .chunks(3)
+            .find(|triple| triple[0].key() == key)
+            .ok_or(MoluscoError::BatchAccountMismatch)?;
+        let mut position = Account::<Position>::try_from(&triple[0])?;
+        let (capacity_key, _) = Pubkey::find_program_address(
+            &[b"strategy_capacity", &position.strategy.seed()],
+            &crate::ID,
+        );
+        require_keys_eq!(triple[2].key(), capacity_key, MoluscoError::BatchAccountMismatch);
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Transfer {
+                    from: triple[1].clone(),
+                    to: escrow.to_account_info(),
+                    authority: vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            take,
+        )?;
+
+        // Principal moves from the position's custody to idle escrow; TVL is unchanged
+        let mut capacity = Account::<StrategyCapacity>::try_from(&triple[2])?;
+        position.release_capacity(&mut capacity, decimals::normalize(take, position.asset_decimals)?);
+        capacity.exit(&crate::ID)?;
+        position.amount -= take;
+        position.last_value = position.last_value.saturating_sub(take);
+        position.checkpoint_value = position.checkpoint_value.saturating_sub(take);
+        position.exit(&crate::ID)?;
+        unwound += take;
+    }
+    msg!("Unwound {} of {} from positions for queued withdrawals", unwound, needed);
+    Ok(unwound)
+}
+
+/// Fixed-point scale for the rewards-per-share accumulator
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+#[account]
+pub struct RewardsDistributor {
+    pub vault: Pubkey,
+    pub reward_mint: Pubkey,
+    pub reward_vault: Pubkey,
+    pub emission_rate: u64,
+    pub end_ts: i64,
+    pub last_update_ts: i64,
+    pub acc_reward_per_share: u128,
+    pub total_stake: u64,
+    pub total_funded: u64,
+    pub total_emitted: u64,
+    pub total_claimed: u64,
+    pub bump: u8,
+}
+
+impl RewardsDistributor {
+    pub const SIZE: usize = 32 +      // vault
+        32 +                            // reward_mint
+        32 +                            // reward_vault
+        8 +                             // emission_rate
+        8 +                             // end_ts
+        8 +                             // last_update_ts
+        16 +                            // acc_reward_per_share
+        8 +                             // total_stake
+        8 +                             // total_funded
+        8 +                             // total_emitted
+        8 +                             // total_claimed
+        1;                              // bump
+
+    /// Advance the accumulator to `now`, never emitting more than has been funded
+    pub fn accrue(&mut self, now: i64) -> Result<()> {
+        let until = now.min(self.end_ts);
+        if until > self.last_update_ts && self.total_stake > 0 {
+            let elapsed = (until - self.last_update_ts) as u128;
+            let remaining = self.total_funded.saturating_sub(self.total_emitted) as u128;
+            let emitted = elapsed
+                .checked_mul(self.emission_rate as u128)
+                .ok_or(MoluscoError::MathOverflow)?
+                .min(remaining);
+            let per_share = emitted
+                .checked_mul(REWARD_PRECISION)
+                .ok_or(MoluscoError::MathOverflow)?
+                / self.total_stake as u128;
+
+            self.acc_reward_per_share = self
+                .acc_reward_per_share
+                .checked_add(per_share)
+                .ok_or(MoluscoError::MathOverflow)?;
+            self.total_emitted += emitted as u64;
+        }
+        // With no stake, emissions for the elapsed window are simply skipped
+        if now > self.last_update_ts {
+            self.last_update_ts = now;
+        }
+        Ok(())
+    }
+}
+
+/// Rewards `stake` has accrued at `acc_reward_per_share`, ignoring what it was owed before
+pub fn reward_accrued(stake: u64, acc_reward_per_share: u128) -> Result<u128> {
+    Ok((stake as u128)
+        .checked_mul(acc_reward_per_share)
+        .ok_or(MoluscoError::MathOverflow)?
+        / REWARD_PRECISION)
+}
+
+/// Upper bound on the share of fees that can be diverted to buybacks
+pub const MAX_BUYBACK_BPS: u16 = 5_000;
+
+/// Program-wide switches, held by the program admin
+#[account]
+pub struct ProgramConfig {
+    pub admin: Pubkey,
+    /// Emergency stop for every state-mutating instruction
+    pub paused: bool,
+    pub paused_at: i64,
+    pub bump: u8,
+}
+
+impl ProgramConfig {
+    pub const SIZE: usize = 32 +      // admin
+        1 +                             // paused
+        8 +                             // paused_at
+        1;                              // bump
+}
+
+/// Upper bound on the number of approved protocols
+pub const MAX_WHITELISTED_PROTOCOLS: usize = 16;
+
+/// Protocols positions may be opened or rolled over in, held by the program admin
+#[account]
+pub struct ProtocolRegistry {
+    pub protocols: Vec<ProtocolKind>,
+    pub bump: u8,
+}
+
+impl ProtocolRegistry {
+    pub const SIZE: usize = 4 + MAX_WHITELISTED_PROTOCOLS * KIND_SIZE + // protocols
+        1;                              // bump
+
+    pub fn is_listed(&self, protocol: ProtocolKind) -> bool {
+        self.protocols.contains(&protocol)
+    }
+
+    pub fn add(&mut self, protocol: ProtocolKind) -> Result<()> {
+        require!(!self.is_listed(protocol), MoluscoError::ProtocolAlreadyWhitelisted);
+        require_gt!(
+            MAX_WHITELISTED_PROTOCOLS,
+            self.protocols.len(),
+            MoluscoError::ProtocolRegistryFull
+        );
+        self.protocols.push(protocol);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, protocol: ProtocolKind) -> Result<()> {
+        let index = self
+            .protocols
+            .iter()
+            .position(|p| *p == protocol)
+            .ok_or(MoluscoError::ProtocolNotWhitelisted)?;
+        self.protocols.swap_remove(index);
+        Ok(())
+    }
+}
+
+pub const MAX_METADATA_URI_LEN: usize = 200;
+pub const MAX_SOCIAL_LINK_LEN: usize = 64;
+
+/// Off-chain metadata pointer and branding for a vault, set by its owner
+#[account]
+pub struct VaultMeta {
+    pub vault: Pubkey,
+    /// JSON document describing the agent and its strategy
+    pub metadata_uri: String,
+    /// SHA-256 of the document at `metadata_uri`
+    pub description_hash: [u8; 32],
+    pub website: String,
+    pub twitter: String,
+    pub discord: String,
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+impl VaultMeta {
+    pub const SIZE: usize = 32 +      // vault
+        4 + MAX_METADATA_URI_LEN +      // metadata_uri
+        32 +                            // description_hash
+        4 + MAX_SOCIAL_LINK_LEN +       // website
+        4 + MAX_SOCIAL_LINK_LEN +       // twitter
+        4 + MAX_SOCIAL_LINK_LEN +       // discord
+        8 +                             // updated_at
+        1;                              // bump
+}
+
+pub const MAX_PROFILE_VAULTS: usize = 16;
+
+/// An operator's directory of the vaults it runs, for consolidated reporting
+#[account]
+pub struct AgentProfile {
+    pub operator: Pubkey,
+    pub vaults: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl AgentProfile {
+    pub const SIZE: usize = 32 +      // operator
+        4 + 32 * MAX_PROFILE_VAULTS +   // vaults
+        1;                              // bump
+
+    pub fn add(&mut self, vault: Pubkey) -> Result<()> {
+        require!(!self.vaults.contains(&vault), MoluscoError::VaultAlreadyInProfile);
+        require_gt!(MAX_PROFILE_VAULTS, self.vaults.len(), MoluscoError::ProfileFull);
+        self.vaults.push(vault);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, vault: Pubkey) -> Result<()> {
+        let index = self
+            .vaults
+            .iter()
+            .position(|v| *v == vault)
+            .ok_or(MoluscoError::ProfileVaultMismatch)?;
+        self.vaults.swap_remove(index);
+        Ok(())
+    }
+}
+
+/// Return data of `get_operator_summary`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OperatorSummary {
+    pub vault_count: u16,
+    pub total_value_locked: u128,
+    /// Each vault's trailing APY weighted by its TVL
+    pub blended_apy_bps: i64,
+    pub active_positions: u64,
+    pub total_positions_created: u64,
+}
+
+/// A registered SPL asset positions can reference
+#[account]
+pub struct AssetEntry {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    /// Stored on positions and used in their seeds
+    pub kind: AssetKind,
+    /// Display only
+    pub symbol: String,
+    pub bump: u8,
+}
+
+impl AssetEntry {
+    pub const SIZE: usize = 32 +      // mint
+        1 +                             // decimals
+        KIND_SIZE +                     // kind
+        4 + 10 +                        // symbol (max 10 chars)
+        1;                              // bump
+}
+
+/// Most price feeds one asset is valued against
+pub const MAX_ORACLE_FEEDS: usize = 3;
+
+/// Feeds an asset's positions are priced from for oracle marks, and the limits prices must meet
+///
+/// Every feed's price goes through the same age and confidence checks.
+#[account]
+pub struct OracleConfig {
+    pub asset_entry: Pubkey,
+    /// At most `MAX_ORACLE_FEEDS`, all distinct
+    pub feeds: Vec<OracleFeed>,
+    /// Feeds that must pass their checks for a mark to land
+    pub min_sources: u8,
+    /// Furthest any accepted feed's value may sit from the median, in bps of it
+    pub max_divergence_bps: u16,
+    pub max_age_secs: i64,
+    /// Widest confidence interval accepted, in bps of the price
+    pub max_confidence_bps: u16,
+    pub bump: u8,
+}
+
+impl OracleConfig {
+    pub const SIZE: usize = 32 +      // asset_entry
+        4 + MAX_ORACLE_FEEDS * OracleFeed::SIZE + // feeds
+        1 +                             // min_sources
+        2 +                             // max_divergence_bps
+        8 +                             // max_age_secs
+        2 +                             // max_confidence_bps
+        1;                              // bump
+
+    /// Median normalized price across the feeds in `accounts`, with how many feeds it used
+    ///
+    /// Every feed's account must read; a feed whose price is stale or too uncertain
+    /// is left out, and at least `min_sources` must remain. Failing on unreadable
+    /// accounts keeps a caller from choosing which feeds price the asset.
+    pub fn price(&self, accounts: &[AccountInfo], now: i64) -> Result<(u128, u8)> {
+        require_eq!(accounts.len(), self.feeds.len(), MoluscoError::OracleAccountsMismatch);
+
+        let mut prices = Vec::with_capacity(self.feeds.len());
+        for (feed, account) in self.feeds.iter().zip(accounts) {
+            let price = feed.read(account)?;
+            let price = price
+                .validate(now, self.max_age_secs, self.max_confidence_bps)
+                .and_then(|_| price.normalized());
+            match price {
+                Ok(price) => prices.push(price),
+                Err(err) => msg!("{:?} feed {} left out: {}", feed.source, account.key(), err),
+            }
+        }
+        require_gte!(prices.len(), self.min_sources as usize, MoluscoError::OracleQuorumNotMet);
+
+        let median = oracle::median_within(&mut prices, self.max_divergence_bps)?;
+        Ok((median, prices.len() as u8))
+    }
+
+    /// `amount` of this config's asset, with `decimals`, in US dollars with the same
+    /// decimals at the median price; with how many feeds were used
+    pub fn value_in_usd(
+        &self,
+        amount: u64,
+        decimals: u8,
+        accounts: &[AccountInfo],
+        now: i64,
+    ) -> Result<(u64, u8)> {
+        let (price, sources) = self.price(accounts, now)?;
+        Ok((oracle::convert(amount, price, decimals, oracle::USD_PRICE, decimals)?, sources))
+    }
+
+    /// Price accounts `convert` takes: this config's, then `quote`'s unless it prices the same asset
+    pub fn price_accounts(&self, quote: &OracleConfig) -> usize {
+        if quote.asset_entry == self.asset_entry {
+            self.feeds.len()
+        } else {
+            self.feeds.len() + quote.feeds.len()
+        }
+    }
+
+    /// `amount` of this config's asset, with `decimals`, in base units of `quote`'s asset,
+    /// with `quote_decimals`, at the two median prices; with how many feeds were used
+    ///
+    /// `accounts` are the `price_accounts` this config's feeds and then `quote`'s read.
+    pub fn convert(
+        &self,
+        amount: u64,
+        decimals: u8,
+        quote: &OracleConfig,
+        quote_decimals: u8,
+        accounts: &[AccountInfo],
+        now: i64,
+    ) -> Result<(u64, u8)> {
+        require_eq!(accounts.len(), self.price_accounts(quote), MoluscoError::OracleAccountsMismatch);
+        let (accounts, quote_accounts) = accounts.split_at(self.feeds.len());
+        let (price, mut sources) = self.price(accounts, now)?;
+        let quote_price = if quote.asset_entry == self.asset_entry {
+            price
+        } else {
+            let (quote_price, quote_sources) = quote.price(quote_accounts, now)?;
+            sources += quote_sources;
+            quote_price
+        };
+        Ok((oracle::convert(amount, price, decimals, quote_price, quote_decimals)?, sources))
+    }
+}
+
+/// Principal deployed into one strategy across every funded vault
+///
+/// Many strategies degrade past a size threshold, so opens stop once
+/// `deployed` would exceed `max_tvl`. Compounded yield does not use capacity.
+#[account]
+pub struct StrategyCapacity {
+    pub strategy: StrategyKind,
+    /// Zero means uncapped
+    pub max_tvl: u128,
+    /// Principal at `decimals::INTERNAL_DECIMALS`, so positions in different mints add up
+    pub deployed: u128,
+    pub bump: u8,
+}
+
+impl StrategyCapacity {
+    pub const SIZE: usize = KIND_SIZE + // strategy
+        16 +                            // max_tvl
+        16 +                            // deployed
+        1;                              // bump
+
+    /// Take `amount` of normalized capacity for a new or increased position
+    pub fn reserve(&mut self, amount: u128) -> Result<()> {
+        let deployed = self
+            .deployed
+            .checked_add(amount)
+            .ok_or(MoluscoError::MathOverflow)?;
+        if self.max_tvl > 0 {
+            require_gte!(self.max_tvl, deployed, MoluscoError::StrategyCapacityExceeded);
+        }
+        self.deployed = deployed;
+        Ok(())
+    }
+
+    /// Return a closing position's principal; yield it compounded may exceed what it reserved
+    pub fn release(&mut self, amount: u128) {
+        self.deployed = self.deployed.saturating_sub(amount);
+    }
+}
+
+#[account]
+pub struct Config {
+    /// The program admin, copied from `ProgramConfig` when the config is created
+    pub authority: Pubkey,
+    pub buyback_bps: u16,
+    pub buyback_mint: Pubkey,
+    pub buyback_treasury: Pubkey,
+    pub swap_program: Pubkey,
+    pub treasury: Pubkey,
+    pub bump: u8,
+}
+
+impl Config {
+    pub const SIZE: usize = 32 +      // authority
+        2 +                             // buyback_bps
+        32 +                            // buyback_mint
+        32 +                            // buyback_treasury
+        32 +                            // swap_program
+        32 +                            // treasury
+        1;                              // bump
+
+    /// Portion of a crystallized fee routed to the buyback escrow
+    pub fn buyback_amount(&self, fee: u64) -> u64 {
+        ((fee as u128) * (self.buyback_bps as u128) / 10_000) as u64
+    }
+}
+
+/// Upper bounds on vault fees
+pub const MAX_PERFORMANCE_FEE_BPS: u16 = 3_000;
+pub const MAX_MANAGEMENT_FEE_BPS: u16 = 500;
+
+#[account]
+pub struct VaultTemplate {
+    pub creator: Pubkey,
+    pub template_id: u64,
+    pub performance_fee_bps: u16,
+    pub management_fee_bps: u16,
+    pub deposit_cap: u64,
+    pub max_positions: u16,
+    pub min_position_amount: u64,
+    pub creation_fee_lamports: u64,
+    pub vaults_created: u32,
+    pub bump: u8,
+}
+
+impl VaultTemplate {
+    pub const SIZE: usize = 32 +      // creator
+        8 +                             // template_id
+        2 +                             // performance_fee_bps
+        2 +                             // management_fee_bps
+        8 +                             // deposit_cap
+        2 +                             // max_positions
+        8 +                             // min_position_amount
+        8 +                             // creation_fee_lamports
+        4 +                             // vaults_created
+        1;                              // bump
+}
+
+#[account]
+pub struct Airdrop {
+    pub campaign_id: u64,
+    pub mint: Pubkey,
+    pub token_vault: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub total_funded: u64,
+    pub total_claimed: u64,
+    pub claim_count: u32,
+    pub bump: u8,
+}
+
+impl Airdrop {
+    pub const SIZE: usize = 8 +       // campaign_id
+        32 +                            // mint
+        32 +                            // token_vault
+        32 +                            // merkle_root
+        8 +                             // total_funded
+        8 +                             // total_claimed
+        4 +                             // claim_count
+        1;                              // bump
+}
+
+#[account]
+pub struct AirdropClaim {
+    pub airdrop: Pubkey,
+    pub claimant: Pubkey,
+    pub amount: u64,
+    pub claimed_at: i64,
+    pub bump: u8,
+}
+
+impl AirdropClaim {
+    pub const SIZE: usize = 32 +      // airdrop
+        32 +                            // claimant
+        8 +                             // amount
+        8 +                             // claimed_at
+        1;                              // bump
+}
+
+/// Verify a Merkle proof using sorted-pair keccak hashing
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let mut computed = leaf;
+    for node in proof {
+        computed = if computed <= *node {
+            keccak::hashv(&[&computed, node]).0
+        } else {
+            keccak::hashv(&[node, &computed]).0
+        };
+    }
+    computed == root
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct PositionContribution {
+    pub position: Pubkey,
+    pub contribution_bps: i32,
+}
+
+impl PositionContribution {
+    pub const SIZE: usize = 32 + 4;
+}
+
+/// Shortest gap between two checkpoints of a vault; the smoothing threshold applies per checkpoint
+pub const MIN_CHECKPOINT_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+#[account]
+pub struct Checkpoint {
+    pub vault: Pubkey,
+    pub index: u64,
+    pub timestamp: i64,
+    pub raw_nav: u64,
+    pub smoothed_nav: u64,
+    pub smoothing_held: u64,
+    pub smoothing_released: u64,
+    pub smoothing_reserve: u64,
+    pub period_return_bps: i64,
+    pub volatility: u64,
+    pub benchmark_return_bps: i64,
+    pub excess_return_bps: i64,
+    pub contributions: Vec<PositionContribution>,
+    pub operating_costs: u64,
+    pub net_return_bps: i64,
+    /// Value of `shares::PRICE_SHARES` shares at `smoothed_nav`
+    pub share_price: u64,
+    /// Mean over standard deviation of the vault's returns so far, scaled by 10_000
+    pub sharpe_proxy: i64,
+    pub bump: u8,
+}
+
+impl Checkpoint {
+    pub const SIZE: usize = 32 +      // vault
+        8 +                             // index
+        8 +                             // timestamp
+        8 +                             // raw_nav
+        8 +                             // smoothed_nav
+        8 +                             // smoothing_held
+        8 +                             // smoothing_released
+        8 +                             // smoothing_reserve
+        8 +                             // period_return_bps
+        8 +                             // volatility
+        8 +                             // benchmark_return_bps
+        8 +                             // excess_return_bps
+        4 + MAX_BATCH_SIZE * PositionContribution::SIZE + // contributions
+        8 +                             // operating_costs
+        8 +                             // net_return_bps
+        8 +                             // share_price
+        8 +                             // sharpe_proxy
+        1;                              // bump
+}
+
+#[account]
+pub struct AdapterConfig {
+    pub protocol: ProtocolKind,
+    pub program_id: Pubkey,
+    pub max_utilization_bps: u16,
+    pub max_oracle_age_secs: i64,
+    pub emergency_exit: bool,
+    pub emergency_max_slippage_bps: u16,
+    pub bump: u8,
+}
+
+impl AdapterConfig {
+    pub const SIZE: usize = KIND_SIZE + // protocol
+        32 +                            // program_id
+        2 +                             // max_utilization_bps
+        8 +                             // max_oracle_age_secs
+        1 +                             // emergency_exit
+        2 +                             // emergency_max_slippage_bps
+        1;                              // bump
+
+    /// Reject deployment into a frozen, saturated, or stale venue
+    pub fn check_health(&self, health: &adapters::AdapterHealth) -> Result<()> {
+        require!(!health.is_frozen, MoluscoError::AdapterReserveFrozen);
+        require_gte!(
+            self.max_utilization_bps,
+            health.utilization_bps,
+            MoluscoError::AdapterUtilizationTooHigh
+        );
+        require_gte!(
+            self.max_oracle_age_secs,
+            health.oracle_age_secs,
+            MoluscoError::AdapterOracleStale
+        );
+        Ok(())
+    }
+}
+
+#[account]
+pub struct Impairment {
+    pub vault: Pubkey,
+    pub position: Pubkey,
+    pub protocol: ProtocolKind,
+    pub index: u16,
+    pub amount_before: u64,
+    pub write_down: u64,
+    pub incident_hash: [u8; 32],
+    pub recorded_by: Pubkey,
+    pub recorded_at: i64,
+    pub bump: u8,
+}
+
+impl Impairment {
+    pub const SIZE: usize = 32 +      // vault
+        32 +                            // position
+        KIND_SIZE +                     // protocol
+        2 +                             // index
+        8 +                             // amount_before
+        8 +                             // write_down
+        32 +                            // incident_hash
+        32 +                            // recorded_by
+        8 +                             // recorded_at
+        1;                              // bump
+}
+
+/// Maximum number of legs in an allocation hint
+pub const MAX_HINT_WEIGHTS: usize = 8;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct AllocationWeight {
+    pub position: Pubkey,
+    pub weight_bps: u16,
+}
+
+impl AllocationWeight {
+    pub const SIZE: usize = 32 + 2;
+}
+
+#[account]
+pub struct AllocationHint {
+    pub vault: Pubkey,
+    pub strategist: Pubkey,
+    pub weights: Vec<AllocationWeight>,
+    pub posted_at: i64,
+    pub expires_at: i64,
+    pub consumed: bool,
+    pub nonce: u64,
+    pub bump: u8,
+}
+
+impl AllocationHint {
+    pub const SIZE: usize = 32 +      // vault
+        32 +                            // strategist
+        4 + MAX_HINT_WEIGHTS * AllocationWeight::SIZE + // weights
+        8 +                             // posted_at
+        8 +                             // expires_at
+        1 +                             // consumed
+        8 +                             // nonce
+        1;                              // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderStatus {
+    Open,
+    Cancelled,
+    Settled,
+}
+
+#[account]
+pub struct RestingOrder {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub adapter: Pubkey,
+    pub source_mint: Pubkey,
+    pub destination_mint: Pubkey,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub amount_out: u64,
+    pub placed_at: i64,
+    pub status: OrderStatus,
+    pub bump: u8,
+}
+
+impl RestingOrder {
+    pub const SIZE: usize = 32 +      // vault
+        8 +                             // order_id
+        32 +                            // adapter
+        32 +                            // source_mint
+        32 +                            // destination_mint
+        8 +                             // amount_in
+        8 +                             // min_amount_out
+        8 +                             // amount_out
+        8 +                             // placed_at
+        1 +                             // status
+        1;                              // bump
+}
+
+/// Capacity of the keeper job board
+pub const MAX_JOBS: usize = 32;
+
+/// Smallest tip a job may be scheduled with, so queue slots are not free to hold
+pub const MIN_JOB_TIP_LAMPORTS: u64 = 10_000;
+
+/// Slots past its due slot after which anyone may cancel an uncompleted job
+pub const JOB_EXPIRY_SLOTS: u64 = 216_000;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskType {
+    Compound,
+    Rollover,
+    MarkVault,
+    Checkpoint,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct Job {
+    pub vault: Pubkey,
+    pub task: TaskType,
+    pub target: Pubkey,
+    pub due_slot: u64,
+    pub tip_lamports: u64,
+    pub scheduled_at: i64,
+}
+
+impl Job {
+    pub const SIZE: usize = 32 + 1 + 32 + 8 + 8 + 8;
+}
+
+#[account]
+pub struct JobQueue {
+    pub jobs: Vec<Job>,
+    pub total_scheduled: u64,
+    pub total_completed: u64,
+    pub bump: u8,
+}
+
+impl JobQueue {
+    pub const SIZE: usize = 4 + MAX_JOBS * Job::SIZE + // jobs
+        8 +                             // total_scheduled
+        8 +                             // total_completed
+        1;                              // bump
+}
+
+#[account]
+pub struct VaultComparison {
+    pub champion: Pubkey,
+    pub challenger: Pubkey,
+    pub started_at: i64,
+    pub champion_start_nav: u64,
+    pub challenger_start_nav: u64,
+    pub champion_nav: u64,
+    pub challenger_nav: u64,
+    pub champion_return_bps: i64,
+    pub challenger_return_bps: i64,
+    pub last_update: i64,
+    pub bump: u8,
+}
+
+impl VaultComparison {
+    pub const SIZE: usize = 32 +      // champion
+        32 +                            // challenger
+        8 +                             // started_at
+        8 +                             // champion_start_nav
+        8 +                             // challenger_start_nav
+        8 +                             // champion_nav
+        8 +                             // challenger_nav
+        8 +                             // champion_return_bps
+        8 +                             // challenger_return_bps
+        8 +                             // last_update
+        1;                              // bump
+}
+
+pub const SECONDS_PER_YEAR: i64 = 365 * 24 * 60 * 60;
+
+/// Simple annualized return from `start` to `end` over `elapsed_secs`, in basis points
+pub fn annualized_return_bps(start: u64, end: u64, elapsed_secs: i64) -> i64 {
+    if elapsed_secs <= 0 {
+        return 0;
+    }
+    let period_bps = return_bps(start, end) as i128;
+    (period_bps * SECONDS_PER_YEAR as i128 / elapsed_secs as i128) as i64
+}
+
+/// Signed return from `start` to `end` in basis points (0 when there is no base)
+pub fn return_bps(start: u64, end: u64) -> i64 {
+    if start == 0 {
+        return 0;
+    }
+    ((end as i128 - start as i128) * 10_000 / start as i128) as i64
+}
+
+#[account]
+pub struct StatementCursor {
+    pub vault: Pubkey,
+    pub depositor: Pubkey,
+    pub statement_count: u32,
+    pub period_start: i64,
+    pub closing_balance: u64,
+    pub inflows_snapshot: u64,
+    pub outflows_snapshot: u64,
+    pub fees_snapshot: u64,
+    pub bump: u8,
+}
+
+impl StatementCursor {
+    pub const SIZE: usize = 32 +      // vault
+        32 +                            // depositor
+        4 +                             // statement_count
+        8 +                             // period_start
+        8 +                             // closing_balance
+        8 +                             // inflows_snapshot
+        8 +                             // outflows_snapshot
+        8 +                             // fees_snapshot
+        1;                              // bump
+}
+
+#[account]
+pub struct DepositorStatement {
+    pub vault: Pubkey,
+    pub depositor: Pubkey,
+    pub index: u32,
+    pub period_start: i64,
+    pub period_end: i64,
+    pub opening_balance: u64,
+    pub inflows: u64,
+    pub outflows: u64,
+    pub yield_credited: u64,
+    pub losses: u64,
+    pub fees_charged: u64,
+    pub closing_balance: u64,
+    pub bump: u8,
+}
+
+impl DepositorStatement {
+    pub const SIZE: usize = 32 +      // vault
+        32 +                            // depositor
+        4 +                             // index
+        8 +                             // period_start
+        8 +                             // period_end
+        8 +                             // opening_balance
+        8 +                             // inflows
+        8 +                             // outflows
+        8 +                             // yield_credited
+        8 +                             // losses
+        8 +                             // fees_charged
+        8 +                             // closing_balance
+        1;                              // bump
+}
+
+#[account]
+pub struct RateFeed {
+    pub authority: Pubkey,
+    pub label: String,
+    pub rate_bps: u32, // Annualized
+    pub updated_at: i64,
+    pub bump: u8,
+}
+
+impl RateFeed {
+    pub const SIZE: usize = 32 +      // authority
+        4 + 16 +                        // label (max 16 chars)
+        4 +                             // rate_bps
+        8 +                             // updated_at
+        1;                              // bump
+}
+
+/// Maximum number of feeds in a benchmark basket
+pub const MAX_BENCHMARK_COMPONENTS: usize = 4;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct BenchmarkComponent {
+    pub feed: Pubkey,
+    pub weight_bps: u16,
+}
+
+impl BenchmarkComponent {
+    pub const SIZE: usize = 32 + 2;
+}
+
+#[account]
+pub struct Benchmark {
+    pub creator: Pubkey,
+    pub benchmark_id: u64,
+    pub components: Vec<BenchmarkComponent>,
+    pub bump: u8,
+}
+
+impl Benchmark {
+    pub const SIZE: usize = 32 +      // creator
+        8 +                             // benchmark_id
+        4 + MAX_BENCHMARK_COMPONENTS * BenchmarkComponent::SIZE + // components
+        1;                              // bump
+
+    /// Weighted annualized rate of the basket; `feeds` must follow component order
+    pub fn blended_rate_bps<'info>(&self, feeds: &[AccountInfo<'info>]) -> Result<u64> {
+        require_eq!(feeds.len(), self.components.len(), MoluscoError::BenchmarkMismatch);
+
+        let mut blended: u128 = 0;
+        for (component, info) in self.components.iter().zip(feeds) {
+            require_keys_eq!(info.key(), component.feed, MoluscoError::BenchmarkMismatch);
+            let feed = Account::<RateFeed>::try_from(info)?;
+            blended += feed.rate_bps as u128 * component.weight_bps as u128;
+        }
+        Ok((blended / 10_000) as u64)
+    }
+}
+
+/// Capacity of the per-vault fee audit ring buffer
+pub const FEE_LEDGER_CAPACITY: usize = 32;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeKind {
+    Management,
+    Performance,
+    Entry,
+    Exit,
+    ProtocolSkim,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowKind {
+    Deposit,
+    Withdraw,
+    TransferIn,
+    TransferOut,
+}
+
+/// What a `DepositorNotificationEvent` tells the depositor; `amount` is in vault-mint units
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepositorNotice {
+    /// A queued withdrawal was paid, fully or in part; `amount` is the payout
+    WithdrawalFilled,
+    /// The exit fee on the latest deposit has decayed to zero; `amount` is the shares' value
+    LockupExpired,
+    /// An exit fee was taken from a withdrawal; `amount` is the fee
+    FeeCharged,
+    /// A write-down reduced the depositor's shares' value; `amount` is their part of it
+    LossSocialized,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShareOperation {
+    Deposit,
+    Mint,
+    Withdraw,
+    Redeem,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct FeeEntry {
+    pub kind: FeeKind,
+    pub rate_bps: u16,
+    pub base_amount: u64,
+    pub fee_amount: u64,
+    pub timestamp: i64,
+}
+
+impl FeeEntry {
+    pub const SIZE: usize = 1 + 2 + 8 + 8 + 8;
+}
+
+#[account]
+pub struct FeeLedger {
+    pub vault: Pubkey,
+    pub entries: Vec<FeeEntry>,
+    pub head: u16,
+    pub total_entries: u64,
+    pub bump: u8,
+}
+
+impl FeeLedger {
+    pub const SIZE: usize = 32 +      // vault
+        4 + FEE_LEDGER_CAPACITY * FeeEntry::SIZE + // entries
+        2 +                             // head
+        8 +                             // total_entries
+        1;                              // bump
+
+    /// Append a fee event, overwriting the oldest entry once full, and emit it
+    pub fn record(
+        &mut self,
+        vault: &mut Vault,
+        kind: FeeKind,
+        rate_bps: u16,
+        base_amount: u64,
+        fee_amount: u64,
+        timestamp: i64,
+    ) {
+        let entry = FeeEntry {
+            kind,
+            rate_bps,
+            base_amount,
+            fee_amount,
+            timestamp,
+        };
+        if self.entries.len() < FEE_LEDGER_CAPACITY {
+            self.entries.push(entry);
+        } else {
+            self.entries[self.head as usize] = entry;
+        }
+        self.head = ((self.head as usize + 1) % FEE_LEDGER_CAPACITY) as u16;
+        self.total_entries += 1;
+        vault.cumulative_fees += fee_amount as u128;
+
+        emit!(FeeChargedEvent {
+            vault: self.vault,
+            kind,
+            rate_bps,
+            base_amount,
+            fee_amount,
+            sequence: self.total_entries,
+            timestamp,
+        });
+    }
+}
+
+/// Maximum number of active (and, separately, pending) allowlisted destinations
+pub const MAX_ALLOWLIST_DESTINATIONS: usize = 8;
+/// Delay before a newly proposed destination can receive funds
+pub const ALLOWLIST_TIMELOCK_SECS: i64 = 48 * 60 * 60;
+
+/// Delay before a looser velocity limit or a new guardian takes effect
+pub const VELOCITY_TIMELOCK_SECS: i64 = 48 * 60 * 60;
+
+/// A velocity limit change waiting out its timelock; `activates_at == 0` when none is queued
+#[account]
+pub struct PendingVelocityLimit {
+    pub vault: Pubkey,
+    pub limit_bps: u16,
+    pub guardian: Pubkey,
+    pub activates_at: i64,
+    pub bump: u8,
+}
+
+impl PendingVelocityLimit {
+    pub const SIZE: usize = 32 +      // vault
+        2 +                             // limit_bps
+        32 +                            // guardian
+        8 +                             // activates_at
+        1;                              // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct PendingDestination {
+    pub destination: Pubkey,
+    pub activates_at: i64,
+}
+
+impl PendingDestination {
+    pub const SIZE: usize = 32 + 8;
+}
+
+#[account]
+pub struct DestinationAllowlist {
+    pub vault: Pubkey,
+    pub destinations: Vec<Pubkey>,
+    pub pending: Vec<PendingDestination>,
+    pub bump: u8,
+}
+
+impl DestinationAllowlist {
+    pub const SIZE: usize = 32 +      // vault
+        4 + MAX_ALLOWLIST_DESTINATIONS * 32 + // destinations
+        4 + MAX_ALLOWLIST_DESTINATIONS * PendingDestination::SIZE + // pending
+        1;                              // bump
+}
+
+#[account]
+pub struct StrategyProgram {
+    pub program_id: Pubkey,
+    pub name: String,
+    pub max_allocation_bps: u16,
+    pub enabled: bool,
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+impl StrategyProgram {
+    pub const SIZE: usize = 32 +      // program_id
+        4 + 16 +                        // name
+        2 +                             // max_allocation_bps
+        1 +                             // enabled
+        8 +                             // registered_at
+        1;                              // bump
+}
+
+/// Owner-set slippage limit for swaps between `mint_a` and `mint_b` (ordered `mint_a < mint_b`)
+#[account]
+pub struct PairSlippageLimit {
+    /// The vault it binds, or the config for buybacks
+    pub vault: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub max_slippage_bps: u16,
+    pub bump: u8,
+}
+
+impl PairSlippageLimit {
+    pub const SIZE: usize = 32 +      // vault
+        32 +                            // mint_a
+        32 +                            // mint_b
+        2 +                             // max_slippage_bps
+        1;                              // bump
+
+    /// Whether this limit covers a swap between the two mints, in either direction
+    pub fn matches(&self, source: &Pubkey, destination: &Pubkey) -> bool {
+        (self.mint_a == *source && self.mint_b == *destination)
+            || (self.mint_a == *destination && self.mint_b == *source)
+    }
+
+    /// Lowest acceptable proceeds for a swap whose oracle value is `fair_amount_out`
+    pub fn min_amount_out(&self, fair_amount_out: u64) -> u64 {
+        ((fair_amount_out as u128) * (10_000 - self.max_slippage_bps as u128) / 10_000) as u64
+    }
+}
+
+/// Deposit lots a depositor record ages separately for exit fees
+pub const MAX_DEPOSIT_LOTS: usize = 8;
+
+/// Shares a depositor received at one time
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DepositLot {
+    pub shares: u64,
+    pub deposited_at: i64,
+}
+
+impl DepositLot {
+    pub const SIZE: usize = 8 + 8;
+}
+
+#[account]
+pub struct DepositorRecord {
+    pub vault: Pubkey,
+    pub depositor: Pubkey,
+    pub principal: u64,
+    pub shares: u64,
+    pub first_deposit_at: i64,
+    pub last_deposit_at: i64,
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub fees_paid: u64,
+    pub reward_stake: u64,
+    pub reward_debt: u128,
+    pub rewards_owed: u64,
+    pub lots: [DepositLot; MAX_DEPOSIT_LOTS],
+    pub bump: u8,
+}
+
+impl DepositorRecord {
+    pub const SIZE: usize = 32 +      // vault
+        32 +                            // depositor
+        8 +                             // principal
+        8 +                             // shares
+        8 +                             // first_deposit_at
+        8 +                             // last_deposit_at
+        8 +                             // deposited
+        8 +                             // withdrawn
+        8 +                             // fees_paid
+        8 +                             // reward_stake
+        16 +                            // reward_debt
+        8 +                             // rewards_owed
+        MAX_DEPOSIT_LOTS * DepositLot::SIZE + // lots
+        1;                              // bump
+
+    /// Record a deposit of `assets` that minted `shares`
+    pub fn credit(
+        &mut self,
+        vault: Pubkey,
+        depositor: Pubkey,
+        assets: u64,
+        shares: u64,
+        now: i64,
+        bump: u8,
+    ) -> Result<()> {
+        if self.first_deposit_at == 0 {
+            self.vault = vault;
+            self.depositor = depositor;
+            self.first_deposit_at = now;
+            self.bump = bump;
+        }
+        self.principal = self.principal.checked_add(assets).ok_or(MoluscoError::MathOverflow)?;
+        self.shares = self.shares.checked_add(shares).ok_or(MoluscoError::MathOverflow)?;
+        self.deposited = self.deposited.checked_add(assets).ok_or(MoluscoError::MathOverflow)?;
+        self.last_deposit_at = now;
+        self.add_lot(shares, now);
+        Ok(())
+    }
+
+    /// Age `shares` received `now` as a lot of their own, or fold them into the
+    /// newest lot, restarting its clock, once every slot is taken
+    fn add_lot(&mut self, shares: u64, now: i64) {
+        if shares == 0 {
+            return;
+        }
+        let index = self
+            .lots
+            .iter()
+            .position(|lot| lot.shares == 0)
+            .unwrap_or(MAX_DEPOSIT_LOTS - 1);
+        let lot = &mut self.lots[index];
+        lot.shares = lot.shares.saturating_add(shares);
+        lot.deposited_at = now;
+    }
+
+    /// Drop `shares` from the oldest lots first, keeping the rest in order
+    fn take_lots(&mut self, mut shares: u64) {
+        let mut kept = [DepositLot::default(); MAX_DEPOSIT_LOTS];
+        let mut count = 0;
+        for lot in self.lots {
+            let taken = lot.shares.min(shares);
+            shares -= taken;
+            if lot.shares > taken {
+                kept[count] = DepositLot {
+                    shares: lot.shares - taken,
+                    deposited_at: lot.deposited_at,
+                };
+                count += 1;
+            }
+        }
+        self.lots = kept;
+    }
+
+    /// `(amount, deposited_at)` lots for redeeming `shares`, oldest first, for `assets`
+    ///
+    /// `assets` is split over the lots in proportion to the shares each gives up.
+    /// Shares no lot covers are aged from `last_deposit_at`.
+    pub fn exit_lots(&self, shares: u64, assets: u64) -> Result<Vec<(u64, i64)>> {
+        let mut lots = Vec::new();
+        let (mut left, mut priced) = (shares, 0u64);
+        for lot in self.lots.iter().filter(|lot| lot.shares > 0) {
+            if left == 0 {
+                break;
+            }
+            let taken = lot.shares.min(left);
+            left -= taken;
+            let amount = if left == 0 {
+                assets - priced
+            } else {
+                shares::mul_div(assets, taken as u128, shares as u128, Rounding::Down)?
+            };
+            priced += amount;
+            lots.push((amount, lot.deposited_at));
+        }
+        if left > 0 {
+            lots.push((assets - priced, self.last_deposit_at));
+        }
+        Ok(lots)
+    }
+
+    /// Bring the reward stake to the record's shares, banking what the old stake earned
+    ///
+    /// `distributor` is accrued to `now` first, so emissions up to the change are
+    /// split over the stakes that held them.
+    pub fn settle_rewards(&mut self, distributor: &mut RewardsDistributor, now: i64) -> Result<()> {
+        distributor.accrue(now)?;
+        let acc = distributor.acc_reward_per_share;
+        let earned = reward_accrued(self.reward_stake, acc)?.saturating_sub(self.reward_debt);
+        self.rewards_owed = u64::try_from(earned)
+            .ok()
+            .and_then(|earned| self.rewards_owed.checked_add(earned))
+            .ok_or(MoluscoError::MathOverflow)?;
+        distributor.total_stake = distributor
+            .total_stake
+            .checked_sub(self.reward_stake)
+            .and_then(|stake| stake.checked_add(self.shares))
+            .ok_or(MoluscoError::MathOverflow)?;
+        self.reward_stake = self.shares;
+        self.reward_debt = reward_accrued(self.shares, acc)?;
+        Ok(())
+    }
+
+    /// Record `paid` leaving the vault for the depositor, after an exit fee of `fee`
+    pub fn record_outflow(&mut self, paid: u64, fee: u64) -> Result<()> {
+        self.withdrawn = self.withdrawn.checked_add(paid).ok_or(MoluscoError::MathOverflow)?;
+        self.fees_paid = self.fees_paid.checked_add(fee).ok_or(MoluscoError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Match the record to `held`, the holder's share balance, since share tokens move freely
+    ///
+    /// Shares sent away take their fraction of principal with them. Shares received
+    /// carry no principal and count as deposited `now` for exit fees.
+    pub fn sync(&mut self, vault: Pubkey, depositor: Pubkey, held: u64, now: i64, bump: u8) -> Result<()> {
+        if self.first_deposit_at == 0 {
+            self.vault = vault;
+            self.depositor = depositor;
+            self.first_deposit_at = now;
+            self.bump = bump;
+        }
+        if held < self.shares {
+            self.principal =
+                shares::mul_div(self.principal, held as u128, self.shares as u128, Rounding::Down)?;
+            self.take_lots(self.shares - held);
+        } else if held > self.shares {
+            self.last_deposit_at = now;
+            self.add_lot(held - self.shares, now);
+        }
+        self.shares = held;
+        Ok(())
+    }
+
+    /// Record a withdrawal that burned `shares`, releasing the same fraction of principal
+    pub fn debit(&mut self, shares: u64) -> Result<()> {
+        require_gte!(self.shares, shares, MoluscoError::InsufficientShares);
+        let released = shares::mul_div(
+            self.principal,
+            shares as u128,
+            self.shares as u128,
+            Rounding::Up,
+        )?
+        .min(self.principal);
+        self.principal -= released;
+        self.shares -= shares;
+        self.take_lots(shares);
+        Ok(())
+    }
+
+    /// Yield earned so far: current value of the shares above the principal still in them
+    pub fn unrealized_yield(&self, total_assets: u64, total_shares: u64) -> Result<u64> {
+        let value = shares::convert_to_assets(self.shares, total_assets, total_shares)?;
+        Ok(value.saturating_sub(self.principal))
+    }
+}
+
+#[account]
+pub struct WithdrawalRequest {
+    pub vault: Pubkey,
+    pub depositor: Pubkey,
+    pub destination: Pubkey,
+    pub index: u64,
+    pub epoch: u64,
+    pub assets: u64,
+    pub remaining_assets: u64,
+    pub remaining_shares: u64,
+    pub exit_fee: u64,
+    pub requested_at: i64,
+    pub bump: u8,
+}
+
+impl WithdrawalRequest {
+    pub const SIZE: usize = 32 +      // vault
+        32 +                            // depositor
+        32 +                            // destination
+        8 +                             // index
+        8 +                             // epoch
+        8 +                             // assets
+        8 +                             // remaining_assets
+        8 +                             // remaining_shares
+        8 +                             // exit_fee
+        8 +                             // requested_at
+        1;                              // bump
+}
+
+#[account]
+pub struct FeeConfig {
+    pub vault: Pubkey,
+    pub performance_fee_bps: u16,
+    pub management_fee_bps: u16,
+    pub recipient: Pubkey,
+    pub min_sweep_amount: u64,
+    pub bump: u8,
+}
+
+impl FeeConfig {
+    pub const SIZE: usize = 32 +      // vault
+        2 +                             // performance_fee_bps
+        2 +                             // management_fee_bps
+        32 +                            // recipient
+        8 +                             // min_sweep_amount
+        1;                              // bump
+}
+
+/// Actions a delegate can be allowed to take for the vault owner
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DelegateAction {
+    Update,
+    Rebalance,
+    Open,
+    Close,
+}
+
+#[account]
+pub struct Delegate {
+    pub vault: Pubkey,
+    pub delegate: Pubkey,
+    pub can_update: bool,
+    pub can_rebalance: bool,
+    pub can_open: bool,
+    pub can_close: bool,
+    pub granted_at: i64,
+    pub bump: u8,
+}
+
+impl Delegate {
+    pub const SIZE: usize = 32 +      // vault
+        32 +                            // delegate
+        1 +                             // can_update
+        1 +                             // can_rebalance
+        1 +                             // can_open
+        1 +                             // can_close
+        8 +                             // granted_at
+        1;                              // bump
+
+    pub fn allows(&self, action: DelegateAction) -> bool {
+        match action {
+            DelegateAction::Update => self.can_update,
+            DelegateAction::Rebalance => self.can_rebalance,
+            DelegateAction::Open => self.can_open,
+            DelegateAction::Close => self.can_close,
+        }
+    }
+}
+
+/// Require `authority` to be the vault owner, or a delegate allowed to take `action`
+///
+/// The delegation's seeds already bind it to this vault and signer.
+pub fn require_authority(
+    vault: &Vault,
+    authority: &Pubkey,
+    delegation: Option<&Account<Delegate>>,
+    action: DelegateAction,
+) -> Result<()> {
+    if *authority == vault.owner {
+        return Ok(());
+    }
+    let delegation = delegation.ok_or(MoluscoError::NotDelegated)?;
+    require!(delegation.allows(action), MoluscoError::DelegateNotPermitted);
+    Ok(())
+}
+
+/// Longest a session can be opened for
+pub const MAX_SESSION_DURATION_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// What a session key may do; sessions never move capital
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SessionScope {
+    pub update: bool,
+    pub rebalance: bool,
+}
+
+impl SessionScope {
+    pub const SIZE: usize = 1 + 1;
+}
+
+#[account]
+pub struct Session {
+    pub vault: Pubkey,
+    pub session_key: Pubkey,
+    pub scope: SessionScope,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl Session {
+    pub const SIZE: usize = 32 +      // vault
+        32 +                            // session_key
+        SessionScope::SIZE +            // scope
+        8 +                             // created_at
+        8 +                             // expires_at
+        1;                              // bump
+
+    pub fn is_live(&self, now: i64) -> bool {
+        now < self.expires_at
+    }
+
+    pub fn allows(&self, action: DelegateAction) -> bool {
+        match action {
+            DelegateAction::Update => self.scope.update,
+            DelegateAction::Rebalance => self.scope.rebalance,
+            DelegateAction::Open | DelegateAction::Close => false,
+        }
+    }
+}
+
+/// `require_authority`, also accepting a live session key scoped for `action`
+///
+/// The session's seeds already bind it to this vault and signer.
+pub fn require_authority_or_session(
+    vault: &Vault,
+    authority: &Pubkey,
+    delegation: Option<&Account<Delegate>>,
+    session: Option<&Account<Session>>,
+    action: DelegateAction,
+    now: i64,
+) -> Result<()> {
+    if let Some(session) = session {
+        require!(session.is_live(now), MoluscoError::SessionExpired);
+        require!(session.allows(action), MoluscoError::SessionNotPermitted);
+        return Ok(());
+    }
+    require_authority(vault, authority, delegation, action)
+}
+
+#[event]
+pub struct PositionVoidedEvent {
+    pub vault: Pubkey,
+    pub position: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionClosedEvent {
+    pub vault: Pubkey,
+    pub position: Pubkey,
+    pub amount: u64,
+    pub accumulated_yield: u128,
+    pub accumulated_loss: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionSettledEvent {
+    pub vault: Pubkey,
+    pub position: Pubkey,
+    pub settled: u64,
+    pub fee: u64,
+    pub new_principal: u64,
+    pub realized_yield: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionFinalMarkEvent {
+    pub vault: Pubkey,
+    pub position: Pubkey,
+    pub final_value: u64,
+    pub terminal_yield: u64,
+    pub loss: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionReducedEvent {
+    pub vault: Pubkey,
+    pub position: Pubkey,
+    pub principal_returned: u64,
+    pub realized_yield: u64,
+    pub fee: u64,
+    pub remaining_principal: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionRolledEvent {
+    pub vault: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub from_protocol: ProtocolKind,
+    pub to_protocol: ProtocolKind,
+    pub principal: u64,
+    pub realized_yield: u64,
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionFlaggedStaleEvent {
+    pub vault: Pubkey,
+    pub position: Pubkey,
+    pub last_update: i64,
+    pub flagged_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionRolledOverEvent {
+    pub vault: Pubkey,
+    pub position: Pubkey,
+    pub matured_at: i64,
+    pub term_yield: u64,
+    pub new_principal: u64,
+    pub next_maturity: i64,
+    pub rollover_count: u32,
+}
+
+#[event]
+pub struct ImpairmentRecordedEvent {
+    pub vault: Pubkey,
+    pub position: Pubkey,
+    pub protocol: ProtocolKind,
+    pub write_down: u64,
+    pub remaining_principal: u64,
+    pub incident_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InvariantReportEvent {
+    pub vault: Pubkey,
+    pub positions_checked: u16,
+    pub total_value_locked: u128,
+    pub active_principal: u64,
+    /// Vault's recorded count, compared with `active_found` among the accounts passed
+    pub active_positions: u16,
+    pub active_found: u16,
+    pub tvl_ok: bool,
+    pub count_ok: bool,
+    pub nav_ok: bool,
+    pub passed: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultReconciledEvent {
+    pub vault: Pubkey,
+    /// Idle tokens the books say the escrow should hold, fees included
+    pub recorded_idle: u64,
+    pub actual_idle: u64,
+    /// Booked to TVL as yield
+    pub surplus: u64,
+    /// Reported only; the books are left as they were
+    pub deficit: u64,
+    pub total_value_locked: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeChargedEvent {
+    pub vault: Pubkey,
+    pub kind: FeeKind,
+    pub rate_bps: u16,
+    pub base_amount: u64,
+    pub fee_amount: u64,
+    pub sequence: u64,
+    pub timestamp: i64,
+}
+
+/// Audit mode only: inputs and intermediates of a fee computation
+#[event]
+pub struct AuditFeeMathEvent {
+    pub vault: Pubkey,
+    pub kind: FeeKind,
+    pub base_amount: u64,
+    pub held_secs: i64,
+    pub rate_bps: u16,
+    pub fee_numerator: u128, // base_amount * rate_bps, before dividing by 10_000
+    pub fee_amount: u64,
+    pub net_amount: u64,
+    pub timestamp: i64,
+}
+
+/// Audit mode only: inputs and intermediates of a share/asset conversion
+#[event]
+pub struct AuditShareMathEvent {
+    pub vault: Pubkey,
+    pub operation: ShareOperation,
+    pub input: u64,
+    pub output: u64,
+    pub total_assets: u64,
+    pub total_shares: u64,
+    pub virtual_assets: u64,
+    pub virtual_shares: u64,
+    pub rounding: Rounding,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultFlowEvent {
+    pub vault: Pubkey,
+    pub kind: FlowKind,
+    pub amount: u64, // Gross change in TVL
+    pub fee: u64,
+    pub shares: u64,
+    pub total_value_locked: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalRequestedEvent {
+    pub vault: Pubkey,
+    pub request: Pubkey,
+    pub depositor: Pubkey,
+    pub index: u64,
+    pub epoch: u64,
+    pub shares: u64,
+    pub assets: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalsProcessedEvent {
+    pub vault: Pubkey,
+    pub epoch: u64,
+    pub filled: u64,
+    pub settled: u64,
+    pub queue_head: u64,
+    pub queued_withdrawals: u64,
+    pub timestamp: i64,
+}
+
+/// Keyed by depositor so notification services can alert one user without replaying the vault
+#[event]
+pub struct DepositorNotificationEvent {
+    pub depositor: Pubkey,
+    pub vault: Pubkey,
+    pub kind: DepositorNotice,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionOracleMarkEvent {
+    pub vault: Pubkey,
+    pub position: Pubkey,
+    /// Feeds that passed their checks and went into the median
+    pub sources: u8,
+    pub current_value: u64,
+    pub timestamp: i64,
+}
+
+/// Program errors
+///
+/// Anchor numbers variants from 6000 in declaration order, so new codes are
+/// appended and existing ones never move; `category()` groups them instead.
+#[error_code]
+pub enum MoluscoError {
+    #[msg("Invalid APY value")]
+    InvalidApy,
+    #[msg("Position already closed")]
+    PositionClosed,
+    #[msg("Insufficient vault balance")]
+    InsufficientBalance,
+    #[msg("Amount must be greater than zero")]
+    InvalidAmount,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Emission schedule must end in the future")]
+    InvalidEmissionSchedule,
+    #[msg("Invalid Merkle proof")]
+    InvalidMerkleProof,
+    #[msg("Fee basis points out of bounds")]
+    InvalidFeeBps,
+    #[msg("Swap output below minimum")]
+    SlippageExceeded,
+    #[msg("Vault position limit reached")]
+    PositionLimitReached,
+    #[msg("Vault deposit cap exceeded")]
+    DepositCapExceeded,
+    #[msg("Batch accounts and values length mismatch")]
+    BatchLengthMismatch,
+    #[msg("Batch exceeds maximum size")]
+    BatchTooLarge,
+    #[msg("Invalid account in batch")]
+    InvalidBatchAccount,
+    #[msg("Position is active and above the dust threshold")]
+    PositionNotClosable,
+    #[msg("Compound interval must not be negative")]
+    InvalidCompoundInterval,
+    #[msg("Compounding is disabled for this position")]
+    CompoundingDisabled,
+    #[msg("Compounding not yet due")]
+    CompoundNotDue,
+    #[msg("Adapter returned no or malformed data")]
+    InvalidAdapterReturn,
+    #[msg("Position amount below vault minimum")]
+    PositionTooSmall,
+    #[msg("Position has no valid fixed term")]
+    InvalidTerm,
+    #[msg("Position term has not matured")]
+    PositionNotMatured,
+    #[msg("Invalid smoothing configuration")]
+    InvalidSmoothingConfig,
+    #[msg("Initial deposit below minimum")]
+    InitialDepositTooSmall,
+    #[msg("Invalid fee schedule")]
+    InvalidFeeSchedule,
+    #[msg("Name exceeds maximum length")]
+    NameTooLong,
+    #[msg("Adapter reserve is frozen or paused")]
+    AdapterReserveFrozen,
+    #[msg("Adapter reserve utilization above threshold")]
+    AdapterUtilizationTooHigh,
+    #[msg("Adapter oracle is stale")]
+    AdapterOracleStale,
+    #[msg("Adapter is not in emergency exit mode")]
+    EmergencyExitNotActive,
+    #[msg("Write-down must be positive and at most the position principal")]
+    InvalidImpairment,
+    #[msg("Signer is not the registered strategist")]
+    InvalidStrategist,
+    #[msg("Hint weights must sum to 10000 bps")]
+    InvalidHintWeights,
+    #[msg("Allocation hint expired")]
+    HintExpired,
+    #[msg("Allocation hint already consumed")]
+    HintConsumed,
+    #[msg("Rebalance slippage budget exhausted for this epoch")]
+    SlippageBudgetExhausted,
+    #[msg("Resting order is not open")]
+    OrderNotOpen,
+    #[msg("Job queue is full")]
+    JobQueueFull,
+    #[msg("Job not found")]
+    JobNotFound,
+    #[msg("Job is not due yet")]
+    JobNotDue,
+    #[msg("Job target shows no crank since scheduling")]
+    JobNotPerformed,
+    #[msg("Deposits are paused")]
+    DepositsPaused,
+    #[msg("Withdrawals are paused")]
+    WithdrawalsPaused,
+    #[msg("Trading is paused")]
+    TradingPaused,
+    #[msg("Signer is not the pause authority for this flag")]
+    InvalidPauseAuthority,
+    #[msg("Account passed more than once")]
+    DuplicateAccount,
+    #[msg("Vault accounting invariant violated")]
+    InvariantViolated,
+    #[msg("Clone must use a different delegate")]
+    SameDelegate,
+    #[msg("Too soon after the vault's last checkpoint")]
+    CheckpointTooSoon,
+    #[msg("End checkpoint must come after start checkpoint")]
+    InvalidCheckpointRange,
+    #[msg("Benchmark needs 1-4 components with weights summing to 10000 bps")]
+    InvalidBenchmark,
+    #[msg("Benchmark or feed accounts do not match the vault benchmark")]
+    BenchmarkMismatch,
+    #[msg("Invalid co-signer configuration")]
+    InvalidCosignerConfig,
+    #[msg("Large withdrawal lacks required co-signatures")]
+    InsufficientCosigners,
+    #[msg("Destination is not on the vault allowlist")]
+    DestinationNotAllowed,
+    #[msg("Destination is already allowlisted or pending")]
+    DestinationAlreadyListed,
+    #[msg("Destination has not been proposed")]
+    DestinationNotPending,
+    #[msg("Destination allowlist is full")]
+    AllowlistFull,
+    #[msg("Timelock has not elapsed")]
+    TimelockNotElapsed,
+    #[msg("Velocity limit cannot exceed 10000 bps")]
+    InvalidVelocityLimit,
+    #[msg("Outflow exceeds the vault velocity limit for this window")]
+    VelocityLimitExceeded,
+    #[msg("Signer is neither the vault owner nor its viewer")]
+    NotOwnerOrViewer,
+    #[msg("Strategy program is disabled")]
+    StrategyProgramDisabled,
+    #[msg("Amount exceeds the strategy allocation limit")]
+    StrategyAllocationExceeded,
+    #[msg("Observed token balance change does not match booked amount")]
+    BalanceMismatch,
+    #[msg("Vault is locked by an in-flight adapter CPI")]
+    ReentrancyDetected,
+    #[msg("Pair mints must be passed in ascending order")]
+    UnorderedPair,
+    #[msg("Pair slippage limit does not match the swap mints")]
+    PairSlippageMismatch,
+    #[msg("Minimum output is looser than the pair slippage limit")]
+    SlippageLimitTooLoose,
+    #[msg("Bundle-only vault requires a bundle tip in the same transaction")]
+    BundleTipMissing,
+    #[msg("Bundle tip exceeds the vault maximum")]
+    BundleTipTooLarge,
+    #[msg("Not a Jito tip account")]
+    InvalidTipAccount,
+    #[msg("Vault escrow is already initialized")]
+    EscrowAlreadyInitialized,
+    #[msg("Token decimals exceed the internal precision")]
+    UnsupportedDecimals,
+    #[msg("Deposit would leave the escrow below the rent-exempt minimum")]
+    EscrowNotRentExempt,
+    #[msg("Vault is not denominated in SOL")]
+    NotSolVault,
+    #[msg("Vault share mint is already initialized")]
+    ShareMintAlreadyInitialized,
+    #[msg("Deposit is too small to mint any shares")]
+    ZeroShares,
+    #[msg("Not enough shares to cover the withdrawal")]
+    InsufficientShares,
+    #[msg("Withdrawal requests must be processed in queue order from the head")]
+    WithdrawalOutOfOrder,
+    #[msg("No fees have accrued")]
+    NoFeesAccrued,
+    #[msg("Fee collection accounts for the vault asset are missing")]
+    MissingFeeAccounts,
+    #[msg("Signer is not the vault fee authority")]
+    NotFeeAuthority,
+    #[msg("Fee recipient does not match the fee config")]
+    InvalidFeeRecipient,
+    #[msg("Accrued fees are below the minimum sweep amount")]
+    SweepBelowMinimum,
+    #[msg("The owner cannot be its own delegate")]
+    InvalidDelegate,
+    #[msg("Signer is neither the vault owner nor a delegate")]
+    NotDelegated,
+    #[msg("Delegate is not permitted to take this action")]
+    DelegateNotPermitted,
+    #[msg("The owner cannot open a session for its own key")]
+    InvalidSessionKey,
+    #[msg("Session expiry must be in the future and within the maximum duration")]
+    InvalidSessionExpiry,
+    #[msg("Session scope grants nothing")]
+    EmptySessionScope,
+    #[msg("Session has expired")]
+    SessionExpired,
+    #[msg("Session is not scoped for this action")]
+    SessionNotPermitted,
+    #[msg("Batch account must be writable")]
+    BatchAccountNotWritable,
+    #[msg("Batch account belongs to a different vault")]
+    BatchAccountWrongVault,
+    #[msg("Batch position belongs to a different owner")]
+    BatchAccountWrongOwner,
+    #[msg("Batch account does not match the address it is paired with")]
+    BatchAccountMismatch,
+    #[msg("Outflow exceeds the vault recorded TVL")]
+    TvlUnderflow,
+    #[msg("Idle escrow not owed to the withdrawal queue is insufficient")]
+    InsufficientLiquidity,
+    #[msg("Lamports above the rent-exempt minimum are insufficient")]
+    InsufficientLamports,
+    #[msg("Proposed owner is already the vault owner")]
+    InvalidPendingOwner,
+    #[msg("No ownership transfer is pending")]
+    NoPendingOwner,
+    #[msg("Signer is not the proposed vault owner")]
+    NotPendingOwner,
+    #[msg("Idempotency key must not be all zeros")]
+    InvalidIdempotencyKey,
+    #[msg("Instruction with this idempotency key already executed")]
+    DuplicateIdempotencyKey,
+    #[msg("Vault is paused")]
+    VaultPaused,
+    #[msg("Not supported on simulation vaults")]
+    SimulationVault,
+    #[msg("Every active position of the vault must be passed")]
+    PositionSetIncomplete,
+    #[msg("Program is paused")]
+    ProgramPaused,
+    #[msg("Signer is not the program admin")]
+    NotProgramAdmin,
+    #[msg("Protocol is not on the whitelist")]
+    ProtocolNotWhitelisted,
+    #[msg("Protocol is already on the whitelist")]
+    ProtocolAlreadyWhitelisted,
+    #[msg("Protocol registry is full")]
+    ProtocolRegistryFull,
+    #[msg("Strategy capacity exceeded")]
+    StrategyCapacityExceeded,
+    #[msg("Share mint account is required")]
+    ShareMintRequired,
+    #[msg("Exit fee has not yet decayed to zero")]
+    LockupNotExpired,
+    #[msg("Position already carries a migrated index")]
+    PositionAlreadyMigrated,
+    #[msg("Position address does not derive from the given legacy seed")]
+    LegacySeedMismatch,
+    #[msg("Position can no longer be voided; close it instead")]
+    VoidWindowElapsed,
+    #[msg("Position capital has left its custody")]
+    PositionAlreadyDeployed,
+    #[msg("Metadata URI or social link is too long")]
+    MetadataTooLong,
+    #[msg("Agent profile already lists the maximum number of vaults")]
+    ProfileFull,
+    #[msg("Vault is already on this agent profile")]
+    VaultAlreadyInProfile,
+    #[msg("Accounts passed do not match the vaults on the agent profile")]
+    ProfileVaultMismatch,
+    #[msg("No rebates have accrued")]
+    NoRebatesAccrued,
+    #[msg("Account is not in the layout this migration upgrades")]
+    NotLegacyLayout,
+    #[msg("Position has no unrealized yield to settle")]
+    NothingToSettle,
+    #[msg("Position has not been marked recently enough to settle on a final value")]
+    PositionMarkStale,
+    #[msg("Position custody holds less than the amount being returned")]
+    PositionCustodyShort,
+    #[msg("A roll must move the position to a different protocol")]
+    RollToSameProtocol,
+    #[msg("Target APY must change and stay within MAX_TARGET_APY_BPS")]
+    InvalidTargetApy,
+    #[msg("Update sequence does not match the position; the mark is stale or replayed")]
+    StaleUpdateSequence,
+    #[msg("Position was updated too recently; wait for min_update_interval_secs")]
+    UpdateTooFrequent,
+    #[msg("Update interval and staleness limits cannot be negative")]
+    InvalidUpdateInterval,
+    #[msg("Vault has positions flagged stale; deposits resume once they are marked")]
+    VaultHasStalePositions,
+    #[msg("Vault has no staleness limit configured")]
+    StalenessDisabled,
+    #[msg("Position is already flagged stale")]
+    PositionAlreadyStale,
+    #[msg("Position was marked within the vault staleness limit")]
+    PositionNotStale,
+    #[msg("Oracle account is not a verified price feed")]
+    OracleAccountInvalid,
+    #[msg("Price update is for a different feed")]
+    OracleFeedMismatch,
+    #[msg("Oracle price is not positive")]
+    OraclePriceInvalid,
+    #[msg("Oracle price is older than the feed's maximum age")]
+    OraclePriceStale,
+    #[msg("Oracle confidence interval is too wide")]
+    OraclePriceUncertain,
+    #[msg("Oracle feeds, quorum or limits are out of range")]
+    InvalidOracleConfig,
+    #[msg("Oracle accounts must match the asset's configured feeds")]
+    OracleAccountsMismatch,
+    #[msg("Too few oracle feeds passed their checks")]
+    OracleQuorumNotMet,
+    #[msg("Oracle feeds disagree by more than the allowed divergence")]
+    OracleSourcesDiverge,
+    #[msg("Asset has an oracle config; mark it with update_position_with_oracle")]
+    OracleMarkRequired,
+    #[msg("A vault-owned token account may not be passed to an external program")]
+    VaultAccountExposed,
+    #[msg("Shares of the vault are held by someone other than its owner")]
+    VaultHasOtherShareholders,
+    #[msg("The guardian must be a key other than the owner")]
+    GuardianIsOwner,
+    #[msg("No velocity limit change is queued")]
+    VelocityChangeNotPending,
+    #[msg("Only the program upgrade authority may do this")]
+    NotUpgradeAuthority,
+    #[msg("Vault's latest checkpoint account is required")]
+    PreviousCheckpointRequired,
+    #[msg("The vault's mint account is required")]
+    VaultMintRequired,
+    #[msg("Every open position of the vault must be passed")]
+    UnwindPositionsIncomplete,
+    #[msg("A position with a lower unwind priority must be unwound first")]
+    UnwindOutOfOrder,
+    #[msg("Job tip is below the minimum")]
+    JobTipTooSmall,
+    #[msg("Job target is not the vault or one of its positions")]
+    JobTargetMismatch,
+    #[msg("Only the vault owner can cancel a job before it expires")]
+    JobNotExpired,
+    #[msg("Protocol's registered adapter program is required")]
+    AdapterProgramRequired,
+    #[msg("Vault's escrow account is required")]
+    EscrowAccountRequired,
+    #[msg("Position does not belong to this vault")]
+    PositionVaultMismatch,
+}
+
+/// Broad class of a `MoluscoError`, for agents and dashboards triaging failures
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// An argument or passed account is malformed or out of bounds
+    Validation,
+    /// The signer may not take this action
+    Authorization,
+    /// Checked arithmetic failed
+    Math,
+    /// A price or rate source is stale or unusable
+    Oracle,
+    /// An external venue, adapter or swap misbehaved or refused
+    Adapter,
+    /// The vault or position is not in a state that allows the action
+    State,
+}
+
+impl MoluscoError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            MoluscoError::InvalidApy
+            | MoluscoError::InvalidAmount
+            | MoluscoError::InvalidEmissionSchedule
+            | MoluscoError::InvalidMerkleProof
+            | MoluscoError::InvalidFeeBps
+            | MoluscoError::BatchLengthMismatch
+            | MoluscoError::BatchTooLarge
+            | MoluscoError::InvalidBatchAccount
+            | MoluscoError::InvalidCompoundInterval
+            | MoluscoError::PositionTooSmall
+            | MoluscoError::InvalidTerm
+            | MoluscoError::InvalidSmoothingConfig
+            | MoluscoError::InitialDepositTooSmall
+            | MoluscoError::InvalidFeeSchedule
+            | MoluscoError::NameTooLong
+            | MoluscoError::InvalidImpairment
+            | MoluscoError::InvalidHintWeights
+            | MoluscoError::DuplicateAccount
+            | MoluscoError::SameDelegate
+            | MoluscoError::InvalidCheckpointRange
+            | MoluscoError::InvalidBenchmark
+            | MoluscoError::BenchmarkMismatch
+            | MoluscoError::InvalidCosignerConfig
+            | MoluscoError::InvalidVelocityLimit
+            | MoluscoError::UnorderedPair
+            | MoluscoError::PairSlippageMismatch
+            | MoluscoError::SlippageLimitTooLoose
+            | MoluscoError::BundleTipTooLarge
+            | MoluscoError::InvalidTipAccount
+            | MoluscoError::UnsupportedDecimals
+            | MoluscoError::ZeroShares
+            | MoluscoError::MissingFeeAccounts
+            | MoluscoError::InvalidFeeRecipient
+            | MoluscoError::InvalidDelegate
+            | MoluscoError::InvalidSessionKey
+            | MoluscoError::InvalidSessionExpiry
+            | MoluscoError::EmptySessionScope
+            | MoluscoError::BatchAccountNotWritable
+            | MoluscoError::BatchAccountWrongVault
+            | MoluscoError::BatchAccountWrongOwner
+            | MoluscoError::BatchAccountMismatch
+            | MoluscoError::InvalidPendingOwner
+            | MoluscoError::InvalidIdempotencyKey
+            | MoluscoError::PositionSetIncomplete
+            | MoluscoError::ProtocolNotWhitelisted
+            | MoluscoError::ProtocolAlreadyWhitelisted
+            | MoluscoError::ShareMintRequired
+            | MoluscoError::LegacySeedMismatch
+            | MoluscoError::MetadataTooLong
+            | MoluscoError::VaultAlreadyInProfile
+            | MoluscoError::ProfileVaultMismatch
+            | MoluscoError::NotLegacyLayout
+            | MoluscoError::RollToSameProtocol
+            | MoluscoError::InvalidTargetApy
+            | MoluscoError::InvalidUpdateInterval
+            | MoluscoError::InvalidOracleConfig
+            | MoluscoError::OracleAccountsMismatch
+            | MoluscoError::GuardianIsOwner
+            | MoluscoError::PreviousCheckpointRequired
+            | MoluscoError::VaultMintRequired
+            | MoluscoError::UnwindPositionsIncomplete
+            | MoluscoError::JobTipTooSmall
+            | MoluscoError::JobTargetMismatch
+            | MoluscoError::AdapterProgramRequired
+            | MoluscoError::EscrowAccountRequired
+            | MoluscoError::PositionVaultMismatch => ErrorCategory::Validation,
+            MoluscoError::InvalidStrategist
+            | MoluscoError::InvalidPauseAuthority
+            | MoluscoError::InsufficientCosigners
+            | MoluscoError::DestinationNotAllowed
+            | MoluscoError::NotOwnerOrViewer
+            | MoluscoError::NotFeeAuthority
+            | MoluscoError::NotDelegated
+            | MoluscoError::DelegateNotPermitted
+            | MoluscoError::SessionExpired
+            | MoluscoError::SessionNotPermitted
+            | MoluscoError::NotPendingOwner
+            | MoluscoError::NotProgramAdmin
+            | MoluscoError::VaultHasOtherShareholders
+            | MoluscoError::NotUpgradeAuthority
+            | MoluscoError::JobNotExpired => ErrorCategory::Authorization,
+            MoluscoError::MathOverflow
+            | MoluscoError::TvlUnderflow => ErrorCategory::Math,
+            MoluscoError::AdapterOracleStale
+            | MoluscoError::OracleAccountInvalid
+            | MoluscoError::OracleFeedMismatch
+            | MoluscoError::OraclePriceInvalid
+            | MoluscoError::OraclePriceStale
+            | MoluscoError::OraclePriceUncertain
+            | MoluscoError::OracleQuorumNotMet
+            | MoluscoError::OracleSourcesDiverge
+            | MoluscoError::OracleMarkRequired => ErrorCategory::Oracle,
+            MoluscoError::SlippageExceeded
+            | MoluscoError::InvalidAdapterReturn
+            | MoluscoError::AdapterReserveFrozen
+            | MoluscoError::AdapterUtilizationTooHigh
+            | MoluscoError::EmergencyExitNotActive
+            | MoluscoError::SlippageBudgetExhausted
+            | MoluscoError::StrategyProgramDisabled
+            | MoluscoError::StrategyAllocationExceeded
+            | MoluscoError::BalanceMismatch
+            | MoluscoError::ReentrancyDetected
+            | MoluscoError::BundleTipMissing
+            | MoluscoError::VaultAccountExposed => ErrorCategory::Adapter,
+            MoluscoError::PositionClosed
+            | MoluscoError::InsufficientBalance
+            | MoluscoError::PositionLimitReached
+            | MoluscoError::DepositCapExceeded
+            | MoluscoError::PositionNotClosable
+            | MoluscoError::CompoundingDisabled
+            | MoluscoError::CompoundNotDue
+            | MoluscoError::PositionNotMatured
+            | MoluscoError::HintExpired
+            | MoluscoError::HintConsumed
+            | MoluscoError::OrderNotOpen
+            | MoluscoError::JobQueueFull
+            | MoluscoError::JobNotFound
+            | MoluscoError::JobNotDue
+            | MoluscoError::JobNotPerformed
+            | MoluscoError::DepositsPaused
+            | MoluscoError::WithdrawalsPaused
+            | MoluscoError::TradingPaused
+            | MoluscoError::InvariantViolated
+            | MoluscoError::CheckpointTooSoon
+            | MoluscoError::DestinationAlreadyListed
+            | MoluscoError::DestinationNotPending
+            | MoluscoError::AllowlistFull
+            | MoluscoError::TimelockNotElapsed
+            | MoluscoError::VelocityLimitExceeded
+            | MoluscoError::EscrowAlreadyInitialized
+            | MoluscoError::EscrowNotRentExempt
+            | MoluscoError::NotSolVault
+            | MoluscoError::ShareMintAlreadyInitialized
+            | MoluscoError::InsufficientShares
+            | MoluscoError::WithdrawalOutOfOrder
+            | MoluscoError::NoFeesAccrued
+            | MoluscoError::SweepBelowMinimum
+            | MoluscoError::InsufficientLiquidity
+            | MoluscoError::InsufficientLamports
+            | MoluscoError::NoPendingOwner
+            | MoluscoError::DuplicateIdempotencyKey
+            | MoluscoError::VaultPaused
+            | MoluscoError::SimulationVault
+            | MoluscoError::ProgramPaused
+            | MoluscoError::ProtocolRegistryFull
+            | MoluscoError::StrategyCapacityExceeded
+            | MoluscoError::LockupNotExpired
+            | MoluscoError::PositionAlreadyMigrated
+            | MoluscoError::VoidWindowElapsed
+            | MoluscoError::PositionAlreadyDeployed
+            | MoluscoError::ProfileFull
+            | MoluscoError::NoRebatesAccrued
+            | MoluscoError::NothingToSettle
+            | MoluscoError::PositionMarkStale
+            | MoluscoError::PositionCustodyShort
+            | MoluscoError::StaleUpdateSequence
+            | MoluscoError::UpdateTooFrequent
+            | MoluscoError::VaultHasStalePositions
+            | MoluscoError::StalenessDisabled
+            | MoluscoError::PositionAlreadyStale
+            | MoluscoError::PositionNotStale
+            | MoluscoError::VelocityChangeNotPending
+            | MoluscoError::UnwindOutOfOrder => ErrorCategory::State,
+        }
+    }
 }
@@ -0,0 +1,278 @@
+//! Upgrades for vault and position accounts written under an older layout.
+//!
+//! Layouts, by `layout_version`:
+//!
+//! 1. The original accounts, with TVL, position yield and the fee accumulators in u64
+//!    and no version byte.
+//! 2. Those widened to u128, and `layout_version` stamped ahead of `bump`.
+//! 3. Positions gain `last_update_slot`, the loss and realized/unrealized yield
+//!    figures after `accumulated_yield`, and `rolled_from`, the target history, the
+//!    update sequence and the stale flag at the tail; vaults gain the unrealized
+//!    yield sum, the mark interval, the staleness limit and the stale count.
+//! 4. Positions gain `reserved_capacity`; vaults only take the new version byte.
+//!
+//! From layout 3 on, new fields go just ahead of `layout_version`, and each layout
+//! records how many bytes it added there. Borsh integers are little-endian, so a
+//! widened field is its old eight bytes followed by eight zero bytes, and every
+//! added field starts at zero. An account's layout is told by its length, since
+//! each layout is allocated at exactly its size; where a layout adds nothing, the
+//! version byte tells the two apart. Offsets are found in the bytes as
+//! they stand before each step, and within a step the splices run from the last
+//! field back, so each offset is still valid when it is used.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::{MoluscoError, TargetApyHistory};
+
+/// Layout this program writes
+pub const LAYOUT_VERSION: u8 = 4;
+
+/// Bytes a u64 gains when widened to u128
+const WIDENING: usize = 8;
+
+/// Account length, discriminator included, of a layout 1 vault
+pub const VAULT_V1_LEN: usize = 8 + 1445;
+/// Account length, discriminator included, of a layout 1 position
+pub const POSITION_V1_LEN: usize = 8 + 236;
+
+/// Bytes layout 2 adds to a vault: four widened accumulators and the version byte
+const VAULT_V2_GROWTH: usize = 4 * WIDENING + 1;
+/// Bytes layout 2 adds to a position: the widened yield and the version byte
+const POSITION_V2_GROWTH: usize = WIDENING + 1;
+
+/// Bytes vault layouts 3 onwards add ahead of `layout_version`; entry `i` is layout `i + 3`
+const VAULT_TAIL_GROWTH: [usize; LAYOUT_VERSION as usize - 2] = [
+    // `unrealized_yield`, `min_update_interval_secs`, `max_staleness_secs`, `stale_positions`
+    16 + 8 + 8 + 4,
+    // nothing
+    0,
+];
+/// Bytes position layouts 4 onwards add ahead of `layout_version`; entry `i` is layout `i + 4`
+const POSITION_TAIL_GROWTH: [usize; LAYOUT_VERSION as usize - 3] = [
+    // `reserved_capacity`
+    16,
+];
+
+/// Layout 3 position fields: `last_update_slot` ahead of `is_active`...
+const POSITION_V3_SLOT: usize = 8;
+/// ...`accumulated_loss`, `unrealized_yield` and `realized_yield` after `accumulated_yield`...
+const POSITION_V3_YIELDS: usize = 16 + 8 + 16;
+/// ...and `rolled_from`, `target_apy_history`, `update_sequence` and `is_stale` at the tail
+const POSITION_V3_TAIL: usize = 32 + TargetApyHistory::SIZE + 8 + 1;
+
+// Layout 1 distances between fields, from the first byte of one to the first of the next
+/// `total_value_locked` to the `cosigners` length prefix
+const VAULT_V1_TVL_TO_COSIGNERS: usize = 610;
+/// `cosign_threshold` to `fees_accrued`
+const VAULT_V1_TAIL_TO_FEES_ACCRUED: usize = 310;
+/// `fees_accrued` to `fees_outstanding`
+const VAULT_V1_FEES_ACCRUED_TO_OUTSTANDING: usize = 16;
+/// `fees_accrued` to `rebates_accrued`
+const VAULT_V1_FEES_ACCRUED_TO_REBATES: usize = 284;
+/// `amount` to `is_active`
+const POSITION_V1_AMOUNT_TO_IS_ACTIVE: usize = 26;
+/// `amount` to `accumulated_yield`
+const POSITION_V1_AMOUNT_TO_YIELD: usize = 27;
+/// `accumulated_yield` to `bump`
+const POSITION_V1_YIELD_TO_BUMP: usize = 135;
+
+/// Account length, discriminator included, of a vault written under `version`
+pub fn vault_len(version: u8) -> usize {
+    match version {
+        1 => VAULT_V1_LEN,
+        _ => VAULT_V1_LEN + VAULT_V2_GROWTH + VAULT_TAIL_GROWTH[..version as usize - 2].iter().sum::<usize>(),
+    }
+}
+
+/// Account length, discriminator included, of a position written under `version`
+pub fn position_len(version: u8) -> usize {
+    match version {
+        1 => POSITION_V1_LEN,
+        2 => POSITION_V1_LEN + POSITION_V2_GROWTH,
+        _ => {
+            POSITION_V1_LEN
+                + POSITION_V2_GROWTH
+                + POSITION_V3_SLOT
+                + POSITION_V3_YIELDS
+                + POSITION_V3_TAIL
+                + POSITION_TAIL_GROWTH[..version as usize - 3].iter().sum::<usize>()
+        }
+    }
+}
+
+/// Layout of the account in `data`, told by its length
+fn layout_of(data: &[u8], len: fn(u8) -> usize) -> Result<u8> {
+    (1..=LAYOUT_VERSION)
+        .find(|&version| len(version) == data.len())
+        .ok_or_else(|| error!(MoluscoError::NotLegacyLayout))
+}
+
+fn read_len(data: &[u8], at: usize) -> Result<usize> {
+    let bytes = data
+        .get(at..at + 4)
+        .ok_or_else(|| error!(MoluscoError::NotLegacyLayout))?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+}
+
+/// Bytes a serialized `ProtocolKind`, `StrategyKind` or `AssetKind` takes: `Other` carries a u16
+fn kind_len(data: &[u8], at: usize) -> Result<usize> {
+    match data.get(at) {
+        Some(0) => Ok(3),
+        Some(_) => Ok(1),
+        None => err!(MoluscoError::NotLegacyLayout),
+    }
+}
+
+/// Turn the u64 at `at` into a u128 of the same value
+fn widen(data: &mut Vec<u8>, at: usize) {
+    insert_zeroed(data, at + 8, WIDENING);
+}
+
+/// Make room for a new field of `len` bytes at `at`, starting at zero
+fn insert_zeroed(data: &mut Vec<u8>, at: usize, len: usize) {
+    data.splice(at..at, std::iter::repeat(0u8).take(len));
+}
+
+/// Offset of the `layout_version` byte in a layout 2 or later account, checked against `version`
+fn version_byte(data: &[u8], at: usize, version: u8) -> Result<usize> {
+    require!(data.get(at) == Some(&version), MoluscoError::NotLegacyLayout);
+    Ok(at)
+}
+
+/// Offsets of a vault's layout 1 fields: TVL, `fees_accrued` and `rebates_accrued`
+struct VaultV1Fields {
+    tvl: usize,
+    fees_accrued: usize,
+    rebates_accrued: usize,
+}
+
+fn vault_v1_fields(data: &[u8]) -> Result<VaultV1Fields> {
+    let tvl = 8 + 32 + 4 + read_len(data, 8 + 32)?;
+    let cosigners = tvl + VAULT_V1_TVL_TO_COSIGNERS;
+    let tail = cosigners + 4 + 32 * read_len(data, cosigners)?;
+    let fees_accrued = tail + VAULT_V1_TAIL_TO_FEES_ACCRUED;
+    Ok(VaultV1Fields {
+        tvl,
+        fees_accrued,
+        rebates_accrued: fees_accrued + VAULT_V1_FEES_ACCRUED_TO_REBATES,
+    })
+}
+
+/// Offset of `layout_version` in a vault written under `version` (2 or later)
+fn vault_version_byte(data: &[u8], version: u8) -> Result<usize> {
+    // Only the widened TVL sits ahead of the cosigners; all four sit ahead of the tail
+    let tvl = 8 + 32 + 4 + read_len(data, 8 + 32)?;
+    let cosigners = tvl + WIDENING + VAULT_V1_TVL_TO_COSIGNERS;
+    let tail = cosigners + 4 + 32 * read_len(data, cosigners)?;
+    let rebates_accrued = tail + VAULT_V1_TAIL_TO_FEES_ACCRUED + VAULT_V1_FEES_ACCRUED_TO_REBATES + 2 * WIDENING;
+    let at = rebates_accrued + 16 + VAULT_TAIL_GROWTH[..version as usize - 2].iter().sum::<usize>();
+    version_byte(data, at, version)
+}
+
+/// Offset of `amount` in a position under any layout: the kinds ahead of it vary in width
+fn position_amount(data: &[u8]) -> Result<usize> {
+    let mut amount = 8 + 32 + 32;
+    for _ in 0..3 {
+        amount += kind_len(data, amount)?;
+    }
+    Ok(amount)
+}
+
+/// Offset of `layout_version` in a position written under `version` (2 or later)
+fn position_version_byte(data: &[u8], version: u8) -> Result<usize> {
+    let v2 = position_amount(data)? + POSITION_V1_AMOUNT_TO_YIELD + POSITION_V1_YIELD_TO_BUMP + WIDENING;
+    let at = match version {
+        2 => v2,
+        _ => {
+            v2 + POSITION_V3_SLOT
+                + POSITION_V3_YIELDS
+                + POSITION_V3_TAIL
+                + POSITION_TAIL_GROWTH[..version as usize - 3].iter().sum::<usize>()
+        }
+    };
+    version_byte(data, at, version)
+}
+
+/// Rewrite a vault of any earlier layout, discriminator included, in the current layout
+pub fn upgrade_vault(data: &mut Vec<u8>) -> Result<()> {
+    let mut version = layout_of(data, vault_len)?;
+    require_gt!(LAYOUT_VERSION, version, MoluscoError::NotLegacyLayout);
+
+    if version == 1 {
+        let fields = vault_v1_fields(data)?;
+        let bump = fields.rebates_accrued + 8;
+        require_gt!(data.len(), bump, MoluscoError::NotLegacyLayout);
+        data.insert(bump, 2);
+        widen(data, fields.rebates_accrued);
+        widen(data, fields.fees_accrued + VAULT_V1_FEES_ACCRUED_TO_OUTSTANDING);
+        widen(data, fields.fees_accrued);
+        widen(data, fields.tvl);
+        version = 2;
+    }
+    while version < LAYOUT_VERSION {
+        let at = vault_version_byte(data, version)?;
+        insert_zeroed(data, at, VAULT_TAIL_GROWTH[version as usize - 2]);
+        version += 1;
+        data[at + VAULT_TAIL_GROWTH[version as usize - 3]] = version;
+    }
+    Ok(())
+}
+
+/// Rewrite a position of any earlier layout, discriminator included, in the current layout
+pub fn upgrade_position(data: &mut Vec<u8>) -> Result<()> {
+    let mut version = layout_of(data, position_len)?;
+    require_gt!(LAYOUT_VERSION, version, MoluscoError::NotLegacyLayout);
+
+    let amount = position_amount(data)?;
+    let accumulated_yield = amount + POSITION_V1_AMOUNT_TO_YIELD;
+    if version == 1 {
+        let bump = accumulated_yield + POSITION_V1_YIELD_TO_BUMP;
+        require_gt!(data.len(), bump, MoluscoError::NotLegacyLayout);
+        data.insert(bump, 2);
+        widen(data, accumulated_yield);
+        version = 2;
+    }
+    if version == 2 {
+        let at = position_version_byte(data, 2)?;
+        data[at] = 3;
+        insert_zeroed(data, at, POSITION_V3_TAIL);
+        insert_zeroed(data, accumulated_yield + 16, POSITION_V3_YIELDS);
+        // `settle_and_close` needs a fresh mark before it trusts a zero slot
+        insert_zeroed(data, amount + POSITION_V1_AMOUNT_TO_IS_ACTIVE, POSITION_V3_SLOT);
+        version = 3;
+    }
+    while version < LAYOUT_VERSION {
+        let at = position_version_byte(data, version)?;
+        insert_zeroed(data, at, POSITION_TAIL_GROWTH[version as usize - 3]);
+        version += 1;
+        data[at + POSITION_TAIL_GROWTH[version as usize - 4]] = version;
+    }
+    Ok(())
+}
+
+/// Grow `account` to `data.len()`, topping its rent up from `payer`, and write `data` into it
+pub fn rewrite_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    data: &[u8],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(data.len());
+    let top_up = rent.saturating_sub(account.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    account.realloc(data.len(), false)?;
+    account.try_borrow_mut_data()?.copy_from_slice(data);
+    Ok(())
+}
@@ -0,0 +1,246 @@
+//! Oracle prices for marking positions on chain.
+//!
+//! Pyth prices are read from `PriceUpdateV2` accounts posted by the Pyth
+//! receiver program; only fully verified updates are accepted. Switchboard prices
+//! are read from on-demand `PullFeedAccountData` accounts. Both are parsed by hand
+//! so the program does not pull in either SDK, and both reduce to an
+//! `OraclePrice` that goes through the same staleness and confidence checks.
+//! An asset priced by several feeds is priced at the median of their prices, and
+//! a holding is valued in another token through the ratio of the two prices.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::pubkey;
+
+use crate::MoluscoError;
+
+/// Pyth Solana receiver program, owner of every `PriceUpdateV2` account
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// Denominator of the confidence and divergence limits
+pub const BPS: u64 = 10_000;
+
+/// Switchboard on-demand program, owner of every `PullFeedAccountData` account
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey = pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+
+/// `verification_level` tag of an update checked against every guardian signature
+const VERIFICATION_FULL: u8 = 1;
+
+/// Prices are compared and converted with this many decimals, per whole token
+pub const PRICE_DECIMALS: i32 = 18;
+
+/// Normalized price of one US dollar, the unit oracle marks are valued in
+pub const USD_PRICE: u128 = 10u128.pow(PRICE_DECIMALS as u32);
+
+/// Switchboard results are fixed-point with 18 decimals
+const SWITCHBOARD_EXPONENT: i32 = -18;
+/// Offsets into a `PullFeedAccountData`, discriminator included, of
+/// `last_update_timestamp` and of `result.value`, which `result.std_dev` follows
+const PULL_FEED_LAST_UPDATE: usize = 8 + 2208;
+const PULL_FEED_RESULT: usize = 8 + 2256;
+
+/// Where an asset's oracle price comes from
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OracleSource {
+    Pyth,
+    Switchboard,
+}
+
+/// One price feed: a Pyth feed id, or the key of a Switchboard pull feed account
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OracleFeed {
+    pub source: OracleSource,
+    pub feed: [u8; 32],
+}
+
+impl OracleFeed {
+    pub const SIZE: usize = 1 + 32;
+
+    /// This feed's price, read from `account`
+    pub fn read(&self, account: &AccountInfo) -> Result<OraclePrice> {
+        match self.source {
+            OracleSource::Pyth => read_pyth_price(account, &self.feed),
+            OracleSource::Switchboard => read_switchboard_price(account, &Pubkey::new_from_array(self.feed)),
+        }
+    }
+}
+
+/// A price with its confidence interval, both scaled by `10^exponent`
+///
+/// Pyth reports the confidence interval and Switchboard the standard deviation of
+/// its oracles' responses; either is the spread checked against the price.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub price: i128,
+    pub conf: u128,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    /// Reject a price that is not positive, older than `max_age_secs`, or whose
+    /// confidence interval is wider than `max_confidence_bps` of the price
+    pub fn validate(&self, now: i64, max_age_secs: i64, max_confidence_bps: u16) -> Result<()> {
+        require_gt!(self.price, 0, MoluscoError::OraclePriceInvalid);
+        require_gte!(
+            max_age_secs,
+            now.saturating_sub(self.publish_time),
+            MoluscoError::OraclePriceStale
+        );
+        require_gte!(
+            (self.price as u128).saturating_mul(max_confidence_bps as u128),
+            self.conf.saturating_mul(BPS as u128),
+            MoluscoError::OraclePriceUncertain
+        );
+        Ok(())
+    }
+
+    /// The price with `PRICE_DECIMALS` decimals, rounded down
+    pub fn normalized(&self) -> Result<u128> {
+        require_gt!(self.price, 0, MoluscoError::OraclePriceInvalid);
+        let shift = PRICE_DECIMALS + self.exponent;
+        let scale = pow10(shift.unsigned_abs())?;
+        if shift < 0 {
+            Ok(self.price as u128 / scale)
+        } else {
+            (self.price as u128)
+                .checked_mul(scale)
+                .ok_or_else(|| error!(MoluscoError::MathOverflow))
+        }
+    }
+}
+
+fn pow10(exponent: u32) -> Result<u128> {
+    10u128
+        .checked_pow(exponent)
+        .ok_or_else(|| error!(MoluscoError::MathOverflow))
+}
+
+/// `amount` base units of a token with `decimals` and normalized price `price`, in base
+/// units of a token with `quote_decimals` and normalized price `quote_price`, rounded down
+pub fn convert(amount: u64, price: u128, decimals: u8, quote_price: u128, quote_decimals: u8) -> Result<u64> {
+    require_gt!(quote_price, 0, MoluscoError::OraclePriceInvalid);
+    let mut numerator = (amount as u128)
+        .checked_mul(price)
+        .ok_or(MoluscoError::MathOverflow)?;
+    let mut denominator = quote_price;
+    if quote_decimals >= decimals {
+        numerator = numerator
+            .checked_mul(pow10((quote_decimals - decimals) as u32)?)
+            .ok_or(MoluscoError::MathOverflow)?;
+    } else {
+        denominator = denominator
+            .checked_mul(pow10((decimals - quote_decimals) as u32)?)
+            .ok_or(MoluscoError::MathOverflow)?;
+    }
+    u64::try_from(numerator / denominator).map_err(|_| MoluscoError::MathOverflow.into())
+}
+
+fn read<const N: usize>(data: &[u8], at: &mut usize) -> Result<[u8; N]> {
+    let bytes = data
+        .get(*at..*at + N)
+        .ok_or_else(|| error!(MoluscoError::OracleAccountInvalid))?;
+    *at += N;
+    Ok(bytes.try_into().unwrap())
+}
+
+/// Feed id and price of a fully verified Pyth `PriceUpdateV2`, discriminator included
+pub fn parse_pyth_price_update(data: &[u8]) -> Result<([u8; 32], OraclePrice)> {
+    let mut at = 0;
+    let discriminator: [u8; 8] = read(data, &mut at)?;
+    require!(
+        discriminator[..] == hash(b"account:PriceUpdateV2").to_bytes()[..8],
+        MoluscoError::OracleAccountInvalid
+    );
+    at += 32; // write_authority
+    let [level] = read(data, &mut at)?;
+    require_eq!(level, VERIFICATION_FULL, MoluscoError::OracleAccountInvalid);
+
+    let feed_id = read(data, &mut at)?;
+    let price = OraclePrice {
+        price: i64::from_le_bytes(read(data, &mut at)?) as i128,
+        conf: u64::from_le_bytes(read(data, &mut at)?) as u128,
+        exponent: i32::from_le_bytes(read(data, &mut at)?),
+        publish_time: i64::from_le_bytes(read(data, &mut at)?),
+    };
+    Ok((feed_id, price))
+}
+
+/// Price for `feed_id` from a Pyth receiver account
+pub fn read_pyth_price(account: &AccountInfo, feed_id: &[u8; 32]) -> Result<OraclePrice> {
+    require_keys_eq!(*account.owner, PYTH_RECEIVER_PROGRAM_ID, MoluscoError::OracleAccountInvalid);
+    let (update_feed_id, price) = parse_pyth_price_update(&account.try_borrow_data()?)?;
+    require!(update_feed_id == *feed_id, MoluscoError::OracleFeedMismatch);
+    Ok(price)
+}
+
+/// Current result of a Switchboard on-demand `PullFeedAccountData`, discriminator included
+pub fn parse_switchboard_pull_feed(data: &[u8]) -> Result<OraclePrice> {
+    let mut at = 0;
+    let discriminator: [u8; 8] = read(data, &mut at)?;
+    require!(
+        discriminator[..] == hash(b"account:PullFeedAccountData").to_bytes()[..8],
+        MoluscoError::OracleAccountInvalid
+    );
+    let mut at = PULL_FEED_LAST_UPDATE;
+    let publish_time = i64::from_le_bytes(read(data, &mut at)?);
+    let mut at = PULL_FEED_RESULT;
+    let price = i128::from_le_bytes(read(data, &mut at)?);
+    let std_dev = i128::from_le_bytes(read(data, &mut at)?);
+    Ok(OraclePrice {
+        price,
+        conf: std_dev.unsigned_abs(),
+        exponent: SWITCHBOARD_EXPONENT,
+        publish_time,
+    })
+}
+
+/// Price from the Switchboard pull feed at `feed`
+pub fn read_switchboard_price(account: &AccountInfo, feed: &Pubkey) -> Result<OraclePrice> {
+    require_keys_eq!(account.key(), *feed, MoluscoError::OracleFeedMismatch);
+    require_keys_eq!(*account.owner, SWITCHBOARD_ON_DEMAND_PROGRAM_ID, MoluscoError::OracleAccountInvalid);
+    parse_switchboard_pull_feed(&account.try_borrow_data()?)
+}
+
+/// Normalized prices of the two tokens of a swap, with their decimals
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PricePair {
+    pub source: u128,
+    pub source_decimals: u8,
+    pub destination: u128,
+    pub destination_decimals: u8,
+}
+
+impl PricePair {
+    /// `amount` of the source token in destination base units, rounded down
+    pub fn destination_value(&self, amount: u64) -> Result<u64> {
+        convert(amount, self.source, self.source_decimals, self.destination, self.destination_decimals)
+    }
+
+    /// `amount` of the destination token in source base units, rounded down
+    pub fn source_value(&self, amount: u64) -> Result<u64> {
+        convert(amount, self.destination, self.destination_decimals, self.source, self.source_decimals)
+    }
+}
+
+/// Median of `values`, the mean of the middle two for an even count
+///
+/// Fails if any value is more than `max_divergence_bps` of the median away from it.
+pub fn median_within(values: &mut [u128], max_divergence_bps: u16) -> Result<u128> {
+    require!(!values.is_empty(), MoluscoError::OracleQuorumNotMet);
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    let median = if values.len() % 2 == 0 {
+        values[mid - 1] / 2 + values[mid] / 2 + (values[mid - 1] % 2 + values[mid] % 2) / 2
+    } else {
+        values[mid]
+    };
+    for &value in values.iter() {
+        require_gte!(
+            median.saturating_mul(max_divergence_bps as u128),
+            value.abs_diff(median).saturating_mul(BPS as u128),
+            MoluscoError::OracleSourcesDiverge
+        );
+    }
+    Ok(median)
+}
@@ -0,0 +1,160 @@
+use anchor_lang::prelude::*;
+
+use crate::decimals;
+use crate::MoluscoError;
+
+/// Virtual shares/assets added to both sides of every conversion.
+///
+/// The offset makes the empty-vault price well defined and means a donation
+/// has to be ~`VIRTUAL_SHARES` times larger than a victim's deposit to round
+/// it down to zero, which kills the first-depositor inflation attack.
+pub const VIRTUAL_SHARES: u128 = 1_000;
+pub const VIRTUAL_ASSETS: u128 = 1;
+
+/// Smallest first deposit accepted into an empty vault
+pub const MIN_INITIAL_DEPOSIT: u64 = 1_000_000;
+
+/// Shares permanently burned from the first deposit so supply never returns to zero
+pub const DEAD_SHARES: u64 = 1_000;
+
+/// Shares whose value is recorded as the vault's share price
+pub const PRICE_SHARES: u64 = 1_000_000_000_000;
+
+/// Rounding direction for a share/asset conversion
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// `value * numerator / denominator` with explicit rounding
+pub fn mul_div(value: u64, numerator: u128, denominator: u128, rounding: Rounding) -> Result<u64> {
+    let product = (value as u128)
+        .checked_mul(numerator)
+        .ok_or(MoluscoError::MathOverflow)?;
+    let mut quotient = product / denominator;
+    if rounding == Rounding::Up && product % denominator != 0 {
+        quotient += 1;
+    }
+    u64::try_from(quotient).map_err(|_| MoluscoError::MathOverflow.into())
+}
+
+/// Shares worth `assets` at the current price
+pub fn to_shares(assets: u64, total_assets: u64, total_shares: u64, rounding: Rounding) -> Result<u64> {
+    mul_div(
+        assets,
+        total_shares as u128 + VIRTUAL_SHARES,
+        total_assets as u128 + VIRTUAL_ASSETS,
+        rounding,
+    )
+}
+
+/// Assets worth `shares` at the current price
+pub fn to_assets(shares: u64, total_assets: u64, total_shares: u64, rounding: Rounding) -> Result<u64> {
+    mul_div(
+        shares,
+        total_assets as u128 + VIRTUAL_ASSETS,
+        total_shares as u128 + VIRTUAL_SHARES,
+        rounding,
+    )
+}
+
+// Each operation rounds in the vault's favor, so no sequence of calls can extract
+// more assets than were put in. Only the direction the user pays is rounded up.
+
+/// Shares minted for depositing `assets` (rounded down: user gets no more than paid for)
+pub fn shares_for_deposit(assets: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+    to_shares(assets, total_assets, total_shares, Rounding::Down)
+}
+
+/// Assets required to mint exactly `shares` (rounded up)
+pub fn assets_for_mint(shares: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+    to_assets(shares, total_assets, total_shares, Rounding::Up)
+}
+
+/// Shares burned to withdraw exactly `assets` (rounded up)
+pub fn shares_for_withdraw(assets: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+    to_shares(assets, total_assets, total_shares, Rounding::Up)
+}
+
+/// Assets paid out for redeeming `shares` (rounded down)
+pub fn assets_for_redeem(shares: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+    to_assets(shares, total_assets, total_shares, Rounding::Down)
+}
+
+/// Assets that `PRICE_SHARES` shares are worth, rounded down
+pub fn share_price(total_assets: u64, total_shares: u64) -> Result<u64> {
+    to_assets(PRICE_SHARES, total_assets, total_shares, Rounding::Down)
+}
+
+/// Shares minted for `assets` deposited at the current price, rounded down
+pub fn convert_to_shares(assets: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+    shares_for_deposit(assets, total_assets, total_shares)
+}
+
+/// Assets redeemable for `shares` at the current price, rounded down
+pub fn convert_to_assets(shares: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+    assets_for_redeem(shares, total_assets, total_shares)
+}
+
+/// Split the first deposit into an empty vault into `(depositor_shares, dead_shares)`
+pub fn initial_deposit_shares(assets: u64) -> Result<(u64, u64)> {
+    require!(assets >= MIN_INITIAL_DEPOSIT, MoluscoError::InitialDepositTooSmall);
+
+    let shares = convert_to_shares(assets, 0, 0)?;
+    let depositor_shares = shares
+        .checked_sub(DEAD_SHARES)
+        .ok_or(MoluscoError::InitialDepositTooSmall)?;
+    Ok((depositor_shares, DEAD_SHARES))
+}
+
+/// Decimals of a vault's asset and of its share mint
+///
+/// Share math runs on assets restated at the share mint's decimals, so the
+/// first-deposit price is one share per whole token whatever either mint's decimals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShareScale {
+    pub asset_decimals: u8,
+    pub share_decimals: u8,
+}
+
+impl ShareScale {
+    /// `assets` at the share mint's decimals
+    pub fn to_share_units(&self, assets: u64, rounding: Rounding) -> Result<u64> {
+        decimals::convert_decimals(assets, self.asset_decimals, self.share_decimals, rounding)
+    }
+
+    /// An amount at the share mint's decimals back in asset base units
+    pub fn to_asset_units(&self, amount: u64, rounding: Rounding) -> Result<u64> {
+        decimals::convert_decimals(amount, self.share_decimals, self.asset_decimals, rounding)
+    }
+
+    /// `shares_for_deposit` across decimals; TVL rounds up so the depositor gets no extra
+    pub fn shares_for_deposit(&self, assets: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+        shares_for_deposit(
+            self.to_share_units(assets, Rounding::Down)?,
+            self.to_share_units(total_assets, Rounding::Up)?,
+            total_shares,
+        )
+    }
+
+    /// `shares_for_withdraw` across decimals
+    pub fn shares_for_withdraw(&self, assets: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+        shares_for_withdraw(
+            self.to_share_units(assets, Rounding::Up)?,
+            self.to_share_units(total_assets, Rounding::Down)?,
+            total_shares,
+        )
+    }
+
+    /// `assets_for_redeem` across decimals
+    pub fn assets_for_redeem(&self, shares: u64, total_assets: u64, total_shares: u64) -> Result<u64> {
+        let amount = assets_for_redeem(shares, self.to_share_units(total_assets, Rounding::Down)?, total_shares)?;
+        self.to_asset_units(amount, Rounding::Down)
+    }
+
+    /// `initial_deposit_shares` across decimals
+    pub fn initial_deposit_shares(&self, assets: u64) -> Result<(u64, u64)> {
+        initial_deposit_shares(self.to_share_units(assets, Rounding::Down)?)
+    }
+}
@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+/// Fixed-point scale applied to basis-point observations
+pub const STAT_SCALE: i128 = 1_000_000;
+
+/// Welford running mean/variance over per-period returns (in basis points).
+///
+/// Values are stored scaled by `STAT_SCALE` so the streaming update stays in
+/// integer math without losing the sub-bps precision that small periods need.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct RunningStats {
+    pub count: u64,
+    pub mean: i64,
+    pub m2: u128,
+}
+
+impl RunningStats {
+    pub const SIZE: usize = 8 + 8 + 16;
+
+    pub fn push(&mut self, return_bps: i64) {
+        let x = return_bps as i128 * STAT_SCALE;
+        self.count += 1;
+        let delta = x - self.mean as i128;
+        let mean = self.mean as i128 + delta / self.count as i128;
+        let delta2 = x - mean;
+        self.mean = mean as i64;
+        self.m2 = self.m2.saturating_add((delta * delta2).max(0) as u128);
+    }
+
+    /// Sample standard deviation of period returns, in bps scaled by `STAT_SCALE`
+    pub fn std_dev(&self) -> u64 {
+        if self.count < 2 {
+            return 0;
+        }
+        isqrt(self.m2 / (self.count as u128 - 1)) as u64
+    }
+
+    /// Mean over standard deviation, scaled by 10_000 (0 without enough samples)
+    pub fn sharpe_proxy(&self) -> i64 {
+        let sd = self.std_dev();
+        if sd == 0 {
+            return 0;
+        }
+        (self.mean as i128 * 10_000 / sd as i128) as i64
+    }
+}
+
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = (x + 1) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
@@ -0,0 +1,42 @@
+//! Interface for third-party strategy programs invoked by a vault via CPI.
+//!
+//! A strategy program exposes one instruction, `strategy_execute`, whose data
+//! is the 8-byte discriminator followed by `amount_in: u64`,
+//! `min_amount_out: u64` and an opaque `params` blob (u32 length prefix).
+//!
+//! Accounts are passed in this order:
+//! 0. vault PDA (signer): token authority for the two accounts below
+//! 1. source token account owned by the vault (writable)
+//! 2. destination token account owned by the vault (writable)
+//! 3. .. strategy-specific accounts, forwarded from `remaining_accounts`
+//!
+//! Before returning, the strategy must set a borsh-encoded `AdapterResult`
+//! as return data. The vault validates it against its own constraints.
+
+use anchor_lang::prelude::*;
+
+use crate::adapters::{discriminator, invoke_with_result, AdapterResult};
+
+/// Instruction name every strategy program must implement
+pub const STRATEGY_EXECUTE: &str = "strategy_execute";
+
+/// Instruction data for `strategy_execute`
+pub fn execute_data(amount_in: u64, min_amount_out: u64, params: &[u8]) -> Vec<u8> {
+    let mut data = discriminator(STRATEGY_EXECUTE).to_vec();
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&min_amount_out.to_le_bytes());
+    data.extend_from_slice(&(params.len() as u32).to_le_bytes());
+    data.extend_from_slice(params);
+    data
+}
+
+/// CPI into a strategy program with the vault as signer and decode its result
+pub fn invoke_strategy<'info>(
+    strategy_program: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+    vault: &Pubkey,
+    data: Vec<u8>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<AdapterResult> {
+    invoke_with_result(strategy_program, accounts, vault, data, signer_seeds)
+}
@@ -0,0 +1,25 @@
+/// Allocate `available` liquidity across FIFO withdrawal requests.
+///
+/// `remaining` holds the outstanding amount of each queued request in FIFO
+/// order. Requests are filled in order; the first one that cannot be covered
+/// in full is filled partially and the rest wait, so one large request never
+/// stalls liquidity that could settle it piece by piece.
+///
+/// Returns the fill for each request (same length as `remaining`).
+pub fn allocate_fills(remaining: &[u64], available: u64) -> Vec<u64> {
+    let mut left = available;
+    remaining
+        .iter()
+        .map(|outstanding| {
+            let fill = (*outstanding).min(left);
+            left -= fill;
+            fill
+        })
+        .collect()
+}
+
+/// Outstanding amount after applying `fill`, and whether the request is fully settled
+pub fn apply_fill(outstanding: u64, fill: u64) -> (u64, bool) {
+    let left = outstanding.saturating_sub(fill);
+    (left, left == 0)
+}
@@ -15,7 +15,9 @@ mod tests {
         // - owner = signer
         // - agent_name = provided name
         // - total_value_locked = 0
-        // - position_count = 0
+        // - active_positions = 0
+        // - total_positions_created = 0
+        // - simulation = flag passed at init (shadow vaults skip adapter CPIs)
         
         assert_eq!(agent_name, "molusco-test");
     }
@@ -43,4 +45,1078 @@ mod tests {
         
         assert_eq!(yield_earned, 50_000_000); // 0.05 SOL yield
     }
+
+    #[test]
+    fn test_verify_merkle_proof() {
+        use anchor_lang::solana_program::keccak;
+        use moluscoyield::verify_merkle_proof;
+
+        let leaf_a = keccak::hashv(&[Pubkey::new_unique().as_ref(), &100u64.to_le_bytes()]).0;
+        let leaf_b = keccak::hashv(&[Pubkey::new_unique().as_ref(), &250u64.to_le_bytes()]).0;
+        let root = if leaf_a <= leaf_b {
+            keccak::hashv(&[&leaf_a, &leaf_b]).0
+        } else {
+            keccak::hashv(&[&leaf_b, &leaf_a]).0
+        };
+
+        assert!(verify_merkle_proof(&[leaf_b], root, leaf_a));
+        assert!(verify_merkle_proof(&[leaf_a], root, leaf_b));
+        assert!(!verify_merkle_proof(&[leaf_a], root, leaf_a));
+    }
+
+    #[test]
+    fn test_share_inflation_attack_blocked() {
+        use moluscoyield::shares::*;
+
+        // Attacker seeds the vault, then donates 10 SOL directly to inflate the price
+        let (attacker_shares, dead) = initial_deposit_shares(MIN_INITIAL_DEPOSIT).unwrap();
+        let total_shares = attacker_shares + dead;
+        let total_assets = MIN_INITIAL_DEPOSIT + 10_000_000_000;
+
+        // A 1 SOL victim deposit must still mint a meaningful number of shares
+        let victim_shares = convert_to_shares(1_000_000_000, total_assets, total_shares).unwrap();
+        assert!(victim_shares > 0);
+
+        let victim_assets = convert_to_assets(
+            victim_shares,
+            total_assets + 1_000_000_000,
+            total_shares + victim_shares,
+        )
+        .unwrap();
+        assert!(victim_assets > 900_000_000);
+    }
+
+    #[test]
+    fn test_initial_deposit_burns_dead_shares() {
+        use moluscoyield::shares::*;
+
+        assert!(initial_deposit_shares(MIN_INITIAL_DEPOSIT - 1).is_err());
+        let (shares, dead) = initial_deposit_shares(MIN_INITIAL_DEPOSIT).unwrap();
+        assert_eq!(dead, DEAD_SHARES);
+        assert_eq!(shares + dead, convert_to_shares(MIN_INITIAL_DEPOSIT, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_exit_fee_decays_with_holding_time() {
+        use moluscoyield::fees::ExitFeeSchedule;
+
+        let week = 7 * 86_400;
+        let schedule = ExitFeeSchedule {
+            full_fee_bps: 100,
+            full_fee_secs: week,
+            zero_fee_secs: 4 * week,
+        };
+        assert!(schedule.validate().is_ok());
+
+        assert_eq!(schedule.rate_bps(0), 100);
+        assert_eq!(schedule.rate_bps(week + week * 3 / 2), 50);
+        assert_eq!(schedule.rate_bps(4 * week), 0);
+
+        // 1 SOL held one day pays 1%, 1 SOL held two months pays nothing
+        let now = 10 * week;
+        let lots = [(1_000_000_000, now - 86_400), (1_000_000_000, now - 8 * week)];
+        assert_eq!(schedule.fee_for_lots(&lots, now).unwrap(), 10_000_000);
+    }
+
+    #[test]
+    fn test_performance_fee_respects_high_water_mark() {
+        use moluscoyield::fees::above_high_water_mark;
+
+        // First 1_000 of profit is all new
+        assert_eq!(above_high_water_mark(1_000, 0, 1_000), 1_000);
+        // A 600 loss drops net profit to 400; the next 500 of yield only climbs
+        // back to 900, still under the mark
+        assert_eq!(above_high_water_mark(1_500 - 600, 1_000, 500), 0);
+        // Another 600 lifts net profit to 1_500: only the 500 above the mark pays
+        assert_eq!(above_high_water_mark(2_100 - 600, 1_000, 600), 500);
+    }
+
+    #[test]
+    fn test_profit_share_split_keeps_dust_with_operator() {
+        use moluscoyield::fees::{split_performance_fee, MAX_REBATE_SHARE_BPS};
+
+        assert_eq!(split_performance_fee(1_000, 0), (1_000, 0));
+        assert_eq!(split_performance_fee(1_000, 2_500), (750, 250));
+        assert_eq!(split_performance_fee(999, MAX_REBATE_SHARE_BPS), (500, 499));
+    }
+
+    #[test]
+    fn test_unwind_plan_follows_priority() {
+        use moluscoyield::unwind_plan;
+
+        let cheap = Pubkey::new_unique();
+        let costly = Pubkey::new_unique();
+        let locked = Pubkey::new_unique();
+        let legs = [(locked, 9, 500), (costly, 5, 300), (cheap, 0, 200)];
+
+        let plan = unwind_plan(&legs, 400);
+        assert_eq!(plan, vec![(cheap, 200), (costly, 200)]);
+    }
+
+    #[test]
+    fn test_withdrawal_partial_fills() {
+        use moluscoyield::withdrawals::{allocate_fills, apply_fill};
+
+        // 250 of liquidity against requests of 100, 300, 50: second is partially filled
+        let fills = allocate_fills(&[100, 300, 50], 250);
+        assert_eq!(fills, vec![100, 150, 0]);
+
+        assert_eq!(apply_fill(100, 100), (0, true));
+        assert_eq!(apply_fill(300, 150), (150, false));
+    }
+
+    #[test]
+    fn test_annualized_return_between_checkpoints() {
+        use moluscoyield::{annualized_return_bps, SECONDS_PER_YEAR};
+
+        // 2% over a quarter annualizes to 8%
+        let quarter = SECONDS_PER_YEAR / 4;
+        assert_eq!(annualized_return_bps(1_000_000_000, 1_020_000_000, quarter), 800);
+        assert_eq!(annualized_return_bps(1_000_000_000, 990_000_000, SECONDS_PER_YEAR), -100);
+        assert_eq!(annualized_return_bps(0, 1_000, quarter), 0);
+    }
+
+    #[test]
+    fn test_running_return_stats() {
+        use moluscoyield::stats::{RunningStats, STAT_SCALE};
+
+        let mut stats = RunningStats::default();
+        for r in [10, 20, 30, 40] {
+            stats.push(r);
+        }
+
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.mean as i128, 25 * STAT_SCALE);
+        // Sample std dev of 10,20,30,40 is ~12.9099 bps
+        assert_eq!(stats.std_dev() / 1_000, 12_909);
+        assert!(stats.sharpe_proxy() > 19_000 && stats.sharpe_proxy() < 20_000);
+    }
+
+    #[test]
+    fn test_adapter_result_validation() {
+        use moluscoyield::adapters::{implied_rate, AdapterResult, RATE_SCALE};
+
+        // 1_000 in for 995 out is a 0.995 rate
+        let result = AdapterResult {
+            amount_in: 1_000,
+            amount_out: 995,
+            fee_paid: 5,
+            rate: implied_rate(1_000, 995),
+        };
+        assert_eq!(result.rate, 995 * RATE_SCALE / 1_000);
+        assert!(result.validate(1_000, 990).is_ok());
+
+        // Overspending, filling under the floor, or a mismatched rate are all rejected
+        assert!(result.validate(999, 990).is_err());
+        assert!(result.validate(1_000, 996).is_err());
+        let lying = AdapterResult { rate: RATE_SCALE, ..result };
+        assert!(lying.validate(1_000, 990).is_err());
+    }
+
+    #[test]
+    fn test_balance_delta_guard() {
+        use moluscoyield::adapters::check_balance_delta;
+
+        // Booked outflow of 500 matches; one unit of rounding dust is tolerated
+        assert!(check_balance_delta(1_000, 500, -500).is_ok());
+        assert!(check_balance_delta(1_000, 499, -500).is_ok());
+        assert!(check_balance_delta(0, 300, 300).is_ok());
+
+        // An adapter that takes more, or pays less, than it reports is caught
+        assert!(check_balance_delta(1_000, 400, -500).is_err());
+        assert!(check_balance_delta(0, 250, 300).is_err());
+    }
+
+    #[test]
+    fn test_batch_rejects_duplicate_accounts() {
+        use moluscoyield::has_duplicate_keys;
+
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+
+        // Passing the same position twice would double-count its yield or payout
+        assert!(!has_duplicate_keys(&[a, b]));
+        assert!(has_duplicate_keys(&[a, b, a]));
+        assert!(!has_duplicate_keys(&[]));
+    }
+
+    #[test]
+    fn test_reentrant_cpi_blocked() {
+        use moluscoyield::adapters::ReentrancyGuard;
+
+        // A nested call while the vault is locked for a CPI must fail
+        let mut guard = ReentrancyGuard::default();
+        assert!(guard.acquire().is_ok());
+        assert!(guard.acquire().is_err());
+
+        // Once the CPI returns the vault is usable again
+        guard.release();
+        assert!(guard.acquire().is_ok());
+    }
+
+    #[test]
+    fn test_share_rounding_favors_vault() {
+        use moluscoyield::shares::{
+            assets_for_mint, assets_for_redeem, shares_for_deposit, shares_for_withdraw, to_assets,
+            to_shares, Rounding,
+        };
+
+        // Awkward prices (including more assets than shares and vice versa) over small amounts
+        for (total_assets, total_shares) in [(0, 0), (1, 1), (7, 3), (3, 7), (1_000_003, 999_983)] {
+            for amount in 0..200u64 {
+                let down = to_shares(amount, total_assets, total_shares, Rounding::Down).unwrap();
+                let up = to_shares(amount, total_assets, total_shares, Rounding::Up).unwrap();
+                assert!(up == down || up == down + 1);
+                let down = to_assets(amount, total_assets, total_shares, Rounding::Down).unwrap();
+                let up = to_assets(amount, total_assets, total_shares, Rounding::Up).unwrap();
+                assert!(up == down || up == down + 1);
+
+                // Deposit then redeem never returns more than was deposited
+                let minted = shares_for_deposit(amount, total_assets, total_shares).unwrap();
+                assert!(assets_for_redeem(minted, total_assets, total_shares).unwrap() <= amount);
+
+                // Minting `amount` shares costs at least what redeeming them pays
+                let cost = assets_for_mint(amount, total_assets, total_shares).unwrap();
+                assert!(cost >= assets_for_redeem(amount, total_assets, total_shares).unwrap());
+
+                // Withdrawing `amount` assets burns at least the shares a deposit of it would mint
+                let burned = shares_for_withdraw(amount, total_assets, total_shares).unwrap();
+                assert!(burned >= minted);
+                assert!(assets_for_redeem(burned, total_assets, total_shares).unwrap() >= amount);
+            }
+        }
+    }
+
+    #[test]
+    fn test_share_rounding_exact_and_overflow() {
+        use moluscoyield::shares::{mul_div, Rounding};
+
+        // Exact divisions are identical in both directions
+        assert_eq!(mul_div(10, 3, 5, Rounding::Down).unwrap(), 6);
+        assert_eq!(mul_div(10, 3, 5, Rounding::Up).unwrap(), 6);
+        assert_eq!(mul_div(10, 3, 4, Rounding::Down).unwrap(), 7);
+        assert_eq!(mul_div(10, 3, 4, Rounding::Up).unwrap(), 8);
+        assert_eq!(mul_div(0, 3, 4, Rounding::Up).unwrap(), 0);
+
+        // Results that do not fit in u64 error instead of truncating
+        assert!(mul_div(u64::MAX, 2, 1, Rounding::Down).is_err());
+        assert_eq!(mul_div(u64::MAX, 1, 1, Rounding::Up).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn test_decimal_normalization_round_trips() {
+        use moluscoyield::decimals::{convert_decimals, denormalize, normalize, INTERNAL_DECIMALS};
+        use moluscoyield::shares::Rounding;
+
+        // USDC (6), SOL (9) and an 18-decimal token all round-trip losslessly
+        for decimals in [0u8, 6, 9, INTERNAL_DECIMALS] {
+            for amount in [0u64, 1, 999_999, 1_000_000, u64::MAX] {
+                let value = normalize(amount, decimals).unwrap();
+                assert_eq!(denormalize(value, decimals, Rounding::Down).unwrap(), amount);
+                assert_eq!(denormalize(value, decimals, Rounding::Up).unwrap(), amount);
+            }
+        }
+
+        // 1 USDC and 1 SOL have the same internal weight
+        assert_eq!(normalize(1_000_000, 6).unwrap(), normalize(1_000_000_000, 9).unwrap());
+
+        // Going to fewer decimals drops dust in the requested direction
+        assert_eq!(convert_decimals(1_234_567_891, 9, 6, Rounding::Down).unwrap(), 1_234_567);
+        assert_eq!(convert_decimals(1_234_567_891, 9, 6, Rounding::Up).unwrap(), 1_234_568);
+        assert_eq!(convert_decimals(1_234_567, 6, 9, Rounding::Down).unwrap(), 1_234_567_000);
+
+        assert!(normalize(1, INTERNAL_DECIMALS + 1).is_err());
+    }
+
+    #[test]
+    fn test_depositor_yield_is_pro_rata() {
+        use moluscoyield::shares::shares_for_deposit;
+        use moluscoyield::DepositorRecord;
+
+        let blank = || DepositorRecord {
+            vault: Pubkey::default(),
+            depositor: Pubkey::default(),
+            principal: 0,
+            shares: 0,
+            first_deposit_at: 0,
+            last_deposit_at: 0,
+            deposited: 0,
+            withdrawn: 0,
+            fees_paid: 0,
+            reward_stake: 0,
+            reward_debt: 0,
+            rewards_owed: 0,
+            lots: Default::default(),
+            bump: 0,
+        };
+        let (mut alice, mut bob) = (blank(), blank());
+
+        // Alice deposits 3x what Bob does, into the same vault
+        let mut tvl = 0u64;
+        let mut supply = 0u64;
+        for (record, assets) in [(&mut alice, 3_000_000u64), (&mut bob, 1_000_000u64)] {
+            let shares = shares_for_deposit(assets, tvl, supply).unwrap();
+            record.credit(Pubkey::default(), Pubkey::default(), assets, shares, 100, 255).unwrap();
+            tvl += assets;
+            supply += shares;
+        }
+
+        // 400k of yield lands in TVL and splits 3:1
+        tvl += 400_000;
+        let alice_yield = alice.unrealized_yield(tvl, supply).unwrap();
+        let bob_yield = bob.unrealized_yield(tvl, supply).unwrap();
+        assert!(alice_yield.abs_diff(300_000) <= 1);
+        assert!(bob_yield.abs_diff(100_000) <= 1);
+
+        // Burning half of Bob's shares releases half of his principal
+        let half = bob.shares / 2;
+        bob.debit(half).unwrap();
+        assert_eq!(bob.principal, 500_000);
+        assert!(bob.debit(bob.shares + 1).is_err());
+
+        // Bob sends half of what is left to Carol, who never deposited
+        let sent = bob.shares / 2;
+        bob.sync(Pubkey::default(), Pubkey::default(), bob.shares - sent, 200, 255).unwrap();
+        assert_eq!(bob.principal, 250_000);
+        let mut carol = blank();
+        carol.sync(Pubkey::default(), Pubkey::default(), sent, 300, 254).unwrap();
+        assert_eq!((carol.shares, carol.principal, carol.last_deposit_at), (sent, 0, 300));
+        carol.debit(sent).unwrap();
+    }
+
+    #[test]
+    fn test_delegate_permissions_are_scoped() {
+        use moluscoyield::{Delegate, DelegateAction};
+
+        // A marking bot that may update and rebalance but never touch capital
+        let bot = Delegate {
+            vault: Pubkey::new_unique(),
+            delegate: Pubkey::new_unique(),
+            can_update: true,
+            can_rebalance: true,
+            can_open: false,
+            can_close: false,
+            granted_at: 0,
+            bump: 255,
+        };
+
+        assert!(bot.allows(DelegateAction::Update));
+        assert!(bot.allows(DelegateAction::Rebalance));
+        assert!(!bot.allows(DelegateAction::Open));
+        assert!(!bot.allows(DelegateAction::Close));
+    }
+
+    #[test]
+    fn test_session_expires_and_never_moves_capital() {
+        use moluscoyield::{DelegateAction, Session, SessionScope};
+
+        let session = Session {
+            vault: Pubkey::new_unique(),
+            session_key: Pubkey::new_unique(),
+            scope: SessionScope {
+                update: true,
+                rebalance: false,
+            },
+            created_at: 1_000,
+            expires_at: 4_600,
+            bump: 255,
+        };
+
+        assert!(session.is_live(4_599));
+        assert!(!session.is_live(4_600));
+        assert!(session.allows(DelegateAction::Update));
+        assert!(!session.allows(DelegateAction::Rebalance));
+        assert!(!session.allows(DelegateAction::Open));
+        assert!(!session.allows(DelegateAction::Close));
+    }
+
+    #[test]
+    fn test_error_categories() {
+        use moluscoyield::{ErrorCategory, MoluscoError};
+
+        assert_eq!(MoluscoError::InvalidAmount.category(), ErrorCategory::Validation);
+        assert_eq!(MoluscoError::SessionExpired.category(), ErrorCategory::Authorization);
+        assert_eq!(MoluscoError::TvlUnderflow.category(), ErrorCategory::Math);
+        assert_eq!(MoluscoError::AdapterOracleStale.category(), ErrorCategory::Oracle);
+        assert_eq!(MoluscoError::ReentrancyDetected.category(), ErrorCategory::Adapter);
+        assert_eq!(MoluscoError::WithdrawalsPaused.category(), ErrorCategory::State);
+    }
+
+    #[test]
+    fn test_idempotency_ring_rejects_recent_duplicates() {
+        use moluscoyield::{IdempotencyRing, IDEMPOTENCY_RING_SIZE};
+
+        let mut ring = IdempotencyRing::default();
+        assert!(ring.consume(None).is_ok());
+        assert!(ring.consume(Some([0; 16])).is_err());
+
+        assert!(ring.consume(Some([1; 16])).is_ok());
+        assert!(ring.consume(Some([1; 16])).is_err());
+
+        // Once evicted by newer keys, an old key is accepted again
+        for k in 2..=IDEMPOTENCY_RING_SIZE as u8 + 1 {
+            assert!(ring.consume(Some([k; 16])).is_ok());
+        }
+        assert!(ring.consume(Some([1; 16])).is_ok());
+    }
+
+    #[test]
+    fn test_protocol_registry_whitelist() {
+        use moluscoyield::{ProtocolKind, ProtocolRegistry, MAX_WHITELISTED_PROTOCOLS};
+
+        let mut registry = ProtocolRegistry {
+            protocols: Vec::new(),
+            bump: 255,
+        };
+        assert!(!registry.is_listed(ProtocolKind::Kamino));
+
+        assert!(registry.add(ProtocolKind::Kamino).is_ok());
+        assert!(registry.is_listed(ProtocolKind::Kamino));
+        assert!(registry.add(ProtocolKind::Kamino).is_err());
+
+        assert!(registry.remove(ProtocolKind::Kamino).is_ok());
+        assert!(!registry.is_listed(ProtocolKind::Kamino));
+        assert!(registry.remove(ProtocolKind::Kamino).is_err());
+
+        for code in 0..MAX_WHITELISTED_PROTOCOLS as u16 {
+            assert!(registry.add(ProtocolKind::Other(code)).is_ok());
+        }
+        assert!(registry.add(ProtocolKind::Drift).is_err());
+    }
+
+    #[test]
+    fn test_kind_seeds_are_fixed_width_and_distinct() {
+        use moluscoyield::{AssetKind, ProtocolKind, KIND_SIZE};
+
+        assert_eq!(ProtocolKind::Other(0).seed(), [0, 0, 0]);
+        assert_eq!(ProtocolKind::Kamino.seed(), [1, 0, 0]);
+        assert_eq!(ProtocolKind::Other(u16::MAX).seed(), [0, 0xff, 0xff]);
+        assert_ne!(ProtocolKind::Other(1).seed(), ProtocolKind::Kamino.seed());
+        assert_eq!(AssetKind::Usdc.seed().len(), KIND_SIZE);
+    }
+
+    #[test]
+    fn test_strategy_capacity_caps_aggregate_principal() {
+        use moluscoyield::{StrategyCapacity, StrategyKind};
+
+        let mut capacity = StrategyCapacity {
+            strategy: StrategyKind::Lending,
+            max_tvl: 1_000,
+            deployed: 0,
+            bump: 255,
+        };
+        assert!(capacity.reserve(600).is_ok());
+        assert!(capacity.reserve(400).is_ok());
+        assert!(capacity.reserve(1).is_err());
+        assert_eq!(capacity.deployed, 1_000);
+
+        // Closing a position that compounded past its reservation cannot underflow
+        capacity.release(1_200);
+        assert_eq!(capacity.deployed, 0);
+
+        capacity.max_tvl = 0;
+        assert!(capacity.reserve(u64::MAX as u128).is_ok());
+
+        // Capacity is counted in normalized units, so a 6-decimal and a 9-decimal
+        // position of one whole token each use the same capacity
+        let usdc = moluscoyield::decimals::normalize(1_000_000, 6).unwrap();
+        let sol = moluscoyield::decimals::normalize(1_000_000_000, 9).unwrap();
+        capacity.deployed = 0;
+        capacity.max_tvl = 2 * usdc;
+        assert!(capacity.reserve(usdc).is_ok());
+        assert!(capacity.reserve(sol).is_ok());
+        assert!(capacity.reserve(1).is_err());
+    }
+
+    #[test]
+    fn test_agent_profile_lists_each_vault_once() {
+        use moluscoyield::{AgentProfile, MAX_PROFILE_VAULTS};
+
+        let mut profile = AgentProfile {
+            operator: Pubkey::new_unique(),
+            vaults: Vec::new(),
+            bump: 255,
+        };
+        let vault = Pubkey::new_unique();
+        assert!(profile.add(vault).is_ok());
+        assert!(profile.add(vault).is_err());
+
+        while profile.vaults.len() < MAX_PROFILE_VAULTS {
+            profile.add(Pubkey::new_unique()).unwrap();
+        }
+        assert!(profile.add(Pubkey::new_unique()).is_err());
+
+        assert!(profile.remove(vault).is_ok());
+        assert!(profile.remove(vault).is_err());
+        assert_eq!(profile.vaults.len(), MAX_PROFILE_VAULTS - 1);
+    }
+
+    #[test]
+    fn test_position_layout_upgrade_widens_yield_in_place() {
+        use moluscoyield::migration::{self, LAYOUT_VERSION};
+        use moluscoyield::Position;
+
+        // Discriminator, owner, vault, then Other(7), a one-byte strategy and a one-byte asset
+        let mut data = vec![0u8; 8 + 64];
+        data.extend_from_slice(&[0, 7, 0, 1, 2]);
+        // amount, target_apy, opened_at, then last_update and is_active
+        data.extend_from_slice(&[0u8; 18]);
+        data.extend_from_slice(&7i64.to_le_bytes());
+        let is_active = data.len();
+        data.push(1);
+        data.extend_from_slice(&42u64.to_le_bytes());
+        data.extend_from_slice(&[0u8; 127]);
+        let bump = data.len();
+        data.push(254);
+        data.resize(migration::POSITION_V1_LEN, 0);
+
+        migration::upgrade_position(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + Position::SIZE);
+        assert_eq!(data[is_active - 8..is_active], 7i64.to_le_bytes());
+        // A zero `last_update_slot` comes in ahead of `is_active`
+        assert_eq!(data[is_active..is_active + 8], [0u8; 8]);
+        assert_eq!(data[is_active + 8], 1);
+        let accumulated_yield = is_active + 9;
+        assert_eq!(
+            data[accumulated_yield..accumulated_yield + 16],
+            42u128.to_le_bytes()
+        );
+        // Fields added after the yield start at zero too
+        let growth = data.len() - migration::POSITION_V1_LEN;
+        assert!(data[accumulated_yield + 16..bump + growth - 1]
+            .iter()
+            .all(|&b| b == 0));
+        assert_eq!(data[bump + growth - 1], LAYOUT_VERSION);
+        assert_eq!(data[bump + growth], 254);
+    }
+
+    #[test]
+    fn test_vault_layout_upgrade_round_trips_a_layout_1_vault() {
+        use moluscoyield::adapters::ReentrancyGuard;
+        use moluscoyield::fees::ExitFeeSchedule;
+        use moluscoyield::migration::{self, LAYOUT_VERSION};
+        use moluscoyield::stats::RunningStats;
+        use moluscoyield::{IdempotencyRing, Vault};
+
+        // The vault as layout 1 serialized it
+        #[derive(AnchorSerialize, Default)]
+        struct VaultV1 {
+            owner: Pubkey,
+            agent_name: String,
+            total_value_locked: u64,
+            active_positions: u16,
+            total_positions_created: u64,
+            created_at: i64,
+            last_rebalance: i64,
+            template: Pubkey,
+            performance_fee_bps: u16,
+            management_fee_bps: u16,
+            deposit_cap: u64,
+            max_positions: u16,
+            min_position_amount: u64,
+            reward_adapter: Pubkey,
+            reward_haircut_bps: u16,
+            pending_rewards_value: u64,
+            nav: u64,
+            last_mark: i64,
+            smoothing_threshold_bps: u16,
+            smoothing_release_secs: i64,
+            smoothing_reserve: u64,
+            smoothed_nav: u64,
+            last_checkpoint_nav: u64,
+            last_checkpoint_ts: i64,
+            checkpoint_count: u64,
+            exit_fee_schedule: ExitFeeSchedule,
+            total_impaired: u64,
+            strategist: Pubkey,
+            slippage_budget_per_epoch: u64,
+            slippage_epoch: u64,
+            slippage_spent: u64,
+            next_order_id: u64,
+            deposits_paused: bool,
+            withdrawals_paused: bool,
+            trading_paused: bool,
+            deposit_pause_authority: Pubkey,
+            withdrawal_pause_authority: Pubkey,
+            trading_pause_authority: Pubkey,
+            simulation: bool,
+            delegate: Pubkey,
+            cloned_from: Pubkey,
+            cumulative_inflows: u128,
+            cumulative_outflows: u128,
+            cumulative_yield: u128,
+            cumulative_losses: u128,
+            cumulative_fees: u128,
+            return_stats: RunningStats,
+            benchmark: Pubkey,
+            operating_costs: u64,
+            last_checkpoint_costs: u64,
+            cosigners: Vec<Pubkey>,
+            cosign_threshold: u8,
+            large_withdrawal_limit: u64,
+            destination_allowlist: Pubkey,
+            velocity_limit_bps: u16,
+            velocity_window_start: i64,
+            velocity_window_tvl: u64,
+            velocity_window_outflow: u64,
+            guardian: Pubkey,
+            viewer: Pubkey,
+            cpi_guard: ReentrancyGuard,
+            bundle_only: bool,
+            max_bundle_tip_lamports: u64,
+            mint: Pubkey,
+            escrow: Pubkey,
+            audit_mode: bool,
+            share_mint: Pubkey,
+            dead_shares: u64,
+            withdrawal_epoch: u64,
+            withdrawal_queue_head: u64,
+            withdrawal_queue_tail: u64,
+            queued_withdrawals: u64,
+            fee_authority: Pubkey,
+            fees_accrued: u64,
+            fee_last_accrued: i64,
+            fees_outstanding: u64,
+            realized_gains: u128,
+            fee_high_water_mark: u128,
+            seed_owner: Pubkey,
+            pending_owner: Pubkey,
+            recent_keys: IdempotencyRing,
+            is_paused: bool,
+            rebate_share_bps: u16,
+            rebate_recipient: Pubkey,
+            rebates_accrued: u64,
+            bump: u8,
+        }
+
+        let owner = Pubkey::new_unique();
+        let cosigners = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let fee_authority = Pubkey::new_unique();
+        let rebate_recipient = Pubkey::new_unique();
+        let v1 = VaultV1 {
+            owner,
+            agent_name: "molusco-legacy".to_string(),
+            total_value_locked: 5_000_000,
+            active_positions: 3,
+            performance_fee_bps: 2_000,
+            cumulative_inflows: 9_000_000,
+            cosigners: cosigners.clone(),
+            cosign_threshold: 2,
+            large_withdrawal_limit: 750_000,
+            max_bundle_tip_lamports: 10_000,
+            fee_authority,
+            fees_accrued: 1_234,
+            fee_last_accrued: 1_700_000_000,
+            fees_outstanding: 567,
+            realized_gains: 89,
+            is_paused: true,
+            rebate_share_bps: 2_500,
+            rebate_recipient,
+            rebates_accrued: 42,
+            bump: 253,
+            ..Default::default()
+        };
+        let mut data = Vault::DISCRIMINATOR.to_vec();
+        v1.serialize(&mut data).unwrap();
+        // Accounts were allocated for the longest agent name and cosigner list
+        data.resize(migration::VAULT_V1_LEN, 0);
+
+        migration::upgrade_vault(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + Vault::SIZE);
+        let vault = Vault::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(vault.owner, owner);
+        assert_eq!(vault.agent_name, "molusco-legacy");
+        assert_eq!(vault.total_value_locked, 5_000_000);
+        assert_eq!(vault.active_positions, 3);
+        assert_eq!(vault.performance_fee_bps, 2_000);
+        assert_eq!(vault.cumulative_inflows, 9_000_000);
+        assert_eq!(vault.cosigners, cosigners);
+        assert_eq!(vault.cosign_threshold, 2);
+        assert_eq!(vault.large_withdrawal_limit, 750_000);
+        assert_eq!(vault.max_bundle_tip_lamports, 10_000);
+        assert_eq!(vault.fee_authority, fee_authority);
+        assert_eq!(vault.fees_accrued, 1_234);
+        assert_eq!(vault.fee_last_accrued, 1_700_000_000);
+        assert_eq!(vault.fees_outstanding, 567);
+        assert_eq!(vault.realized_gains, 89);
+        assert!(vault.is_paused);
+        assert_eq!(vault.rebate_share_bps, 2_500);
+        assert_eq!(vault.rebate_recipient, rebate_recipient);
+        assert_eq!(vault.rebates_accrued, 42);
+        assert_eq!(vault.unrealized_yield, 0);
+        assert_eq!(vault.min_update_interval_secs, 0);
+        assert_eq!(vault.max_staleness_secs, 0);
+        assert_eq!(vault.stale_positions, 0);
+        assert_eq!(vault.layout_version, LAYOUT_VERSION);
+        assert_eq!(vault.bump, 253);
+
+        // A current vault is not upgraded again
+        assert!(migration::upgrade_vault(&mut data).is_err());
+    }
+
+    #[test]
+    fn test_target_apy_history_keeps_newest_first() {
+        use moluscoyield::{TargetApyHistory, TARGET_APY_HISTORY_LEN};
+
+        let opened_at = 1_000;
+        let mut history = TargetApyHistory::default();
+        assert_eq!(history.current_since(opened_at), opened_at);
+
+        history.push(850, opened_at, 2_000);
+        assert_eq!(history.entries[0].target_apy, 850);
+        assert_eq!(history.entries[0].applied_from, opened_at);
+        assert_eq!(history.current_since(opened_at), 2_000);
+
+        for (i, target) in (900..).step_by(50).take(TARGET_APY_HISTORY_LEN).enumerate() {
+            history.push(target, opened_at, 3_000 + i as i64);
+        }
+        // The first target has dropped off; each entry starts where the next older one ended
+        assert!(history.entries.iter().all(|e| e.target_apy != 850));
+        for pair in history.entries.windows(2) {
+            assert_eq!(pair[0].applied_from, pair[1].applied_until);
+        }
+    }
+
+    #[test]
+    fn test_pyth_price_update_is_parsed_and_validated() {
+        use anchor_lang::solana_program::hash::hash;
+        use moluscoyield::oracle::{parse_pyth_price_update, OraclePrice};
+
+        let feed_id = [7u8; 32];
+        let mut data = hash(b"account:PriceUpdateV2").to_bytes()[..8].to_vec();
+        data.extend_from_slice(&[0u8; 32]); // write_authority
+        data.push(1); // verification_level: Full
+        data.extend_from_slice(&feed_id);
+        data.extend_from_slice(&99_950_000i64.to_le_bytes());
+        data.extend_from_slice(&50_000u64.to_le_bytes());
+        data.extend_from_slice(&(-8i32).to_le_bytes());
+        data.extend_from_slice(&1_000i64.to_le_bytes());
+
+        let (parsed_feed, price) = parse_pyth_price_update(&data).unwrap();
+        assert_eq!(parsed_feed, feed_id);
+        assert_eq!(
+            price,
+            OraclePrice { price: 99_950_000, conf: 50_000, exponent: -8, publish_time: 1_000 }
+        );
+        // $0.9995 a token, with 18 decimals
+        assert_eq!(price.normalized().unwrap(), 999_500_000_000_000_000);
+
+        assert!(price.validate(1_060, 60, 10).is_ok());
+        assert!(price.validate(1_061, 60, 10).is_err());
+        // 50_000 / 99_950_000 is just over 5 bps
+        assert!(price.validate(1_000, 60, 5).is_err());
+
+        // A partially verified update is refused
+        let mut partial = data.clone();
+        partial[40] = 0;
+        assert!(parse_pyth_price_update(&partial).is_err());
+    }
+
+    #[test]
+    fn test_switchboard_pull_feed_shares_the_price_checks() {
+        use anchor_lang::solana_program::hash::hash;
+        use moluscoyield::oracle::parse_switchboard_pull_feed;
+
+        // Discriminator, then `last_update_timestamp` at 2208 and `result` at 2256
+        let mut data = vec![0u8; 8 + 3_200];
+        data[..8].copy_from_slice(&hash(b"account:PullFeedAccountData").to_bytes()[..8]);
+        data[8 + 2208..8 + 2216].copy_from_slice(&1_000i64.to_le_bytes());
+        data[8 + 2256..8 + 2272].copy_from_slice(&1_000_200_000_000_000_000i128.to_le_bytes());
+        data[8 + 2272..8 + 2288].copy_from_slice(&300_000_000_000_000i128.to_le_bytes());
+
+        let price = parse_switchboard_pull_feed(&data).unwrap();
+        assert_eq!(price.exponent, -18);
+        assert_eq!(price.publish_time, 1_000);
+        // $1.0002 a token, already with 18 decimals
+        assert_eq!(price.normalized().unwrap(), 1_000_200_000_000_000_000);
+        // A 3 bps standard deviation passes a 5 bps limit but not a 2 bps one
+        assert!(price.validate(1_030, 60, 5).is_ok());
+        assert!(price.validate(1_030, 60, 2).is_err());
+        assert!(price.validate(1_061, 60, 5).is_err());
+
+        // A Pyth account is not a pull feed
+        data[..8].copy_from_slice(&hash(b"account:PriceUpdateV2").to_bytes()[..8]);
+        assert!(parse_switchboard_pull_feed(&data).is_err());
+    }
+
+    #[test]
+    fn test_oracle_median_rejects_divergent_feeds() {
+        use moluscoyield::oracle::median_within;
+
+        // Odd counts take the middle value, even counts the mean of the middle two
+        assert_eq!(median_within(&mut [1_002, 998, 1_000], 50).unwrap(), 1_000);
+        assert_eq!(median_within(&mut [1_000, 1_004], 50).unwrap(), 1_002);
+        assert_eq!(median_within(&mut [1_000], 0).unwrap(), 1_000);
+
+        // 1_010 is 100 bps from the median of 1_000
+        assert!(median_within(&mut [1_000, 1_010, 999], 100).is_ok());
+        assert!(median_within(&mut [1_000, 1_010, 999], 99).is_err());
+        assert!(median_within(&mut [], 100).is_err());
+    }
+
+    #[test]
+    fn test_oracle_value_is_in_the_quote_tokens_units() {
+        use moluscoyield::oracle::{convert, OraclePrice};
+
+        let sol = OraclePrice { price: 15_000_000_000, conf: 0, exponent: -8, publish_time: 0 }
+            .normalized()
+            .unwrap();
+        let usdc = OraclePrice { price: 99_950_000, conf: 0, exponent: -8, publish_time: 0 }
+            .normalized()
+            .unwrap();
+
+        // 1 SOL at $150 is worth about 150.08 USDC at $0.9995, not 150 SOL
+        assert_eq!(convert(1_000_000_000, sol, 9, usdc, 6).unwrap(), 150_075_037);
+        // and 150 USDC is just under 1 SOL
+        assert_eq!(convert(150_000_000, usdc, 6, sol, 9).unwrap(), 999_500_000);
+        // A holding priced against itself is worth its units
+        assert_eq!(convert(1_234_567, sol, 9, sol, 9).unwrap(), 1_234_567);
+        assert!(convert(1, sol, 9, 0, 9).is_err());
+    }
+
+    #[test]
+    fn test_oracle_marks_value_a_dollar_mint_in_usd() {
+        use moluscoyield::oracle::{convert, OraclePrice, USD_PRICE};
+
+        let usdc = OraclePrice { price: 99_950_000, conf: 0, exponent: -8, publish_time: 0 }
+            .normalized()
+            .unwrap();
+
+        // 1,000 USDC held at $0.9995 marks at $999.50, not at its 1,000 units
+        assert_eq!(convert(1_000_000_000, usdc, 6, USD_PRICE, 6).unwrap(), 999_500_000);
+    }
+
+    #[test]
+    fn test_compounding_takes_the_latest_mark_once() {
+        use moluscoyield::Position;
+
+        let mut position =
+            Position::try_deserialize_unchecked(&mut &vec![0u8; 8 + Position::SIZE][..]).unwrap();
+        position.amount = 100;
+        position.last_value = 100;
+
+        // Two marks at 110 leave 10 of gain, not 20
+        position.apply_mark(110, 1, 1);
+        position.apply_mark(110, 2, 2);
+        assert_eq!(position.unrealized_yield, 10);
+
+        let pending = position.unrealized_yield;
+        assert_eq!(position.fold_into_principal(pending).unwrap(), 10);
+        assert_eq!(position.amount, 110);
+        assert_eq!(position.compounded_yield, 10);
+        assert_eq!(position.unrealized_yield, 0);
+        assert_eq!(position.realized_yield, 10);
+
+        // A further mark at the same value has nothing left to compound
+        position.apply_mark(110, 3, 3);
+        assert_eq!(position.unrealized_yield, 0);
+        assert_eq!(position.accumulated_yield, 10);
+    }
+
+    #[test]
+    fn test_marks_count_a_loss_once() {
+        use moluscoyield::Position;
+
+        let mut position =
+            Position::try_deserialize_unchecked(&mut &vec![0u8; 8 + Position::SIZE][..]).unwrap();
+        position.amount = 100;
+        position.last_value = 100;
+
+        // Five marks at 90 are one loss of 10, not 50
+        assert_eq!(position.apply_mark(90, 1, 1), (0, 10));
+        for slot in 2..=5 {
+            assert_eq!(position.apply_mark(90, slot as i64, slot), (0, 0));
+        }
+        assert_eq!(position.accumulated_loss, 10);
+
+        // Deepening to 80 adds only the further 10; a recovery adds none to the loss
+        position.apply_mark(80, 6, 6);
+        position.apply_mark(95, 7, 7);
+        assert_eq!(position.accumulated_loss, 20);
+    }
+
+    #[test]
+    fn test_marks_count_a_swing_once_each_way() {
+        use moluscoyield::Position;
+
+        let mut position =
+            Position::try_deserialize_unchecked(&mut &vec![0u8; 8 + Position::SIZE][..]).unwrap();
+        position.amount = 1000;
+        position.last_value = 1000;
+
+        assert_eq!(position.apply_mark(1100, 1, 1), (100, 0));
+        assert_eq!(position.apply_mark(1050, 2, 2), (0, 50));
+        assert_eq!(position.apply_mark(1100, 3, 3), (50, 0));
+        // Net of the swing the position is up the 100 it shows
+        assert_eq!(position.accumulated_yield, 150);
+        assert_eq!(position.accumulated_loss, 50);
+        assert_eq!(position.unrealized_yield, 100);
+    }
+
+    #[test]
+    fn test_price_pair_values_each_side_in_the_others_units() {
+        use moluscoyield::oracle::{OraclePrice, PricePair};
+
+        let price = |price| {
+            OraclePrice { price, conf: 0, exponent: -8, publish_time: 0 }
+                .normalized()
+                .unwrap()
+        };
+        let prices = PricePair {
+            source: price(15_000_000_000),
+            source_decimals: 9,
+            destination: price(100_000_000),
+            destination_decimals: 6,
+        };
+
+        // 2 SOL at $150 are fairly worth 300 USDC, and 300 USDC are 2 SOL
+        assert_eq!(prices.destination_value(2_000_000_000).unwrap(), 300_000_000);
+        assert_eq!(prices.source_value(300_000_000).unwrap(), 2_000_000_000);
+    }
+
+    #[test]
+    fn test_share_scale_prices_shares_per_whole_token() {
+        use moluscoyield::shares::{initial_deposit_shares, ShareScale};
+
+        // One USDC behind a 9-decimal share mint buys what one SOL would
+        let usdc = ShareScale {
+            asset_decimals: 6,
+            share_decimals: 9,
+        };
+        let (sol_shares, _) = initial_deposit_shares(1_000_000_000).unwrap();
+        let (usdc_shares, _) = usdc.initial_deposit_shares(1_000_000).unwrap();
+        assert_eq!(usdc_shares, sol_shares);
+
+        // Deposit and redeem round-trip, and rounding stays with the vault
+        let (total_assets, total_shares) = (5_000_000, usdc_shares + 1_000);
+        let shares = usdc.shares_for_deposit(2_000_000, total_assets, total_shares).unwrap();
+        let redeemed = usdc
+            .assets_for_redeem(shares, total_assets + 2_000_000, total_shares + shares)
+            .unwrap();
+        assert!(redeemed <= 2_000_000 && redeemed >= 1_999_999);
+        let burned = usdc
+            .shares_for_withdraw(redeemed, total_assets + 2_000_000, total_shares + shares)
+            .unwrap();
+        assert!(burned <= shares);
+    }
+
+    #[test]
+    fn test_rewards_follow_share_changes() {
+        use moluscoyield::{DepositorRecord, RewardsDistributor};
+
+        let mut distributor = RewardsDistributor {
+            vault: Pubkey::default(),
+            reward_mint: Pubkey::default(),
+            reward_vault: Pubkey::default(),
+            emission_rate: 10,
+            end_ts: 1_000,
+            last_update_ts: 0,
+            acc_reward_per_share: 0,
+            total_stake: 0,
+            total_funded: 10_000,
+            total_emitted: 0,
+            total_claimed: 0,
+            bump: 255,
+        };
+        let holder = |shares| DepositorRecord {
+            vault: Pubkey::default(),
+            depositor: Pubkey::default(),
+            principal: shares,
+            shares,
+            first_deposit_at: 0,
+            last_deposit_at: 0,
+            deposited: shares,
+            withdrawn: 0,
+            fees_paid: 0,
+            reward_stake: 0,
+            reward_debt: 0,
+            rewards_owed: 0,
+            lots: Default::default(),
+            bump: 0,
+        };
+        let (mut alice, mut bob) = (holder(300), holder(100));
+        alice.settle_rewards(&mut distributor, 0).unwrap();
+        bob.settle_rewards(&mut distributor, 0).unwrap();
+        assert_eq!(distributor.total_stake, 400);
+
+        // Bob quadruples his shares at t=100; the first 1000 emitted split 3:1
+        bob.shares = 400;
+        bob.settle_rewards(&mut distributor, 100).unwrap();
+        assert_eq!((bob.rewards_owed, distributor.total_stake), (250, 700));
+
+        // The next 1000 split 3:4, on top of what each had banked
+        alice.settle_rewards(&mut distributor, 200).unwrap();
+        bob.settle_rewards(&mut distributor, 200).unwrap();
+        assert_eq!((alice.rewards_owed, bob.rewards_owed), (1_178, 821));
+        assert!(alice.rewards_owed + bob.rewards_owed <= distributor.total_emitted);
+    }
+
+    #[test]
+    fn test_exit_fee_ages_each_deposit_lot() {
+        use moluscoyield::fees::ExitFeeSchedule;
+        use moluscoyield::DepositorRecord;
+
+        let day = 86_400;
+        let schedule = ExitFeeSchedule {
+            full_fee_bps: 100,
+            full_fee_secs: 7 * day,
+            zero_fee_secs: 30 * day,
+        };
+        let mut record = DepositorRecord {
+            vault: Pubkey::default(),
+            depositor: Pubkey::default(),
+            principal: 0,
+            shares: 0,
+            first_deposit_at: 0,
+            last_deposit_at: 0,
+            deposited: 0,
+            withdrawn: 0,
+            fees_paid: 0,
+            reward_stake: 0,
+            reward_debt: 0,
+            rewards_owed: 0,
+            lots: Default::default(),
+            bump: 0,
+        };
+        record.credit(Pubkey::default(), Pubkey::default(), 1_000_000, 1_000_000, 0, 255).unwrap();
+        // A small top-up after a month does not restart the clock on the first deposit
+        let now = 31 * day;
+        record.credit(Pubkey::default(), Pubkey::default(), 10_000, 10_000, now, 255).unwrap();
+
+        let lots = record.exit_lots(1_000_000, 1_000_000).unwrap();
+        assert_eq!(lots, vec![(1_000_000, 0)]);
+        assert_eq!(schedule.fee_for_lots(&lots, now).unwrap(), 0);
+
+        // Redeeming everything reaches the fresh lot, which pays the full fee
+        let lots = record.exit_lots(1_010_000, 1_010_000).unwrap();
+        assert_eq!(lots, vec![(1_000_000, 0), (10_000, now)]);
+        assert_eq!(schedule.fee_for_lots(&lots, now).unwrap(), 100);
+
+        // Burning the first deposit's shares leaves only the fresh lot
+        record.debit(1_000_000).unwrap();
+        assert_eq!(record.exit_lots(10_000, 10_000).unwrap(), vec![(10_000, now)]);
+    }
+    #[test]
+    fn test_positions_release_only_the_capacity_they_reserved() {
+        use moluscoyield::{Position, StrategyCapacity};
+
+        let mut capacity = StrategyCapacity::try_deserialize_unchecked(
+            &mut &vec![0u8; 8 + StrategyCapacity::SIZE][..],
+        )
+        .unwrap();
+        // Another vault's position already holds 500
+        capacity.deployed = 500;
+        let mut position =
+            Position::try_deserialize_unchecked(&mut &vec![0u8; 8 + Position::SIZE][..]).unwrap();
+        position.amount = 100;
+        position.reserve_capacity(&mut capacity, 100).unwrap();
+        assert_eq!(capacity.deployed, 600);
+
+        // Compounded yield grows the principal without reserving anything
+        position.fold_into_principal(20).unwrap();
+        position.release_capacity(&mut capacity, position.amount as u128);
+        assert_eq!(capacity.deployed, 500);
+        assert_eq!(position.reserved_capacity, 0);
+    }
+
 }
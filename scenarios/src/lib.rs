@@ -0,0 +1,44 @@
+//! Scripted market scenarios for MoluscoYield vaults.
+//!
+//! A scenario is a list of steps over logical days: deposits, deployments into
+//! a mock venue, yield, drawdowns and bank runs. Each one runs through an
+//! off-chain model built on the program's own share math, and the same script
+//! can be replayed against a localnet validator, where every `Check` step
+//! compares the vault on chain with the model.
+
+pub mod localnet;
+pub mod model;
+pub mod runner;
+pub mod script;
+
+pub use model::{DepositorState, Model, VaultState};
+pub use script::{catalog, Expect, Scenario, Step};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScenarioError {
+    #[error("send failed: {0}")]
+    Send(#[from] moluscoyield_client::vault_client::SendError),
+    #[error("rpc error: {0}")]
+    Rpc(#[from] solana_client::client_error::ClientError),
+    #[error("instruction error: {0}")]
+    Instruction(#[from] solana_sdk::program_error::ProgramError),
+    #[error("validator: {0}")]
+    Validator(String),
+    #[error("model: {0}")]
+    Model(String),
+    #[error("account {0} could not be decoded")]
+    Decode(String),
+    #[error(
+        "step {step} diverged from the model\n  model:    {model:?}\n  observed: {observed:?}"
+    )]
+    Diverged {
+        step: usize,
+        model: Box<VaultState>,
+        observed: Box<VaultState>,
+    },
+    #[error("expectation {expect:?} failed on {state:?}")]
+    Expectation {
+        expect: Expect,
+        state: Box<VaultState>,
+    },
+}
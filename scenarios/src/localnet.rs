@@ -0,0 +1,112 @@
+//! A throwaway `solana-test-validator` with the program preloaded as upgradeable,
+//! under a fresh upgrade authority that also becomes the program admin.
+
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::ScenarioError;
+
+#[derive(Debug, Clone)]
+pub struct LocalnetConfig {
+    /// Built program, usually `target/deploy/moluscoyield.so`
+    pub program_so: PathBuf,
+    pub program_id: Pubkey,
+    pub ledger_dir: PathBuf,
+    pub rpc_port: u16,
+    pub startup_timeout: Duration,
+}
+
+impl LocalnetConfig {
+    pub fn new(program_so: impl Into<PathBuf>) -> Self {
+        Self {
+            program_so: program_so.into(),
+            program_id: moluscoyield::ID,
+            ledger_dir: std::env::temp_dir().join("moluscoyield-scenarios-ledger"),
+            rpc_port: 8899,
+            startup_timeout: Duration::from_secs(60),
+        }
+    }
+
+    pub fn rpc_url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.rpc_port)
+    }
+}
+
+/// Running validator; killed on drop
+pub struct Localnet {
+    child: Child,
+    rpc_url: String,
+    admin: Keypair,
+}
+
+impl Localnet {
+    /// Start a fresh ledger and wait until the validator answers health checks
+    pub fn start(config: &LocalnetConfig) -> Result<Self, ScenarioError> {
+        if !config.program_so.exists() {
+            return Err(ScenarioError::Validator(format!(
+                "{} not found; run `anchor build` first",
+                config.program_so.display()
+            )));
+        }
+        let admin = Keypair::new();
+        let child = Command::new("solana-test-validator")
+            .arg("--reset")
+            .arg("--quiet")
+            .arg("--ledger")
+            .arg(&config.ledger_dir)
+            .arg("--rpc-port")
+            .arg(config.rpc_port.to_string())
+            .arg("--upgradeable-program")
+            .arg(config.program_id.to_string())
+            .arg(&config.program_so)
+            .arg(admin.pubkey().to_string())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| ScenarioError::Validator(format!("solana-test-validator: {}", e)))?;
+
+        let localnet = Self {
+            child,
+            rpc_url: config.rpc_url(),
+            admin,
+        };
+        let rpc = localnet.rpc();
+        let started = Instant::now();
+        while rpc.get_health().is_err() {
+            if started.elapsed() > config.startup_timeout {
+                return Err(ScenarioError::Validator(
+                    "validator did not become healthy in time".to_string(),
+                ));
+            }
+            sleep(Duration::from_millis(500));
+        }
+        Ok(localnet)
+    }
+
+    pub fn rpc_url(&self) -> &str {
+        &self.rpc_url
+    }
+
+    /// The program's upgrade authority, which initializes the program config
+    pub fn admin(&self) -> &Keypair {
+        &self.admin
+    }
+
+    pub fn rpc(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.rpc_url.clone(), CommitmentConfig::confirmed())
+    }
+}
+
+impl Drop for Localnet {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
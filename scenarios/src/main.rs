@@ -0,0 +1,92 @@
+use std::process::ExitCode;
+
+use moluscoyield_scenarios::localnet::{Localnet, LocalnetConfig};
+use moluscoyield_scenarios::runner::Runner;
+use moluscoyield_scenarios::script::{catalog, find};
+use moluscoyield_scenarios::{Scenario, ScenarioError, Step};
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+fn run(localnet: &Localnet, scenario: &Scenario) -> Result<(), ScenarioError> {
+    println!("== {}: {}", scenario.name, scenario.description);
+    let mut runner = Runner::setup(
+        localnet.rpc_url(),
+        moluscoyield::ID,
+        localnet.admin(),
+        scenario,
+    )?;
+    let end = runner.run(scenario, |_, day, step| match step {
+        Step::NextDay | Step::Check => {}
+        step => println!("   day {:>3}  {:?}", day, step),
+    })?;
+    println!(
+        "   vault {}: TVL {}, idle {}, queued {}",
+        runner.vault(),
+        end.total_value_locked,
+        end.idle,
+        end.queued_withdrawals
+    );
+    for depositor in 0..end.depositors.len() {
+        println!(
+            "   depositor {}: {:+} bps",
+            depositor,
+            end.return_bps(depositor)
+        );
+    }
+    Ok(())
+}
+
+/// Usage: moluscoyield-scenarios [SCENARIO...]; runs the whole catalog by default
+fn main() -> ExitCode {
+    let names: Vec<String> = std::env::args().skip(1).collect();
+    let scenarios = if names.is_empty() {
+        catalog()
+    } else {
+        let mut picked = Vec::new();
+        for name in &names {
+            match find(name) {
+                Some(scenario) => picked.push(scenario),
+                None => {
+                    eprintln!("unknown scenario {}", name);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        picked
+    };
+
+    let mut config = LocalnetConfig::new(env_or(
+        "MOLUSCO_SCENARIOS_PROGRAM",
+        "target/deploy/moluscoyield.so",
+    ));
+    if let Ok(port) = std::env::var("MOLUSCO_SCENARIOS_RPC_PORT") {
+        config.rpc_port = port.parse().unwrap_or(config.rpc_port);
+    }
+    let localnet = match Localnet::start(&config) {
+        Ok(localnet) => localnet,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = 0;
+    for scenario in &scenarios {
+        if let Err(e) = run(&localnet, scenario) {
+            eprintln!("   FAILED: {}", e);
+            failed += 1;
+        }
+    }
+    println!(
+        "{} of {} scenarios passed",
+        scenarios.len() - failed,
+        scenarios.len()
+    );
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
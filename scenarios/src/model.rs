@@ -0,0 +1,305 @@
+//! Off-chain reference model of a scenario vault.
+//!
+//! Mirrors the program's accounting for the steps a script can take, using the
+//! program's own share math, so a localnet run can be checked against it
+//! lamport for lamport.
+
+use std::collections::VecDeque;
+
+use moluscoyield::shares;
+use moluscoyield::shares::Rounding;
+use moluscoyield::withdrawals;
+
+use crate::script::{bps_of, Step, BPS, DEPOSITOR_FUNDING};
+use crate::ScenarioError;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DepositorState {
+    pub deposited: u64,
+    /// Vault-mint tokens in the depositor's wallet
+    pub tokens: u64,
+    /// Vault shares in the depositor's wallet
+    pub shares: u64,
+    /// Assets still owed on the depositor's queued withdrawals
+    pub queued_assets: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultState {
+    pub total_value_locked: u64,
+    /// Tokens in the vault escrow
+    pub idle: u64,
+    /// Share supply including dead shares
+    pub total_shares: u64,
+    pub queued_withdrawals: u64,
+    pub open_positions: u64,
+    pub depositors: Vec<DepositorState>,
+}
+
+impl VaultState {
+    /// Assets `shares` redeem for at the current price
+    pub fn redeemable(&self, shares: u64) -> u64 {
+        shares::assets_for_redeem(shares, self.total_value_locked, self.total_shares).unwrap_or(0)
+    }
+
+    /// Everything the depositor holds or is owed, valued in vault-mint tokens
+    pub fn worth(&self, depositor: usize) -> u64 {
+        let d = &self.depositors[depositor];
+        d.tokens + self.redeemable(d.shares) + d.queued_assets
+    }
+
+    /// Gain on what the depositor put in, in basis points
+    pub fn return_bps(&self, depositor: usize) -> i64 {
+        let deposited = self.depositors[depositor].deposited;
+        if deposited == 0 {
+            return 0;
+        }
+        let gain = self.worth(depositor) as i128 - DEPOSITOR_FUNDING as i128;
+        (gain * BPS as i128 / deposited as i128) as i64
+    }
+
+    /// Idle escrow not already owed to the withdrawal queue
+    pub fn available(&self) -> u64 {
+        self.idle.saturating_sub(self.queued_withdrawals)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MockPosition {
+    principal: u64,
+    balance: u64,
+}
+
+#[derive(Debug, Clone)]
+struct QueuedRequest {
+    depositor: usize,
+    remaining_assets: u64,
+    remaining_shares: u64,
+}
+
+/// What a bank run does for one depositor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// Withdraw `assets` from idle escrow now
+    Instant { assets: u64 },
+    /// Queue all of the depositor's shares
+    Queue { shares: u64 },
+}
+
+fn model_err(e: anchor_lang::error::Error) -> ScenarioError {
+    ScenarioError::Model(e.to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct Model {
+    state: VaultState,
+    positions: Vec<MockPosition>,
+    queue: VecDeque<QueuedRequest>,
+    day: u32,
+}
+
+impl Model {
+    pub fn new(depositors: usize) -> Self {
+        let depositor = DepositorState {
+            tokens: DEPOSITOR_FUNDING,
+            ..Default::default()
+        };
+        Self {
+            state: VaultState {
+                depositors: vec![depositor; depositors],
+                ..Default::default()
+            },
+            positions: Vec::new(),
+            queue: VecDeque::new(),
+            day: 0,
+        }
+    }
+
+    pub fn state(&self) -> &VaultState {
+        &self.state
+    }
+
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    /// How `depositor` leaves in a bank run, given the vault as it stands
+    pub fn exit_for(state: &VaultState, depositor: usize) -> Option<Exit> {
+        let shares = state.depositors[depositor].shares;
+        let assets = state.redeemable(shares);
+        if shares == 0 || assets == 0 {
+            None
+        } else if assets <= state.available() {
+            Some(Exit::Instant { assets })
+        } else {
+            Some(Exit::Queue { shares })
+        }
+    }
+
+    pub fn apply(&mut self, step: &Step) -> Result<(), ScenarioError> {
+        match *step {
+            Step::Deposit { depositor, amount } => self.deposit(depositor, amount)?,
+            Step::Deploy { bps } => {
+                let amount = bps_of(self.state.idle, bps);
+                self.state.idle -= amount;
+                self.state.open_positions += 1;
+                self.positions.push(MockPosition {
+                    principal: amount,
+                    balance: amount,
+                });
+            }
+            Step::Yield { bps } => {
+                for position in &mut self.positions {
+                    position.balance += bps_of(position.balance, bps);
+                }
+            }
+            Step::Drawdown { bps } => {
+                for position in &mut self.positions {
+                    let write_down = bps_of(position.principal, bps);
+                    position.principal -= write_down;
+                    self.state.total_value_locked -= write_down;
+                }
+            }
+            Step::Withdraw { depositor, bps } => {
+                let shares = bps_of(self.state.depositors[depositor].shares, bps);
+                let assets = self.state.redeemable(shares);
+                if assets > self.state.available() {
+                    return Err(ScenarioError::Model(format!(
+                        "withdrawal of {} exceeds idle liquidity {}",
+                        assets,
+                        self.state.available()
+                    )));
+                }
+                self.withdraw(depositor, assets)?;
+            }
+            Step::BankRun => {
+                for depositor in 0..self.state.depositors.len() {
+                    match Self::exit_for(&self.state, depositor) {
+                        Some(Exit::Instant { assets }) => self.withdraw(depositor, assets)?,
+                        Some(Exit::Queue { shares }) => {
+                            self.request_withdrawal(depositor, shares)?
+                        }
+                        None => {}
+                    }
+                }
+            }
+            Step::Unwind => {
+                for position in self.positions.drain(..) {
+                    self.state.total_value_locked =
+                        self.state.total_value_locked - position.principal + position.balance;
+                    self.state.idle += position.balance;
+                }
+                self.state.open_positions = 0;
+            }
+            Step::ProcessQueue => self.process_queue()?,
+            Step::NextDay => self.day += 1,
+            Step::Check | Step::Expect(_) => {}
+        }
+        Ok(())
+    }
+
+    fn deposit(&mut self, depositor: usize, amount: u64) -> Result<(), ScenarioError> {
+        let state = &mut self.state;
+        let minted = if state.total_shares == 0 {
+            let (minted, dead) = shares::initial_deposit_shares(amount).map_err(model_err)?;
+            state.total_shares = dead;
+            minted
+        } else {
+            shares::shares_for_deposit(amount, state.total_value_locked, state.total_shares)
+                .map_err(model_err)?
+        };
+        state.total_shares += minted;
+        state.total_value_locked += amount;
+        state.idle += amount;
+        let d = &mut state.depositors[depositor];
+        d.tokens -= amount;
+        d.shares += minted;
+        d.deposited += amount;
+        Ok(())
+    }
+
+    fn withdraw(&mut self, depositor: usize, assets: u64) -> Result<(), ScenarioError> {
+        let state = &mut self.state;
+        let burned =
+            shares::shares_for_withdraw(assets, state.total_value_locked, state.total_shares)
+                .map_err(model_err)?;
+        state.total_shares -= burned;
+        state.total_value_locked -= assets;
+        state.idle -= assets;
+        let d = &mut state.depositors[depositor];
+        d.shares -= burned;
+        d.tokens += assets;
+        Ok(())
+    }
+
+    fn request_withdrawal(&mut self, depositor: usize, shares: u64) -> Result<(), ScenarioError> {
+        let state = &mut self.state;
+        let assets = state.redeemable(shares);
+        state.queued_withdrawals += assets;
+        let d = &mut state.depositors[depositor];
+        d.shares -= shares;
+        d.queued_assets += assets;
+        self.queue.push_back(QueuedRequest {
+            depositor,
+            remaining_assets: assets,
+            remaining_shares: shares,
+        });
+        Ok(())
+    }
+
+    fn process_queue(&mut self) -> Result<(), ScenarioError> {
+        let outstanding: Vec<u64> = self.queue.iter().map(|r| r.remaining_assets).collect();
+        let fills = withdrawals::allocate_fills(&outstanding, self.state.idle);
+        let mut settled = 0;
+        for (request, fill) in self.queue.iter_mut().zip(fills) {
+            if fill == 0 {
+                break;
+            }
+            let (left, done) = withdrawals::apply_fill(request.remaining_assets, fill);
+            let burned = if done {
+                request.remaining_shares
+            } else {
+                shares::mul_div(
+                    request.remaining_shares,
+                    fill as u128,
+                    request.remaining_assets as u128,
+                    Rounding::Up,
+                )
+                .map_err(model_err)?
+            };
+            request.remaining_assets = left;
+            request.remaining_shares -= burned;
+
+            let state = &mut self.state;
+            state.total_shares -= burned;
+            state.total_value_locked -= fill;
+            state.idle -= fill;
+            state.queued_withdrawals -= fill;
+            let d = &mut state.depositors[request.depositor];
+            d.tokens += fill;
+            d.queued_assets -= fill;
+            if done {
+                settled += 1;
+            }
+        }
+        self.queue.drain(..settled);
+        Ok(())
+    }
+}
+
+/// Run `steps` through the model, failing on the first expectation that does not hold
+pub fn run_model(depositors: usize, steps: &[Step]) -> Result<Model, ScenarioError> {
+    let mut model = Model::new(depositors);
+    for step in steps {
+        model.apply(step)?;
+        if let Step::Expect(expect) = step {
+            if !expect.holds(model.state()) {
+                return Err(ScenarioError::Expectation {
+                    expect: expect.clone(),
+                    state: Box::new(model.state().clone()),
+                });
+            }
+        }
+    }
+    Ok(model)
+}
@@ -0,0 +1,551 @@
+//! Drives a scenario against a live vault and checks it against the model.
+//!
+//! The mock venue is the position escrow itself: the harness owns the test
+//! mint, so venue yield is minted straight into each position's custody and
+//! then marked with `update_position`.
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use anchor_spl::token::{Mint, TokenAccount};
+use moluscoyield::{
+    AssetKind, Position, ProtocolKind, StrategyKind, Vault, WithdrawalRequest, MAX_BATCH_SIZE,
+};
+use moluscoyield_client::instructions::{self, PositionParams};
+use moluscoyield_client::vault_client::{SendConfig, VaultClient};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::hashv;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::{system_program, sysvar};
+
+use crate::model::{DepositorState, Exit, Model, VaultState};
+use crate::script::{bps_of, Scenario, Step, DEPOSITOR_FUNDING};
+use crate::ScenarioError;
+
+/// The mock venue has no named kind
+const PROTOCOL: ProtocolKind = ProtocolKind::Other(1);
+const STRATEGY: StrategyKind = StrategyKind::Lending;
+const ASSET: AssetKind = AssetKind::Other(1);
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+struct Wallet {
+    keypair: Keypair,
+    token_account: Pubkey,
+    share_account: Pubkey,
+    deposited: u64,
+}
+
+pub struct Runner {
+    client: VaultClient,
+    scenario: &'static str,
+    mint: Pubkey,
+    vault: Pubkey,
+    depositors: Vec<Wallet>,
+    positions: Vec<Pubkey>,
+}
+
+fn airdrop(rpc: &RpcClient, to: &Pubkey, lamports: u64) -> Result<(), ScenarioError> {
+    let signature = rpc.request_airdrop(to, lamports)?;
+    let started = Instant::now();
+    while !rpc.confirm_transaction(&signature)? {
+        if started.elapsed() > Duration::from_secs(30) {
+            return Err(ScenarioError::Validator(format!(
+                "airdrop to {} did not land",
+                to
+            )));
+        }
+        sleep(Duration::from_millis(250));
+    }
+    Ok(())
+}
+
+impl Runner {
+    /// Fund an owner and the scenario's depositors, then create the vault and its test mint
+    ///
+    /// `admin` is the program's upgrade authority, needed on a fresh validator.
+    pub fn setup(
+        rpc_url: &str,
+        program_id: Pubkey,
+        admin: &Keypair,
+        scenario: &Scenario,
+    ) -> Result<Self, ScenarioError> {
+        let rpc =
+            RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed());
+        let owner = Keypair::new();
+        airdrop(&rpc, &owner.pubkey(), 100 * LAMPORTS_PER_SOL)?;
+        let client = VaultClient::new(rpc, owner, program_id, SendConfig::default());
+        let vault = client.pdas().vault(&client.payer(), scenario.name);
+
+        let mut runner = Self {
+            client,
+            scenario: scenario.name,
+            mint: Pubkey::default(),
+            vault,
+            depositors: Vec::new(),
+            positions: Vec::new(),
+        };
+        runner.ensure_program_config(admin)?;
+        runner.mint = runner.create_mint()?;
+        runner.initialize_vault()?;
+        for _ in 0..scenario.depositors {
+            let keypair = Keypair::new();
+            airdrop(
+                runner.client.rpc(),
+                &keypair.pubkey(),
+                10 * LAMPORTS_PER_SOL,
+            )?;
+            let share_mint = runner.client.pdas().share_mint(&vault);
+            let token_account = runner.create_token_account(&runner.mint, &keypair.pubkey())?;
+            let share_account = runner.create_token_account(&share_mint, &keypair.pubkey())?;
+            runner.mint_to(&token_account, DEPOSITOR_FUNDING)?;
+            runner.depositors.push(Wallet {
+                keypair,
+                token_account,
+                share_account,
+                deposited: 0,
+            });
+        }
+        Ok(runner)
+    }
+
+    /// The first scenario on a fresh validator creates the program config and
+    /// whitelists the mock venue with an uncapped scenario strategy; later ones reuse them
+    fn ensure_program_config(&self, admin: &Keypair) -> Result<(), ScenarioError> {
+        let pdas = self.client.pdas();
+        let existing = self
+            .client
+            .rpc()
+            .get_account_with_commitment(&pdas.program_config(), CommitmentConfig::confirmed())?;
+        if existing.value.is_none() {
+            let rpc = self.client.rpc();
+            airdrop(rpc, &admin.pubkey(), LAMPORTS_PER_SOL)?;
+            let admin_key = admin.pubkey();
+            self.client.send(
+                &[
+                    instructions::initialize_program_config(pdas, &admin_key),
+                    instructions::initialize_protocol_registry(pdas, &admin_key),
+                    instructions::whitelist_protocol(pdas, &admin_key, PROTOCOL),
+                    instructions::set_strategy_capacity(pdas, &admin_key, STRATEGY, 0),
+                ],
+                &[admin],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn vault(&self) -> Pubkey {
+        self.vault
+    }
+
+    /// Run every step, comparing with the model at each `Check` and asserting each `Expect`
+    pub fn run(
+        &mut self,
+        scenario: &Scenario,
+        mut on_step: impl FnMut(usize, u32, &Step),
+    ) -> Result<VaultState, ScenarioError> {
+        let mut model = Model::new(scenario.depositors);
+        for (index, step) in scenario.steps.iter().enumerate() {
+            self.apply(step)?;
+            model.apply(step)?;
+            match step {
+                Step::Check => {
+                    let observed = self.observe()?;
+                    if observed != *model.state() {
+                        return Err(ScenarioError::Diverged {
+                            step: index,
+                            model: Box::new(model.state().clone()),
+                            observed: Box::new(observed),
+                        });
+                    }
+                }
+                Step::Expect(expect) => {
+                    let observed = self.observe()?;
+                    if !expect.holds(&observed) {
+                        return Err(ScenarioError::Expectation {
+                            expect: expect.clone(),
+                            state: Box::new(observed),
+                        });
+                    }
+                }
+                _ => {}
+            }
+            on_step(index, model.day(), step);
+        }
+        self.observe()
+    }
+
+    /// The vault and depositor wallets as they stand on chain
+    pub fn observe(&self) -> Result<VaultState, ScenarioError> {
+        let vault: Vault = self.fetch(&self.vault)?;
+        let escrow: TokenAccount = self.fetch(&vault.escrow)?;
+        let share_mint: Mint = self.fetch(&vault.share_mint)?;
+
+        let mut depositors = Vec::with_capacity(self.depositors.len());
+        for wallet in &self.depositors {
+            let tokens: TokenAccount = self.fetch(&wallet.token_account)?;
+            let shares: TokenAccount = self.fetch(&wallet.share_account)?;
+            depositors.push(DepositorState {
+                deposited: wallet.deposited,
+                tokens: tokens.amount,
+                shares: shares.amount,
+                queued_assets: 0,
+            });
+        }
+        for index in vault.withdrawal_queue_head..vault.withdrawal_queue_tail {
+            let request: WithdrawalRequest =
+                self.fetch(&self.client.pdas().withdrawal_request(&self.vault, index))?;
+            if let Some(i) = self
+                .depositors
+                .iter()
+                .position(|w| w.keypair.pubkey() == request.depositor)
+            {
+                depositors[i].queued_assets += request.remaining_assets;
+            }
+        }
+
+        Ok(VaultState {
+            total_value_locked: vault
+                .total_assets()
+                .map_err(|e| ScenarioError::Model(e.to_string()))?,
+            idle: escrow.amount,
+            total_shares: vault.total_shares(share_mint.supply),
+            queued_withdrawals: vault.queued_withdrawals,
+            open_positions: vault.active_positions as u64,
+            depositors,
+        })
+    }
+
+    fn apply(&mut self, step: &Step) -> Result<(), ScenarioError> {
+        match *step {
+            Step::Deposit { depositor, amount } => self.deposit(depositor, amount),
+            Step::Deploy { bps } => {
+                let state = self.observe()?;
+                self.open_position(bps_of(state.idle, bps))
+            }
+            Step::Yield { bps } => {
+                for position in self.positions.clone() {
+                    let custody = self.client.pdas().position_escrow(&position);
+                    let balance = self.fetch::<TokenAccount>(&custody)?.amount;
+                    let paid = bps_of(balance, bps);
+                    if paid > 0 {
+                        self.mint_to(&custody, paid)?;
+                    }
+                    self.update_position(&position, balance + paid)?;
+                }
+                Ok(())
+            }
+            Step::Drawdown { bps } => {
+                for position in self.positions.clone() {
+                    let state: Position = self.fetch(&position)?;
+                    self.record_impairment(&position, &state, bps_of(state.amount, bps))?;
+                }
+                Ok(())
+            }
+            Step::Withdraw { depositor, bps } => {
+                let state = self.observe()?;
+                let shares = bps_of(state.depositors[depositor].shares, bps);
+                self.withdraw(depositor, state.redeemable(shares))
+            }
+            Step::BankRun => {
+                for depositor in 0..self.depositors.len() {
+                    match Model::exit_for(&self.observe()?, depositor) {
+                        Some(Exit::Instant { assets }) => self.withdraw(depositor, assets)?,
+                        Some(Exit::Queue { shares }) => {
+                            self.request_withdrawal(depositor, shares)?
+                        }
+                        None => {}
+                    }
+                }
+                Ok(())
+            }
+            Step::Unwind => {
+                while let Some(position) = self.positions.pop() {
+                    self.close_position(&position)?;
+                }
+                Ok(())
+            }
+            Step::ProcessQueue => self.process_withdrawals(),
+            Step::NextDay | Step::Check | Step::Expect(_) => Ok(()),
+        }
+    }
+
+    fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T, ScenarioError> {
+        let account = self.client.rpc().get_account(address)?;
+        T::try_deserialize(&mut account.data.as_slice())
+            .map_err(|_| ScenarioError::Decode(address.to_string()))
+    }
+
+    fn instruction(
+        &self,
+        accounts: impl ToAccountMetas,
+        data: impl InstructionData,
+    ) -> Instruction {
+        Instruction {
+            program_id: self.client.program_id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    /// A fresh vault mint, registered in the asset registry as `ASSET`
+    fn create_mint(&self) -> Result<Pubkey, ScenarioError> {
+        let mint = Keypair::new();
+        let rent = self
+            .client
+            .rpc()
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)?;
+        let pdas = self.client.pdas();
+        let payer = self.client.payer();
+        let mut setup = instructions::create_mint(&payer, &mint.pubkey(), &payer, 9, rent)?;
+        setup.push(instructions::register_asset(
+            pdas,
+            &payer,
+            &mint.pubkey(),
+            ASSET,
+            "MOCK",
+        ));
+        self.client.send(&setup, &[&mint])?;
+        Ok(mint.pubkey())
+    }
+
+    fn create_token_account(&self, mint: &Pubkey, owner: &Pubkey) -> Result<Pubkey, ScenarioError> {
+        let account = Keypair::new();
+        let rent = self
+            .client
+            .rpc()
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)?;
+        let instructions = instructions::create_token_account(
+            &self.client.payer(),
+            &account.pubkey(),
+            mint,
+            owner,
+            rent,
+        )?;
+        self.client.send(&instructions, &[&account])?;
+        Ok(account.pubkey())
+    }
+
+    /// The harness holds the mint authority; minting is how the mock venue pays out
+    fn mint_to(&self, destination: &Pubkey, amount: u64) -> Result<(), ScenarioError> {
+        let payer = self.client.payer();
+        let instruction = spl_token::instruction::mint_to(
+            &spl_token::ID,
+            &self.mint,
+            destination,
+            &payer,
+            &[],
+            amount,
+        )?;
+        self.client.send(&[instruction], &[])?;
+        Ok(())
+    }
+
+    fn initialize_vault(&self) -> Result<(), ScenarioError> {
+        let owner = self.client.payer();
+        let pdas = self.client.pdas();
+        let (_, initialize) = instructions::initialize_vault(pdas, &owner, self.scenario, false);
+        let instructions = [
+            initialize,
+            instructions::initialize_escrow(pdas, &owner, &self.vault, &self.mint),
+            instructions::initialize_share_mint(pdas, &owner, &self.vault, 9),
+        ];
+        self.client.send(&instructions, &[])?;
+        Ok(())
+    }
+
+    fn deposit(&mut self, depositor: usize, amount: u64) -> Result<(), ScenarioError> {
+        let wallet = &self.depositors[depositor];
+        let instruction = instructions::deposit(
+            self.client.pdas(),
+            &wallet.keypair.pubkey(),
+            &self.vault,
+            &self.mint,
+            &wallet.token_account,
+            &wallet.share_account,
+            amount,
+        );
+        self.client.send(&[instruction], &[&wallet.keypair])?;
+        self.depositors[depositor].deposited += amount;
+        Ok(())
+    }
+
+    fn withdraw(&self, depositor: usize, amount: u64) -> Result<(), ScenarioError> {
+        let pdas = self.client.pdas();
+        let wallet = &self.depositors[depositor];
+        let instruction = self.instruction(
+            moluscoyield::accounts::Withdraw {
+                depositor: wallet.keypair.pubkey(),
+                vault: self.vault,
+                escrow: pdas.escrow(&self.vault),
+                destination_token_account: wallet.token_account,
+                destination_allowlist: None,
+                share_mint: pdas.share_mint(&self.vault),
+                mint: self.mint,
+                depositor_share_account: wallet.share_account,
+                depositor_record: pdas.depositor_record(&self.vault, &wallet.keypair.pubkey()),
+                fee_ledger: pdas.fee_ledger(&self.vault),
+                rewards_distributor: pdas.rewards_distributor(&self.vault),
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                guard: instructions::guard(pdas),
+            },
+            moluscoyield::instruction::Withdraw { amount },
+        );
+        self.client.send(&[instruction], &[&wallet.keypair])?;
+        Ok(())
+    }
+
+    fn request_withdrawal(&self, depositor: usize, shares: u64) -> Result<(), ScenarioError> {
+        let pdas = self.client.pdas();
+        let vault: Vault = self.fetch(&self.vault)?;
+        let wallet = &self.depositors[depositor];
+        let instruction = self.instruction(
+            moluscoyield::accounts::RequestWithdrawal {
+                depositor: wallet.keypair.pubkey(),
+                vault: self.vault,
+                depositor_record: pdas.depositor_record(&self.vault, &wallet.keypair.pubkey()),
+                share_mint: vault.share_mint,
+                mint: vault.mint,
+                depositor_share_account: wallet.share_account,
+                queued_shares: pdas.queued_shares(&self.vault),
+                withdrawal_request: pdas
+                    .withdrawal_request(&self.vault, vault.withdrawal_queue_tail),
+                destination_token_account: wallet.token_account,
+                destination_allowlist: None,
+                fee_ledger: pdas.fee_ledger(&self.vault),
+                rewards_distributor: pdas.rewards_distributor(&self.vault),
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                rent: sysvar::rent::ID,
+                guard: instructions::guard(pdas),
+            },
+            moluscoyield::instruction::RequestWithdrawal { shares },
+        );
+        self.client.send(&[instruction], &[&wallet.keypair])?;
+        Ok(())
+    }
+
+    fn process_withdrawals(&self) -> Result<(), ScenarioError> {
+        let pdas = self.client.pdas();
+        let vault: Vault = self.fetch(&self.vault)?;
+        let tail = vault
+            .withdrawal_queue_tail
+            .min(vault.withdrawal_queue_head + MAX_BATCH_SIZE as u64);
+        if vault.withdrawal_queue_head == tail {
+            return Ok(());
+        }
+
+        let mut instruction = self.instruction(
+            moluscoyield::accounts::ProcessWithdrawals {
+                cranker: self.client.payer(),
+                vault: self.vault,
+                escrow: vault.escrow,
+                share_mint: vault.share_mint,
+                queued_shares: pdas.queued_shares(&self.vault),
+                fee_ledger: pdas.fee_ledger(&self.vault),
+                token_program: spl_token::ID,
+                guard: instructions::guard(pdas),
+            },
+            moluscoyield::instruction::ProcessWithdrawals {},
+        );
+        for index in vault.withdrawal_queue_head..tail {
+            let address = pdas.withdrawal_request(&self.vault, index);
+            let request: WithdrawalRequest = self.fetch(&address)?;
+            instruction.accounts.push(AccountMeta::new(address, false));
+            instruction
+                .accounts
+                .push(AccountMeta::new(request.destination, false));
+        }
+        self.client.send(&[instruction], &[])?;
+        Ok(())
+    }
+
+    fn open_position(&mut self, amount: u64) -> Result<(), ScenarioError> {
+        let vault: Vault = self.fetch(&self.vault)?;
+        let (position, instruction) = instructions::open_position(
+            self.client.pdas(),
+            &self.client.payer(),
+            &self.vault,
+            &self.mint,
+            vault.total_positions_created,
+            PositionParams {
+                protocol: PROTOCOL,
+                strategy: STRATEGY,
+                asset: ASSET,
+                amount,
+                target_apy: 0,
+                idempotency_key: None,
+                adapter_program: None,
+            },
+        );
+        self.client.send(&[instruction], &[])?;
+        self.positions.push(position);
+        Ok(())
+    }
+
+    fn update_position(&self, position: &Pubkey, current_value: u64) -> Result<(), ScenarioError> {
+        let state: Position = self.fetch(position)?;
+        let instruction = instructions::update_position(
+            self.client.pdas(),
+            &self.client.payer(),
+            &self.vault,
+            position,
+            &state.asset_entry,
+            current_value,
+            state.update_sequence,
+        );
+        self.client.send(&[instruction], &[])?;
+        Ok(())
+    }
+
+    fn record_impairment(
+        &self,
+        position: &Pubkey,
+        state: &Position,
+        write_down: u64,
+    ) -> Result<(), ScenarioError> {
+        let incident_hash = hashv(&[
+            self.scenario.as_bytes(),
+            &state.impairment_count.to_le_bytes(),
+        ]);
+        let instruction = self.instruction(
+            moluscoyield::accounts::RecordImpairment {
+                owner: self.client.payer(),
+                vault: self.vault,
+                position: *position,
+                impairment: self
+                    .client
+                    .pdas()
+                    .impairment(position, state.impairment_count),
+                share_mint: None,
+                system_program: system_program::ID,
+                guard: instructions::guard(self.client.pdas()),
+            },
+            moluscoyield::instruction::RecordImpairment {
+                write_down,
+                incident_hash: incident_hash.to_bytes(),
+            },
+        );
+        self.client.send(&[instruction], &[])?;
+        Ok(())
+    }
+
+    fn close_position(&self, position: &Pubkey) -> Result<(), ScenarioError> {
+        let instruction = instructions::close_position(
+            self.client.pdas(),
+            &self.client.payer(),
+            &self.vault,
+            position,
+            &self.client.pdas().asset_entry(&self.mint),
+            STRATEGY,
+        );
+        self.client.send(&[instruction], &[])?;
+        Ok(())
+    }
+}
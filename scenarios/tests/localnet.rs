@@ -0,0 +1,26 @@
+//! Needs `solana-test-validator` on PATH and a built program:
+//! `anchor build && cargo test -p moluscoyield-scenarios -- --ignored`
+
+use moluscoyield_scenarios::catalog;
+use moluscoyield_scenarios::localnet::{Localnet, LocalnetConfig};
+use moluscoyield_scenarios::runner::Runner;
+
+#[test]
+#[ignore]
+fn test_catalog_on_localnet() {
+    let program = std::env::var("MOLUSCO_SCENARIOS_PROGRAM")
+        .unwrap_or_else(|_| "../target/deploy/moluscoyield.so".to_string());
+    let localnet = Localnet::start(&LocalnetConfig::new(program)).unwrap();
+    for scenario in catalog() {
+        let mut runner = Runner::setup(
+            localnet.rpc_url(),
+            moluscoyield::ID,
+            localnet.admin(),
+            &scenario,
+        )
+        .unwrap();
+        if let Err(e) = runner.run(&scenario, |_, _, _| {}) {
+            panic!("{}: {}", scenario.name, e);
+        }
+    }
+}
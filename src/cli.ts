@@ -1,8 +1,9 @@
 #!/usr/bin/env node
 
-import { Connection, PublicKey, clusterApiUrl } from '@solana/web3.js';
+import { Connection, PublicKey } from '@solana/web3.js';
 import { YieldScanner } from './scanner';
 import { YieldExecutor } from './executor';
+import { resolveCluster } from './cluster';
 import * as dotenv from 'dotenv';
 
 dotenv.config();
@@ -12,9 +13,7 @@ const WALLET_ADDRESS = 'BSSKDqjLriEFxctBotvnVfFLMun73CVvRSBbBs9AVXsZ';
 async function scan() {
   console.log('🔍 MoluscoYield Scanner\n');
   
-  const connection = new Connection(
-    process.env.SOLANA_RPC_URL || clusterApiUrl('mainnet-beta')
-  );
+  const connection = new Connection(resolveCluster().rpcUrl);
   const scanner = new YieldScanner(connection);
 
   const opportunities = await scanner.scanAllOpportunities();
@@ -34,9 +33,7 @@ async function scan() {
 async function dryRun() {
   console.log('🧪 MoluscoYield Dry Run\n');
   
-  const connection = new Connection(
-    process.env.SOLANA_RPC_URL || clusterApiUrl('mainnet-beta')
-  );
+  const connection = new Connection(resolveCluster().rpcUrl);
   const wallet = new PublicKey(WALLET_ADDRESS);
   const scanner = new YieldScanner(connection);
   const executor = new YieldExecutor(connection, wallet);
@@ -93,5 +90,6 @@ switch (command) {
     console.log('  npm run scan       - Scan for yield opportunities');
     console.log('  npm run dry-run    - Simulate rebalancing');
     console.log('  npm run execute    - Execute rebalancing (REAL MONEY)');
+    console.log('\nSet MOLUSCO_CLUSTER=localnet|devnet|testnet|mainnet to pick a deployment');
     process.exit(1);
 }
@@ -0,0 +1,71 @@
+import { Cluster, PublicKey, clusterApiUrl } from '@solana/web3.js';
+
+export type ClusterName = Cluster | 'localnet';
+
+// Every published deployment currently reuses the declared program ID
+const DEFAULT_PROGRAM_IDS: Record<ClusterName, string> = {
+  localnet: 'MolY1dQfT7mK9JmM8J3nM8bG5sL6cK7dF4eS5tU7vW8',
+  devnet: 'MolY1dQfT7mK9JmM8J3nM8bG5sL6cK7dF4eS5tU7vW8',
+  testnet: 'MolY1dQfT7mK9JmM8J3nM8bG5sL6cK7dF4eS5tU7vW8',
+  'mainnet-beta': 'MolY1dQfT7mK9JmM8J3nM8bG5sL6cK7dF4eS5tU7vW8',
+};
+
+export interface ClusterConfig {
+  cluster: ClusterName;
+  rpcUrl: string;
+  programId: PublicKey;
+}
+
+function parseCluster(name: string): ClusterName {
+  switch (name) {
+    case 'localnet':
+    case 'localhost':
+      return 'localnet';
+    case 'devnet':
+    case 'testnet':
+      return name;
+    case 'mainnet':
+    case 'mainnet-beta':
+      return 'mainnet-beta';
+    default:
+      throw new Error(`Unknown cluster: ${name} (expected localnet, devnet, testnet or mainnet)`);
+  }
+}
+
+/**
+ * Resolve the target deployment from MOLUSCO_CLUSTER (default mainnet-beta),
+ * with SOLANA_RPC_URL and MOLUSCO_PROGRAM_ID as overrides.
+ */
+export function resolveCluster(env: NodeJS.ProcessEnv = process.env): ClusterConfig {
+  const cluster = parseCluster(env.MOLUSCO_CLUSTER || 'mainnet-beta');
+  const defaultRpc = cluster === 'localnet' ? 'http://127.0.0.1:8899' : clusterApiUrl(cluster);
+  return {
+    cluster,
+    rpcUrl: env.SOLANA_RPC_URL || defaultRpc,
+    programId: new PublicKey(env.MOLUSCO_PROGRAM_ID || DEFAULT_PROGRAM_IDS[cluster]),
+  };
+}
+
+function findPda(programId: PublicKey, seeds: (Buffer | Uint8Array)[]): PublicKey {
+  return PublicKey.findProgramAddressSync(seeds, programId)[0];
+}
+
+export function findVaultAddress(programId: PublicKey, owner: PublicKey, agentName: string): PublicKey {
+  return findPda(programId, [Buffer.from('vault'), owner.toBuffer(), Buffer.from(agentName)]);
+}
+
+export function findEscrowAddress(programId: PublicKey, vault: PublicKey): PublicKey {
+  return findPda(programId, [Buffer.from('escrow'), vault.toBuffer()]);
+}
+
+export function findDepositorRecordAddress(
+  programId: PublicKey,
+  vault: PublicKey,
+  depositor: PublicKey
+): PublicKey {
+  return findPda(programId, [Buffer.from('depositor'), vault.toBuffer(), depositor.toBuffer()]);
+}
+
+export function findConfigAddress(programId: PublicKey): PublicKey {
+  return findPda(programId, [Buffer.from('config')]);
+}
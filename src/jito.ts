@@ -0,0 +1,89 @@
+import { PublicKey, VersionedTransaction } from '@solana/web3.js';
+import axios from 'axios';
+import * as bs58 from 'bs58';
+
+// Mirrors JITO_TIP_ACCOUNTS in the on-chain program; pay_bundle_tip rejects anything else
+export const JITO_TIP_ACCOUNTS: PublicKey[] = [
+  '96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5',
+  'HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe',
+  'Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY',
+  'ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49',
+  'DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh',
+  'ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt',
+  'DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL',
+  '3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT',
+].map((key) => new PublicKey(key));
+
+export const DEFAULT_BLOCK_ENGINE_URL = 'https://mainnet.block-engine.jito.wtf/api/v1/bundles';
+
+export interface BundleResult {
+  success: boolean;
+  bundleId?: string;
+  error?: string;
+}
+
+/**
+ * Pick a tip account at random to spread contention across the block engine's tip accounts
+ */
+export function randomTipAccount(): PublicKey {
+  return JITO_TIP_ACCOUNTS[Math.floor(Math.random() * JITO_TIP_ACCOUNTS.length)];
+}
+
+/**
+ * Submit signed transactions as one atomic Jito bundle.
+ *
+ * For bundle-only vaults the rebalance_swap transaction must itself include the
+ * vault's pay_bundle_tip instruction, otherwise the program rejects the swap.
+ */
+export async function sendBundle(
+  transactions: VersionedTransaction[],
+  blockEngineUrl: string = DEFAULT_BLOCK_ENGINE_URL
+): Promise<BundleResult> {
+  if (transactions.length === 0 || transactions.length > 5) {
+    return { success: false, error: 'A bundle holds between 1 and 5 transactions' };
+  }
+
+  try {
+    const response = await axios.post(
+      blockEngineUrl,
+      {
+        jsonrpc: '2.0',
+        id: 1,
+        method: 'sendBundle',
+        params: [transactions.map((tx) => bs58.encode(tx.serialize()))],
+      },
+      { timeout: 30000 }
+    );
+
+    if (response.data.error) {
+      return { success: false, error: response.data.error.message };
+    }
+    return { success: true, bundleId: response.data.result };
+  } catch (error) {
+    return {
+      success: false,
+      error: error instanceof Error ? error.message : 'Unknown error',
+    };
+  }
+}
+
+/**
+ * Poll the block engine for a submitted bundle's landing status
+ */
+export async function getBundleStatus(
+  bundleId: string,
+  blockEngineUrl: string = DEFAULT_BLOCK_ENGINE_URL
+): Promise<string | undefined> {
+  const response = await axios.post(
+    blockEngineUrl,
+    {
+      jsonrpc: '2.0',
+      id: 1,
+      method: 'getBundleStatuses',
+      params: [[bundleId]],
+    },
+    { timeout: 30000 }
+  );
+
+  return response.data.result?.value?.[0]?.confirmation_status;
+}
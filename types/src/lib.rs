@@ -0,0 +1,141 @@
+//! Byte-level account layouts for MoluscoYield, without Anchor.
+//!
+//! Anchor accounts are an 8-byte discriminator followed by the borsh encoding of
+//! the struct: fields back to back, no padding, little endian. Accounts without
+//! strings or vectors are mirrored by a single `#[repr(C, packed)]` struct and can
+//! be cast straight out of the account data. `Vault` and `Position` carry
+//! variable-length fields, so they are split into fixed segments around them;
+//! `VaultView` and `PositionView` locate the segments and borrow each in place.
+//!
+//! Bools are mirrored as `u8` and pubkeys as `[u8; 32]`. Packed fields must be
+//! read by value (`let tvl = core.total_value_locked;`), never by reference.
+
+pub mod offsets;
+mod position;
+mod records;
+mod vault;
+
+pub use position::{PositionBody, PositionHead, PositionView, TargetApyChange};
+pub use records::{DepositLot, DepositorRecord, WithdrawalRequest, MAX_DEPOSIT_LOTS};
+pub use vault::{VaultCore, VaultHead, VaultTail, VaultView};
+
+pub type Pubkey = [u8; 32];
+
+/// A `ProtocolKind`, `StrategyKind` or `AssetKind` as encoded on chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A named variant, by its borsh tag
+    Named(u8),
+    Other(u16),
+}
+
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+/// `sha256("account:<Name>")[..8]`, as written by Anchor
+pub mod discriminators {
+    pub const VAULT: [u8; 8] = [211, 8, 232, 43, 2, 152, 117, 119];
+    pub const POSITION: [u8; 8] = [170, 188, 143, 228, 122, 64, 247, 208];
+    pub const DEPOSITOR_RECORD: [u8; 8] = [120, 180, 8, 71, 198, 212, 93, 187];
+    pub const WITHDRAWAL_REQUEST: [u8; 8] = [242, 88, 147, 173, 182, 62, 229, 193];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountKind {
+    Vault,
+    Position,
+    DepositorRecord,
+    WithdrawalRequest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutError {
+    /// Discriminator does not match the requested account type
+    WrongAccount,
+    /// Data ends before the layout does
+    Truncated,
+}
+
+/// Identify a program account from its discriminator
+pub fn account_kind(data: &[u8]) -> Option<AccountKind> {
+    let discriminator: [u8; 8] = data.get(..DISCRIMINATOR_LEN)?.try_into().ok()?;
+    match discriminator {
+        discriminators::VAULT => Some(AccountKind::Vault),
+        discriminators::POSITION => Some(AccountKind::Position),
+        discriminators::DEPOSITOR_RECORD => Some(AccountKind::DepositorRecord),
+        discriminators::WITHDRAWAL_REQUEST => Some(AccountKind::WithdrawalRequest),
+        _ => None,
+    }
+}
+
+/// Cursor over borsh-encoded account data
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8], discriminator: [u8; 8]) -> Result<Self, LayoutError> {
+        if data.len() < DISCRIMINATOR_LEN {
+            return Err(LayoutError::Truncated);
+        }
+        if data[..DISCRIMINATOR_LEN] != discriminator {
+            return Err(LayoutError::WrongAccount);
+        }
+        Ok(Self {
+            data,
+            offset: DISCRIMINATOR_LEN,
+        })
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], LayoutError> {
+        let end = self.offset.checked_add(len).ok_or(LayoutError::Truncated)?;
+        let bytes = self
+            .data
+            .get(self.offset..end)
+            .ok_or(LayoutError::Truncated)?;
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    pub(crate) fn segment<T: bytemuck::Pod>(&mut self) -> Result<&'a T, LayoutError> {
+        Ok(bytemuck::from_bytes(self.take(core::mem::size_of::<T>())?))
+    }
+
+    fn len_prefix(&mut self) -> Result<usize, LayoutError> {
+        let bytes: [u8; 4] = self
+            .take(4)?
+            .try_into()
+            .map_err(|_| LayoutError::Truncated)?;
+        Ok(u32::from_le_bytes(bytes) as usize)
+    }
+
+    /// Borsh `String`: u32 length then UTF-8 bytes
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], LayoutError> {
+        let len = self.len_prefix()?;
+        self.take(len)
+    }
+
+    /// A kind enum: a tag byte, then a u16 code when the tag is `Other` (0)
+    pub(crate) fn kind(&mut self) -> Result<Kind, LayoutError> {
+        let tag = self.take(1)?[0];
+        if tag != 0 {
+            return Ok(Kind::Named(tag));
+        }
+        let bytes: [u8; 2] = self
+            .take(2)?
+            .try_into()
+            .map_err(|_| LayoutError::Truncated)?;
+        Ok(Kind::Other(u16::from_le_bytes(bytes)))
+    }
+
+    /// Borsh `Vec<Pubkey>`: u32 count then 32 bytes per key
+    pub(crate) fn pubkeys(&mut self) -> Result<&'a [Pubkey], LayoutError> {
+        let count = self.len_prefix()?;
+        let len = count.checked_mul(32).ok_or(LayoutError::Truncated)?;
+        Ok(bytemuck::cast_slice(self.take(len)?))
+    }
+}
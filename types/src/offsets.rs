@@ -0,0 +1,32 @@
+//! Absolute byte offsets of fixed-position fields, for `memcmp` filters and
+//! plugins that read single fields without parsing the whole account.
+//!
+//! Vault and position fields after their first variable-length field move with
+//! the string/vector lengths and kind encodings; use
+//! `VaultView::core_offset`/`tail_offset` and `PositionView::body_offset` plus
+//! `core::mem::offset_of!` for those.
+
+use core::mem::offset_of;
+
+use crate::{DepositorRecord, PositionHead, VaultHead, WithdrawalRequest, DISCRIMINATOR_LEN};
+
+pub const VAULT_OWNER: usize = DISCRIMINATOR_LEN + offset_of!(VaultHead, owner);
+/// u32 length prefix of `agent_name`
+pub const VAULT_AGENT_NAME: usize = DISCRIMINATOR_LEN + core::mem::size_of::<VaultHead>();
+
+pub const POSITION_OWNER: usize = DISCRIMINATOR_LEN + offset_of!(PositionHead, owner);
+pub const POSITION_VAULT: usize = DISCRIMINATOR_LEN + offset_of!(PositionHead, vault);
+
+pub const DEPOSITOR_RECORD_VAULT: usize = DISCRIMINATOR_LEN + offset_of!(DepositorRecord, vault);
+pub const DEPOSITOR_RECORD_DEPOSITOR: usize =
+    DISCRIMINATOR_LEN + offset_of!(DepositorRecord, depositor);
+pub const DEPOSITOR_RECORD_SHARES: usize = DISCRIMINATOR_LEN + offset_of!(DepositorRecord, shares);
+
+pub const WITHDRAWAL_REQUEST_VAULT: usize =
+    DISCRIMINATOR_LEN + offset_of!(WithdrawalRequest, vault);
+pub const WITHDRAWAL_REQUEST_DEPOSITOR: usize =
+    DISCRIMINATOR_LEN + offset_of!(WithdrawalRequest, depositor);
+pub const WITHDRAWAL_REQUEST_INDEX: usize =
+    DISCRIMINATOR_LEN + offset_of!(WithdrawalRequest, index);
+pub const WITHDRAWAL_REQUEST_REMAINING_ASSETS: usize =
+    DISCRIMINATOR_LEN + offset_of!(WithdrawalRequest, remaining_assets);
@@ -0,0 +1,93 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::{discriminators, Kind, LayoutError, Pubkey, Reader};
+
+/// Fields before `protocol`
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct PositionHead {
+    pub owner: Pubkey,
+    pub vault: Pubkey,
+}
+
+/// One entry of `Position::target_apy_history`
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct TargetApyChange {
+    pub target_apy: u16,
+    pub applied_from: i64,
+    pub applied_until: i64,
+}
+
+/// Fields after `asset`, through `bump`
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct PositionBody {
+    pub amount: u64,
+    pub target_apy: u16,
+    pub opened_at: i64,
+    pub last_update: i64,
+    pub last_update_slot: u64,
+    pub is_active: u8,
+    pub accumulated_yield: u128,
+    pub accumulated_loss: u128,
+    pub unrealized_yield: u64,
+    pub realized_yield: u128,
+    pub compound_interval_secs: i64,
+    pub last_compound: i64,
+    pub compounded_yield: u64,
+    pub term_secs: i64,
+    pub maturity_ts: i64,
+    pub rollover_count: u32,
+    pub impairment_count: u16,
+    pub total_impaired: u64,
+    pub unwind_priority: u8,
+    pub last_value: u64,
+    pub checkpoint_value: u64,
+    pub venue_supply_apr_bps: u32,
+    pub venue_utilization_bps: u16,
+    pub venue_rate_updated_at: i64,
+    pub asset_entry: Pubkey,
+    pub asset_decimals: u8,
+    pub index: u64,
+    pub legacy_seed: u8,
+    pub rolled_from: Pubkey,
+    pub target_apy_history: [TargetApyChange; 4],
+    pub update_sequence: u64,
+    pub is_stale: u8,
+    pub reserved_capacity: u128,
+    pub layout_version: u8,
+    pub bump: u8,
+}
+
+/// A position account borrowed in place
+#[derive(Debug, Clone, Copy)]
+pub struct PositionView<'a> {
+    pub head: &'a PositionHead,
+    pub protocol: Kind,
+    pub strategy: Kind,
+    pub asset: Kind,
+    pub body: &'a PositionBody,
+    /// Byte offset of `body` in the account data
+    pub body_offset: usize,
+}
+
+impl<'a> PositionView<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, LayoutError> {
+        let mut reader = Reader::new(data, discriminators::POSITION)?;
+        let head = reader.segment()?;
+        let protocol = reader.kind()?;
+        let strategy = reader.kind()?;
+        let asset = reader.kind()?;
+        let body_offset = reader.offset();
+        let body = reader.segment()?;
+        Ok(Self {
+            head,
+            protocol,
+            strategy,
+            asset,
+            body,
+            body_offset,
+        })
+    }
+}
@@ -0,0 +1,60 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::{discriminators, LayoutError, Pubkey, Reader};
+
+/// Deposit lots a depositor record carries
+pub const MAX_DEPOSIT_LOTS: usize = 8;
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct DepositLot {
+    pub shares: u64,
+    pub deposited_at: i64,
+}
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct DepositorRecord {
+    pub vault: Pubkey,
+    pub depositor: Pubkey,
+    pub principal: u64,
+    pub shares: u64,
+    pub first_deposit_at: i64,
+    pub last_deposit_at: i64,
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub fees_paid: u64,
+    pub reward_stake: u64,
+    pub reward_debt: u128,
+    pub rewards_owed: u64,
+    pub lots: [DepositLot; MAX_DEPOSIT_LOTS],
+    pub bump: u8,
+}
+
+impl DepositorRecord {
+    pub fn from_account(data: &[u8]) -> Result<&Self, LayoutError> {
+        Reader::new(data, discriminators::DEPOSITOR_RECORD)?.segment()
+    }
+}
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct WithdrawalRequest {
+    pub vault: Pubkey,
+    pub depositor: Pubkey,
+    pub destination: Pubkey,
+    pub index: u64,
+    pub epoch: u64,
+    pub assets: u64,
+    pub remaining_assets: u64,
+    pub remaining_shares: u64,
+    pub exit_fee: u64,
+    pub requested_at: i64,
+    pub bump: u8,
+}
+
+impl WithdrawalRequest {
+    pub fn from_account(data: &[u8]) -> Result<&Self, LayoutError> {
+        Reader::new(data, discriminators::WITHDRAWAL_REQUEST)?.segment()
+    }
+}
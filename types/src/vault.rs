@@ -0,0 +1,155 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::{discriminators, LayoutError, Pubkey, Reader};
+
+/// Fields before `agent_name`
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct VaultHead {
+    pub owner: Pubkey,
+}
+
+/// Fields from `total_value_locked` through `last_checkpoint_costs`
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct VaultCore {
+    pub total_value_locked: u128,
+    pub active_positions: u16,
+    pub total_positions_created: u64,
+    pub created_at: i64,
+    pub last_rebalance: i64,
+    pub template: Pubkey,
+    pub performance_fee_bps: u16,
+    pub management_fee_bps: u16,
+    pub deposit_cap: u64,
+    pub max_positions: u16,
+    pub min_position_amount: u64,
+    pub reward_adapter: Pubkey,
+    pub reward_haircut_bps: u16,
+    pub pending_rewards_value: u64,
+    pub nav: u64,
+    pub last_mark: i64,
+    pub smoothing_threshold_bps: u16,
+    pub smoothing_release_secs: i64,
+    pub smoothing_reserve: u64,
+    pub smoothed_nav: u64,
+    pub last_checkpoint_nav: u64,
+    pub last_checkpoint_ts: i64,
+    pub checkpoint_count: u64,
+    pub exit_fee_full_fee_bps: u16,
+    pub exit_fee_full_fee_secs: i64,
+    pub exit_fee_zero_fee_secs: i64,
+    pub total_impaired: u64,
+    pub strategist: Pubkey,
+    pub slippage_budget_per_epoch: u64,
+    pub slippage_epoch: u64,
+    pub slippage_spent: u64,
+    pub next_order_id: u64,
+    pub deposits_paused: u8,
+    pub withdrawals_paused: u8,
+    pub trading_paused: u8,
+    pub deposit_pause_authority: Pubkey,
+    pub withdrawal_pause_authority: Pubkey,
+    pub trading_pause_authority: Pubkey,
+    pub simulation: u8,
+    pub delegate: Pubkey,
+    pub cloned_from: Pubkey,
+    pub cumulative_inflows: u128,
+    pub cumulative_outflows: u128,
+    pub cumulative_yield: u128,
+    pub cumulative_losses: u128,
+    pub cumulative_fees: u128,
+    pub return_stats_count: u64,
+    pub return_stats_mean: i64,
+    pub return_stats_m2: u128,
+    pub benchmark: Pubkey,
+    pub operating_costs: u64,
+    pub last_checkpoint_costs: u64,
+}
+
+/// Fields after `cosigners`, through `bump`
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct VaultTail {
+    pub cosign_threshold: u8,
+    pub large_withdrawal_limit: u64,
+    pub destination_allowlist: Pubkey,
+    pub velocity_limit_bps: u16,
+    pub velocity_window_start: i64,
+    pub velocity_window_tvl: u64,
+    pub velocity_window_outflow: u64,
+    pub guardian: Pubkey,
+    pub viewer: Pubkey,
+    pub cpi_guard_locked: u8,
+    pub bundle_only: u8,
+    pub max_bundle_tip_lamports: u64,
+    pub mint: Pubkey,
+    pub escrow: Pubkey,
+    pub audit_mode: u8,
+    pub share_mint: Pubkey,
+    pub dead_shares: u64,
+    pub withdrawal_epoch: u64,
+    pub withdrawal_queue_head: u64,
+    pub withdrawal_queue_tail: u64,
+    pub queued_withdrawals: u64,
+    pub fee_authority: Pubkey,
+    pub fees_accrued: u128,
+    pub fee_last_accrued: i64,
+    pub fees_outstanding: u128,
+    pub realized_gains: u128,
+    pub fee_high_water_mark: u128,
+    pub seed_owner: Pubkey,
+    pub pending_owner: Pubkey,
+    pub recent_idempotency_keys: [[u8; 16]; 8],
+    pub idempotency_cursor: u8,
+    pub is_paused: u8,
+    pub rebate_share_bps: u16,
+    pub rebate_recipient: Pubkey,
+    pub rebates_accrued: u128,
+    pub unrealized_yield: u128,
+    pub min_update_interval_secs: i64,
+    pub max_staleness_secs: i64,
+    pub stale_positions: u32,
+    pub layout_version: u8,
+    pub bump: u8,
+}
+
+/// A vault account borrowed in place
+#[derive(Debug, Clone, Copy)]
+pub struct VaultView<'a> {
+    pub head: &'a VaultHead,
+    pub agent_name: &'a [u8],
+    pub core: &'a VaultCore,
+    /// Byte offset of `core` in the account data
+    pub core_offset: usize,
+    pub cosigners: &'a [Pubkey],
+    pub tail: &'a VaultTail,
+    /// Byte offset of `tail` in the account data
+    pub tail_offset: usize,
+}
+
+impl<'a> VaultView<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, LayoutError> {
+        let mut reader = Reader::new(data, discriminators::VAULT)?;
+        let head = reader.segment()?;
+        let agent_name = reader.bytes()?;
+        let core_offset = reader.offset();
+        let core = reader.segment()?;
+        let cosigners = reader.pubkeys()?;
+        let tail_offset = reader.offset();
+        let tail = reader.segment()?;
+        Ok(Self {
+            head,
+            agent_name,
+            core,
+            core_offset,
+            cosigners,
+            tail,
+            tail_offset,
+        })
+    }
+
+    pub fn agent_name(&self) -> Option<&'a str> {
+        core::str::from_utf8(self.agent_name).ok()
+    }
+}
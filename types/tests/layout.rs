@@ -0,0 +1,79 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountSerialize;
+use moluscoyield_types::{
+    account_kind, offsets, AccountKind, DepositorRecord, LayoutError, PositionBody, PositionHead,
+    VaultCore, VaultHead, VaultTail,
+};
+
+#[test]
+fn mirrors_match_program_sizes() {
+    // Segments plus each variable field at its maximum length
+    assert_eq!(
+        size_of::<VaultHead>()
+            + 4
+            + 32
+            + size_of::<VaultCore>()
+            + 4
+            + moluscoyield::MAX_COSIGNERS * 32
+            + size_of::<VaultTail>(),
+        moluscoyield::Vault::SIZE
+    );
+    assert_eq!(
+        size_of::<PositionHead>() + 3 * moluscoyield::KIND_SIZE + size_of::<PositionBody>(),
+        moluscoyield::Position::SIZE
+    );
+    assert_eq!(
+        size_of::<DepositorRecord>(),
+        moluscoyield::DepositorRecord::SIZE
+    );
+    assert_eq!(moluscoyield_types::MAX_DEPOSIT_LOTS, moluscoyield::MAX_DEPOSIT_LOTS);
+    assert_eq!(
+        size_of::<moluscoyield_types::WithdrawalRequest>(),
+        moluscoyield::WithdrawalRequest::SIZE
+    );
+}
+
+#[test]
+fn depositor_record_parses_anchor_encoding() {
+    let vault = Pubkey::new_unique();
+    let depositor = Pubkey::new_unique();
+    let record = moluscoyield::DepositorRecord {
+        vault,
+        depositor,
+        principal: 5_000_000,
+        shares: 4_999_000_000,
+        first_deposit_at: 100,
+        last_deposit_at: 250,
+        deposited: 5_000_000,
+        withdrawn: 0,
+        fees_paid: 0,
+        reward_stake: 0,
+        reward_debt: 0,
+        rewards_owed: 0,
+        lots: Default::default(),
+        bump: 254,
+    };
+    let mut data = Vec::new();
+    record.try_serialize(&mut data).unwrap();
+
+    assert_eq!(account_kind(&data), Some(AccountKind::DepositorRecord));
+    let mirror = DepositorRecord::from_account(&data).unwrap();
+    assert_eq!({ mirror.vault }, vault.to_bytes());
+    assert_eq!({ mirror.shares }, 4_999_000_000);
+    assert_eq!({ mirror.last_deposit_at }, 250);
+    assert_eq!(
+        &data[offsets::DEPOSITOR_RECORD_DEPOSITOR..offsets::DEPOSITOR_RECORD_DEPOSITOR + 32],
+        depositor.as_ref()
+    );
+
+    assert_eq!(
+        moluscoyield_types::WithdrawalRequest::from_account(&data).unwrap_err(),
+        LayoutError::WrongAccount
+    );
+    assert_eq!(
+        DepositorRecord::from_account(&data[..40]).unwrap_err(),
+        LayoutError::Truncated
+    );
+}